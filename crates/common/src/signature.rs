@@ -1,9 +1,83 @@
 use fake::Dummy;
+use pathfinder_crypto::hash::poseidon_hash_many;
+use pathfinder_crypto::signature::{ecdsa_verify_partial, SignatureError};
+use pathfinder_crypto::MontFelt;
 
-use crate::BlockCommitmentSignatureElem;
+use crate::{BlockCommitmentSignatureElem, BlockHash, PublicKey, StateDiffCommitment};
 
 #[derive(Default, Debug, Clone, PartialEq, Dummy)]
 pub struct BlockCommitmentSignature {
     pub r: BlockCommitmentSignatureElem,
     pub s: BlockCommitmentSignatureElem,
 }
+
+impl BlockCommitmentSignature {
+    /// Verifies that the sequencer owning `public_key` signed the block hash and state diff
+    /// commitment of a block.
+    ///
+    /// The signed message is the Poseidon hash of the two values. The sequencer publishes only
+    /// the x-coordinate of its public key.
+    pub fn verify(
+        &self,
+        public_key: PublicKey,
+        block_hash: BlockHash,
+        state_diff_commitment: StateDiffCommitment,
+    ) -> Result<(), SignatureError> {
+        let message = poseidon_hash_many(&[
+            MontFelt::from(block_hash.0),
+            MontFelt::from(state_diff_commitment.0),
+        ]);
+
+        ecdsa_verify_partial(public_key.0, message.into(), self.r.0, self.s.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macro_prelude::*;
+
+    // Mainnet block 350000 from https://alpha-mainnet.starknet.io/feeder_gateway/get_signature?blockNumber=350000
+    const PUBLIC_KEY: PublicKey =
+        public_key!("0x48253ff2c3bed7af18bde0b611b083b39445959102d4947c51c4db6aa4f4e58");
+    const BLOCK_HASH: BlockHash =
+        block_hash!("0x6f7342a680d7f99bdfdd859f587c75299e7ffabe62c071ded3a6d8a34cb132c");
+    const STATE_DIFF_COMMITMENT: StateDiffCommitment =
+        state_diff_commitment!("0x432e8e2ad833548e1c1077fc298991b055ba1e6f7a17dd332db98f4f428c56c");
+
+    fn signature() -> BlockCommitmentSignature {
+        BlockCommitmentSignature {
+            r: block_commitment_signature_elem!(
+                "0x95e98f5b91d39ae2b1bf77447a4fc01725352ae8b0b2c0a3fe09d43d1d9e57"
+            ),
+            s: block_commitment_signature_elem!(
+                "0x541b2db8dae6d5ae24b34e427d251edc2e94dcffddd85f207e1b51f2f4bb1ef"
+            ),
+        }
+    }
+
+    #[test]
+    fn valid() {
+        signature()
+            .verify(PUBLIC_KEY, BLOCK_HASH, STATE_DIFF_COMMITMENT)
+            .unwrap();
+    }
+
+    #[test]
+    fn wrong_message() {
+        let result = signature().verify(PUBLIC_KEY, BLOCK_HASH, StateDiffCommitment::ZERO);
+        assert_eq!(result, Err(SignatureError::Signature));
+    }
+
+    #[test]
+    fn signature_above_curve_order() {
+        let mut signature = signature();
+        // The largest field element, which is above the curve order.
+        signature.s = block_commitment_signature_elem!(
+            "0x0800000000000011000000000000000000000000000000000000000000000000"
+        );
+
+        let result = signature.verify(PUBLIC_KEY, BLOCK_HASH, STATE_DIFF_COMMITMENT);
+        assert_eq!(result, Err(SignatureError::Signature));
+    }
+}
@@ -140,9 +140,10 @@ pub fn ecdsa_verify_inner(
     let f_r = MontFelt::from(r);
     let f_s = MontFelt::from(s);
 
-    let cf_z = CurveOrderMontFelt::try_from(f_z).unwrap();
-    let cf_r = CurveOrderMontFelt::try_from(f_r).unwrap();
-    let cf_s = CurveOrderMontFelt::try_from(f_s).unwrap();
+    // Values which are not below the curve order may come from untrusted signatures.
+    let cf_z = CurveOrderMontFelt::try_from(f_z).map_err(|_| SignatureError::Message)?;
+    let cf_r = CurveOrderMontFelt::try_from(f_r).map_err(|_| SignatureError::Signature)?;
+    let cf_s = CurveOrderMontFelt::try_from(f_s).map_err(|_| SignatureError::Signature)?;
 
     // Check hard bound on message and signature.
     if f_z >= UPPER_BOUND {
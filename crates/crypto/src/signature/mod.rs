@@ -1,3 +1,5 @@
 mod ecdsa;
 
-pub use ecdsa::{ecdsa_sign, ecdsa_sign_k, ecdsa_verify, ecdsa_verify_partial, get_pk};
+pub use ecdsa::{
+    ecdsa_sign, ecdsa_sign_k, ecdsa_verify, ecdsa_verify_partial, get_pk, SignatureError,
+};
@@ -0,0 +1,687 @@
+//! An [EthereumApi] which spreads requests over several Ethereum endpoints, so that a single
+//! provider outage does not stall L1 sync.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use pathfinder_common::EthereumChain;
+use primitive_types::H160;
+use tokio::sync::mpsc;
+
+use crate::{
+    ConsumedMessageToL1Log, EthereumApi, EthereumClient, EthereumStateUpdate, MessageToL2Log,
+    StateUpdateLog,
+};
+
+const METRIC_REQUESTS: &str = "ethereum_requests_total";
+const METRIC_FAILED_REQUESTS: &str = "ethereum_requests_failed_total";
+const METRIC_LATENCY: &str = "ethereum_endpoint_latency_seconds";
+const METRIC_HEALTHY: &str = "ethereum_endpoint_healthy";
+const METHODS: [&str; 7] = [
+    "get_starknet_state",
+    "get_chain",
+    "get_finalized_block_number",
+    "get_state_update_logs",
+    "get_message_to_l2_logs",
+    "get_consumed_message_to_l1_logs",
+    "subscribe_new_heads",
+];
+
+/// Requests taking longer than this are considered failed, so that a slow endpoint does not hold
+/// up the others.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// An endpoint is unhealthy after this many consecutive failures, and is then only tried once
+/// the healthy endpoints have failed as well.
+const UNHEALTHY_AFTER_FAILURES: u32 = 3;
+/// Unhealthy endpoints are given another chance once they have not failed for this long.
+const UNHEALTHY_COOLDOWN: Duration = Duration::from_secs(60);
+/// The weight of the latest request in an endpoint's average latency.
+const LATENCY_WEIGHT: f64 = 0.2;
+
+/// Sends requests to the healthiest and fastest of several Ethereum endpoints, failing over to
+/// the next one on errors and timeouts.
+///
+/// The finalized state of the core contract, the latest finalized block and the core contract's
+/// logs can additionally be required to agree between a quorum of endpoints, so that a single
+/// faulty provider cannot feed wrong L1 data.
+///
+/// Logs are only requested from endpoints which have finalized the last block of the range, since
+/// an endpoint lagging behind would return incomplete logs.
+#[derive(Clone)]
+pub struct FailoverClient {
+    endpoints: Arc<[Endpoint]>,
+    quorum: usize,
+    timeout: Duration,
+}
+
+struct Endpoint {
+    client: EthereumClient,
+    /// Identifies the endpoint in logs and metrics. Only the origin of the URL is used since
+    /// providers tend to include API keys in the path.
+    label: String,
+    health: Mutex<Health>,
+    /// The latest finalized block reported by the endpoint.
+    finalized: AtomicU64,
+}
+
+#[derive(Default)]
+struct Health {
+    consecutive_failures: u32,
+    last_failure: Option<Instant>,
+    /// The moving average of the latency of successful requests.
+    latency: Option<Duration>,
+}
+
+impl Health {
+    fn is_healthy(&self) -> bool {
+        self.consecutive_failures < UNHEALTHY_AFTER_FAILURES
+            || self
+                .last_failure
+                .map_or(true, |last| last.elapsed() >= UNHEALTHY_COOLDOWN)
+    }
+}
+
+impl FailoverClient {
+    /// Creates a client for the given endpoints, all of which use the same password if one is
+    /// given. `quorum` is the number of endpoints which must agree on the finalized L1 state.
+    pub fn new(
+        urls: Vec<reqwest::Url>,
+        password: Option<&str>,
+        quorum: usize,
+    ) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            !urls.is_empty(),
+            "At least one Ethereum endpoint is required"
+        );
+        anyhow::ensure!(
+            (1..=urls.len()).contains(&quorum),
+            "The quorum must be between 1 and the number of Ethereum endpoints ({})",
+            urls.len()
+        );
+
+        let endpoints = urls
+            .into_iter()
+            .map(|url| {
+                let label = url.origin().ascii_serialization();
+                let client = match password {
+                    Some(password) => EthereumClient::with_password(url, password)?,
+                    None => EthereumClient::new(url)?,
+                };
+                Ok(Endpoint {
+                    client,
+                    label,
+                    health: Default::default(),
+                    finalized: Default::default(),
+                })
+            })
+            .collect::<anyhow::Result<Arc<[_]>>>()?;
+
+        Ok(Self {
+            endpoints,
+            quorum,
+            timeout: REQUEST_TIMEOUT,
+        })
+    }
+
+    /// Registers the metrics of every endpoint, so that they are exported before the first
+    /// request.
+    pub fn register_metrics(&self) {
+        for endpoint in self.endpoints.iter() {
+            for method in METHODS {
+                metrics::register_counter!(METRIC_REQUESTS, "endpoint" => endpoint.label.clone(), "method" => method);
+                metrics::register_counter!(METRIC_FAILED_REQUESTS, "endpoint" => endpoint.label.clone(), "method" => method);
+            }
+            metrics::gauge!(METRIC_HEALTHY, 1.0, "endpoint" => endpoint.label.clone());
+        }
+    }
+
+    /// Returns the endpoints in the order they should be tried: healthy endpoints first, then
+    /// by average latency. Endpoints which have not responded yet are tried first.
+    fn by_preference(&self) -> Vec<&Endpoint> {
+        let mut endpoints = self.endpoints.iter().collect::<Vec<_>>();
+        endpoints.sort_by_cached_key(|endpoint| {
+            let health = endpoint.health.lock().unwrap();
+            (!health.is_healthy(), health.latency.unwrap_or_default())
+        });
+        endpoints
+    }
+
+    /// Sends the request to one endpoint after the other until one succeeds, skipping endpoints
+    /// which have not finalized the block `finalized`, if given.
+    async fn failover<T, F, Fut>(
+        &self,
+        method: &'static str,
+        finalized: Option<u64>,
+        request: F,
+    ) -> anyhow::Result<T>
+    where
+        F: Fn(EthereumClient) -> Fut + Send + Sync,
+        Fut: Future<Output = anyhow::Result<T>> + Send,
+        T: Send,
+    {
+        let mut error = None;
+        for endpoint in self.by_preference() {
+            let result = async {
+                if let Some(block) = finalized {
+                    endpoint.ensure_finalized(block, self.timeout).await?;
+                }
+                endpoint
+                    .request(method, self.timeout, request(endpoint.client.clone()))
+                    .await
+            };
+            match result.await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    tracing::debug!(endpoint=%endpoint.label, %method, reason=?e, "Ethereum request failed, trying the next endpoint");
+                    error = Some(e);
+                }
+            }
+        }
+
+        Err(error.expect("There is at least one endpoint"))
+    }
+
+    /// Sends the request to all endpoints which have finalized the block `finalized`, if given,
+    /// and returns the successful responses if there are at least as many as the quorum.
+    async fn responses<T, F, Fut>(
+        &self,
+        method: &'static str,
+        finalized: Option<u64>,
+        request: F,
+    ) -> anyhow::Result<Vec<(&Endpoint, T)>>
+    where
+        F: Fn(EthereumClient) -> Fut + Send + Sync,
+        Fut: Future<Output = anyhow::Result<T>> + Send,
+        T: Send,
+    {
+        let request = &request;
+        let results = futures::future::join_all(self.endpoints.iter().map(|endpoint| async move {
+            let result = async {
+                if let Some(block) = finalized {
+                    endpoint.ensure_finalized(block, self.timeout).await?;
+                }
+                endpoint
+                    .request(method, self.timeout, request(endpoint.client.clone()))
+                    .await
+            };
+            (endpoint, result.await)
+        }))
+        .await;
+
+        let mut responses = Vec::with_capacity(results.len());
+        for (endpoint, result) in results {
+            match result {
+                Ok(value) => responses.push((endpoint, value)),
+                Err(e) => {
+                    tracing::debug!(endpoint=%endpoint.label, %method, reason=?e, "Ethereum request failed")
+                }
+            }
+        }
+
+        anyhow::ensure!(
+            responses.len() >= self.quorum,
+            "Only {} of {} Ethereum endpoints responded to {method}, but a quorum of {} is required",
+            responses.len(),
+            self.endpoints.len(),
+            self.quorum
+        );
+
+        Ok(responses)
+    }
+
+    /// Returns the response on which at least a quorum of endpoints agree. Without a quorum the
+    /// request is simply failed over between endpoints.
+    async fn agreed<T, F, Fut>(
+        &self,
+        method: &'static str,
+        finalized: Option<u64>,
+        request: F,
+    ) -> anyhow::Result<T>
+    where
+        F: Fn(EthereumClient) -> Fut + Send + Sync,
+        Fut: Future<Output = anyhow::Result<T>> + Send,
+        T: PartialEq + Send,
+    {
+        if self.quorum == 1 {
+            return self.failover(method, finalized, request).await;
+        }
+
+        let mut responses = self.responses(method, finalized, request).await?;
+        let agreed = responses.iter().position(|(_, candidate)| {
+            responses
+                .iter()
+                .filter(|(_, value)| value == candidate)
+                .count()
+                >= self.quorum
+        });
+
+        match agreed {
+            Some(index) => {
+                let (_, value) = responses.swap_remove(index);
+                for (endpoint, _) in responses.iter().filter(|(_, other)| other != &value) {
+                    tracing::warn!(endpoint=%endpoint.label, %method, "Ethereum endpoint disagrees with the quorum");
+                }
+                Ok(value)
+            }
+            None => {
+                let endpoints = responses
+                    .iter()
+                    .map(|(endpoint, _)| endpoint.label.as_str())
+                    .collect::<Vec<_>>();
+                anyhow::bail!(
+                    "No {} of the Ethereum endpoints {endpoints:?} agree on the response to {method}",
+                    self.quorum
+                )
+            }
+        }
+    }
+}
+
+impl Endpoint {
+    /// Fails unless the endpoint has finalized `block`, asking it for its latest finalized block
+    /// only if the one it reported last is older.
+    async fn ensure_finalized(&self, block: u64, timeout: Duration) -> anyhow::Result<()> {
+        if self.finalized.load(Ordering::Relaxed) >= block {
+            return Ok(());
+        }
+
+        let finalized = self
+            .request(
+                "get_finalized_block_number",
+                timeout,
+                self.client.get_finalized_block_number(),
+            )
+            .await?;
+        self.finalized.fetch_max(finalized, Ordering::Relaxed);
+        anyhow::ensure!(
+            finalized >= block,
+            "Endpoint has only finalized block {finalized}, but block {block} was requested"
+        );
+
+        Ok(())
+    }
+
+    async fn request<T>(
+        &self,
+        method: &'static str,
+        timeout: Duration,
+        request: impl Future<Output = anyhow::Result<T>>,
+    ) -> anyhow::Result<T> {
+        metrics::increment_counter!(METRIC_REQUESTS, "endpoint" => self.label.clone(), "method" => method);
+
+        let start = Instant::now();
+        let result = match tokio::time::timeout(timeout, request).await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow::anyhow!("Request timed out after {timeout:?}")),
+        };
+
+        let mut health = self.health.lock().unwrap();
+        match &result {
+            Ok(_) => {
+                let latency = start.elapsed();
+                let latency = match health.latency {
+                    Some(average) => {
+                        average.mul_f64(1.0 - LATENCY_WEIGHT) + latency.mul_f64(LATENCY_WEIGHT)
+                    }
+                    None => latency,
+                };
+                health.latency = Some(latency);
+                health.consecutive_failures = 0;
+                metrics::gauge!(METRIC_LATENCY, latency.as_secs_f64(), "endpoint" => self.label.clone());
+            }
+            Err(_) => {
+                health.consecutive_failures = health.consecutive_failures.saturating_add(1);
+                health.last_failure = Some(Instant::now());
+                metrics::increment_counter!(METRIC_FAILED_REQUESTS, "endpoint" => self.label.clone(), "method" => method);
+            }
+        }
+        let healthy = if health.is_healthy() { 1.0 } else { 0.0 };
+        metrics::gauge!(METRIC_HEALTHY, healthy, "endpoint" => self.label.clone());
+
+        result
+    }
+}
+
+#[async_trait::async_trait]
+impl EthereumApi for FailoverClient {
+    async fn get_starknet_state(&self, address: &H160) -> anyhow::Result<EthereumStateUpdate> {
+        let address = *address;
+        self.agreed("get_starknet_state", None, move |client| async move {
+            client.get_starknet_state(&address).await
+        })
+        .await
+    }
+
+    async fn get_chain(&self) -> anyhow::Result<EthereumChain> {
+        self.failover("get_chain", None, |client| async move {
+            client.get_chain().await
+        })
+        .await
+    }
+
+    /// With a quorum, this is the latest block which at least a quorum of endpoints have
+    /// finalized.
+    async fn get_finalized_block_number(&self) -> anyhow::Result<u64> {
+        let method = "get_finalized_block_number";
+        let request =
+            |client: EthereumClient| async move { client.get_finalized_block_number().await };
+
+        if self.quorum == 1 {
+            return self.failover(method, None, request).await;
+        }
+
+        let mut numbers = self
+            .responses(method, None, request)
+            .await?
+            .into_iter()
+            .map(|(endpoint, number)| {
+                endpoint.finalized.fetch_max(number, Ordering::Relaxed);
+                number
+            })
+            .collect::<Vec<_>>();
+        numbers.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(numbers[self.quorum - 1])
+    }
+
+    async fn get_state_update_logs(
+        &self,
+        address: &H160,
+        from_block: u64,
+        to_block: u64,
+    ) -> anyhow::Result<Vec<StateUpdateLog>> {
+        let address = *address;
+        self.agreed(
+            "get_state_update_logs",
+            Some(to_block),
+            move |client| async move {
+                client
+                    .get_state_update_logs(&address, from_block, to_block)
+                    .await
+            },
+        )
+        .await
+    }
+
+    async fn get_message_to_l2_logs(
+        &self,
+        address: &H160,
+        from_block: u64,
+        to_block: u64,
+    ) -> anyhow::Result<Vec<MessageToL2Log>> {
+        let address = *address;
+        self.agreed(
+            "get_message_to_l2_logs",
+            Some(to_block),
+            move |client| async move {
+                client
+                    .get_message_to_l2_logs(&address, from_block, to_block)
+                    .await
+            },
+        )
+        .await
+    }
+
+    async fn get_consumed_message_to_l1_logs(
+        &self,
+        address: &H160,
+        from_block: u64,
+        to_block: u64,
+    ) -> anyhow::Result<Vec<ConsumedMessageToL1Log>> {
+        let address = *address;
+        self.agreed(
+            "get_consumed_message_to_l1_logs",
+            Some(to_block),
+            move |client| async move {
+                client
+                    .get_consumed_message_to_l1_logs(&address, from_block, to_block)
+                    .await
+            },
+        )
+        .await
+    }
+
+    /// Subscribes using the preferred endpoint which supports subscriptions.
+    async fn subscribe_new_heads(&self) -> anyhow::Result<mpsc::Receiver<u64>> {
+        let method = "subscribe_new_heads";
+        let mut error = None;
+        for endpoint in self
+            .by_preference()
+            .into_iter()
+            .filter(|endpoint| endpoint.client.supports_subscriptions())
+        {
+            match endpoint
+                .request(method, self.timeout, endpoint.client.subscribe_new_heads())
+                .await
+            {
+                Ok(heads) => return Ok(heads),
+                Err(e) => {
+                    tracing::debug!(endpoint=%endpoint.label, %method, reason=?e, "Ethereum request failed, trying the next endpoint");
+                    error = Some(e);
+                }
+            }
+        }
+
+        Err(error.unwrap_or_else(|| anyhow::anyhow!("No Ethereum endpoint supports subscriptions")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use reqwest::Url;
+
+    use super::*;
+
+    fn finalized_block_mock(server: &MockServer, number: u64) {
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("eth_getBlockByNumber");
+            then.status(200).json_body(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": {
+                    "number": format!("0x{number:x}"),
+                    "hash": "0x0",
+                }
+            }));
+        });
+    }
+
+    fn logs_mock<'a>(server: &'a MockServer, logs: serde_json::Value) -> httpmock::Mock<'a> {
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("eth_getLogs");
+            then.status(200).json_body(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": logs,
+            }));
+        })
+    }
+
+    fn state_update_log(state_root: &str) -> serde_json::Value {
+        serde_json::json!({
+            "blockNumber": "0x10",
+            "transactionHash": "0xabcd",
+            "data": format!(
+                "0x{:0>64}{:0>64}{:0>64}",
+                state_root.trim_start_matches("0x"),
+                "5",
+                "6"
+            ),
+        })
+    }
+
+    fn client(servers: &[&MockServer], quorum: usize) -> FailoverClient {
+        let urls = servers
+            .iter()
+            .map(|server| Url::parse(&server.url("/")).unwrap())
+            .collect();
+        FailoverClient::new(urls, None, quorum).unwrap()
+    }
+
+    #[tokio::test]
+    async fn fails_over_to_healthy_endpoint() {
+        let failing = MockServer::start_async().await;
+        failing.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(500);
+        });
+        let healthy = MockServer::start_async().await;
+        finalized_block_mock(&healthy, 100);
+
+        let client = client(&[&failing, &healthy], 1);
+        for _ in 0..UNHEALTHY_AFTER_FAILURES {
+            assert_eq!(client.get_finalized_block_number().await.unwrap(), 100);
+        }
+
+        // The failing endpoint is now unhealthy, and is no longer tried first.
+        let preferred = client.by_preference()[0].label.clone();
+        assert_eq!(
+            preferred,
+            Url::parse(&healthy.url("/"))
+                .unwrap()
+                .origin()
+                .ascii_serialization()
+        );
+    }
+
+    #[tokio::test]
+    async fn times_out_slow_endpoint() {
+        let slow = MockServer::start_async().await;
+        slow.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200).delay(Duration::from_secs(5));
+        });
+        let fast = MockServer::start_async().await;
+        finalized_block_mock(&fast, 100);
+
+        let mut client = client(&[&slow, &fast], 1);
+        client.timeout = Duration::from_millis(100);
+        assert_eq!(client.get_finalized_block_number().await.unwrap(), 100);
+    }
+
+    #[tokio::test]
+    async fn finalized_block_of_quorum() {
+        let servers = [
+            MockServer::start_async().await,
+            MockServer::start_async().await,
+            MockServer::start_async().await,
+        ];
+        for (server, number) in servers.iter().zip([100, 102, 101]) {
+            finalized_block_mock(server, number);
+        }
+        let servers = servers.iter().collect::<Vec<_>>();
+
+        let result = client(&servers, 2).get_finalized_block_number().await;
+        assert_eq!(result.unwrap(), 101);
+        let result = client(&servers, 3).get_finalized_block_number().await;
+        assert_eq!(result.unwrap(), 100);
+    }
+
+    #[tokio::test]
+    async fn quorum_must_agree() {
+        let servers = [
+            MockServer::start_async().await,
+            MockServer::start_async().await,
+            MockServer::start_async().await,
+        ];
+        for (server, state_root) in servers.iter().zip(["0x1", "0x1", "0x2"]) {
+            finalized_block_mock(server, 0x10);
+            logs_mock(server, serde_json::json!([state_update_log(state_root)]));
+        }
+        let servers = servers.iter().collect::<Vec<_>>();
+
+        let logs = client(&servers, 2)
+            .get_state_update_logs(&H160::zero(), 0, 0x10)
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(
+            logs[0].update.state_root,
+            pathfinder_common::StateCommitment(pathfinder_crypto::Felt::from_u64(1))
+        );
+
+        client(&servers, 3)
+            .get_state_update_logs(&H160::zero(), 0, 0x10)
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn quorum_must_agree_on_messages() {
+        let servers = [
+            MockServer::start_async().await,
+            MockServer::start_async().await,
+            MockServer::start_async().await,
+        ];
+        for (server, payload) in servers.iter().zip(["1", "1", "2"]) {
+            finalized_block_mock(server, 0x10);
+            logs_mock(
+                server,
+                serde_json::json!([{
+                    "blockNumber": "0x10",
+                    "logIndex": "0x0",
+                    "transactionHash": "0xabcd",
+                    "topics": [
+                        format!("0x{:0>64}", "c"),
+                        format!("0x{:0>64}", "a"),
+                        format!("0x{:0>64}", "b"),
+                    ],
+                    "data": format!("0x{:0>64}{:0>64}{:0>64}", "20", "1", payload),
+                }]),
+            );
+        }
+        let servers = servers.iter().collect::<Vec<_>>();
+
+        let logs = client(&servers, 2)
+            .get_consumed_message_to_l1_logs(&H160::zero(), 0, 0x10)
+            .await
+            .unwrap();
+        let expected = client(&servers[..1], 1)
+            .get_consumed_message_to_l1_logs(&H160::zero(), 0, 0x10)
+            .await
+            .unwrap();
+        assert_eq!(logs, expected);
+
+        client(&servers, 3)
+            .get_consumed_message_to_l1_logs(&H160::zero(), 0, 0x10)
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn logs_from_endpoint_which_finalized_range() {
+        let lagging = MockServer::start_async().await;
+        finalized_block_mock(&lagging, 0x8);
+        let lagging_logs = logs_mock(&lagging, serde_json::json!([]));
+        let synced = MockServer::start_async().await;
+        finalized_block_mock(&synced, 0x10);
+        logs_mock(&synced, serde_json::json!([state_update_log("0x1")]));
+
+        // The lagging endpoint is preferred, but has not finalized the end of the range.
+        let logs = client(&[&lagging, &synced], 1)
+            .get_state_update_logs(&H160::zero(), 0, 0x10)
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        lagging_logs.assert_hits(0);
+
+        let logs = client(&[&lagging, &synced], 1)
+            .get_state_update_logs(&H160::zero(), 0, 0x8)
+            .await
+            .unwrap();
+        assert!(logs.is_empty());
+        lagging_logs.assert_hits(1);
+    }
+
+    #[test]
+    fn quorum_is_validated() {
+        let url = Url::parse("http://localhost:8545").unwrap();
+        FailoverClient::new(vec![url.clone()], None, 2).unwrap_err();
+        FailoverClient::new(vec![url], None, 0).unwrap_err();
+        FailoverClient::new(vec![], None, 1).unwrap_err();
+    }
+}
@@ -1,6 +1,13 @@
+use anyhow::Context;
 use pathfinder_common::{BlockHash, BlockNumber, EthereumChain, StateCommitment};
 use pathfinder_crypto::Felt;
 use primitive_types::{H160, H256, U256};
+use tokio::sync::mpsc;
+
+mod failover;
+mod ws;
+
+pub use failover::FailoverClient;
 
 pub mod core_addr {
     use const_decoder::Decoder;
@@ -16,6 +23,16 @@ pub mod core_addr {
         Decoder::Hex.decode(b"4737c0c1B4D5b1A687B42610DdabEE781152359c");
 }
 
+/// L1 blocks at or before the deployment of each core contract in [core_addr], from which its
+/// logs are backfilled. Earlier blocks cannot contain any of its logs.
+pub mod core_deployment_block {
+    pub const MAINNET: u64 = 13_000_000;
+    pub const GOERLI_TESTNET: u64 = 4_000_000;
+    pub const GOERLI_INTEGRATION: u64 = 4_000_000;
+    pub const SEPOLIA_TESTNET: u64 = 4_000_000;
+    pub const SEPOLIA_INTEGRATION: u64 = 4_000_000;
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct EthereumStateUpdate {
     pub state_root: StateCommitment,
@@ -23,20 +40,102 @@ pub struct EthereumStateUpdate {
     pub block_hash: BlockHash,
 }
 
+/// A `LogStateUpdate` event emitted by the Starknet core contract when it accepted a state
+/// update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateUpdateLog {
+    pub update: EthereumStateUpdate,
+    /// The L1 block containing the transaction which accepted the state update.
+    pub l1_block_number: u64,
+    pub l1_transaction_hash: H256,
+}
+
+/// A `LogMessageToL2` event emitted by the Starknet core contract when an L1 contract sent a
+/// message to L2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageToL2Log {
+    /// The hash of the message, which is also the message hash of the L1 handler transaction
+    /// consuming it on L2.
+    pub message_hash: H256,
+    /// The L1 block containing the transaction which sent the message.
+    pub l1_block_number: u64,
+    pub l1_transaction_hash: H256,
+}
+
+/// A `ConsumedMessageToL1` event emitted by the Starknet core contract when an L1 contract
+/// consumed a message sent from L2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumedMessageToL1Log {
+    /// The hash of the message, which identifies it among the L2 to L1 messages of receipts.
+    pub message_hash: H256,
+    /// The L1 block containing the transaction which consumed the message.
+    pub l1_block_number: u64,
+    /// The index of the log within its L1 block.
+    pub l1_log_index: u64,
+    pub l1_transaction_hash: H256,
+}
+
 #[async_trait::async_trait]
 pub trait EthereumApi {
     async fn get_starknet_state(&self, address: &H160) -> anyhow::Result<EthereumStateUpdate>;
     async fn get_chain(&self) -> anyhow::Result<EthereumChain>;
+    /// Returns the number of the latest finalized L1 block.
+    async fn get_finalized_block_number(&self) -> anyhow::Result<u64>;
+    /// Returns the state updates logged by the core contract at `address` between the L1 blocks
+    /// `from_block` and `to_block` inclusive, in the order they were logged.
+    async fn get_state_update_logs(
+        &self,
+        address: &H160,
+        from_block: u64,
+        to_block: u64,
+    ) -> anyhow::Result<Vec<StateUpdateLog>>;
+    /// Returns the L1 to L2 messages logged by the core contract at `address` between the L1
+    /// blocks `from_block` and `to_block` inclusive, in the order they were logged.
+    async fn get_message_to_l2_logs(
+        &self,
+        address: &H160,
+        from_block: u64,
+        to_block: u64,
+    ) -> anyhow::Result<Vec<MessageToL2Log>>;
+    /// Returns the consumptions of L2 to L1 messages logged by the core contract at `address`
+    /// between the L1 blocks `from_block` and `to_block` inclusive, in the order they were logged.
+    async fn get_consumed_message_to_l1_logs(
+        &self,
+        address: &H160,
+        from_block: u64,
+        to_block: u64,
+    ) -> anyhow::Result<Vec<ConsumedMessageToL1Log>>;
+    /// Subscribes to new L1 blocks, returning a channel which receives the number of the latest
+    /// block whenever a new one is seen. Blocks may be skipped if the receiver falls behind, and
+    /// the channel is closed when the subscription is dropped. Only WebSocket endpoints support
+    /// subscriptions.
+    async fn subscribe_new_heads(&self) -> anyhow::Result<mpsc::Receiver<u64>>;
 }
 
 #[derive(Clone, Debug)]
 pub struct EthereumClient {
-    http: reqwest::Client,
-    url: reqwest::Url,
+    transport: Transport,
+}
+
+#[derive(Clone, Debug)]
+enum Transport {
+    Http {
+        client: reqwest::Client,
+        url: reqwest::Url,
+    },
+    Ws(ws::WsClient),
 }
 
 const HTTP_OK: u16 = 200;
 
+/// The signature of the core contract's `LogStateUpdate` event. Versions of the contract
+/// before Starknet v0.11 logged no block hash, so their state updates are not picked up.
+const LOG_STATE_UPDATE: &str = "LogStateUpdate(uint256,int256,uint256)";
+/// The signature of the core contract's `LogMessageToL2` event.
+const LOG_MESSAGE_TO_L2: &str = "LogMessageToL2(address,uint256,uint256,uint256[],uint256,uint256)";
+/// The signature of the core contract's `ConsumedMessageToL1` event.
+const CONSUMED_MESSAGE_TO_L1: &str = "ConsumedMessageToL1(uint256,address,uint256[])";
+
 impl EthereumClient {
     pub fn with_password(mut url: reqwest::Url, password: &str) -> anyhow::Result<Self> {
         url.set_password(Some(password))
@@ -44,14 +143,26 @@ impl EthereumClient {
         Self::new(url)
     }
 
+    /// Creates a client which connects over a WebSocket for `ws://` and `wss://` URLs, and
+    /// over HTTP otherwise.
     pub fn new(url: reqwest::Url) -> anyhow::Result<Self> {
-        Ok(Self {
-            http: reqwest::ClientBuilder::new().build()?,
-            url,
-        })
+        let transport = match url.scheme() {
+            "ws" | "wss" => Transport::Ws(ws::WsClient::new(url)),
+            _ => Transport::Http {
+                client: reqwest::ClientBuilder::new().build()?,
+                url,
+            },
+        };
+
+        Ok(Self { transport })
     }
 
-    async fn get_finalized_block_hash(&self) -> anyhow::Result<H256> {
+    /// Whether [EthereumApi::subscribe_new_heads] is supported by the endpoint's transport.
+    pub fn supports_subscriptions(&self) -> bool {
+        matches!(self.transport, Transport::Ws(_))
+    }
+
+    async fn get_finalized_block(&self) -> anyhow::Result<serde_json::Value> {
         self.call_ethereum(serde_json::json!({
             "jsonrpc": "2.0",
             "method": "eth_getBlockByNumber",
@@ -62,7 +173,12 @@ impl EthereumClient {
             "id": 0
         }))
         .await
-        .and_then(|value| get_h256(&value["hash"]))
+    }
+
+    async fn get_finalized_block_hash(&self) -> anyhow::Result<H256> {
+        self.get_finalized_block()
+            .await
+            .and_then(|value| get_h256(&value["hash"]))
     }
 
     async fn call_starknet_contract(
@@ -88,8 +204,48 @@ impl EthereumClient {
         .await
     }
 
+    /// Returns the logs of the event with the given signature emitted by the contract at
+    /// `address`, leaving out logs which were removed by a reorg.
+    async fn get_logs(
+        &self,
+        address: &H160,
+        from_block: u64,
+        to_block: u64,
+        signature: &str,
+    ) -> anyhow::Result<Vec<serde_json::Value>> {
+        let logs = self
+            .call_ethereum(serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "eth_getLogs",
+                "params": [
+                    {
+                        "address": format!("0x{}", hex::encode(address.as_bytes())),
+                        "fromBlock": format!("0x{from_block:x}"),
+                        "toBlock": format!("0x{to_block:x}"),
+                        "topics": [encode_event_topic(signature.as_bytes())]
+                    }
+                ],
+                "id": 0
+            }))
+            .await?;
+
+        let serde_json::Value::Array(logs) = logs else {
+            anyhow::bail!("Logs are not an array");
+        };
+
+        Ok(logs
+            .into_iter()
+            .filter(|log| log["removed"].as_bool() != Some(true))
+            .collect())
+    }
+
     async fn call_ethereum(&self, value: serde_json::Value) -> anyhow::Result<serde_json::Value> {
-        let res = self.http.post(self.url.clone()).json(&value).send().await?;
+        let (client, url) = match &self.transport {
+            Transport::Http { client, url } => (client, url),
+            Transport::Ws(ws) => return ws.call(value).await,
+        };
+
+        let res = client.post(url.clone()).json(&value).send().await?;
 
         let status = res.status();
         let (code, message) = (status.as_u16(), status.as_str());
@@ -99,6 +255,9 @@ impl EthereumClient {
         }
 
         let response: serde_json::Value = res.json().await?;
+        if let Some(error) = response.get("error") {
+            anyhow::bail!("Ethereum call failed: {error}");
+        }
         Ok(response["result"].clone())
     }
 }
@@ -147,6 +306,229 @@ impl EthereumApi for EthereumClient {
             x => EthereumChain::Other(x),
         })
     }
+
+    async fn get_finalized_block_number(&self) -> anyhow::Result<u64> {
+        self.get_finalized_block()
+            .await
+            .and_then(|value| get_u256(&value["number"]))
+            .map(|number| number.as_u64())
+    }
+
+    async fn get_state_update_logs(
+        &self,
+        address: &H160,
+        from_block: u64,
+        to_block: u64,
+    ) -> anyhow::Result<Vec<StateUpdateLog>> {
+        let logs = self
+            .get_logs(address, from_block, to_block, LOG_STATE_UPDATE)
+            .await?;
+
+        let mut updates = Vec::with_capacity(logs.len());
+        for log in logs {
+            if let Some(update) =
+                parse_state_update_log(&log).context("Parsing state update log")?
+            {
+                updates.push(update);
+            }
+        }
+
+        Ok(updates)
+    }
+
+    async fn get_message_to_l2_logs(
+        &self,
+        address: &H160,
+        from_block: u64,
+        to_block: u64,
+    ) -> anyhow::Result<Vec<MessageToL2Log>> {
+        self.get_logs(address, from_block, to_block, LOG_MESSAGE_TO_L2)
+            .await?
+            .iter()
+            .map(|log| parse_message_to_l2_log(log).context("Parsing message to L2 log"))
+            .collect()
+    }
+
+    async fn get_consumed_message_to_l1_logs(
+        &self,
+        address: &H160,
+        from_block: u64,
+        to_block: u64,
+    ) -> anyhow::Result<Vec<ConsumedMessageToL1Log>> {
+        self.get_logs(address, from_block, to_block, CONSUMED_MESSAGE_TO_L1)
+            .await?
+            .iter()
+            .map(|log| {
+                parse_consumed_message_to_l1_log(log).context("Parsing consumed message to L1 log")
+            })
+            .collect()
+    }
+
+    async fn subscribe_new_heads(&self) -> anyhow::Result<mpsc::Receiver<u64>> {
+        let Transport::Ws(ws) = &self.transport else {
+            anyhow::bail!("Subscriptions require a WebSocket endpoint");
+        };
+
+        let mut heads = ws
+            .subscribe(serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "eth_subscribe",
+                "params": ["newHeads"],
+                "id": 0
+            }))
+            .await?;
+
+        // Only the latest block is kept, since receivers are only interested in there being a
+        // new one.
+        let (tx, rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            while let Some(head) = heads.recv().await {
+                match get_u256(&head["number"]) {
+                    Ok(number) => {
+                        if let Err(mpsc::error::TrySendError::Closed(_)) =
+                            tx.try_send(number.as_u64())
+                        {
+                            break;
+                        }
+                    }
+                    Err(e) => tracing::debug!(reason=?e, "Ignoring malformed new L1 block"),
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+}
+
+/// Returns `None` for updates which do not refer to a block, which the core contract logs when
+/// it is initialized.
+fn parse_state_update_log(log: &serde_json::Value) -> anyhow::Result<Option<StateUpdateLog>> {
+    let data = log["data"].as_str().context("Missing log data")?;
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(data)).context("Decoding log data")?;
+    anyhow::ensure!(
+        data.len() == 96,
+        "Unexpected log data length {}",
+        data.len()
+    );
+
+    // The block number is a signed integer, which is negative if there is no block yet.
+    let block_number = U256::from_big_endian(&data[32..64]);
+    if block_number.bit(255) {
+        return Ok(None);
+    }
+
+    let update = EthereumStateUpdate {
+        state_root: StateCommitment(get_felt(H256::from_slice(&data[0..32]))?),
+        block_number: get_number(block_number)?,
+        block_hash: BlockHash(get_felt(H256::from_slice(&data[64..96]))?),
+    };
+
+    Ok(Some(StateUpdateLog {
+        update,
+        l1_block_number: get_u256(&log["blockNumber"])?.as_u64(),
+        l1_transaction_hash: get_h256(&log["transactionHash"])?,
+    }))
+}
+
+/// The message hash is the keccak hash of the sender, recipient, nonce, selector, payload length
+/// and payload, each encoded as 32 bytes.
+fn parse_message_to_l2_log(log: &serde_json::Value) -> anyhow::Result<MessageToL2Log> {
+    let topics = log["topics"].as_array().context("Missing log topics")?;
+    anyhow::ensure!(
+        topics.len() == 4,
+        "Unexpected number of log topics {}",
+        topics.len()
+    );
+    let from_address = get_h256(&topics[1])?;
+    let to_address = get_h256(&topics[2])?;
+    let selector = get_h256(&topics[3])?;
+
+    // The payload is encoded after the nonce and fee, at the offset given by the first word.
+    let data = log["data"].as_str().context("Missing log data")?;
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(data)).context("Decoding log data")?;
+    anyhow::ensure!(
+        data.len() >= 128 && data.len() % 32 == 0,
+        "Unexpected log data length {}",
+        data.len()
+    );
+    let offset = U256::from_big_endian(&data[0..32]);
+    anyhow::ensure!(
+        offset == U256::from(96u32),
+        "Unexpected payload offset {offset}"
+    );
+    let nonce = &data[32..64];
+    let payload = &data[96..];
+    let payload_len = U256::from_big_endian(&payload[0..32]);
+    anyhow::ensure!(
+        payload_len == U256::from(payload.len() / 32 - 1),
+        "Payload length {payload_len} does not match the log data"
+    );
+
+    let mut message = Vec::with_capacity(128 + payload.len());
+    message.extend_from_slice(from_address.as_bytes());
+    message.extend_from_slice(to_address.as_bytes());
+    message.extend_from_slice(nonce);
+    message.extend_from_slice(selector.as_bytes());
+    message.extend_from_slice(payload);
+
+    let mut message_hash = H256::zero();
+    keccak_hash::keccak_256(&message, message_hash.as_bytes_mut());
+
+    Ok(MessageToL2Log {
+        message_hash,
+        l1_block_number: get_u256(&log["blockNumber"])?.as_u64(),
+        l1_transaction_hash: get_h256(&log["transactionHash"])?,
+    })
+}
+
+/// The message hash is the keccak hash of the sender, recipient, payload length and payload,
+/// each encoded as 32 bytes.
+fn parse_consumed_message_to_l1_log(
+    log: &serde_json::Value,
+) -> anyhow::Result<ConsumedMessageToL1Log> {
+    let topics = log["topics"].as_array().context("Missing log topics")?;
+    anyhow::ensure!(
+        topics.len() == 3,
+        "Unexpected number of log topics {}",
+        topics.len()
+    );
+    let from_address = get_h256(&topics[1])?;
+    let to_address = get_h256(&topics[2])?;
+
+    // The payload is the only non-indexed field, so it is encoded right after its offset.
+    let data = log["data"].as_str().context("Missing log data")?;
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(data)).context("Decoding log data")?;
+    anyhow::ensure!(
+        data.len() >= 64 && data.len() % 32 == 0,
+        "Unexpected log data length {}",
+        data.len()
+    );
+    let offset = U256::from_big_endian(&data[0..32]);
+    anyhow::ensure!(
+        offset == U256::from(32u32),
+        "Unexpected payload offset {offset}"
+    );
+    let payload = &data[32..];
+    let payload_len = U256::from_big_endian(&payload[0..32]);
+    anyhow::ensure!(
+        payload_len == U256::from(payload.len() / 32 - 1),
+        "Payload length {payload_len} does not match the log data"
+    );
+
+    let mut message = Vec::with_capacity(64 + payload.len());
+    message.extend_from_slice(from_address.as_bytes());
+    message.extend_from_slice(to_address.as_bytes());
+    message.extend_from_slice(payload);
+
+    let mut message_hash = H256::zero();
+    keccak_hash::keccak_256(&message, message_hash.as_bytes_mut());
+
+    Ok(ConsumedMessageToL1Log {
+        message_hash,
+        l1_block_number: get_u256(&log["blockNumber"])?.as_u64(),
+        l1_log_index: get_u256(&log["logIndex"])?.as_u64(),
+        l1_transaction_hash: get_h256(&log["transactionHash"])?,
+    })
 }
 
 fn encode_ethereum_call_data(signature: &[u8]) -> String {
@@ -155,6 +537,12 @@ fn encode_ethereum_call_data(signature: &[u8]) -> String {
     format!("0x{}", hex::encode(&output[0..4]))
 }
 
+fn encode_event_topic(signature: &[u8]) -> String {
+    let mut output: [u8; 32] = Default::default();
+    keccak_hash::keccak_256(signature, &mut output[..]);
+    format!("0x{}", hex::encode(output))
+}
+
 fn get_h256(value: &serde_json::Value) -> anyhow::Result<H256> {
     use std::str::FromStr;
     value
@@ -311,6 +699,148 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_state_update_logs() -> anyhow::Result<()> {
+        let server = MockServer::start_async().await;
+
+        let mock_logs = server.mock(|when, then| {
+            when.path("/")
+                .method(POST)
+                .header("Content-type", "application/json")
+                .body(r#"{"id":0,"jsonrpc":"2.0","method":"eth_getLogs","params":[{"address":"0xc662c410c0ecf747543f5ba90660f6abebd9c8c4","fromBlock":"0x10","toBlock":"0x20","topics":["0xd342ddf7a308dec111745b00315c14b7efb2bdae570a6856e088ed0c65a3576c"]}]}"#);
+            then.status(200)
+                .header("Content-type", "application/json")
+                .body(r#"{"jsonrpc":"2.0","id":0,"result":[
+                    {"blockNumber":"0x11","transactionHash":"0x00000000000000000000000000000000000000000000000000000000000000aa","removed":false,"data":"0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000000"},
+                    {"blockNumber":"0x1f","transactionHash":"0x00000000000000000000000000000000000000000000000000000000000000bb","removed":false,"data":"0x00000000000000000000000000000000000000000000000000000000000012340000000000000000000000000000000000000000000000000000000000007eeb000000000000000000000000000000000000000000000000000000000000abcd"}
+                ]}"#);
+        });
+
+        let url = Url::parse(&server.url("/"))?;
+        let eth = EthereumClient::new(url)?;
+
+        let addr = H160::from_slice(&core_addr::MAINNET);
+        let logs = eth.get_state_update_logs(&addr, 0x10, 0x20).await?;
+
+        mock_logs.assert();
+        // The log of the contract's initialization refers to no block, and is skipped.
+        assert_eq!(
+            logs,
+            vec![StateUpdateLog {
+                update: EthereumStateUpdate {
+                    state_root: StateCommitment(Felt::from_hex_str("0x1234")?),
+                    block_number: BlockNumber::new_or_panic(0x7eeb),
+                    block_hash: BlockHash(Felt::from_hex_str("0xabcd")?),
+                },
+                l1_block_number: 0x1f,
+                l1_transaction_hash: H256::from_low_u64_be(0xbb),
+            }]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_message_to_l2_logs() -> anyhow::Result<()> {
+        let server = MockServer::start_async().await;
+
+        // Message taken from mainnet, sent by the StarkGate ETH bridge.
+        let mock_logs = server.mock(|when, then| {
+            when.path("/")
+                .method(POST)
+                .header("Content-type", "application/json")
+                .body(r#"{"id":0,"jsonrpc":"2.0","method":"eth_getLogs","params":[{"address":"0xc662c410c0ecf747543f5ba90660f6abebd9c8c4","fromBlock":"0x10","toBlock":"0x20","topics":["0xdb80dd488acf86d17c747445b0eabb5d57c541d3bd7b6b87af987858e5066b2b"]}]}"#);
+            then.status(200)
+                .header("Content-type", "application/json")
+                .body(r#"{"jsonrpc":"2.0","id":0,"result":[
+                    {"blockNumber":"0x1e","transactionHash":"0x00000000000000000000000000000000000000000000000000000000000000cc","removed":true,"topics":["0xdb80dd488acf86d17c747445b0eabb5d57c541d3bd7b6b87af987858e5066b2b","0x000000000000000000000000ae0ee0a63a2ce6baeeffe56e7714fb4efe48d419","0x073314940630fd6dcda0d772d4c972c4e0a9946bef9dabf4ef84eda8ef542b82","0x02d757788a8d8d6f21d1cd40bce38a8222d70654214e96ff95d8086e684fbee5"],"data":"0x0000000000000000000000000000000000000000000000000000000000000060000000000000000000000000000000000000000000000000000000000017824b000000000000000000000000000000000000000000000000002386f26fc10000000000000000000000000000000000000000000000000000000000000000000302c63ec1313901744d1321b93bda51418cc18998a1562d368960711367f7530f0000000000000000000000000000000000000000000000000011e14e1039c0000000000000000000000000000000000000000000000000000000000000000000"},
+                    {"blockNumber":"0x1f","transactionHash":"0x00000000000000000000000000000000000000000000000000000000000000cc","removed":false,"topics":["0xdb80dd488acf86d17c747445b0eabb5d57c541d3bd7b6b87af987858e5066b2b","0x000000000000000000000000ae0ee0a63a2ce6baeeffe56e7714fb4efe48d419","0x073314940630fd6dcda0d772d4c972c4e0a9946bef9dabf4ef84eda8ef542b82","0x02d757788a8d8d6f21d1cd40bce38a8222d70654214e96ff95d8086e684fbee5"],"data":"0x0000000000000000000000000000000000000000000000000000000000000060000000000000000000000000000000000000000000000000000000000017824b000000000000000000000000000000000000000000000000002386f26fc10000000000000000000000000000000000000000000000000000000000000000000302c63ec1313901744d1321b93bda51418cc18998a1562d368960711367f7530f0000000000000000000000000000000000000000000000000011e14e1039c0000000000000000000000000000000000000000000000000000000000000000000"}
+                ]}"#);
+        });
+
+        let url = Url::parse(&server.url("/"))?;
+        let eth = EthereumClient::new(url)?;
+
+        let addr = H160::from_slice(&core_addr::MAINNET);
+        let logs = eth.get_message_to_l2_logs(&addr, 0x10, 0x20).await?;
+
+        mock_logs.assert();
+        // The hash matches the message hash of the L1 handler transaction on L2.
+        assert_eq!(
+            logs,
+            vec![MessageToL2Log {
+                message_hash: H256::from_str(
+                    "573aeff3cf703775e8a76a27adee9e80f2ce558a6a38ec87e0249a8b175e5c1a"
+                )?,
+                l1_block_number: 0x1f,
+                l1_transaction_hash: H256::from_low_u64_be(0xcc),
+            }]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_consumed_message_to_l1_logs() -> anyhow::Result<()> {
+        let server = MockServer::start_async().await;
+
+        let mock_logs = server.mock(|when, then| {
+            when.path("/")
+                .method(POST)
+                .header("Content-type", "application/json")
+                .body(r#"{"id":0,"jsonrpc":"2.0","method":"eth_getLogs","params":[{"address":"0xc662c410c0ecf747543f5ba90660f6abebd9c8c4","fromBlock":"0x10","toBlock":"0x20","topics":["0x7a06c571aa77f34d9706c51e5d8122b5595aebeaa34233bfe866f22befb973b1"]}]}"#);
+            then.status(200)
+                .header("Content-type", "application/json")
+                .body(r#"{"jsonrpc":"2.0","id":0,"result":[{"blockNumber":"0x1f","logIndex":"0x5","transactionHash":"0x00000000000000000000000000000000000000000000000000000000000000dd","removed":false,"topics":["0x7a06c571aa77f34d9706c51e5d8122b5595aebeaa34233bfe866f22befb973b1","0x073314940630fd6dcda0d772d4c972c4e0a9946bef9dabf4ef84eda8ef542b82","0x000000000000000000000000ae0ee0a63a2ce6baeeffe56e7714fb4efe48d419"],"data":"0x00000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000003000000000000000000000000000000000000000000000000000000000000000002c63ec1313901744d1321b93bda51418cc18998a1562d368960711367f7530f0000000000000000000000000000000000000000000000000011e14e1039c000"}]}"#);
+        });
+
+        let url = Url::parse(&server.url("/"))?;
+        let eth = EthereumClient::new(url)?;
+
+        let addr = H160::from_slice(&core_addr::MAINNET);
+        let logs = eth
+            .get_consumed_message_to_l1_logs(&addr, 0x10, 0x20)
+            .await?;
+
+        mock_logs.assert();
+        assert_eq!(
+            logs,
+            vec![ConsumedMessageToL1Log {
+                message_hash: H256::from_str(
+                    "0f26d71f9ef69bbb8a7a2f66e7edd84dfa757460cf7223484f7b48e7a26027a5"
+                )?,
+                l1_block_number: 0x1f,
+                l1_log_index: 5,
+                l1_transaction_hash: H256::from_low_u64_be(0xdd),
+            }]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_error_response() -> anyhow::Result<()> {
+        let server = MockServer::start_async().await;
+
+        server.mock(|when, then| {
+            when.path("/").method(POST);
+            then.status(200)
+                .header("Content-type", "application/json")
+                .body(r#"{"jsonrpc":"2.0","id":0,"error":{"code":-32005,"message":"query returned more than 10000 results"}}"#);
+        });
+
+        let url = Url::parse(&server.url("/"))?;
+        let eth = EthereumClient::new(url)?;
+
+        let addr = H160::from_slice(&core_addr::MAINNET);
+        let error = eth
+            .get_state_update_logs(&addr, 0, 1_000_000)
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("more than 10000 results"),
+            "{error}"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_h256() {
         assert!(H256::from_str(
@@ -0,0 +1,387 @@
+//! JSON-RPC over a WebSocket connection, which unlike HTTP allows subscribing to notifications
+//! such as new blocks.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use futures::{SinkExt, StreamExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// A request which has not been answered for this long drops the connection, which is
+/// re-established by the next request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// The connection is pinged this often, and dropped if the previous ping was not answered by
+/// the time the next one is due. Otherwise a connection which silently stopped working would
+/// only be noticed once a request times out, and subscriptions would not notice at all.
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+/// Notifications are dropped while this many are waiting to be received by a subscriber.
+const SUBSCRIPTION_BUFFER: usize = 16;
+
+/// A client for a WebSocket endpoint. The connection is shared by all clones of the client, and
+/// is re-established by the next request once it has been closed or dropped because it stopped
+/// responding.
+#[derive(Clone, Debug)]
+pub(crate) struct WsClient {
+    url: reqwest::Url,
+    connection: Arc<tokio::sync::Mutex<Option<mpsc::UnboundedSender<Command>>>>,
+}
+
+#[derive(Debug)]
+enum Command {
+    Call {
+        request: serde_json::Value,
+        reply: oneshot::Sender<anyhow::Result<serde_json::Value>>,
+    },
+    Subscribe {
+        request: serde_json::Value,
+        notifications: mpsc::Sender<serde_json::Value>,
+        reply: oneshot::Sender<anyhow::Result<()>>,
+    },
+}
+
+impl WsClient {
+    pub fn new(url: reqwest::Url) -> Self {
+        Self {
+            url,
+            connection: Default::default(),
+        }
+    }
+
+    /// Sends a JSON-RPC request and returns its result.
+    pub async fn call(&self, request: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        let (reply, response) = oneshot::channel();
+        let connection = self.send(Command::Call { request, reply }).await?;
+        self.wait_for(connection, response).await
+    }
+
+    /// Sends an `eth_subscribe` request, and returns a channel receiving the result of each
+    /// notification. The channel is closed when the connection is closed, and the subscription
+    /// is cancelled once the receiver is dropped.
+    pub async fn subscribe(
+        &self,
+        request: serde_json::Value,
+    ) -> anyhow::Result<mpsc::Receiver<serde_json::Value>> {
+        let (notifications, receiver) = mpsc::channel(SUBSCRIPTION_BUFFER);
+        let (reply, response) = oneshot::channel();
+        let connection = self
+            .send(Command::Subscribe {
+                request,
+                notifications,
+                reply,
+            })
+            .await?;
+        self.wait_for(connection, response).await?;
+
+        Ok(receiver)
+    }
+
+    /// Sends the command over the current connection, connecting first if there is none, and
+    /// returns the connection used.
+    async fn send(&self, command: Command) -> anyhow::Result<mpsc::UnboundedSender<Command>> {
+        let mut connection = self.connection.lock().await;
+        let commands = match connection.as_ref() {
+            Some(commands) if !commands.is_closed() => commands,
+            _ => connection.insert(self.connect().await?),
+        };
+
+        commands
+            .send(command)
+            .map_err(|_| anyhow::anyhow!("WebSocket connection closed"))?;
+
+        Ok(commands.clone())
+    }
+
+    /// Waits for the response to a request sent over `connection`, and drops the connection if
+    /// it does not arrive in time.
+    async fn wait_for<T>(
+        &self,
+        connection: mpsc::UnboundedSender<Command>,
+        response: oneshot::Receiver<anyhow::Result<T>>,
+    ) -> anyhow::Result<T> {
+        match tokio::time::timeout(REQUEST_TIMEOUT, response).await {
+            Ok(result) => result.context("WebSocket connection closed")?,
+            Err(_) => {
+                // Dropping the last sender ends the connection's task, unless another request
+                // has already replaced the connection.
+                let mut current = self.connection.lock().await;
+                if current
+                    .as_ref()
+                    .is_some_and(|current| current.same_channel(&connection))
+                {
+                    tracing::debug!("WebSocket request timed out, dropping the connection");
+                    *current = None;
+                }
+                anyhow::bail!("WebSocket request timed out")
+            }
+        }
+    }
+
+    async fn connect(&self) -> anyhow::Result<mpsc::UnboundedSender<Command>> {
+        let mut request = self
+            .url
+            .as_str()
+            .into_client_request()
+            .context("Creating WebSocket request")?;
+        // Unlike reqwest, tungstenite does not authenticate using the URL's credentials.
+        if let Some(password) = self.url.password() {
+            let credentials = base64::encode(format!("{}:{password}", self.url.username()));
+            request.headers_mut().insert(
+                "Authorization",
+                format!("Basic {credentials}")
+                    .parse()
+                    .context("Creating authorization header")?,
+            );
+        }
+
+        let (socket, _) =
+            tokio::time::timeout(CONNECT_TIMEOUT, tokio_tungstenite::connect_async(request))
+                .await
+                .context("Connecting to WebSocket timed out")?
+                .context("Connecting to WebSocket")?;
+
+        let (commands, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(socket, receiver));
+
+        Ok(commands)
+    }
+}
+
+/// A request waiting for its response.
+enum Pending {
+    Call(oneshot::Sender<anyhow::Result<serde_json::Value>>),
+    Subscribe {
+        notifications: mpsc::Sender<serde_json::Value>,
+        reply: oneshot::Sender<anyhow::Result<()>>,
+    },
+}
+
+/// Sends the commands over the socket and dispatches the responses and notifications received,
+/// until the socket is closed or all clients are dropped. Pending requests and subscriptions
+/// are dropped along with the connection, which their receivers observe as it being closed.
+async fn run<S>(
+    socket: tokio_tungstenite::WebSocketStream<S>,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+) where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let (mut sink, mut stream) = socket.split();
+    let mut next_id = 0u64;
+    let mut pending = HashMap::new();
+    let mut subscriptions = HashMap::<String, mpsc::Sender<serde_json::Value>>::new();
+
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    ping.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick completes immediately, and there is no ping to answer yet.
+    ping.tick().await;
+    let mut awaiting_pong = false;
+
+    loop {
+        tokio::select! {
+            _ = ping.tick() => {
+                if awaiting_pong {
+                    tracing::debug!("WebSocket ping was not answered, dropping the connection");
+                    break;
+                }
+                if let Err(e) = sink.send(Message::Ping(Vec::new())).await {
+                    tracing::debug!(reason=%e, "Sending WebSocket ping failed");
+                    break;
+                }
+                awaiting_pong = true;
+            }
+            command = commands.recv() => {
+                let Some(command) = command else {
+                    break;
+                };
+
+                let (mut request, waiting) = match command {
+                    Command::Call { request, reply } => (request, Pending::Call(reply)),
+                    Command::Subscribe { request, notifications, reply } => {
+                        (request, Pending::Subscribe { notifications, reply })
+                    }
+                };
+                next_id += 1;
+                request["id"] = next_id.into();
+                if let Err(e) = sink.send(Message::Text(request.to_string())).await {
+                    tracing::debug!(reason=%e, "Sending WebSocket request failed");
+                    break;
+                }
+                pending.insert(next_id, waiting);
+            }
+            message = stream.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(Message::Pong(_))) => {
+                        awaiting_pong = false;
+                        continue;
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        tracing::debug!(reason=%e, "Receiving from WebSocket failed");
+                        break;
+                    }
+                };
+                let Ok(mut message) = serde_json::from_str::<serde_json::Value>(&text) else {
+                    tracing::debug!(%text, "Ignoring malformed WebSocket message");
+                    continue;
+                };
+
+                if message["method"] == "eth_subscription" {
+                    let mut params = message["params"].take();
+                    let result = params
+                        .get_mut("result")
+                        .map(serde_json::Value::take)
+                        .unwrap_or_default();
+                    let Some(id) = params["subscription"].as_str() else {
+                        continue;
+                    };
+                    let Some(notifications) = subscriptions.get(id) else {
+                        continue;
+                    };
+                    if let Err(mpsc::error::TrySendError::Closed(_)) = notifications.try_send(result) {
+                        subscriptions.remove(id);
+                        next_id += 1;
+                        let request = serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "method": "eth_unsubscribe",
+                            "params": [id],
+                            "id": next_id,
+                        });
+                        if sink.send(Message::Text(request.to_string())).await.is_err() {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+
+                let Some(waiting) = message["id"].as_u64().and_then(|id| pending.remove(&id)) else {
+                    continue;
+                };
+                let result = match message.get("error") {
+                    Some(error) => Err(anyhow::anyhow!("Ethereum call failed: {error}")),
+                    None => Ok(message["result"].take()),
+                };
+                match waiting {
+                    Pending::Call(reply) => {
+                        let _ = reply.send(result);
+                    }
+                    Pending::Subscribe { notifications, reply } => {
+                        let result = result.and_then(|id| match id {
+                            serde_json::Value::String(id) => {
+                                subscriptions.insert(id, notifications);
+                                Ok(())
+                            }
+                            other => Err(anyhow::anyhow!("Unexpected subscription ID {other}")),
+                        });
+                        let _ = reply.send(result);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::EthereumChain;
+
+    use super::*;
+    use crate::{EthereumApi, EthereumClient};
+
+    #[tokio::test]
+    async fn calls_and_new_heads() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // Answers a chain ID request and a subscription with a single notification, then
+        // closes the connection.
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(Message::Text(text))) = socket.next().await {
+                let request = serde_json::from_str::<serde_json::Value>(&text).unwrap();
+                let id = &request["id"];
+                match request["method"].as_str().unwrap() {
+                    "eth_chainId" => {
+                        let response =
+                            serde_json::json!({"jsonrpc": "2.0", "id": id, "result": "0x1"});
+                        socket
+                            .send(Message::Text(response.to_string()))
+                            .await
+                            .unwrap();
+                    }
+                    "eth_subscribe" => {
+                        let response =
+                            serde_json::json!({"jsonrpc": "2.0", "id": id, "result": "0xabc"});
+                        socket
+                            .send(Message::Text(response.to_string()))
+                            .await
+                            .unwrap();
+                        let notification = serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "method": "eth_subscription",
+                            "params": {"subscription": "0xabc", "result": {"number": "0x10"}}
+                        });
+                        socket
+                            .send(Message::Text(notification.to_string()))
+                            .await
+                            .unwrap();
+                        return;
+                    }
+                    method => panic!("Unexpected method {method}"),
+                }
+            }
+        });
+
+        let client = EthereumClient::new(reqwest::Url::parse(&url).unwrap()).unwrap();
+        assert!(client.supports_subscriptions());
+        assert_eq!(client.get_chain().await.unwrap(), EthereumChain::Mainnet);
+
+        let mut heads = client.subscribe_new_heads().await.unwrap();
+        assert_eq!(heads.recv().await, Some(0x10));
+        // The subscription ends with the connection.
+        assert_eq!(heads.recv().await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unanswered_request_drops_connection() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // Ignores the requests of the first connection, and answers those of the second one.
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut unresponsive = tokio_tungstenite::accept_async(stream).await.unwrap();
+            tokio::spawn(async move { while unresponsive.next().await.is_some() {} });
+
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(message)) = socket.next().await {
+                let Message::Text(text) = message else {
+                    continue;
+                };
+                let request = serde_json::from_str::<serde_json::Value>(&text).unwrap();
+                let response =
+                    serde_json::json!({"jsonrpc": "2.0", "id": request["id"], "result": "0x1"});
+                socket
+                    .send(Message::Text(response.to_string()))
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let client = WsClient::new(reqwest::Url::parse(&url).unwrap());
+        let request = serde_json::json!({"jsonrpc": "2.0", "method": "eth_chainId", "params": []});
+
+        let error = client.call(request.clone()).await.unwrap_err();
+        assert!(error.to_string().contains("timed out"), "{error}");
+
+        let result = client.call(request).await.unwrap();
+        assert_eq!(result, "0x1");
+    }
+}
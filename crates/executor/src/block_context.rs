@@ -8,21 +8,50 @@ use super::execution_state::ExecutionState;
 
 use super::felt::IntoStarkFelt;
 
-// NOTE: this is currently the same for _all_ networks
+// NOTE: this is currently the same for all public networks
 pub const ETH_FEE_TOKEN_ADDRESS: ContractAddress =
     contract_address!("0x049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7");
 pub const STRK_FEE_TOKEN_ADDRESS: ContractAddress =
     contract_address!("0x04718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d");
 
+/// Chain specific parameters used for execution.
+///
+/// The default matches the public Starknet networks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExecutionConfig {
+    pub eth_fee_token_address: ContractAddress,
+    pub strk_fee_token_address: ContractAddress,
+    pub invoke_tx_max_n_steps: u32,
+    pub validate_max_n_steps: u32,
+    pub max_recursion_depth: usize,
+    /// Fee weight of Cairo steps and of each builtin, keyed by resource name.
+    pub vm_resource_fee_costs: HashMap<String, f64>,
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            eth_fee_token_address: ETH_FEE_TOKEN_ADDRESS,
+            strk_fee_token_address: STRK_FEE_TOKEN_ADDRESS,
+            invoke_tx_max_n_steps: 3_000_000,
+            validate_max_n_steps: 1_000_000,
+            max_recursion_depth: 50,
+            vm_resource_fee_costs: default_resource_fee_costs(),
+        }
+    }
+}
+
 pub(super) fn construct_block_context(
     execution_state: &ExecutionState<'_>,
 ) -> anyhow::Result<BlockContext> {
+    let config = &execution_state.config;
+
     let eth_fee_token_address = starknet_api::core::ContractAddress(
-        PatriciaKey::try_from(ETH_FEE_TOKEN_ADDRESS.0.into_starkfelt())
+        PatriciaKey::try_from(config.eth_fee_token_address.0.into_starkfelt())
             .expect("ETH fee token address overflow"),
     );
     let strk_fee_token_address = starknet_api::core::ContractAddress(
-        PatriciaKey::try_from(STRK_FEE_TOKEN_ADDRESS.0.into_starkfelt())
+        PatriciaKey::try_from(config.strk_fee_token_address.0.into_starkfelt())
             .expect("STRK fee token address overflow"),
     );
 
@@ -49,14 +78,14 @@ pub(super) fn construct_block_context(
             strk_fee_token_address,
             eth_fee_token_address,
         },
-        vm_resource_fee_cost: Arc::new(default_resource_fee_costs()),
+        vm_resource_fee_cost: Arc::new(config.vm_resource_fee_costs.clone()),
         gas_prices: blockifier::block_context::GasPrices {
             eth_l1_gas_price: execution_state.header.eth_l1_gas_price.0,
             strk_l1_gas_price: execution_state.header.strk_l1_gas_price.0,
         },
-        invoke_tx_max_n_steps: 3_000_000,
-        validate_max_n_steps: 1_000_000,
-        max_recursion_depth: 50,
+        invoke_tx_max_n_steps: config.invoke_tx_max_n_steps,
+        validate_max_n_steps: config.validate_max_n_steps,
+        max_recursion_depth: config.max_recursion_depth,
     })
 }
 
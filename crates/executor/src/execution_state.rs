@@ -1,5 +1,8 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 
+use super::block_context::ExecutionConfig;
+use super::fork::ForkState;
 use super::pending::PendingStateReader;
 use super::state_reader::PathfinderStateReader;
 use crate::IntoStarkFelt;
@@ -11,7 +14,10 @@ use blockifier::{
         state_api::State,
     },
 };
-use pathfinder_common::{BlockHeader, ChainId, StateUpdate};
+use pathfinder_common::{
+    BlockHeader, ChainId, ContractAddress, StateUpdate, StorageAddress, StorageValue,
+};
+use primitive_types::U256;
 
 pub struct ExecutionState<'tx> {
     transaction: &'tx pathfinder_storage::Transaction<'tx>,
@@ -19,6 +25,10 @@ pub struct ExecutionState<'tx> {
     pub header: BlockHeader,
     execute_on_parent_state: bool,
     pending_state: Option<Arc<StateUpdate>>,
+    fork: Option<Arc<dyn ForkState>>,
+    pub(super) config: Arc<ExecutionConfig>,
+    fee_token_balances: Vec<(ContractAddress, U256)>,
+    pub(super) impersonated_accounts: HashSet<ContractAddress>,
 }
 
 impl<'tx> ExecutionState<'tx> {
@@ -40,6 +50,7 @@ impl<'tx> ExecutionState<'tx> {
             self.transaction,
             block_number,
             self.pending_state.is_some(),
+            self.fork.as_deref(),
         );
         let pending_state_reader = PendingStateReader::new(raw_reader, self.pending_state.clone());
         let mut cached_state =
@@ -50,10 +61,17 @@ impl<'tx> ExecutionState<'tx> {
         if self.execute_on_parent_state && self.header.number.get() >= 10 {
             let block_number_whose_hash_becomes_available =
                 pathfinder_common::BlockNumber::new_or_panic(self.header.number.get() - 10);
-            let block_hash = self
+            let block_hash = match self
                 .transaction
                 .block_hash(block_number_whose_hash_becomes_available.into())?
-                .context("Getting historical block hash")?;
+            {
+                Some(block_hash) => Some(block_hash),
+                None => match &self.fork {
+                    Some(fork) => fork.block_hash(block_number_whose_hash_becomes_available)?,
+                    None => None,
+                },
+            }
+            .context("Getting historical block hash")?;
 
             tracing::trace!(%block_number_whose_hash_becomes_available, %block_hash, "Setting historical block hash");
 
@@ -70,6 +88,18 @@ impl<'tx> ExecutionState<'tx> {
             )
         }
 
+        for (contract_address, key, value) in self.fee_token_balance_overrides() {
+            cached_state.set_storage_at(
+                starknet_api::core::ContractAddress(starknet_api::core::PatriciaKey::try_from(
+                    contract_address.0.into_starkfelt(),
+                )?),
+                starknet_api::state::StorageKey(starknet_api::core::PatriciaKey::try_from(
+                    key.0.into_starkfelt(),
+                )?),
+                value.0.into_starkfelt(),
+            )
+        }
+
         Ok((cached_state, block_context))
     }
 
@@ -85,6 +115,10 @@ impl<'tx> ExecutionState<'tx> {
             header,
             pending_state,
             execute_on_parent_state: true,
+            fork: None,
+            config: Default::default(),
+            fee_token_balances: Vec::new(),
+            impersonated_accounts: HashSet::new(),
         }
     }
 
@@ -100,6 +134,54 @@ impl<'tx> ExecutionState<'tx> {
             header,
             pending_state,
             execute_on_parent_state: false,
+            fork: None,
+            config: Default::default(),
+            fee_token_balances: Vec::new(),
+            impersonated_accounts: HashSet::new(),
+        }
+    }
+
+    /// Falls back to `fork` for state which is missing from the database.
+    pub fn with_fork(self, fork: Option<Arc<dyn ForkState>>) -> Self {
+        Self { fork, ..self }
+    }
+
+    /// Skips `__validate__` when simulating transactions sent by `account`, so that they can be
+    /// simulated without a valid signature.
+    pub fn with_impersonated_account(mut self, account: ContractAddress) -> Self {
+        self.impersonated_accounts.insert(account);
+        self
+    }
+
+    /// Uses the chain specific execution parameters in `config` instead of the defaults.
+    pub fn with_config(self, config: Arc<ExecutionConfig>) -> Self {
+        Self { config, ..self }
+    }
+
+    /// Sets the ETH and STRK fee token balance of `address` by overriding the fee token
+    /// contracts' storage.
+    pub fn with_fee_token_balance(mut self, address: ContractAddress, balance: U256) -> Self {
+        self.fee_token_balances.push((address, balance));
+        self
+    }
+
+    fn fee_token_balance_overrides(&self) -> Vec<(ContractAddress, StorageAddress, StorageValue)> {
+        let mut overrides = Vec::new();
+        for &(address, balance) in &self.fee_token_balances {
+            // Balances are stored as a u256 in two consecutive slots, low word first.
+            let low = StorageAddress::from_map_name_and_key(b"ERC20_balances", address.0);
+            let high = StorageAddress::new_or_panic(low.0 + pathfinder_crypto::Felt::from(1u64));
+            let low_value = StorageValue(balance.low_u128().into());
+            let high_value = StorageValue((balance >> 128).low_u128().into());
+
+            for token in [
+                self.config.eth_fee_token_address,
+                self.config.strk_fee_token_address,
+            ] {
+                overrides.push((token, low, low_value));
+                overrides.push((token, high, high_value));
+            }
         }
+        overrides
     }
 }
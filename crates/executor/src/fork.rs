@@ -0,0 +1,41 @@
+use pathfinder_common::{
+    BlockHash, BlockNumber, CasmHash, ClassHash, ContractAddress, ContractNonce, StorageAddress,
+    StorageValue,
+};
+
+/// State of a remote chain which execution falls back to for anything missing from the local
+/// database.
+///
+/// This allows executing transactions on top of a remote chain's state at a given block without
+/// having synced it. Implementations are expected to answer queries as of that block.
+pub trait ForkState: Send + Sync {
+    fn storage_value(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageAddress,
+    ) -> anyhow::Result<StorageValue>;
+
+    fn contract_nonce(&self, contract_address: ContractAddress) -> anyhow::Result<ContractNonce>;
+
+    /// Returns `None` if no contract is deployed at the address.
+    fn contract_class_hash(
+        &self,
+        contract_address: ContractAddress,
+    ) -> anyhow::Result<Option<ClassHash>>;
+
+    /// Returns `None` if the class has not been declared.
+    fn class_definition(&self, class_hash: ClassHash) -> anyhow::Result<Option<ForkClass>>;
+
+    /// Returns the hash of a block preceding the local chain.
+    fn block_hash(&self, block: BlockNumber) -> anyhow::Result<Option<BlockHash>>;
+}
+
+/// An executable class definition fetched from the [ForkState].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ForkClass {
+    Cairo(Vec<u8>),
+    Sierra {
+        casm_definition: Vec<u8>,
+        casm_hash: CasmHash,
+    },
+}
@@ -5,6 +5,7 @@ pub(crate) mod error;
 pub(crate) mod estimate;
 pub(crate) mod execution_state;
 pub(crate) mod felt;
+pub(crate) mod fork;
 pub(crate) mod lru_cache;
 pub(crate) mod pending;
 pub(crate) mod simulate;
@@ -12,13 +13,14 @@ pub(crate) mod state_reader;
 pub(crate) mod transaction;
 pub mod types;
 
-pub use block_context::ETH_FEE_TOKEN_ADDRESS;
+pub use block_context::{ExecutionConfig, ETH_FEE_TOKEN_ADDRESS, STRK_FEE_TOKEN_ADDRESS};
 pub use call::call;
 pub use class::{parse_casm_definition, parse_deprecated_class_definition};
 pub use error::{CallError, TransactionExecutionError};
 pub use estimate::estimate;
 pub use execution_state::ExecutionState;
 pub use felt::{IntoFelt, IntoStarkFelt};
+pub use fork::{ForkClass, ForkState};
 pub use simulate::{simulate, trace, TraceCache};
 
 // re-export blockifier transaction type since it's exposed on our API
@@ -49,6 +49,7 @@ pub fn simulate(
     skip_fee_charge: bool,
 ) -> Result<Vec<TransactionSimulation>, TransactionExecutionError> {
     let block_number = execution_state.header.number;
+    let impersonated_accounts = std::mem::take(&mut execution_state.impersonated_accounts);
 
     let (mut state, block_context) = execution_state.starknet_state()?;
 
@@ -66,14 +67,13 @@ pub fn simulate(
             blockifier::transaction::objects::FeeType::Eth => PriceUnit::Wei,
         };
 
+        let impersonated = super::transaction::sender_address(&transaction)
+            .is_some_and(|sender| impersonated_accounts.contains(&sender));
+        let validate = !skip_validate && !impersonated;
+
         let mut tx_state = CachedState::<_>::create_transactional(&mut state);
         let tx_info = transaction
-            .execute(
-                &mut tx_state,
-                &block_context,
-                !skip_fee_charge,
-                !skip_validate,
-            )
+            .execute(&mut tx_state, &block_context, !skip_fee_charge, validate)
             .and_then(|mut tx_info| {
                 // skipping fee charge in .execute() means that the fee isn't calculated, do that explicitly
                 // some other cases, like having max_fee=0 also lead to not calculating fees
@@ -104,6 +104,7 @@ pub fn simulate(
                         overall_fee: tx_info.actual_fee.0.into(),
                         unit,
                     },
+                    execution_resources: (&tx_info.actual_resources).into(),
                     trace: to_trace(transaction_type, tx_info, state_diff),
                 });
             }
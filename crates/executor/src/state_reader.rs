@@ -3,6 +3,7 @@ use pathfinder_common::{BlockNumber, ClassHash, StorageAddress, StorageValue};
 use pathfinder_crypto::Felt;
 use starknet_api::{hash::StarkFelt, StarknetApiError};
 
+use crate::fork::{ForkClass, ForkState};
 use crate::lru_cache::GLOBAL_CACHE;
 
 use super::felt::{IntoFelt, IntoStarkFelt};
@@ -14,6 +15,8 @@ pub(super) struct PathfinderStateReader<'tx> {
     // This flag makes it possible to find these classes -- essentially makes the state
     // reader look up classes which are not declared at a canonical block yet.
     ignore_block_number_for_classes: bool,
+    // State missing from the database is read from here instead.
+    fork: Option<&'tx dyn ForkState>,
 }
 
 impl<'tx> PathfinderStateReader<'tx> {
@@ -21,11 +24,13 @@ impl<'tx> PathfinderStateReader<'tx> {
         transaction: &'tx pathfinder_storage::Transaction<'tx>,
         block_number: Option<BlockNumber>,
         ignore_block_number_for_classes: bool,
+        fork: Option<&'tx dyn ForkState>,
     ) -> Self {
         Self {
             transaction,
             block_number,
             ignore_block_number_for_classes,
+            fork,
         }
     }
 
@@ -63,23 +68,7 @@ impl<'tx> PathfinderStateReader<'tx> {
         if let Some((definition_block_number, casm_definition)) =
             casm_definition.map_err(map_anyhow_to_state_err)?
         {
-            let casm_definition = String::from_utf8(casm_definition).map_err(|error| {
-                StateError::StateReadError(format!(
-                    "Class definition is not valid UTF-8: {}",
-                    error
-                ))
-            })?;
-
-            let casm_class =
-                blockifier::execution::contract_class::ContractClassV1::try_from_json_string(
-                    &casm_definition,
-                )
-                .map_err(StateError::ProgramError)?;
-
-            return Ok((
-                definition_block_number,
-                blockifier::execution::contract_class::ContractClass::V1(casm_class),
-            ));
+            return Ok((definition_block_number, parse_casm(casm_definition)?));
         }
 
         let definition = if self.ignore_block_number_for_classes {
@@ -96,23 +85,23 @@ impl<'tx> PathfinderStateReader<'tx> {
         if let Some((definition_block_number, definition)) =
             definition.map_err(map_anyhow_to_state_err)?
         {
-            let definition = String::from_utf8(definition).map_err(|error| {
-                StateError::StateReadError(format!(
-                    "Class definition is not valid UTF-8: {}",
-                    error
-                ))
-            })?;
-
-            let class =
-                blockifier::execution::contract_class::ContractClassV0::try_from_json_string(
-                    &definition,
-                )
-                .map_err(StateError::ProgramError)?;
+            return Ok((definition_block_number, parse_cairo(definition)?));
+        }
 
-            return Ok((
-                definition_block_number,
-                blockifier::execution::contract_class::ContractClass::V0(class),
-            ));
+        if let Some(fork) = self.fork {
+            tracing::trace!("Fetching class from fork");
+
+            // Not cached globally as there's no block number to associate the class with.
+            match fork
+                .class_definition(pathfinder_class_hash)
+                .map_err(map_anyhow_to_state_err)?
+            {
+                Some(ForkClass::Cairo(definition)) => return Ok((None, parse_cairo(definition)?)),
+                Some(ForkClass::Sierra {
+                    casm_definition, ..
+                }) => return Ok((None, parse_casm(casm_definition)?)),
+                None => {}
+            }
         }
 
         tracing::trace!("Class definition not found");
@@ -150,8 +139,15 @@ impl StateReader for PathfinderStateReader<'_> {
         let storage_val = self
             .transaction
             .storage_value(block_id, pathfinder_contract_address, storage_key)
-            .map_err(map_anyhow_to_state_err)?
-            .unwrap_or(StorageValue(Felt::ZERO));
+            .map_err(map_anyhow_to_state_err)?;
+
+        let storage_val = match (storage_val, self.fork) {
+            (Some(value), _) => value,
+            (None, Some(fork)) => fork
+                .storage_value(pathfinder_contract_address, storage_key)
+                .map_err(map_anyhow_to_state_err)?,
+            (None, None) => StorageValue(Felt::ZERO),
+        };
 
         tracing::trace!(storage_value=%storage_val, "Got storage value");
 
@@ -180,8 +176,15 @@ impl StateReader for PathfinderStateReader<'_> {
         let nonce = self
             .transaction
             .contract_nonce(pathfinder_contract_address, block_id)
-            .map_err(map_anyhow_to_state_err)?
-            .unwrap_or(pathfinder_common::ContractNonce::ZERO);
+            .map_err(map_anyhow_to_state_err)?;
+
+        let nonce = match (nonce, self.fork) {
+            (Some(nonce), _) => nonce,
+            (None, Some(fork)) => fork
+                .contract_nonce(pathfinder_contract_address)
+                .map_err(map_anyhow_to_state_err)?,
+            (None, None) => pathfinder_common::ContractNonce::ZERO,
+        };
 
         Ok(starknet_api::core::Nonce(nonce.0.into_starkfelt()))
     }
@@ -208,6 +211,13 @@ impl StateReader for PathfinderStateReader<'_> {
             .contract_class_hash(block_id, pathfinder_contract_address)
             .map_err(map_anyhow_to_state_err)?;
 
+        let class_hash = match (class_hash, self.fork) {
+            (None, Some(fork)) => fork
+                .contract_class_hash(pathfinder_contract_address)
+                .map_err(map_anyhow_to_state_err)?,
+            (class_hash, _) => class_hash,
+        };
+
         let Some(class_hash) = class_hash else {
             return Ok(starknet_api::core::ClassHash(
                 ClassHash::ZERO.0.into_starkfelt(),
@@ -268,7 +278,19 @@ impl StateReader for PathfinderStateReader<'_> {
             self.transaction.casm_hash_at(block_id, class_hash)
         };
 
-        let casm_hash = casm_hash.map_err(map_anyhow_to_state_err)?.ok_or_else(|| {
+        let casm_hash = match (casm_hash.map_err(map_anyhow_to_state_err)?, self.fork) {
+            (Some(casm_hash), _) => Some(casm_hash),
+            (None, Some(fork)) => match fork
+                .class_definition(class_hash)
+                .map_err(map_anyhow_to_state_err)?
+            {
+                Some(ForkClass::Sierra { casm_hash, .. }) => Some(casm_hash),
+                _ => None,
+            },
+            (None, None) => None,
+        };
+
+        let casm_hash = casm_hash.ok_or_else(|| {
             StateError::StateReadError("Error getting compiled class hash".to_owned())
         })?;
 
@@ -278,6 +300,38 @@ impl StateReader for PathfinderStateReader<'_> {
     }
 }
 
+fn parse_casm(
+    definition: Vec<u8>,
+) -> Result<blockifier::execution::contract_class::ContractClass, StateError> {
+    let definition = String::from_utf8(definition).map_err(|error| {
+        StateError::StateReadError(format!("Class definition is not valid UTF-8: {}", error))
+    })?;
+
+    let class =
+        blockifier::execution::contract_class::ContractClassV1::try_from_json_string(&definition)
+            .map_err(StateError::ProgramError)?;
+
+    Ok(blockifier::execution::contract_class::ContractClass::V1(
+        class,
+    ))
+}
+
+fn parse_cairo(
+    definition: Vec<u8>,
+) -> Result<blockifier::execution::contract_class::ContractClass, StateError> {
+    let definition = String::from_utf8(definition).map_err(|error| {
+        StateError::StateReadError(format!("Class definition is not valid UTF-8: {}", error))
+    })?;
+
+    let class =
+        blockifier::execution::contract_class::ContractClassV0::try_from_json_string(&definition)
+            .map_err(StateError::ProgramError)?;
+
+    Ok(blockifier::execution::contract_class::ContractClass::V0(
+        class,
+    ))
+}
+
 fn map_anyhow_to_state_err(error: anyhow::Error) -> StateError {
     tracing::error!(%error, "Internal error in execution state reader");
     StateError::StateReadError(error.to_string())
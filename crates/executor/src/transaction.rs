@@ -3,7 +3,7 @@ use blockifier::transaction::{
     transaction_execution::Transaction,
 };
 
-use pathfinder_common::TransactionHash;
+use pathfinder_common::{ContractAddress, TransactionHash};
 
 use super::felt::IntoFelt;
 
@@ -36,3 +36,23 @@ pub fn fee_type(transaction: &Transaction) -> FeeType {
         Transaction::L1HandlerTransaction(tx) => tx.fee_type(),
     }
 }
+
+/// The account a transaction is sent from, i.e. the account whose `__validate__` is run.
+pub fn sender_address(transaction: &Transaction) -> Option<ContractAddress> {
+    let address = match transaction {
+        Transaction::AccountTransaction(tx) => match tx {
+            blockifier::transaction::account_transaction::AccountTransaction::Declare(tx) => {
+                tx.tx().sender_address()
+            }
+            blockifier::transaction::account_transaction::AccountTransaction::DeployAccount(tx) => {
+                tx.contract_address
+            }
+            blockifier::transaction::account_transaction::AccountTransaction::Invoke(tx) => {
+                tx.tx.sender_address()
+            }
+        },
+        Transaction::L1HandlerTransaction(_) => return None,
+    };
+
+    Some(ContractAddress::new_or_panic(address.0.key().into_felt()))
+}
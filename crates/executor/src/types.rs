@@ -33,6 +33,8 @@ pub enum EntryPointType {
 pub struct TransactionSimulation {
     pub trace: TransactionTrace,
     pub fee_estimation: FeeEstimate,
+    /// Resources consumed by the transaction as a whole, i.e. what the fee was charged for.
+    pub execution_resources: ExecutionResources,
 }
 
 impl TransactionSimulation {
@@ -341,3 +343,29 @@ impl From<cairo_vm::vm::runners::cairo_runner::ExecutionResources> for Execution
         }
     }
 }
+
+impl From<&blockifier::transaction::objects::ResourcesMapping> for ExecutionResources {
+    /// Note that the resource mapping does not track memory holes, so these are always zero.
+    fn from(value: &blockifier::transaction::objects::ResourcesMapping) -> Self {
+        use cairo_vm::vm::runners::builtin_runner::{
+            BITWISE_BUILTIN_NAME, EC_OP_BUILTIN_NAME, HASH_BUILTIN_NAME, KECCAK_BUILTIN_NAME,
+            POSEIDON_BUILTIN_NAME, RANGE_CHECK_BUILTIN_NAME, SEGMENT_ARENA_BUILTIN_NAME,
+            SIGNATURE_BUILTIN_NAME,
+        };
+
+        let get = |name: &str| *value.0.get(name).unwrap_or(&0);
+
+        Self {
+            steps: get(blockifier::abi::constants::N_STEPS_RESOURCE),
+            memory_holes: 0,
+            range_check_builtin_applications: get(RANGE_CHECK_BUILTIN_NAME),
+            pedersen_builtin_applications: get(HASH_BUILTIN_NAME),
+            poseidon_builtin_applications: get(POSEIDON_BUILTIN_NAME),
+            ec_op_builtin_applications: get(EC_OP_BUILTIN_NAME),
+            ecdsa_builtin_applications: get(SIGNATURE_BUILTIN_NAME),
+            bitwise_builtin_applications: get(BITWISE_BUILTIN_NAME),
+            keccak_builtin_applications: get(KECCAK_BUILTIN_NAME),
+            segment_arena_builtin: get(SEGMENT_ARENA_BUILTIN_NAME),
+        }
+    }
+}
@@ -10,7 +10,7 @@
 //!   3. [Params](stage::Params) where you select the retry behavior.
 //!   4. [Final](stage::Final) where you select the REST operation type, which is then executed.
 use crate::metrics::{with_metrics, BlockTag, RequestMetadata};
-use pathfinder_common::{BlockId, ClassHash, TransactionHash};
+use pathfinder_common::{BlockId, ClassHash, ContractAddress, StorageAddress, TransactionHash};
 use starknet_gateway_types::error::SequencerError;
 
 const X_THROTTLING_BYPASS: &str = "X-Throttling-Bypass";
@@ -133,13 +133,16 @@ impl<'a> Request<'a, stage::Method> {
         add_transaction,
         get_block,
         get_class_by_hash,
+        get_class_hash_at,
         get_compiled_class_by_class_hash,
+        get_nonce,
         get_transaction,
         get_state_update,
         get_contract_addresses,
         get_block_traces,
         get_transaction_trace,
         get_signature,
+        get_storage_at,
     );
 
     /// Appends the given method to the request url.
@@ -184,6 +187,15 @@ impl<'a> Request<'a, stage::Params> {
         self.add_param("classHash", &class_hash.0.to_hex_str())
     }
 
+    pub fn with_contract_address(self, address: ContractAddress) -> Self {
+        self.add_param("contractAddress", &address.0.to_hex_str())
+    }
+
+    pub fn with_storage_address(self, key: StorageAddress) -> Self {
+        // The feeder gateway expects the key in decimal.
+        self.add_param("key", &pathfinder_serde::starkhash_to_dec_str(&key.0))
+    }
+
     pub fn with_optional_token(self, token: Option<&str>) -> Self {
         match token {
             Some(token) => self.add_param("token", token),
@@ -384,7 +396,7 @@ async fn parse_raw(response: reqwest::Response) -> Result<reqwest::Response, Seq
 pub trait RequestState {}
 
 /// Wrapper function to allow retrying sequencer queries in an exponential manner.
-async fn retry0<T, Fut, FutureFactory, Ret>(
+pub(crate) async fn retry0<T, Fut, FutureFactory, Ret>(
     future_factory: FutureFactory,
     retry_condition: Ret,
 ) -> Result<T, SequencerError>
@@ -404,7 +416,7 @@ where
 }
 
 /// Determines if an error is retryable or not.
-fn retry_condition(e: &SequencerError) -> bool {
+pub(crate) fn retry_condition(e: &SequencerError) -> bool {
     use reqwest::StatusCode;
     use tracing::{debug, error, info, warn};
 
@@ -438,6 +450,8 @@ fn retry_condition(e: &SequencerError) -> bool {
             true
         }
         SequencerError::StarknetError(_) => false,
+        // Downloading the block again is up to the caller, which already tried several times.
+        SequencerError::SourcesDisagree { .. } => false,
         SequencerError::InvalidStarknetErrorVariant => {
             error!(reason=%e, "Request failed, retrying");
             true
@@ -1,6 +1,7 @@
 //! Starknet L2 sequencer client.
 use pathfinder_common::{
-    BlockHash, BlockId, BlockNumber, Chain, ClassHash, StateUpdate, TransactionHash,
+    BlockHash, BlockId, BlockNumber, Chain, ClassHash, ContractAddress, ContractNonce, StateUpdate,
+    StorageAddress, StorageValue, TransactionHash,
 };
 use reqwest::Url;
 use starknet_gateway_types::trace::{BlockTrace, TransactionTrace};
@@ -9,6 +10,9 @@ use std::{fmt::Debug, result::Result, time::Duration};
 
 mod builder;
 mod metrics;
+mod sources;
+
+pub use sources::MultiSourceClient;
 
 #[allow(unused_variables)]
 #[mockall::automock]
@@ -111,6 +115,47 @@ pub trait GatewayApi: Sync {
     async fn signature(&self, block: BlockId) -> Result<reply::BlockSignature, SequencerError> {
         unimplemented!();
     }
+
+    async fn storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageAddress,
+        block: BlockId,
+    ) -> Result<StorageValue, SequencerError> {
+        unimplemented!();
+    }
+
+    async fn nonce_at(
+        &self,
+        contract_address: ContractAddress,
+        block: BlockId,
+    ) -> Result<ContractNonce, SequencerError> {
+        unimplemented!();
+    }
+
+    async fn class_hash_at(
+        &self,
+        contract_address: ContractAddress,
+        block: BlockId,
+    ) -> Result<ClassHash, SequencerError> {
+        unimplemented!();
+    }
+
+    async fn class_by_hash(
+        &self,
+        class_hash: ClassHash,
+        block: BlockId,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        unimplemented!();
+    }
+
+    async fn casm_by_hash(
+        &self,
+        class_hash: ClassHash,
+        block: BlockId,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        unimplemented!();
+    }
 }
 
 /// This is a **temporary** measure to keep the sync logic unchanged
@@ -217,6 +262,47 @@ impl<T: GatewayApi + Sync + Send> GatewayApi for std::sync::Arc<T> {
     async fn signature(&self, block: BlockId) -> Result<reply::BlockSignature, SequencerError> {
         self.as_ref().signature(block).await
     }
+
+    async fn storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageAddress,
+        block: BlockId,
+    ) -> Result<StorageValue, SequencerError> {
+        self.as_ref().storage_at(contract_address, key, block).await
+    }
+
+    async fn nonce_at(
+        &self,
+        contract_address: ContractAddress,
+        block: BlockId,
+    ) -> Result<ContractNonce, SequencerError> {
+        self.as_ref().nonce_at(contract_address, block).await
+    }
+
+    async fn class_hash_at(
+        &self,
+        contract_address: ContractAddress,
+        block: BlockId,
+    ) -> Result<ClassHash, SequencerError> {
+        self.as_ref().class_hash_at(contract_address, block).await
+    }
+
+    async fn class_by_hash(
+        &self,
+        class_hash: ClassHash,
+        block: BlockId,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        self.as_ref().class_by_hash(class_hash, block).await
+    }
+
+    async fn casm_by_hash(
+        &self,
+        class_hash: ClassHash,
+        block: BlockId,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        self.as_ref().casm_by_hash(class_hash, block).await
+    }
 }
 
 /// Starknet sequencer client using REST API.
@@ -572,6 +658,94 @@ impl GatewayApi for Client {
             .get()
             .await
     }
+
+    /// Gets the value of a storage slot at the given block.
+    #[tracing::instrument(skip(self))]
+    async fn storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageAddress,
+        block: BlockId,
+    ) -> Result<StorageValue, SequencerError> {
+        self.feeder_gateway_request()
+            .get_storage_at()
+            .with_contract_address(contract_address)
+            .with_storage_address(key)
+            .with_block(block)
+            .with_retry(self.retry)
+            .get()
+            .await
+    }
+
+    /// Gets the nonce of a contract at the given block.
+    #[tracing::instrument(skip(self))]
+    async fn nonce_at(
+        &self,
+        contract_address: ContractAddress,
+        block: BlockId,
+    ) -> Result<ContractNonce, SequencerError> {
+        self.feeder_gateway_request()
+            .get_nonce()
+            .with_contract_address(contract_address)
+            .with_block(block)
+            .with_retry(self.retry)
+            .get()
+            .await
+    }
+
+    /// Gets the class hash of a contract at the given block.
+    ///
+    /// Fails with [UninitializedContract](starknet_gateway_types::error::KnownStarknetErrorCode::UninitializedContract)
+    /// if no contract is deployed at the address.
+    #[tracing::instrument(skip(self))]
+    async fn class_hash_at(
+        &self,
+        contract_address: ContractAddress,
+        block: BlockId,
+    ) -> Result<ClassHash, SequencerError> {
+        self.feeder_gateway_request()
+            .get_class_hash_at()
+            .with_contract_address(contract_address)
+            .with_block(block)
+            .with_retry(self.retry)
+            .get()
+            .await
+    }
+
+    /// Gets the class declared under the class hash as of the given block.
+    ///
+    /// Fails with [UndeclaredClass](starknet_gateway_types::error::KnownStarknetErrorCode::UndeclaredClass)
+    /// if the class was not declared by then.
+    #[tracing::instrument(skip(self))]
+    async fn class_by_hash(
+        &self,
+        class_hash: ClassHash,
+        block: BlockId,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        self.feeder_gateway_request()
+            .get_class_by_hash()
+            .with_class_hash(class_hash)
+            .with_block(block)
+            .with_retry(self.retry)
+            .get_as_bytes()
+            .await
+    }
+
+    /// Gets the CASM of the class hash as of the given block.
+    #[tracing::instrument(skip(self))]
+    async fn casm_by_hash(
+        &self,
+        class_hash: ClassHash,
+        block: BlockId,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        self.feeder_gateway_request()
+            .get_compiled_class_by_class_hash()
+            .with_class_hash(class_hash)
+            .with_block(block)
+            .with_retry(self.retry)
+            .get_as_bytes()
+            .await
+    }
 }
 
 #[async_trait::async_trait]
@@ -1171,6 +1345,62 @@ mod tests {
         client.eth_contract_addresses().await.unwrap();
     }
 
+    mod contract_state {
+        use super::*;
+
+        #[tokio::test]
+        async fn storage_at() {
+            let (_jh, client) = setup([(
+                "/feeder_gateway/get_storage_at?contractAddress=0x123&key=255&blockNumber=10",
+                (r#""0xabc""#, 200),
+            )]);
+            let value = client
+                .storage_at(
+                    contract_address!("0x123"),
+                    storage_address!("0xff"),
+                    BlockNumber::new_or_panic(10).into(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(value, storage_value!("0xabc"));
+        }
+
+        #[tokio::test]
+        async fn nonce_at() {
+            let (_jh, client) = setup([(
+                "/feeder_gateway/get_nonce?contractAddress=0x123&blockNumber=10",
+                (r#""0x5""#, 200),
+            )]);
+            let nonce = client
+                .nonce_at(
+                    contract_address!("0x123"),
+                    BlockNumber::new_or_panic(10).into(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(nonce, contract_nonce!("0x5"));
+        }
+
+        #[tokio::test]
+        async fn class_hash_at_uninitialized_contract() {
+            let (_jh, client) = setup([(
+                "/feeder_gateway/get_class_hash_at?contractAddress=0x123&blockNumber=10",
+                response_from(KnownStarknetErrorCode::UninitializedContract),
+            )]);
+            let error = client
+                .class_hash_at(
+                    contract_address!("0x123"),
+                    BlockNumber::new_or_panic(10).into(),
+                )
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                SequencerError::StarknetError(e) => assert_eq!(e.code, KnownStarknetErrorCode::UninitializedContract.into())
+            );
+        }
+    }
+
     mod add_transaction {
         use super::*;
         use pathfinder_common::ContractAddress;
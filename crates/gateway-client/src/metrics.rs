@@ -164,6 +164,8 @@ pub async fn with_metrics<T>(
                 increment_failed(meta, REASON_RATE_LIMITING);
             }
             SequencerError::ReqwestError(_) => {}
+            // Only returned by the multi-source client, which is not metered itself.
+            SequencerError::SourcesDisagree { .. } => {}
         }
 
         e
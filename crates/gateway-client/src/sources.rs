@@ -0,0 +1,547 @@
+//! Syncing from several feeder gateways, such as the official one together with mirrors or
+//! proxies of it.
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use pathfinder_common::{
+    BlockHash, BlockId, BlockNumber, ClassHash, ContractAddress, ContractNonce, StateUpdate,
+    StorageAddress, StorageValue, TransactionHash,
+};
+use reqwest::Url;
+use starknet_gateway_types::error::{KnownStarknetErrorCode, SequencerError};
+use starknet_gateway_types::trace::{BlockTrace, TransactionTrace};
+use starknet_gateway_types::{reply, request};
+
+use crate::{Client, GatewayApi, GossipApi};
+
+/// How long to wait before downloading a block again after the sources disagreed about it.
+const DISAGREEMENT_RETRY_DELAY: Duration = Duration::from_secs(5);
+/// How many times a block is downloaded while the sources disagree about it, before giving up.
+const MAX_DISAGREEMENTS: usize = 3;
+
+/// A client for several feeder gateway sources.
+///
+/// Requests are sent to one source until it fails, after which the next source is used. If all
+/// sources fail, the request is retried like a [Client] request. Errors reported by Starknet
+/// itself are not failures of the source and are returned as is, except that a block which the
+/// active source does not have is requested from the other sources before reporting it as not
+/// found. Transactions are always submitted to the gateway of the first source.
+///
+/// Optionally, downloaded blocks are cross-checked: their hash and state commitment must match
+/// those of every other source which has the block. Only blocks downloaded by
+/// [state_update_with_block](GatewayApi::state_update_with_block) by number are cross-checked,
+/// which is how blocks are synced. Disagreements are logged together with the sources, and the
+/// block is downloaded again from the next source. If the sources still disagree after
+/// [MAX_DISAGREEMENTS] attempts, a [SequencerError::SourcesDisagree] naming them is returned.
+#[derive(Clone, Debug)]
+pub struct MultiSourceClient {
+    sources: Arc<[Source]>,
+    /// Index of the source requests are sent to.
+    active: Arc<AtomicUsize>,
+    retry: bool,
+    cross_check: bool,
+}
+
+#[derive(Debug)]
+struct Source {
+    /// Requests of the client are not retried, as they are retried across all sources instead.
+    client: Client,
+    /// The feeder gateway URL without credentials or query, for logging.
+    label: String,
+}
+
+impl MultiSourceClient {
+    /// Creates a client for the feeder gateway of `primary`, followed by the `mirrors`.
+    pub fn new(primary: Client, mirrors: &[Url]) -> anyhow::Result<Self> {
+        let retry = primary.retry;
+        let mut clients = vec![primary];
+        for mirror in mirrors {
+            let client = Client::with_urls(clients[0].gateway.clone(), mirror.clone())?;
+            clients.push(client);
+        }
+
+        Ok(Self::from_clients(clients, retry))
+    }
+
+    fn from_clients(clients: Vec<Client>, retry: bool) -> Self {
+        let sources = clients
+            .into_iter()
+            .map(|client| Source {
+                label: format!(
+                    "{}{}",
+                    client.feeder_gateway.origin().ascii_serialization(),
+                    client.feeder_gateway.path()
+                ),
+                client: Client {
+                    retry: false,
+                    ..client
+                },
+            })
+            .collect();
+
+        Self {
+            sources,
+            active: Default::default(),
+            retry,
+            cross_check: false,
+        }
+    }
+
+    /// Cross-checks the hash and state commitment of downloaded blocks between the sources.
+    pub fn with_cross_check(self, cross_check: bool) -> Self {
+        Self {
+            cross_check,
+            ..self
+        }
+    }
+
+    /// Use this method to disable retry logic for all __non write__ requests when testing.
+    pub fn disable_retry_for_tests(self) -> Self {
+        Self {
+            retry: false,
+            ..self
+        }
+    }
+
+    fn primary(&self) -> &Client {
+        &self.sources[0].client
+    }
+
+    async fn request<'a, T, F, Fut>(&'a self, retry: bool, request: F) -> Result<T, SequencerError>
+    where
+        F: Fn(&'a Client) -> Fut,
+        Fut: Future<Output = Result<T, SequencerError>>,
+    {
+        self.request_with_source(retry, request)
+            .await
+            .map(|(_, result)| result)
+    }
+
+    /// Sends the request to each source in turn, starting with the active one, until one of them
+    /// succeeds. Returns the result together with the source it was received from.
+    async fn request_with_source<'a, T, F, Fut>(
+        &'a self,
+        retry: bool,
+        request: F,
+    ) -> Result<(&'a Source, T), SequencerError>
+    where
+        F: Fn(&'a Client) -> Fut,
+        Fut: Future<Output = Result<T, SequencerError>>,
+    {
+        let request = &request;
+        let attempt = move || async move {
+            let mut error = None;
+            for _ in 0..self.sources.len() {
+                let index = self.active.load(Ordering::Relaxed);
+                let source = &self.sources[index];
+                match request(&source.client).await {
+                    Ok(result) => return Ok((source, result)),
+                    Err(SequencerError::StarknetError(e))
+                        if e.code == KnownStarknetErrorCode::BlockNotFound.into() =>
+                    {
+                        // The active source may be a mirror which lags behind the others, so the
+                        // block is only reported missing if no other source has it either.
+                        for offset in 1..self.sources.len() {
+                            let other = &self.sources[(index + offset) % self.sources.len()];
+                            if let Ok(result) = request(&other.client).await {
+                                return Ok((other, result));
+                            }
+                        }
+                        return Err(SequencerError::StarknetError(e));
+                    }
+                    Err(e @ SequencerError::StarknetError(_)) => return Err(e),
+                    Err(e) => {
+                        if self.sources.len() > 1 {
+                            tracing::debug!(source=%source.label, reason=%e, "Feeder gateway request failed, switching to the next source");
+                        }
+                        // Another request may have already switched away from this source.
+                        let next = (index + 1) % self.sources.len();
+                        let _ = self.active.compare_exchange(
+                            index,
+                            next,
+                            Ordering::Relaxed,
+                            Ordering::Relaxed,
+                        );
+                        error = Some(e);
+                    }
+                }
+            }
+
+            Err(error.expect("There is at least one source"))
+        };
+
+        match retry {
+            true => crate::builder::retry0(attempt, crate::builder::retry_condition).await,
+            false => attempt().await,
+        }
+    }
+
+    /// Returns the labels of the other sources which disagree with the state update received from
+    /// `source`. Sources which fail to provide the block, for example because they lag behind, are
+    /// not considered to disagree.
+    async fn disagreeing<'a>(
+        &'a self,
+        source: &Source,
+        number: BlockNumber,
+        state_update: &StateUpdate,
+    ) -> Vec<&'a str> {
+        let others = self
+            .sources
+            .iter()
+            .filter(|other| !std::ptr::eq(*other, source))
+            .map(|other| async move { (other, other.client.state_update(number.into()).await) });
+
+        let mut disagreeing = Vec::new();
+        for (other, result) in futures::future::join_all(others).await {
+            match result {
+                Ok(other_update)
+                    if other_update.block_hash == state_update.block_hash
+                        && other_update.state_commitment == state_update.state_commitment => {}
+                Ok(other_update) => {
+                    tracing::warn!(
+                        block=%number,
+                        source=%source.label,
+                        block_hash=%state_update.block_hash,
+                        state_commitment=%state_update.state_commitment,
+                        other_source=%other.label,
+                        other_block_hash=%other_update.block_hash,
+                        other_state_commitment=%other_update.state_commitment,
+                        "Feeder gateway sources disagree about block"
+                    );
+                    disagreeing.push(other.label.as_str());
+                }
+                Err(e) => {
+                    tracing::debug!(block=%number, source=%other.label, reason=%e, "Cross-checking block failed")
+                }
+            }
+        }
+
+        disagreeing
+    }
+
+    /// Makes the source after `source` the active one, unless another request already switched
+    /// away from it.
+    fn fail_over(&self, source: &Source) {
+        let Some(index) = self
+            .sources
+            .iter()
+            .position(|other| std::ptr::eq(other, source))
+        else {
+            return;
+        };
+        let next = (index + 1) % self.sources.len();
+        let _ = self
+            .active
+            .compare_exchange(index, next, Ordering::Relaxed, Ordering::Relaxed);
+    }
+}
+
+#[async_trait::async_trait]
+impl GatewayApi for MultiSourceClient {
+    async fn block(&self, block: BlockId) -> Result<reply::MaybePendingBlock, SequencerError> {
+        self.request(self.retry, |client| client.block(block)).await
+    }
+
+    async fn block_without_retry(
+        &self,
+        block: BlockId,
+    ) -> Result<reply::MaybePendingBlock, SequencerError> {
+        self.request(false, |client| client.block_without_retry(block))
+            .await
+    }
+
+    async fn block_header(
+        &self,
+        block: BlockId,
+    ) -> Result<(BlockNumber, BlockHash), SequencerError> {
+        self.request(self.retry, |client| client.block_header(block))
+            .await
+    }
+
+    async fn pending_class_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        self.request(self.retry, |client| {
+            client.pending_class_by_hash(class_hash)
+        })
+        .await
+    }
+
+    async fn pending_casm_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        self.request(self.retry, |client| client.pending_casm_by_hash(class_hash))
+            .await
+    }
+
+    async fn transaction(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<reply::TransactionStatus, SequencerError> {
+        self.request(self.retry, |client| client.transaction(transaction_hash))
+            .await
+    }
+
+    async fn state_update(&self, block: BlockId) -> Result<StateUpdate, SequencerError> {
+        self.request(self.retry, |client| client.state_update(block))
+            .await
+    }
+
+    async fn state_update_with_block(
+        &self,
+        block: BlockId,
+    ) -> Result<(reply::MaybePendingBlock, StateUpdate), SequencerError> {
+        let mut attempt = 1;
+        loop {
+            let (source, result) = self
+                .request_with_source(self.retry, |client| client.state_update_with_block(block))
+                .await?;
+
+            let number = match block {
+                BlockId::Number(number) if self.cross_check => number,
+                _ => return Ok(result),
+            };
+            let disagreeing = self.disagreeing(source, number, &result.1).await;
+            if disagreeing.is_empty() {
+                return Ok(result);
+            }
+
+            if attempt == MAX_DISAGREEMENTS {
+                return Err(SequencerError::SourcesDisagree {
+                    block: number,
+                    sources: format!("{} disagrees with {}", source.label, disagreeing.join(", ")),
+                });
+            }
+            attempt += 1;
+
+            // The active source may be the one in the wrong.
+            self.fail_over(source);
+            tokio::time::sleep(DISAGREEMENT_RETRY_DELAY).await;
+        }
+    }
+
+    async fn eth_contract_addresses(&self) -> Result<reply::EthContractAddresses, SequencerError> {
+        self.request(self.retry, |client| client.eth_contract_addresses())
+            .await
+    }
+
+    async fn add_invoke_transaction(
+        &self,
+        invoke: request::add_transaction::InvokeFunction,
+    ) -> Result<reply::add_transaction::InvokeResponse, SequencerError> {
+        self.primary().add_invoke_transaction(invoke).await
+    }
+
+    async fn add_declare_transaction(
+        &self,
+        declare: request::add_transaction::Declare,
+        token: Option<String>,
+    ) -> Result<reply::add_transaction::DeclareResponse, SequencerError> {
+        self.primary().add_declare_transaction(declare, token).await
+    }
+
+    async fn add_deploy_account(
+        &self,
+        deploy: request::add_transaction::DeployAccount,
+    ) -> Result<reply::add_transaction::DeployAccountResponse, SequencerError> {
+        self.primary().add_deploy_account(deploy).await
+    }
+
+    async fn block_traces(&self, block: BlockId) -> Result<BlockTrace, SequencerError> {
+        self.request(self.retry, |client| client.block_traces(block))
+            .await
+    }
+
+    async fn transaction_trace(
+        &self,
+        transaction: TransactionHash,
+    ) -> Result<TransactionTrace, SequencerError> {
+        self.request(self.retry, |client| client.transaction_trace(transaction))
+            .await
+    }
+
+    async fn signature(&self, block: BlockId) -> Result<reply::BlockSignature, SequencerError> {
+        self.request(self.retry, |client| client.signature(block))
+            .await
+    }
+
+    async fn storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageAddress,
+        block: BlockId,
+    ) -> Result<StorageValue, SequencerError> {
+        self.request(self.retry, |client| {
+            client.storage_at(contract_address, key, block)
+        })
+        .await
+    }
+
+    async fn nonce_at(
+        &self,
+        contract_address: ContractAddress,
+        block: BlockId,
+    ) -> Result<ContractNonce, SequencerError> {
+        self.request(self.retry, |client| {
+            client.nonce_at(contract_address, block)
+        })
+        .await
+    }
+
+    async fn class_hash_at(
+        &self,
+        contract_address: ContractAddress,
+        block: BlockId,
+    ) -> Result<ClassHash, SequencerError> {
+        self.request(self.retry, |client| {
+            client.class_hash_at(contract_address, block)
+        })
+        .await
+    }
+
+    async fn class_by_hash(
+        &self,
+        class_hash: ClassHash,
+        block: BlockId,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        self.request(self.retry, |client| client.class_by_hash(class_hash, block))
+            .await
+    }
+
+    async fn casm_by_hash(
+        &self,
+        class_hash: ClassHash,
+        block: BlockId,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        self.request(self.retry, |client| client.casm_by_hash(class_hash, block))
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl GossipApi for MultiSourceClient {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{response_from, setup};
+    use assert_matches::assert_matches;
+    use pathfinder_common::macro_prelude::*;
+    use starknet_gateway_test_fixtures::v0_12_2;
+
+    const CONTRACT_ADDRESSES: &str = r#"{"Starknet":"0xde29d060d45901fb19ed6c6e959eb22d8626708e","GpsStatementVerifier":"0xab43ba48c9edf4c2c4bb01237348d1d7b28ef168"}"#;
+    const STATE_UPDATE_WITH_BLOCK_PATH: &str =
+        "/feeder_gateway/get_state_update?blockNumber=350000&includeBlock=true";
+    const STATE_UPDATE_PATH: &str = "/feeder_gateway/get_state_update?blockNumber=350000";
+    const BLOCK: BlockNumber = BlockNumber::new_or_panic(350000);
+
+    fn state_update_with_block() -> (String, u16) {
+        let mut json: serde_json::Value =
+            serde_json::from_str(v0_12_2::state_update::PENDING_WITH_BLOCK).unwrap();
+        json["state_update"] = serde_json::from_str(v0_12_2::state_update::BLOCK_350000).unwrap();
+        (json.to_string(), 200)
+    }
+
+    fn forked_state_update() -> (String, u16) {
+        let mut json: serde_json::Value =
+            serde_json::from_str(v0_12_2::state_update::BLOCK_350000).unwrap();
+        json["block_hash"] = "0x1234".into();
+        (json.to_string(), 200)
+    }
+
+    #[tokio::test]
+    async fn switches_to_next_source_on_failure() {
+        let (_jh0, failing) = setup([("/feeder_gateway/get_contract_addresses", ("", 503))]);
+        let (_jh1, mirror) = setup([(
+            "/feeder_gateway/get_contract_addresses",
+            (CONTRACT_ADDRESSES, 200),
+        )]);
+        let client = MultiSourceClient::from_clients(vec![failing, mirror], false);
+
+        client.eth_contract_addresses().await.unwrap();
+        assert_eq!(client.active.load(Ordering::Relaxed), 1);
+
+        // The failed source is not tried again while the mirror works.
+        client.eth_contract_addresses().await.unwrap();
+        assert_eq!(client.active.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn starknet_errors_are_returned_as_is() {
+        let (_jh0, primary) = setup([(
+            STATE_UPDATE_PATH,
+            response_from(KnownStarknetErrorCode::BlockNotFound),
+        )]);
+        let (_jh1, mirror) = setup::<&str, &str, 0>([]);
+        let client = MultiSourceClient::from_clients(vec![primary, mirror], false);
+
+        let error = client.state_update(BLOCK.into()).await.unwrap_err();
+        assert_matches!(error, SequencerError::StarknetError(e) => {
+            assert_eq!(e.code, KnownStarknetErrorCode::BlockNotFound.into())
+        });
+        assert_eq!(client.active.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn block_missing_from_lagging_source_is_requested_from_others() {
+        let (_jh0, primary) = setup([(
+            STATE_UPDATE_PATH,
+            (v0_12_2::state_update::BLOCK_350000.to_owned(), 200),
+        )]);
+        let (_jh1, lagging) = setup([(
+            STATE_UPDATE_PATH,
+            response_from(KnownStarknetErrorCode::BlockNotFound),
+        )]);
+        let client = MultiSourceClient::from_clients(vec![primary, lagging], false);
+        client.active.store(1, Ordering::Relaxed);
+
+        let state_update = client.state_update(BLOCK.into()).await.unwrap();
+        assert_eq!(
+            state_update.block_hash,
+            block_hash!("0x6f7342a680d7f99bdfdd859f587c75299e7ffabe62c071ded3a6d8a34cb132c")
+        );
+        // The lagging source remains active, it is not failing.
+        assert_eq!(client.active.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn cross_check_ignores_lagging_sources() {
+        let (_jh0, primary) = setup([(STATE_UPDATE_WITH_BLOCK_PATH, state_update_with_block())]);
+        let (_jh1, agreeing) = setup([(
+            STATE_UPDATE_PATH,
+            (v0_12_2::state_update::BLOCK_350000.to_owned(), 200),
+        )]);
+        let (_jh2, lagging) = setup([(
+            STATE_UPDATE_PATH,
+            response_from(KnownStarknetErrorCode::BlockNotFound),
+        )]);
+        let client = MultiSourceClient::from_clients(vec![primary, agreeing, lagging], false)
+            .with_cross_check(true);
+
+        client.state_update_with_block(BLOCK.into()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn cross_check_withholds_disputed_block() {
+        let (_jh0, primary) = setup([(STATE_UPDATE_WITH_BLOCK_PATH, state_update_with_block())]);
+        let (_jh1, forked) = setup([(STATE_UPDATE_PATH, forked_state_update())]);
+        let client = MultiSourceClient::from_clients(vec![primary, forked], false);
+
+        // The mirror is not queried without cross-checking.
+        client.state_update_with_block(BLOCK.into()).await.unwrap();
+
+        let client = client.with_cross_check(true);
+        tokio::time::timeout(
+            Duration::from_millis(500),
+            client.state_update_with_block(BLOCK.into()),
+        )
+        .await
+        .unwrap_err();
+        // The block is downloaded again from the next source.
+        assert_eq!(client.active.load(Ordering::Relaxed), 1);
+    }
+}
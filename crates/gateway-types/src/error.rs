@@ -14,6 +14,12 @@ pub enum SequencerError {
     /// not informative enough or bloated
     #[error("error decoding response body: invalid error variant")]
     InvalidStarknetErrorVariant,
+    /// The feeder gateway sources kept disagreeing about a block while cross-checking it.
+    #[error("feeder gateway sources disagree about block {block}: {sources}")]
+    SourcesDisagree {
+        block: pathfinder_common::BlockNumber,
+        sources: String,
+    },
 }
 
 /// Used for deserializing specific Starknet sequencer error data.
@@ -349,6 +349,32 @@ pub mod transaction {
         pub to_address: EthereumAddress,
     }
 
+    impl L2ToL1Message {
+        /// The hash which identifies the message when it is consumed on L1.
+        pub fn calculate_message_hash(&self) -> H256 {
+            use sha3::{Digest, Keccak256};
+
+            let mut hash = Keccak256::new();
+
+            hash.update(self.from_address.0.as_be_bytes());
+            // Pad the ethereum address to 32 bytes to match a felt.
+            hash.update([0u8; 12]);
+            hash.update(self.to_address.0.as_bytes());
+
+            // Pad the u64 to 32 bytes to match a felt.
+            hash.update([0u8; 24]);
+            hash.update((self.payload.len() as u64).to_be_bytes());
+
+            for elem in &self.payload {
+                hash.update(elem.0.as_be_bytes());
+            }
+
+            let hash = <[u8; 32]>::from(hash.finalize());
+
+            hash.into()
+        }
+    }
+
     #[derive(Clone, Default, Debug, Deserialize, Serialize, PartialEq, Eq, Dummy)]
     #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
     pub enum ExecutionStatus {
@@ -1991,7 +2017,7 @@ mod tests {
     use crate::reply::state_update::{
         DeclaredSierraClass, DeployedContract, ReplacedClass, StorageDiff,
     };
-    use crate::reply::transaction::L1HandlerTransaction;
+    use crate::reply::transaction::{L1HandlerTransaction, L2ToL1Message};
 
     /// The aim of these tests is to make sure pathfinder is still able to correctly
     /// deserialize replies from the mainnet sequencer when it still is using some
@@ -2258,6 +2284,35 @@ mod tests {
         assert_eq!(message_hash, expected);
     }
 
+    #[test]
+    fn l2_to_l1_message_hash() {
+        use pathfinder_common::macro_prelude::*;
+        use pathfinder_common::EthereumAddress;
+
+        let message = L2ToL1Message {
+            from_address: contract_address!(
+                "0x73314940630fd6dcda0d772d4c972c4e0a9946bef9dabf4ef84eda8ef542b82"
+            ),
+            payload: vec![
+                l2_to_l1_message_payload_elem!("0x0"),
+                l2_to_l1_message_payload_elem!(
+                    "0x2c63ec1313901744d1321b93bda51418cc18998a1562d368960711367f7530f"
+                ),
+                l2_to_l1_message_payload_elem!("0x11e14e1039c000"),
+            ],
+            to_address: EthereumAddress(
+                primitive_types::H160::from_str("0xae0ee0a63a2ce6baeeffe56e7714fb4efe48d419")
+                    .unwrap(),
+            ),
+        };
+
+        let expected =
+            H256::from_str("0f26d71f9ef69bbb8a7a2f66e7edd84dfa757460cf7223484f7b48e7a26027a5")
+                .unwrap();
+
+        assert_eq!(message.calculate_message_hash(), expected);
+    }
+
     mod block_signature {
         use pathfinder_common::{
             block_commitment_signature_elem, block_hash, state_diff_commitment, BlockNumber,
@@ -1,13 +1,11 @@
-use std::collections::HashMap;
-
 use anyhow::Context;
 use pathfinder_common::{
     BlockNumber, ClassCommitment, ClassCommitmentLeafHash, ClassHash, SierraHash,
 };
 use pathfinder_crypto::Felt;
-use pathfinder_storage::{Node, Transaction};
+use pathfinder_storage::Transaction;
 
-use crate::tree::MerkleTree;
+use crate::tree::{MerkleTree, TrieUpdate};
 use pathfinder_common::hash::PoseidonHash;
 
 /// A [Patricia Merkle tree](MerkleTree) used to calculate commitments to Starknet's Sierra classes.
@@ -61,12 +59,33 @@ impl<'tx> ClassCommitmentTree<'tx> {
     }
 
     /// Commits the changes and calculates the new node hashes. Returns the new commitment and
-    /// any potentially newly created nodes.
-    pub fn commit(self) -> anyhow::Result<(ClassCommitment, HashMap<Felt, Node>)> {
+    /// the [TrieUpdate] containing any potentially newly created and removed nodes.
+    pub fn commit(self) -> anyhow::Result<(ClassCommitment, TrieUpdate)> {
         let update = self.tree.commit(&self.storage)?;
 
         let commitment = ClassCommitment(update.root);
-        Ok((commitment, update.nodes))
+        Ok((commitment, update))
+    }
+
+    /// Recomputes the class commitment of `block` from the stored trie and the class commitment
+    /// leaves. See [`MerkleTree::verify`].
+    pub fn verify(
+        tx: &'tx Transaction<'tx>,
+        block: BlockNumber,
+    ) -> anyhow::Result<ClassCommitment> {
+        let root = tx
+            .class_root_index(block)
+            .context("Querying class root index")?;
+        let Some(root) = root else {
+            return Ok(ClassCommitment::ZERO);
+        };
+
+        let storage = ClassStorage {
+            tx,
+            block: Some(block),
+        };
+
+        MerkleTree::<PoseidonHash, 251>::verify(root, &storage).map(ClassCommitment)
     }
 }
 
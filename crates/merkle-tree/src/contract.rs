@@ -5,7 +5,7 @@
 
 use crate::{
     merkle_node::InternalNode,
-    tree::{MerkleTree, Visit},
+    tree::{MerkleTree, TrieUpdate, Visit},
 };
 use anyhow::Context;
 use bitvec::{prelude::Msb0, slice::BitSlice};
@@ -16,8 +16,7 @@ use pathfinder_common::{
     StorageCommitment, StorageValue,
 };
 use pathfinder_crypto::Felt;
-use pathfinder_storage::{Node, Transaction};
-use std::collections::HashMap;
+use pathfinder_storage::Transaction;
 use std::ops::ControlFlow;
 
 /// A [Patricia Merkle tree](MerkleTree) used to calculate commitments to a Starknet contract's storage.
@@ -69,6 +68,29 @@ impl<'tx> ContractsStorageTree<'tx> {
         self
     }
 
+    /// Recomputes the storage root of `contract` at `block` from the stored trie and the storage
+    /// values at its leaves. See [`MerkleTree::verify`].
+    pub fn verify(
+        tx: &'tx Transaction<'tx>,
+        contract: ContractAddress,
+        block: BlockNumber,
+    ) -> anyhow::Result<ContractRoot> {
+        let root = tx
+            .contract_root_index(block, contract)
+            .context("Querying contract root index")?;
+        let Some(root) = root else {
+            return Ok(ContractRoot::ZERO);
+        };
+
+        let storage = ContractStorage {
+            tx,
+            block: Some(block),
+            contract,
+        };
+
+        MerkleTree::<PedersenHash, 251>::verify(root, &storage).map(ContractRoot)
+    }
+
     /// Generates a proof for `key`. See [`MerkleTree::get_proof`].
     pub fn get_proof(
         tx: &'tx Transaction<'tx>,
@@ -99,11 +121,11 @@ impl<'tx> ContractsStorageTree<'tx> {
     }
 
     /// Commits the changes and calculates the new node hashes. Returns the new commitment and
-    /// any potentially newly created nodes.
-    pub fn commit(self) -> anyhow::Result<(ContractRoot, HashMap<Felt, Node>)> {
+    /// the [TrieUpdate] containing any potentially newly created and removed nodes.
+    pub fn commit(self) -> anyhow::Result<(ContractRoot, TrieUpdate)> {
         let update = self.tree.commit(&self.storage)?;
         let commitment = ContractRoot(update.root);
-        Ok((commitment, update.nodes))
+        Ok((commitment, update))
     }
 
     /// See [`MerkleTree::dfs`]
@@ -166,11 +188,32 @@ impl<'tx> StorageCommitmentTree<'tx> {
     }
 
     /// Commits the changes and calculates the new node hashes. Returns the new commitment and
-    /// any potentially newly created nodes.
-    pub fn commit(self) -> anyhow::Result<(StorageCommitment, HashMap<Felt, Node>)> {
+    /// the [TrieUpdate] containing any potentially newly created and removed nodes.
+    pub fn commit(self) -> anyhow::Result<(StorageCommitment, TrieUpdate)> {
         let update = self.tree.commit(&self.storage)?;
         let commitment = StorageCommitment(update.root);
-        Ok((commitment, update.nodes))
+        Ok((commitment, update))
+    }
+
+    /// Recomputes the storage commitment of `block` from the stored trie and the contract state
+    /// hashes at its leaves. See [`MerkleTree::verify`].
+    pub fn verify(
+        tx: &'tx Transaction<'tx>,
+        block: BlockNumber,
+    ) -> anyhow::Result<StorageCommitment> {
+        let root = tx
+            .storage_root_index(block)
+            .context("Querying storage root index")?;
+        let Some(root) = root else {
+            return Ok(StorageCommitment::ZERO);
+        };
+
+        let storage = StorageTrieStorage {
+            tx,
+            block: Some(block),
+        };
+
+        MerkleTree::<PedersenHash, 251>::verify(root, &storage).map(StorageCommitment)
     }
 
     /// Generates a proof for the given `key`. See [`MerkleTree::get_proof`].
@@ -16,6 +16,8 @@ pub struct ContractStateUpdateResult {
     did_storage_updates: bool,
     // trie nodes to be inserted into the database
     nodes: HashMap<Felt, Node>,
+    // indices of trie nodes which are no longer part of the contract's trie
+    nodes_removed: Vec<u64>,
 }
 
 impl ContractStateUpdateResult {
@@ -28,7 +30,7 @@ impl ContractStateUpdateResult {
         if self.did_storage_updates {
             let root_index = if !self.root.0.is_zero() && !self.nodes.is_empty() {
                 let root_index = transaction
-                    .insert_contract_trie(self.root, &self.nodes)
+                    .insert_contract_trie(block, self.root, &self.nodes)
                     .context("Persisting contract trie")?;
                 Some(root_index)
             } else {
@@ -38,6 +40,10 @@ impl ContractStateUpdateResult {
             transaction
                 .insert_contract_root(block, self.contract_address, root_index)
                 .context("Inserting contract's root index")?;
+
+            transaction
+                .insert_contract_trie_removals(block, &self.nodes_removed)
+                .context("Inserting contract trie removals")?;
         }
 
         transaction
@@ -57,7 +63,7 @@ pub fn update_contract_state(
     block: BlockNumber,
 ) -> anyhow::Result<ContractStateUpdateResult> {
     // Load the contract tree and insert the updates.
-    let (new_root, nodes) = if !updates.is_empty() {
+    let (new_root, nodes, nodes_removed) = if !updates.is_empty() {
         let mut contract_tree = match block.parent() {
             Some(parent) => ContractsStorageTree::load(transaction, contract_address, parent)
                 .context("Loading contract storage tree")?
@@ -71,18 +77,18 @@ pub fn update_contract_state(
                 .set(*key, *value)
                 .context("Update contract storage tree")?;
         }
-        let (contract_root, nodes) = contract_tree
+        let (contract_root, trie_update) = contract_tree
             .commit()
             .context("Apply contract storage tree changes")?;
 
-        (contract_root, nodes)
+        (contract_root, trie_update.nodes, trie_update.nodes_removed)
     } else {
         let current_root = transaction
             .contract_root(block, contract_address)
             .context("Querying current contract root")?
             .unwrap_or_default();
 
-        (current_root, Default::default())
+        (current_root, Default::default(), Default::default())
     };
 
     let class_hash = if contract_address == ContractAddress::ONE {
@@ -115,6 +121,7 @@ pub fn update_contract_state(
         root: new_root,
         did_storage_updates: !updates.is_empty(),
         nodes,
+        nodes_removed,
     })
 }
 
@@ -65,6 +65,9 @@ pub struct MerkleTree<H: FeltHash, const HEIGHT: usize> {
     /// If enables, node hashes are verified as they are resolved. This allows
     /// testing for database corruption.
     verify_hashes: bool,
+    /// Indices of stored nodes which have been resolved into the in-memory tree. These
+    /// get replaced by new nodes on commit, and are therefore no longer part of the new tree.
+    nodes_removed: RefCell<Vec<u64>>,
 }
 
 /// The result of committing a [MerkleTree]. Contains the new root and any
@@ -74,6 +77,8 @@ pub struct TrieUpdate {
     /// New nodes added. Note that these may contain false positives if the
     /// mutations resulted in removing and then re-adding the same nodes within the tree.
     pub nodes: HashMap<Felt, Node>,
+    /// Indices of stored nodes which are part of the previous tree, but not of the new one.
+    pub nodes_removed: Vec<u64>,
 }
 
 impl<H: FeltHash, const HEIGHT: usize> MerkleTree<H, HEIGHT> {
@@ -84,6 +89,7 @@ impl<H: FeltHash, const HEIGHT: usize> MerkleTree<H, HEIGHT> {
             _hasher: std::marker::PhantomData,
             verify_hashes: false,
             leaves: Default::default(),
+            nodes_removed: Default::default(),
         }
     }
 
@@ -98,6 +104,7 @@ impl<H: FeltHash, const HEIGHT: usize> MerkleTree<H, HEIGHT> {
             _hasher: std::marker::PhantomData,
             verify_hashes: false,
             leaves: Default::default(),
+            nodes_removed: Default::default(),
         }
     }
 
@@ -114,6 +121,7 @@ impl<H: FeltHash, const HEIGHT: usize> MerkleTree<H, HEIGHT> {
             match &mut *root.borrow_mut() {
                 InternalNode::Unresolved(idx) => {
                     let mut root = self.resolve(storage, *idx, 0).context("Resolving root")?;
+                    self.nodes_removed.borrow_mut().push(*idx);
                     self.commit_subtree(&mut root, &mut added, storage, BitVec::new())?
                 }
                 other => self.commit_subtree(other, &mut added, storage, BitVec::new())?,
@@ -123,7 +131,11 @@ impl<H: FeltHash, const HEIGHT: usize> MerkleTree<H, HEIGHT> {
             Felt::ZERO
         };
 
-        Ok(TrieUpdate { root, nodes: added })
+        Ok(TrieUpdate {
+            root,
+            nodes: added,
+            nodes_removed: self.nodes_removed.take(),
+        })
     }
 
     /// Persists any changes in this subtree to storage.
@@ -560,6 +572,92 @@ impl<H: FeltHash, const HEIGHT: usize> MerkleTree<H, HEIGHT> {
         Ok(nodes)
     }
 
+    /// Recomputes the hash of every node in the stored tree with the given root, starting from
+    /// the leaves, and checks it against the node's stored hash.
+    ///
+    /// Returns the root hash. This reads the entire tree, which can take a long time for large
+    /// trees.
+    pub fn verify(root: u64, storage: &impl Storage) -> anyhow::Result<Felt> {
+        Self::verify_subtree(storage, root, &mut BitVec::new())
+    }
+
+    fn verify_subtree(
+        storage: &impl Storage,
+        index: u64,
+        path: &mut BitVec<u8, Msb0>,
+    ) -> anyhow::Result<Felt> {
+        let height = path.len();
+        anyhow::ensure!(
+            height < HEIGHT,
+            "Node {index} at height {height} exceeds the tree height {HEIGHT}"
+        );
+
+        let node = storage
+            .get(index)?
+            .with_context(|| format!("Node {index} at height {height} is missing"))?;
+
+        let leaf = |path: &BitSlice<u8, Msb0>| -> anyhow::Result<Felt> {
+            anyhow::ensure!(
+                path.len() == HEIGHT,
+                "Leaf of node {index} at height {height} is not at the bottom of the tree"
+            );
+            storage
+                .leaf(path)
+                .context("Querying leaf")?
+                .with_context(|| format!("Leaf of node {index} at height {height} is missing"))
+        };
+
+        let hash = match node {
+            StoredNode::Binary { left, right } => {
+                path.push(Direction::Left.into());
+                let left = Self::verify_subtree(storage, left, path)?;
+                path.pop();
+
+                path.push(Direction::Right.into());
+                let right = Self::verify_subtree(storage, right, path)?;
+                path.pop();
+
+                BinaryNode::calculate_hash::<H>(left, right)
+            }
+            StoredNode::Edge { child, path: edge } => {
+                path.extend_from_bitslice(&edge);
+                let child = Self::verify_subtree(storage, child, path)?;
+                path.truncate(height);
+
+                EdgeNode::calculate_hash::<H>(child, &edge)
+            }
+            StoredNode::LeafBinary => {
+                path.push(Direction::Left.into());
+                let left = leaf(path)?;
+                path.pop();
+
+                path.push(Direction::Right.into());
+                let right = leaf(path)?;
+                path.pop();
+
+                BinaryNode::calculate_hash::<H>(left, right)
+            }
+            StoredNode::LeafEdge { path: edge } => {
+                path.extend_from_bitslice(&edge);
+                let child = leaf(path)?;
+                path.truncate(height);
+
+                EdgeNode::calculate_hash::<H>(child, &edge)
+            }
+        };
+
+        let stored = storage
+            .hash(index)
+            .context("Querying node hash")?
+            .with_context(|| format!("Hash of node {index} at height {height} is missing"))?;
+        anyhow::ensure!(
+            stored == hash,
+            "Node {index} at height {height} has hash {stored} but its children hash to {hash}"
+        );
+
+        Ok(hash)
+    }
+
     /// Traverses from the current root towards destination node.
     /// Returns the list of nodes along the path.
     ///
@@ -591,6 +689,7 @@ impl<H: FeltHash, const HEIGHT: usize> MerkleTree<H, HEIGHT> {
                 Unresolved(idx) => {
                     let node = self.resolve(storage, idx, height)?;
                     current.swap(&RefCell::new(node));
+                    self.nodes_removed.borrow_mut().push(idx);
                     current
                 }
                 Binary(binary) => {
@@ -666,16 +765,19 @@ impl<H: FeltHash, const HEIGHT: usize> MerkleTree<H, HEIGHT> {
     /// This can occur when mutating the tree (e.g. deleting a child of a binary node), and is an illegal state
     /// (since edge nodes __must be__ maximal subtrees).
     fn merge_edges(&self, storage: &impl Storage, parent: &mut EdgeNode) -> anyhow::Result<()> {
-        let resolved_child = match &*parent.child.borrow() {
-            InternalNode::Unresolved(hash) => {
-                self.resolve(storage, *hash, parent.height + parent.path.len())?
-            }
-            other => other.clone(),
+        let (resolved_child, index) = match &*parent.child.borrow() {
+            InternalNode::Unresolved(idx) => (
+                self.resolve(storage, *idx, parent.height + parent.path.len())?,
+                Some(*idx),
+            ),
+            other => (other.clone(), None),
         };
 
         if let Some(child_edge) = resolved_child.as_edge().cloned() {
             parent.path.extend_from_bitslice(&child_edge.path);
             parent.child = child_edge.child;
+            // The child edge is merged into the parent and is no longer a node of its own.
+            self.nodes_removed.borrow_mut().extend(index);
         }
 
         Ok(())
@@ -845,7 +947,7 @@ mod tests {
         let update = tree.commit(storage).unwrap();
 
         let mut indices = HashMap::new();
-        let mut idx = storage.nodes.len();
+        let mut idx = storage.nodes.keys().max().map_or(0, |idx| idx + 1);
         for hash in update.nodes.keys() {
             indices.insert(*hash, idx as u64);
             idx += 1;
@@ -1377,6 +1479,64 @@ mod tests {
         }
     }
 
+    mod nodes_removed {
+        use super::*;
+        use std::collections::HashSet;
+
+        fn reachable(storage: &TestStorage, index: u64, nodes: &mut HashSet<u64>) {
+            nodes.insert(index);
+            match &storage.nodes.get(&index).expect("Node should exist").1 {
+                StoredNode::Binary { left, right } => {
+                    reachable(storage, *left, nodes);
+                    reachable(storage, *right, nodes);
+                }
+                StoredNode::Edge { child, .. } => reachable(storage, *child, nodes),
+                StoredNode::LeafBinary | StoredNode::LeafEdge { .. } => {}
+            }
+        }
+
+        #[test]
+        fn only_nodes_of_the_new_tree_remain() {
+            let mut storage = TestStorage::default();
+
+            let key0 = felt!("0x99cadc82").view_bits().to_bitvec();
+            let key1 = felt!("0x901823").view_bits().to_bitvec();
+            let key2 = felt!("0x8975").view_bits().to_bitvec();
+
+            let mut uut = TestTree::empty();
+            uut.set(&storage, key0.clone(), felt!("0x1")).unwrap();
+            uut.set(&storage, key1.clone(), felt!("0x2")).unwrap();
+            uut.set(&storage, key2.clone(), felt!("0x3")).unwrap();
+            let mut root = commit_and_persist(uut, &mut storage);
+
+            // Update, delete and re-insert leaves, which covers both replaced and merged nodes.
+            let updates = [
+                (key1.clone(), felt!("0x22")),
+                (key0.clone(), Felt::ZERO),
+                (key0, felt!("0x11")),
+            ];
+            for (key, value) in updates {
+                let mut uut = TestTree::new(root.1);
+                uut.set(&storage, key, value).unwrap();
+
+                let removed = uut.clone().commit(&storage).unwrap().nodes_removed;
+                assert!(!removed.is_empty());
+                root = commit_and_persist(uut, &mut storage);
+
+                for index in removed {
+                    storage
+                        .nodes
+                        .remove(&index)
+                        .expect("Removed node should exist");
+                }
+
+                let mut nodes = HashSet::new();
+                reachable(&storage, root.1, &mut nodes);
+                assert_eq!(nodes, storage.nodes.keys().copied().collect());
+            }
+        }
+    }
+
     mod real_world {
         use super::*;
         use pathfinder_common::felt;
@@ -1649,6 +1809,54 @@ mod tests {
         }
     }
 
+    mod verify {
+        use super::*;
+
+        #[test]
+        fn matches_committed_root() {
+            let mut storage = TestStorage::default();
+            let mut uut = TestTree::empty();
+            for i in 0..20u64 {
+                let key = Felt::from_u64(i * 997).view_bits().to_bitvec();
+                uut.set(&storage, key, Felt::from_u64(i + 1)).unwrap();
+            }
+            let (root, root_idx) = commit_and_persist(uut, &mut storage);
+
+            assert_eq!(TestTree::verify(root_idx, &storage).unwrap(), root);
+        }
+
+        #[test]
+        fn detects_corrupted_leaf() {
+            let mut storage = TestStorage::default();
+            let mut uut = TestTree::empty();
+            uut.set(&storage, felt!("0x1").view_bits().to_bitvec(), felt!("0xa"))
+                .unwrap();
+            uut.set(&storage, felt!("0x2").view_bits().to_bitvec(), felt!("0xb"))
+                .unwrap();
+            let (_, root_idx) = commit_and_persist(uut, &mut storage);
+
+            storage.leaves.insert(felt!("0x1"), felt!("0xc"));
+
+            TestTree::verify(root_idx, &storage).unwrap_err();
+        }
+
+        #[test]
+        fn detects_missing_node() {
+            let mut storage = TestStorage::default();
+            let mut uut = TestTree::empty();
+            uut.set(&storage, felt!("0x1").view_bits().to_bitvec(), felt!("0xa"))
+                .unwrap();
+            uut.set(&storage, felt!("0x2").view_bits().to_bitvec(), felt!("0xb"))
+                .unwrap();
+            let (_, root_idx) = commit_and_persist(uut, &mut storage);
+
+            let other = *storage.nodes.keys().find(|idx| **idx != root_idx).unwrap();
+            storage.nodes.remove(&other);
+
+            TestTree::verify(root_idx, &storage).unwrap_err();
+        }
+    }
+
     mod proofs {
         use crate::storage::Storage;
         use crate::tree::tests::commit_and_persist;
@@ -3,7 +3,7 @@ use std::num::NonZeroU32;
 use anyhow::Context;
 use pathfinder_common::{BlockHash, BlockNumber, Chain, ChainId, StarknetVersion};
 use pathfinder_crypto::Felt;
-use pathfinder_lib::state::block_hash::{verify_block_hash, VerifyResult};
+use pathfinder_lib::state::block_hash::{verify_block_hash, BlockHashMetaInfo, VerifyResult};
 use pathfinder_storage::{JournalMode, Storage};
 use starknet_gateway_types::reply::{Block, Status};
 
@@ -73,7 +73,12 @@ fn main() -> anyhow::Result<()> {
         };
         parent_block_hash = block_hash;
 
-        let result = verify_block_hash(&block, chain, chain_id, block_hash)?;
+        let result = verify_block_hash(
+            &block,
+            BlockHashMetaInfo::for_chain(chain),
+            chain_id,
+            block_hash,
+        )?;
         match result {
             VerifyResult::Match(_) => {}
             VerifyResult::NotVerifiable => println!(
@@ -4,7 +4,7 @@ use ipnet::IpNet;
 #[cfg(feature = "p2p")]
 use p2p::libp2p::Multiaddr;
 use pathfinder_common::AllowedOrigins;
-use pathfinder_storage::JournalMode;
+use pathfinder_storage::{JournalMode, TriePruneMode};
 use reqwest::Url;
 use std::collections::HashSet;
 use std::net::SocketAddr;
@@ -20,7 +20,11 @@ use pathfinder_common::consts::VERGEN_GIT_DESCRIBE;
 #[command(
     about = "A Starknet node implemented by Equilibrium Labs. Submit bug reports and issues at https://github.com/eqlabs/pathfinder."
 )]
+#[command(subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    subcommand: Option<Subcommand>,
+
     #[arg(
         long,
         value_name = "DIR", 
@@ -41,16 +45,32 @@ struct Cli {
 
     #[arg(
         long = "ethereum.url",
-        long_help = r"This should point to the HTTP RPC endpoint of your Ethereum entry-point, typically a local Ethereum client or a hosted gateway service such as Infura or Cloudflare.
+        long_help = r"This should point to the HTTP or WebSocket RPC endpoint of your Ethereum entry-point, typically a local Ethereum client or a hosted gateway service such as Infura or Cloudflare.
+
+WebSocket endpoints are notified of new L1 blocks instead of being polled.
+
+A comma separated list of endpoints can be given, in which case requests fail over to the next endpoint when one fails or is slow to respond. The password applies to all endpoints.
 
 Examples:
     infura: https://goerli.infura.io/v3/<PROJECT_ID>
-    geth:   https://localhost:8545",
-        value_name = "HTTP(s) URL",
+    geth:   https://localhost:8545
+    geth:   ws://localhost:8546
+    a list: https://localhost:8545,https://goerli.infura.io/v3/<PROJECT_ID>",
+        value_name = "URL LIST",
         value_hint = clap::ValueHint::Url,
+        value_delimiter = ',',
         env = "PATHFINDER_ETHEREUM_API_URL", 
     )]
-    ethereum_url: Url,
+    ethereum_url: Vec<Url>,
+
+    #[arg(
+        long = "ethereum.quorum",
+        long_help = "The number of Ethereum endpoints which must agree on the finalized L1 state before it is used. Must not exceed the number of endpoints given by --ethereum.url.",
+        value_name = "NUMBER",
+        env = "PATHFINDER_ETHEREUM_API_QUORUM",
+        default_value = "1"
+    )]
+    ethereum_quorum: NonZeroUsize,
 
     #[arg(
         long = "http-rpc",
@@ -128,6 +148,64 @@ Examples:
     )]
     poll_interval: std::num::NonZeroU64,
 
+    #[arg(
+        long = "sync.download-window",
+        long_help = "The number of blocks which are downloaded concurrently while catching up to \
+                     the latest block. A value of 1 downloads one block at a time.",
+        default_value = "8",
+        env = "PATHFINDER_SYNC_DOWNLOAD_WINDOW"
+    )]
+    download_window: NonZeroUsize,
+
+    #[arg(
+        long = "sync.feeder-gateway-mirrors",
+        long_help = "Additional feeder gateways to sync from, such as mirrors or proxies of the \
+                     network's feeder gateway. Requests switch to the next feeder gateway whenever \
+                     one fails.",
+        value_name = "URL LIST",
+        value_hint = clap::ValueHint::Url,
+        value_delimiter = ',',
+        env = "PATHFINDER_SYNC_FEEDER_GATEWAY_MIRRORS"
+    )]
+    feeder_gateway_mirrors: Vec<Url>,
+
+    #[arg(
+        long = "sync.cross-check-mirrors",
+        long_help = "Only sync a block once every feeder gateway mirror which has it agrees on its \
+                     hash and state commitment. Disagreements are logged together with the \
+                     feeder gateways involved, and the block is downloaded again from the next \
+                     feeder gateway. Sync fails and restarts if they still disagree after three \
+                     attempts. Only synced blocks are cross-checked, other requests are not.",
+        action = clap::ArgAction::Set,
+        default_value = "false",
+        env = "PATHFINDER_SYNC_CROSS_CHECK_MIRRORS",
+        value_name = "BOOL"
+    )]
+    cross_check_mirrors: bool,
+
+    #[arg(
+        long = "sync.stop-at-block",
+        value_name = "BLOCK NUMBER",
+        long_help = "Stop syncing once this block has been synced, and keep serving it as the \
+                     latest block. Pending data is not polled, and a reorg of the chain at or \
+                     below this block is reported as an error instead of being applied. Not \
+                     supported with p2p sync.",
+        env = "PATHFINDER_SYNC_STOP_AT_BLOCK"
+    )]
+    stop_at_block: Option<u64>,
+
+    #[arg(
+        long = "sync.execute-pending",
+        long_help = "Execute the transactions of the pending block locally, and serve the \
+                     resulting receipts and state diff for pending queries instead of the \
+                     feeder gateway's. Differences to the feeder gateway's data are logged.",
+        action = clap::ArgAction::Set,
+        default_value = "false",
+        env = "PATHFINDER_SYNC_EXECUTE_PENDING",
+        value_name = "BOOL"
+    )]
+    execute_pending: bool,
+
     #[arg(
         long = "color",
         long_help = "This flag controls when to use colors in the output logs.",
@@ -182,6 +260,20 @@ This should only be enabled for debugging purposes as it adds substantial proces
     )]
     rpc_batch_concurrency_limit: NonZeroUsize,
 
+    #[arg(
+        long = "rpc.backup-directory",
+        long_help = "Enables the pathfinder_backupDatabase RPC method, which writes database backups \
+            into this directory. The method is disabled if this is not set.
+
+WARNING: the method is not authenticated and is available to anyone who can reach the RPC \
+            server. Only enable it if the RPC server is bound to a local or otherwise trusted \
+            address, since each backup is a full copy of the database.",
+        value_name = "DIR",
+        value_hint = clap::ValueHint::DirPath,
+        env = "PATHFINDER_RPC_BACKUP_DIRECTORY"
+    )]
+    rpc_backup_directory: Option<PathBuf>,
+
     #[arg(
         long = "sync.enable",
         long_help = "Enable syncing the chain",
@@ -191,6 +283,18 @@ This should only be enabled for debugging purposes as it adds substantial proces
     )]
     is_sync_enabled: bool,
 
+    #[arg(
+        long = "read-only",
+        long_help = "Run as a read-only replica of a database owned by another pathfinder node. \
+            The database is neither migrated nor written to, and sync is disabled. RPC is still \
+            served, and new blocks are picked up by polling the database while the pending block \
+            is polled from the gateway. Ethereum and p2p are not used, so --network must be given.",
+        env = "PATHFINDER_READ_ONLY",
+        default_value = "false",
+        action=ArgAction::Set
+    )]
+    read_only: bool,
+
     #[arg(
         long = "rpc.enable",
         long_help = "Enable serving RPC API",
@@ -218,6 +322,31 @@ This should only be enabled for debugging purposes as it adds substantial proces
     )]
     event_bloom_filter_cache_size: std::num::NonZeroUsize,
 
+    #[arg(
+        long = "storage.state-tries",
+        long_help = "Configures how much of the state trie history is stored. \
+            'archive' keeps the tries of all blocks, while 'pruned(N)' keeps only the tries of \
+            the latest block and the N blocks before it. Pruning greatly reduces the database size, \
+            but reorgs deeper than N blocks cannot be handled and storage proofs are only \
+            available for the retained blocks. Pruning can only be enabled for a new database. \
+            Defaults to the mode the database was created with, or 'archive' for a new database.",
+        value_name = "archive|pruned(N)",
+        env = "PATHFINDER_STORAGE_STATE_TRIES",
+        value_parser = parse_state_tries
+    )]
+    state_tries: Option<TriePruneMode>,
+
+    #[arg(
+        long = "storage.block-body-history",
+        long_help = "Keeps the transactions, receipts and events of only the latest block and the N \
+            blocks before it. Block headers, state updates and signatures are kept for all blocks. \
+            RPC methods and peers requesting the pruned data are answered with a 'pruned' error. \
+            Block bodies of all blocks are kept if this is not set.",
+        value_name = "N",
+        env = "PATHFINDER_STORAGE_BLOCK_BODY_HISTORY"
+    )]
+    block_body_history: Option<u64>,
+
     #[arg(
         long = "rpc.get-events-max-blocks-to-scan",
         long_help = "The number of blocks to scan for events when querying for events. \
@@ -237,6 +366,335 @@ This should only be enabled for debugging purposes as it adds substantial proces
     get_events_max_uncached_bloom_filters_to_load: std::num::NonZeroUsize,
 }
 
+/// One-off commands which run instead of the node.
+#[derive(clap::Subcommand)]
+pub enum Subcommand {
+    /// Re-executes historical blocks and reports any differences against the stored receipts.
+    Replay(ReplayConfig),
+    /// Database maintenance commands.
+    #[command(subcommand)]
+    Db(DbCommand),
+}
+
+#[derive(clap::Subcommand)]
+pub enum DbCommand {
+    /// Writes a consistent copy of a database, which may be in use by a running node.
+    Backup(DbBackupConfig),
+    /// Exports the state at a block to a snapshot, from which a new database can be initialized.
+    ExportSnapshot(DbExportSnapshotConfig),
+    /// Initializes a new database from a snapshot, verifying its state commitment.
+    ImportSnapshot(DbImportSnapshotConfig),
+    /// Checks the consistency of the data stored for a range of blocks.
+    Verify(DbVerifyConfig),
+    /// Removes all blocks after the given block. The node must not be running.
+    RevertTo(DbRevertConfig),
+    /// Reports the space used by each table and category of data.
+    Stats(DbStatsConfig),
+}
+
+#[derive(clap::Args)]
+pub struct DbBackupConfig {
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path to the database to back up"
+    )]
+    pub database: PathBuf,
+
+    #[arg(
+        value_name = "DESTINATION",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path of the backup file, which must not exist yet"
+    )]
+    pub destination: PathBuf,
+
+    #[arg(
+        long = "pages-per-step",
+        long_help = "The number of database pages copied at once",
+        default_value = "1024"
+    )]
+    pub pages_per_step: std::num::NonZeroU32,
+
+    #[arg(
+        long = "pause",
+        value_name = "MILLISECONDS",
+        long_help = "Time to wait between copying steps. Smaller steps and longer pauses reduce the load on a running node, at the cost of a slower backup.",
+        default_value = "10"
+    )]
+    pub pause_ms: u64,
+}
+
+#[derive(clap::Args)]
+pub struct DbExportSnapshotConfig {
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path to the database to export the state from"
+    )]
+    pub database: PathBuf,
+
+    #[arg(
+        long,
+        value_name = "BLOCK_NUMBER",
+        long_help = "The block whose state is exported. Defaults to the latest block in the database."
+    )]
+    pub block: Option<u64>,
+
+    #[arg(
+        value_name = "OUTPUT",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path of the snapshot file, which must not exist yet"
+    )]
+    pub output: PathBuf,
+}
+
+#[derive(clap::Args)]
+pub struct DbImportSnapshotConfig {
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path of the database to create, which must not exist yet"
+    )]
+    pub database: PathBuf,
+
+    #[arg(
+        value_name = "SNAPSHOT",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path to the snapshot file to import"
+    )]
+    pub snapshot: PathBuf,
+}
+
+#[derive(clap::Args)]
+pub struct DbVerifyConfig {
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path to the database to verify"
+    )]
+    pub database: PathBuf,
+
+    #[arg(
+        long = "from",
+        value_name = "BLOCK",
+        long_help = "First block to verify",
+        default_value = "0"
+    )]
+    pub from: u64,
+
+    #[arg(
+        long = "to",
+        value_name = "BLOCK",
+        long_help = "Last block to verify (inclusive). Defaults to the latest block in the database."
+    )]
+    pub to: Option<u64>,
+
+    #[arg(
+        long = "output",
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "File to which the report is written, as one JSON object per line for each inconsistency found"
+    )]
+    pub output: PathBuf,
+
+    #[arg(
+        long = "tries",
+        long_help = "Recompute the storage and class tries of the last block, and the storage tries of all its contracts, from their leaves, verifying every node's hash. This reads the entire tries and can take a long time.",
+        action = clap::ArgAction::Set,
+        default_value = "false"
+    )]
+    pub tries: bool,
+
+    #[arg(
+        long = "hints",
+        long_help = "Include a hint on how to repair each inconsistency in the report",
+        action = clap::ArgAction::Set,
+        default_value = "false"
+    )]
+    pub hints: bool,
+}
+
+#[derive(clap::Args)]
+pub struct DbRevertConfig {
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path to the database to revert"
+    )]
+    pub database: PathBuf,
+
+    #[arg(
+        value_name = "BLOCK",
+        long_help = "The block to revert to, which becomes the latest block in the database"
+    )]
+    pub block: u64,
+
+    #[arg(
+        long = "dry-run",
+        long_help = "Only print a summary of what would be removed, without changing the database",
+        action = clap::ArgAction::Set,
+        default_value = "false"
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long = "yes",
+        long_help = "Revert without asking for confirmation",
+        action = clap::ArgAction::Set,
+        default_value = "false"
+    )]
+    pub yes: bool,
+}
+
+#[derive(clap::Args)]
+pub struct DbStatsConfig {
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path to the database to report on"
+    )]
+    pub database: PathBuf,
+
+    #[arg(
+        long = "format",
+        long_help = "The output format. 'prometheus' can be used with node exporter's textfile collector.",
+        value_enum,
+        default_value = "table"
+    )]
+    pub format: DbStatsFormat,
+
+    #[arg(
+        long = "block-body-history",
+        value_name = "NUM_BLOCKS",
+        long_help = "Estimate the space freed by keeping only the bodies of the latest block and this many blocks before it"
+    )]
+    pub block_body_history: Option<u64>,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbStatsFormat {
+    Table,
+    Json,
+    Prometheus,
+}
+
+#[derive(clap::Args)]
+pub struct ReplayConfig {
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path to the database containing the blocks to replay"
+    )]
+    pub database: PathBuf,
+
+    #[arg(
+        long = "from",
+        value_name = "BLOCK",
+        long_help = "First block to replay",
+        default_value = "0"
+    )]
+    pub from: u64,
+
+    #[arg(
+        long = "to",
+        value_name = "BLOCK",
+        long_help = "Last block to replay (inclusive). Defaults to the latest block in the database."
+    )]
+    pub to: Option<u64>,
+
+    #[arg(
+        long = "jobs",
+        long_help = "The number of blocks that are executed concurrently. Defaults to the number of CPU cores available."
+    )]
+    pub jobs: Option<std::num::NonZeroUsize>,
+
+    #[arg(
+        long = "output",
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = r"File to which the report is written, as one JSON object per line for each block containing differences.
+
+Progress is checkpointed in a file next to it with an additional '.checkpoint' extension."
+    )]
+    pub output: PathBuf,
+
+    #[arg(
+        long = "resume",
+        long_help = "Continue from the last checkpoint of a previous run with the same output file, instead of starting over",
+        action = clap::ArgAction::Set,
+        default_value = "false"
+    )]
+    pub resume: bool,
+
+    #[arg(
+        long = "contract",
+        value_name = "ADDRESS LIST",
+        long_help = "Comma separated list of contract addresses. Only transactions sent to or by these contracts are reported.",
+        value_delimiter = ',',
+        value_parser = parse_contract_address
+    )]
+    pub contracts: Vec<pathfinder_common::ContractAddress>,
+
+    #[arg(
+        long = "transaction-type",
+        value_name = "TYPE LIST",
+        long_help = "Comma separated list of transaction types. Only transactions of these types are reported.",
+        value_delimiter = ',',
+        value_enum
+    )]
+    pub transaction_types: Vec<TransactionType>,
+
+    #[arg(
+        long = "chain-id",
+        value_name = "CHAIN ID",
+        long_help = "The chain ID of a custom network, e.g. SN_MY_CHAIN. Required if the database is not of one of the public networks."
+    )]
+    pub chain_id: Option<String>,
+
+    #[arg(
+        long = "chain-config",
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path to a JSON file with the parameters of the network, as used by the node with '--chain-config'. Parameters which are not set default to those of the network the database belongs to."
+    )]
+    pub chain_config: Option<PathBuf>,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransactionType {
+    Declare,
+    Deploy,
+    DeployAccount,
+    Invoke,
+    L1Handler,
+}
+
+fn parse_contract_address(s: &str) -> anyhow::Result<pathfinder_common::ContractAddress> {
+    use anyhow::Context;
+
+    let felt = pathfinder_crypto::Felt::from_hex_str(s)?;
+    pathfinder_common::ContractAddress::new(felt).context("Contract address out of range")
+}
+
+fn parse_state_tries(s: &str) -> Result<TriePruneMode, String> {
+    if s == "archive" {
+        return Ok(TriePruneMode::Archive);
+    }
+
+    s.strip_prefix("pruned(")
+        .and_then(|s| s.strip_suffix(')'))
+        .and_then(|n| n.parse().ok())
+        .map(|num_blocks_kept| TriePruneMode::Prune { num_blocks_kept })
+        .ok_or_else(|| "expected 'archive' or 'pruned(N)'".to_owned())
+}
+
 #[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
 pub enum Color {
     Auto,
@@ -269,7 +727,9 @@ struct NetworkCli {
         long = "network",
         long_help = r"Specify the Starknet network for pathfinder to operate on.
 
-Note that 'custom' requires also setting the --gateway-url and --feeder-gateway-url options.",
+Note that 'custom' requires also setting the --gateway-url and --feeder-gateway-url options.
+
+'devnet' runs a local development network, for which pathfinder produces its own blocks. This requires setting either the --devnet.genesis or the --devnet.fork-url option.",
         value_enum,
         env = "PATHFINDER_NETWORK"
     )]
@@ -277,7 +737,7 @@ Note that 'custom' requires also setting the --gateway-url and --feeder-gateway-
 
     #[arg(
         long,
-        long_help = "Set a custom Starknet chain ID (e.g. SN_GOERLI)",
+        long_help = "Set a custom Starknet chain ID (e.g. SN_GOERLI). Defaults to SN_DEVNET for '--network devnet'.",
         value_name = "CHAIN ID",
         env = "PATHFINDER_CHAIN_ID",
         required_if_eq("network", Network::Custom)
@@ -302,6 +762,52 @@ Note that 'custom' requires also setting the --gateway-url and --feeder-gateway-
         required_if_eq("network", Network::Custom),
     )]
     gateway: Option<Url>,
+
+    #[arg(
+        long = "chain-config",
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path to a JSON file with the parameters of a custom Starknet network, such as its fee token addresses, execution limits and block hash algorithm cutoffs. Parameters which are not set default to those of the public networks. Requires '--network custom' or '--network devnet'.",
+        env = "PATHFINDER_CHAIN_CONFIG"
+    )]
+    chain_config: Option<PathBuf>,
+
+    #[arg(
+        long = "devnet.genesis",
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath,
+        long_help = "Path to the JSON file describing the genesis block of the development network. Requires '--network devnet'.",
+        env = "PATHFINDER_DEVNET_GENESIS",
+        conflicts_with = "devnet_fork_url"
+    )]
+    devnet_genesis: Option<PathBuf>,
+
+    #[arg(
+        long = "devnet.fork-url",
+        value_name = "URL",
+        value_hint = clap::ValueHint::Url,
+        long_help = "Fork the development network from the state of a remote network, as served by this feeder gateway. State which is missing locally is fetched on demand and cached. Note that transactions must be signed for the chain ID set by --chain-id. Requires '--network devnet'.",
+        env = "PATHFINDER_DEVNET_FORK_URL",
+        requires = "devnet_fork_block"
+    )]
+    devnet_fork_url: Option<Url>,
+
+    #[arg(
+        long = "devnet.fork-block",
+        value_name = "BLOCK NUMBER",
+        long_help = "The block of the remote network to fork from. Requires '--devnet.fork-url'.",
+        env = "PATHFINDER_DEVNET_FORK_BLOCK",
+        requires = "devnet_fork_url"
+    )]
+    devnet_fork_block: Option<u64>,
+
+    #[arg(
+        long = "devnet.block-time",
+        value_name = "SECONDS",
+        long_help = "Seal a new block at this interval. If not set, blocks are only created on demand using `pathfinder_createBlock`. Requires '--network devnet'.",
+        env = "PATHFINDER_DEVNET_BLOCK_TIME"
+    )]
+    devnet_block_time: Option<std::num::NonZeroU64>,
 }
 
 #[cfg(feature = "p2p")]
@@ -411,6 +917,7 @@ enum Network {
     SepoliaTestnet,
     SepoliaIntegration,
     Custom,
+    Devnet,
 }
 
 impl From<Network> for clap::builder::OsStr {
@@ -422,6 +929,7 @@ impl From<Network> for clap::builder::OsStr {
             Network::SepoliaTestnet => "sepolia-testnet",
             Network::SepoliaIntegration => "sepolia-integration",
             Network::Custom => "custom",
+            Network::Devnet => "devnet",
         }
         .into()
     }
@@ -496,9 +1004,21 @@ enum RpcCorsDomainsParseError {
     WildcardAmongOtherValues,
 }
 
+/// What pathfinder was asked to do.
+pub enum Command {
+    /// Run the node.
+    Node(Box<Config>),
+    /// Run a one-off [Subcommand] and exit.
+    Subcommand {
+        subcommand: Subcommand,
+        color: Color,
+    },
+}
+
 pub struct Config {
     pub data_directory: PathBuf,
-    pub ethereum: Ethereum,
+    /// Always set, except when running a devnet.
+    pub ethereum: Option<Ethereum>,
     pub rpc_address: SocketAddr,
     pub rpc_cors_domains: Option<AllowedOrigins>,
     pub rpc_root_version: RpcVersion,
@@ -509,22 +1029,32 @@ pub struct Config {
     pub sqlite_wal: JournalMode,
     pub max_rpc_connections: std::num::NonZeroUsize,
     pub poll_interval: std::time::Duration,
+    pub download_window: NonZeroUsize,
+    pub feeder_gateway_mirrors: Vec<Url>,
+    pub cross_check_mirrors: bool,
+    pub stop_at_block: Option<pathfinder_common::BlockNumber>,
+    pub execute_pending: bool,
     pub color: Color,
     pub p2p: P2PConfig,
     pub debug: DebugConfig,
     pub verify_tree_hashes: bool,
     pub rpc_batch_concurrency_limit: NonZeroUsize,
+    pub rpc_backup_directory: Option<PathBuf>,
     pub is_sync_enabled: bool,
+    pub read_only: bool,
     pub is_rpc_enabled: bool,
     pub gateway_api_key: Option<String>,
     pub event_bloom_filter_cache_size: NonZeroUsize,
+    pub state_tries: Option<TriePruneMode>,
+    pub block_body_history: Option<u64>,
     pub get_events_max_blocks_to_scan: NonZeroUsize,
     pub get_events_max_uncached_bloom_filters_to_load: NonZeroUsize,
 }
 
 pub struct Ethereum {
-    pub url: Url,
+    pub urls: Vec<Url>,
     pub password: Option<String>,
+    pub quorum: NonZeroUsize,
 }
 
 pub enum NetworkConfig {
@@ -537,6 +1067,22 @@ pub enum NetworkConfig {
         gateway: Url,
         feeder_gateway: Url,
         chain_id: String,
+        chain_config: Option<PathBuf>,
+    },
+    Devnet {
+        source: DevnetSource,
+        chain_id: String,
+        chain_config: Option<PathBuf>,
+        block_time: Option<std::time::Duration>,
+    },
+}
+
+/// What a devnet's chain is built on.
+pub enum DevnetSource {
+    Genesis(PathBuf),
+    Fork {
+        feeder_gateway: Url,
+        block: pathfinder_common::BlockNumber,
     },
 }
 
@@ -562,6 +1108,32 @@ pub struct DebugConfig {
 impl NetworkConfig {
     fn from_components(args: NetworkCli) -> Option<Self> {
         use Network::*;
+
+        let devnet_args = args.devnet_genesis.is_some()
+            || args.devnet_block_time.is_some()
+            || args.devnet_fork_url.is_some();
+        if devnet_args && !matches!(args.network, Some(Devnet)) {
+            use clap::error::ErrorKind;
+
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--devnet.genesis, --devnet.fork-url and --devnet.block-time may only be used with --network devnet",
+                )
+                .exit()
+        }
+
+        if args.chain_config.is_some() && !matches!(args.network, Some(Custom | Devnet)) {
+            use clap::error::ErrorKind;
+
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--chain-config may only be used with --network custom or devnet",
+                )
+                .exit()
+        }
+
         let cfg = match (
             args.network,
             args.gateway,
@@ -574,11 +1146,39 @@ impl NetworkConfig {
                     gateway,
                     feeder_gateway,
                     chain_id,
+                    chain_config: args.chain_config,
                 }
             }
             (Some(Custom), _, _, _) => {
                 unreachable!("`--network custom` requirements are handled by clap derive")
             }
+            (Some(Devnet), None, None, chain_id) => NetworkConfig::Devnet {
+                source: match (args.devnet_genesis, args.devnet_fork_url, args.devnet_fork_block) {
+                    (Some(genesis), None, None) => DevnetSource::Genesis(genesis),
+                    (None, Some(feeder_gateway), Some(block)) => DevnetSource::Fork {
+                        feeder_gateway,
+                        block: pathfinder_common::BlockNumber::new(block).unwrap_or_else(|| {
+                            Cli::command()
+                                .error(
+                                    clap::error::ErrorKind::ValueValidation,
+                                    "--devnet.fork-block is out of range",
+                                )
+                                .exit()
+                        }),
+                    },
+                    _ => Cli::command()
+                        .error(
+                            clap::error::ErrorKind::MissingRequiredArgument,
+                            "--network devnet requires either --devnet.genesis or --devnet.fork-url",
+                        )
+                        .exit(),
+                },
+                chain_id: chain_id.unwrap_or_else(|| "SN_DEVNET".to_owned()),
+                chain_config: args.chain_config,
+                block_time: args
+                    .devnet_block_time
+                    .map(|secs| std::time::Duration::from_secs(secs.get())),
+            },
             // Handle non-custom variants in an inner match so that the compiler will force
             // us to handle a new network variants explicitly. Otherwise we end up with a
             // catch-all arm that would swallow new variants silently.
@@ -589,13 +1189,14 @@ impl NetworkConfig {
                 SepoliaTestnet => NetworkConfig::SepoliaTestnet,
                 SepoliaIntegration => NetworkConfig::SepoliaIntegration,
                 Custom => unreachable!("Network::Custom handled in outer arm already"),
+                Devnet => unreachable!("Network::Devnet handled in outer arm already"),
             },
             // clap does not support disallowing args based on an enum value, so we have check for
             // `--network non-custom` + custom required args manually.
             _ => {
                 use clap::error::ErrorKind;
 
-                Cli::command().error(ErrorKind::ArgumentConflict, "--gateway-url, --feeder-gateway-url and --chain-id may only be used with --network custom").exit()
+                Cli::command().error(ErrorKind::ArgumentConflict, "--gateway-url and --feeder-gateway-url may only be used with --network custom, and --chain-id only with --network custom or devnet").exit()
             }
         };
 
@@ -664,18 +1265,81 @@ impl DebugConfig {
     }
 }
 
-impl Config {
+impl Command {
     pub fn parse() -> Self {
         let cli = Cli::parse();
 
+        match cli.subcommand {
+            Some(subcommand) => Command::Subcommand {
+                subcommand,
+                color: cli.color,
+            },
+            None => Command::Node(Box::new(Config::from_cli(cli))),
+        }
+    }
+}
+
+impl Config {
+    fn from_cli(cli: Cli) -> Self {
         let network = NetworkConfig::from_components(cli.network);
 
+        // A devnet does not follow any L1, and a read-only node follows the database of another
+        // node instead, so Ethereum is only required otherwise.
+        let ethereum = match (&network, cli.ethereum_url) {
+            (Some(NetworkConfig::Devnet { .. }), _) => None,
+            (None, _) if cli.read_only => {
+                use clap::error::ErrorKind;
+
+                // The default network is otherwise determined from the Ethereum chain.
+                Cli::command()
+                    .error(
+                        ErrorKind::MissingRequiredArgument,
+                        "--network is required in read-only mode",
+                    )
+                    .exit()
+            }
+            (_, _) if cli.read_only => None,
+            (_, urls) if urls.is_empty() => {
+                use clap::error::ErrorKind;
+
+                Cli::command()
+                    .error(
+                        ErrorKind::MissingRequiredArgument,
+                        "--ethereum.url is required unless running with --network devnet or in read-only mode",
+                    )
+                    .exit()
+            }
+            (_, urls) if urls.len() < cli.ethereum_quorum.get() => {
+                use clap::error::ErrorKind;
+
+                Cli::command()
+                    .error(
+                        ErrorKind::ValueValidation,
+                        "--ethereum.quorum must not exceed the number of --ethereum.url endpoints",
+                    )
+                    .exit()
+            }
+            (_, urls) => Some(Ethereum {
+                password: cli.ethereum_password,
+                urls,
+                quorum: cli.ethereum_quorum,
+            }),
+        };
+
+        // P2P sync does not stop at a block, so the flag would be silently ignored.
+        #[cfg(feature = "p2p")]
+        if cli.stop_at_block.is_some() {
+            Cli::command()
+                .error(
+                    clap::error::ErrorKind::ArgumentConflict,
+                    "--sync.stop-at-block is not supported with p2p sync",
+                )
+                .exit()
+        }
+
         Config {
             data_directory: cli.data_directory,
-            ethereum: Ethereum {
-                password: cli.ethereum_password,
-                url: cli.ethereum_url,
-            },
+            ethereum,
             rpc_address: cli.rpc_address,
             rpc_cors_domains: parse_cors_or_exit(cli.rpc_cors_domains),
             rpc_root_version: cli.rpc_root_version,
@@ -689,15 +1353,33 @@ impl Config {
             },
             max_rpc_connections: cli.max_rpc_connections,
             poll_interval: std::time::Duration::from_secs(cli.poll_interval.get()),
+            download_window: cli.download_window,
+            feeder_gateway_mirrors: cli.feeder_gateway_mirrors,
+            cross_check_mirrors: cli.cross_check_mirrors,
+            stop_at_block: cli.stop_at_block.map(|block| {
+                pathfinder_common::BlockNumber::new(block).unwrap_or_else(|| {
+                    Cli::command()
+                        .error(
+                            clap::error::ErrorKind::ValueValidation,
+                            "--sync.stop-at-block is out of range",
+                        )
+                        .exit()
+                })
+            }),
+            execute_pending: cli.execute_pending,
             color: cli.color,
             p2p: P2PConfig::parse_or_exit(cli.p2p),
             debug: DebugConfig::parse(cli.debug),
             verify_tree_hashes: cli.verify_tree_node_data,
             rpc_batch_concurrency_limit: cli.rpc_batch_concurrency_limit,
+            rpc_backup_directory: cli.rpc_backup_directory,
             is_sync_enabled: cli.is_sync_enabled,
+            read_only: cli.read_only,
             is_rpc_enabled: cli.is_rpc_enabled,
             gateway_api_key: cli.gateway_api_key,
             event_bloom_filter_cache_size: cli.event_bloom_filter_cache_size,
+            state_tries: cli.state_tries,
+            block_body_history: cli.block_body_history,
             get_events_max_blocks_to_scan: cli.get_events_max_blocks_to_scan,
             get_events_max_uncached_bloom_filters_to_load: cli
                 .get_events_max_uncached_bloom_filters_to_load,
@@ -739,8 +1421,8 @@ pub struct WebsocketConfig {
 
 #[cfg(test)]
 mod tests {
-    use super::{AllowedOrigins, RpcCorsDomainsParseError};
-    use crate::config::parse_cors;
+    use super::{AllowedOrigins, RpcCorsDomainsParseError, TriePruneMode};
+    use crate::config::{parse_cors, parse_state_tries};
 
     #[test]
     fn parse_cors_domains() {
@@ -816,4 +1498,19 @@ mod tests {
             )
         });
     }
+
+    #[test]
+    fn parse_state_tries_mode() {
+        assert_eq!(parse_state_tries("archive"), Ok(TriePruneMode::Archive));
+        assert_eq!(
+            parse_state_tries("pruned(20)"),
+            Ok(TriePruneMode::Prune {
+                num_blocks_kept: 20
+            })
+        );
+
+        for invalid in ["", "pruned", "pruned()", "pruned(-1)", "pruned(20", "full"] {
+            parse_state_tries(invalid).unwrap_err();
+        }
+    }
 }
@@ -0,0 +1,22 @@
+//! Database maintenance commands.
+
+use crate::config::DbCommand;
+
+mod backup;
+mod revert;
+mod snapshot;
+mod stats;
+#[cfg(test)]
+mod test_utils;
+mod verify;
+
+pub fn run(command: DbCommand) -> anyhow::Result<()> {
+    match command {
+        DbCommand::Backup(config) => backup::run(config),
+        DbCommand::ExportSnapshot(config) => snapshot::export(config),
+        DbCommand::ImportSnapshot(config) => snapshot::import(config),
+        DbCommand::Verify(config) => verify::run(config),
+        DbCommand::RevertTo(config) => revert::run(config),
+        DbCommand::Stats(config) => stats::run(config),
+    }
+}
@@ -0,0 +1,45 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use pathfinder_storage::{BackupOptions, BackupProgress};
+
+use crate::config::DbBackupConfig;
+
+/// How often the progress of the backup is logged.
+const PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+pub fn run(config: DbBackupConfig) -> anyhow::Result<()> {
+    let options = BackupOptions {
+        pages_per_step: config.pages_per_step,
+        pause: Duration::from_millis(config.pause_ms),
+    };
+
+    tracing::info!(
+        database=%config.database.display(),
+        destination=%config.destination.display(),
+        "Starting database backup"
+    );
+
+    let started = Instant::now();
+    let mut last_logged = started;
+    pathfinder_storage::backup_file(
+        &config.database,
+        &config.destination,
+        options,
+        |BackupProgress {
+             pages_copied,
+             pages_total,
+         }| {
+            if last_logged.elapsed() >= PROGRESS_LOG_INTERVAL {
+                let percent = pages_copied * 100 / pages_total.max(1);
+                tracing::info!(%pages_copied, %pages_total, "Backup {percent}% complete");
+                last_logged = Instant::now();
+            }
+        },
+    )
+    .context("Backing up database")?;
+
+    tracing::info!(elapsed=?started.elapsed(), "Database backup complete");
+
+    Ok(())
+}
@@ -0,0 +1,271 @@
+//! Reverts the database to an earlier block, removing all later blocks in the same way as an
+//! L2 reorg does.
+
+use std::io::{BufRead, Write};
+use std::num::NonZeroU32;
+
+use anyhow::Context;
+use pathfinder_common::BlockNumber;
+use pathfinder_storage::{BlockId, JournalMode, Storage, TransactionBehavior};
+
+use crate::config::DbRevertConfig;
+
+/// What reverting removes from the database.
+#[derive(Debug, Default)]
+struct Summary {
+    blocks: u64,
+    transactions: u64,
+    events: u64,
+    classes: usize,
+}
+
+pub fn run(config: DbRevertConfig) -> anyhow::Result<()> {
+    let target = BlockNumber::new(config.block).context("Block number out of range")?;
+
+    let storage = Storage::migrate(config.database.clone(), JournalMode::WAL, 1)
+        .context("Opening database")?
+        .create_pool(NonZeroU32::new(1).unwrap())
+        .context("Creating database connection pool")?;
+    let mut db = storage
+        .connection()
+        .context("Opening database connection")?;
+
+    let (head, target_header, summary) = {
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let (head, _) = tx
+            .block_id(BlockId::Latest)?
+            .context("Database contains no blocks")?;
+        let target_header = tx
+            .block_header(target.into())?
+            .with_context(|| format!("Block {target} does not exist in the database"))?;
+        if head == target {
+            tracing::info!(block=%target, "Database is already at this block, nothing to revert");
+            return Ok(());
+        }
+
+        anyhow::ensure!(
+            !tx.trie_pruned(target).context("Querying trie pruning")?,
+            "The state tries of block {target} have been pruned, so the database cannot be reverted to it"
+        );
+
+        let mut summary = Summary {
+            classes: tx
+                .count_classes_declared_after(target)
+                .context("Counting classes")?,
+            ..Default::default()
+        };
+        let mut block = head;
+        while block > target {
+            let header = tx
+                .block_header(block.into())?
+                .with_context(|| format!("Header of block {block} is missing"))?;
+            summary.blocks += 1;
+            summary.transactions += header.transaction_count as u64;
+            summary.events += header.event_count as u64;
+            block -= 1;
+        }
+
+        (head, target_header, summary)
+    };
+
+    tracing::info!(
+        from=%head,
+        to=%target,
+        hash=%target_header.hash,
+        blocks=%summary.blocks,
+        transactions=%summary.transactions,
+        events=%summary.events,
+        classes=%summary.classes,
+        "Revert summary"
+    );
+
+    if config.dry_run {
+        tracing::info!("Dry run, no changes were made");
+        return Ok(());
+    }
+
+    if !config.yes && !confirm(head, target)? {
+        tracing::info!("Revert cancelled, no changes were made");
+        return Ok(());
+    }
+
+    // Takes the write lock, so that no other connection can write while reverting. This only
+    // fails if another connection is writing, so a running node which is idle is not detected.
+    let tx = db
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .context("Creating database transaction, make sure the node is not running")?;
+
+    let (current_head, _) = tx
+        .block_id(BlockId::Latest)?
+        .context("Database contains no blocks")?;
+    anyhow::ensure!(
+        current_head == head,
+        "The database changed after the summary, its head is now {current_head} instead of {head}. Make sure the node is not running"
+    );
+
+    tx.purge_classes_declared_after(target)
+        .context("Purging classes")?;
+    tx.increment_reorg_counter()
+        .context("Incrementing reorg counter")?;
+
+    let mut block = head;
+    while block > target {
+        tx.purge_block(block)
+            .with_context(|| format!("Purging block {block} from database"))?;
+        block -= 1;
+    }
+
+    let l1_l2_head = tx.l1_l2_pointer().context("Query L1-L2 head")?;
+    if let Some(l1_l2_head) = l1_l2_head {
+        if l1_l2_head > target {
+            tx.update_l1_l2_pointer(Some(target))
+                .context("Updating L1-L2 head")?;
+        }
+    }
+
+    tx.commit().context("Committing database transaction")?;
+    tracing::info!(block=%target, "Database reverted");
+
+    Ok(())
+}
+
+fn confirm(head: BlockNumber, target: BlockNumber) -> anyhow::Result<bool> {
+    let mut stderr = std::io::stderr();
+    write!(
+        stderr,
+        "This permanently removes blocks {}..={head}. Type 'yes' to continue: ",
+        target + 1
+    )
+    .and_then(|_| stderr.flush())
+    .context("Writing confirmation prompt")?;
+
+    let mut answer = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .context("Reading confirmation")?;
+
+    Ok(answer.trim() == "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use pathfinder_common::{BlockHeader, ContractAddress, StorageAddress};
+    use pathfinder_crypto::Felt;
+    use pathfinder_merkle_tree::StorageCommitmentTree;
+
+    use super::*;
+    use crate::db::test_utils::{create_database, CONTRACT};
+
+    fn revert(database: &Path, block: u64, dry_run: bool) {
+        run(DbRevertConfig {
+            database: database.to_owned(),
+            block,
+            dry_run,
+            yes: true,
+        })
+        .unwrap();
+    }
+
+    fn rows_after(database: &Path, table: &str, block: u64) -> u64 {
+        let column = if table == "block_headers" {
+            "number"
+        } else {
+            "block_number"
+        };
+        rusqlite::Connection::open(database)
+            .unwrap()
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {table} WHERE {column} > ?"),
+                [block],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    fn assert_head(storage: &Storage, header: &BlockHeader) {
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        assert_eq!(
+            tx.block_id(BlockId::Latest).unwrap(),
+            Some((header.number, header.hash))
+        );
+    }
+
+    #[test]
+    fn revert_populated_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("pathfinder.sqlite");
+        let (storage, headers) = create_database(&database, 5);
+        drop(storage);
+
+        revert(&database, 2, false);
+
+        let storage = Storage::migrate(database.clone(), JournalMode::WAL, 1)
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        assert_head(&storage, &headers[2]);
+
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        assert_eq!(
+            StorageCommitmentTree::verify(&tx, headers[2].number).unwrap(),
+            headers[2].storage_commitment
+        );
+
+        let key = |n: u64| StorageAddress::new_or_panic(Felt::from_u64(n));
+        assert!(tx
+            .storage_value(BlockId::Latest, CONTRACT, key(2))
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            tx.storage_value(BlockId::Latest, CONTRACT, key(3)).unwrap(),
+            None
+        );
+        let deployed = |n: u64| ContractAddress::new_or_panic(Felt::from_u64(0x1000 + n));
+        assert!(tx.contract_exists(deployed(2), BlockId::Latest).unwrap());
+        assert!(!tx.contract_exists(deployed(3), BlockId::Latest).unwrap());
+        assert!(!tx.contract_exists(deployed(4), BlockId::Latest).unwrap());
+        for header in &headers[3..] {
+            assert!(!tx.block_exists(header.hash.into()).unwrap());
+            assert_eq!(tx.state_update(header.number.into()).unwrap(), None);
+        }
+        drop(tx);
+        drop(db);
+        drop(storage);
+
+        for table in [
+            "block_headers",
+            "storage_updates",
+            "contract_updates",
+            "storage_roots",
+            "class_roots",
+            "contract_roots",
+            "contract_state_hashes",
+        ] {
+            assert_eq!(rows_after(&database, table, 2), 0, "{table}");
+            assert_ne!(rows_after(&database, table, 1), 0, "{table}");
+        }
+        let reorg_counter: i64 = rusqlite::Connection::open(&database)
+            .unwrap()
+            .query_row("SELECT counter FROM reorg_counter", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(reorg_counter, 1);
+    }
+
+    #[test]
+    fn dry_run_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("pathfinder.sqlite");
+        let (storage, headers) = create_database(&database, 5);
+
+        revert(&database, 2, true);
+
+        assert_head(&storage, &headers[4]);
+        assert_eq!(rows_after(&database, "storage_updates", 2), 2);
+    }
+}
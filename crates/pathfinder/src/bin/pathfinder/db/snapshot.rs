@@ -0,0 +1,1075 @@
+//! Portable snapshots of the state at a single block, from which a new database can be
+//! initialized instead of syncing from genesis.
+//!
+//! A snapshot starts with [MAGIC], followed by a sequence of chunks. Each chunk is a JSON encoded
+//! [Chunk], compressed into a zstd frame including a checksum of its content, and prefixed with
+//! the frame's length. The first chunk is always the [Manifest] and the last one is
+//! [Chunk::End], so that truncated snapshots are detected.
+//!
+//! A snapshot contains the headers of all blocks up to and including the snapshot's block, but
+//! only the state at that block. The transactions and state diffs of older blocks are not
+//! available in an imported database, and their block bodies are reported as pruned. Querying
+//! the state of older blocks fails instead of returning the state of the snapshot's block.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use pathfinder_common::state_update::{ContractClassUpdate, ContractUpdate, SystemContractUpdate};
+use pathfinder_common::{
+    BlockHash, BlockHeader, BlockNumber, BlockTimestamp, CasmHash, ChainId, ClassCommitment,
+    ClassHash, ContractAddress, ContractNonce, EventCommitment, GasPrice, SequencerAddress,
+    SierraHash, StarknetVersion, StateCommitment, StateUpdate, StorageAddress, StorageCommitment,
+    StorageValue, TransactionCommitment,
+};
+use pathfinder_crypto::Felt;
+use pathfinder_lib::state::block_hash::{verify_header_hash, BlockHashMetaInfo, VerifyResult};
+use pathfinder_merkle_tree::contract_state::update_contract_state;
+use pathfinder_merkle_tree::{ClassCommitmentTree, StorageCommitmentTree};
+use pathfinder_storage::{BlockId, JournalMode, Storage, Transaction};
+use starknet_gateway_types::class_hash::{compute_class_hash, ComputedClassHash};
+
+use crate::config::{DbExportSnapshotConfig, DbImportSnapshotConfig};
+
+/// Identifies the file as a snapshot, including the version of its format.
+const MAGIC: &[u8; 8] = b"PFSNAP01";
+
+const HEADERS_PER_CHUNK: usize = 10_000;
+const CLASSES_PER_CHUNK: usize = 100;
+const CONTRACTS_PER_CHUNK: usize = 10_000;
+/// Contracts with more storage entries than this are split across multiple chunks.
+const STORAGE_ENTRIES_PER_CHUNK: usize = 100_000;
+
+const COMPRESSION_LEVEL: i32 = 10;
+/// Larger chunks are rejected as corrupted, instead of allocating whatever their length claims.
+const MAX_CHUNK_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// How often the progress of an export or import is logged.
+const PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Chunk {
+    Manifest(Manifest),
+    Headers(Vec<Header>),
+    Classes(Vec<Class>),
+    Contracts(Vec<Contract>),
+    /// Marks the end of the snapshot, and contains the number of chunks preceding it.
+    End {
+        chunks: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Manifest {
+    block_number: BlockNumber,
+    block_hash: BlockHash,
+    state_commitment: StateCommitment,
+}
+
+/// A serializable copy of a [BlockHeader].
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Header {
+    hash: BlockHash,
+    parent_hash: BlockHash,
+    number: BlockNumber,
+    timestamp: BlockTimestamp,
+    eth_l1_gas_price: GasPrice,
+    strk_l1_gas_price: GasPrice,
+    sequencer_address: SequencerAddress,
+    starknet_version: StarknetVersion,
+    class_commitment: ClassCommitment,
+    event_commitment: EventCommitment,
+    state_commitment: StateCommitment,
+    storage_commitment: StorageCommitment,
+    transaction_commitment: TransactionCommitment,
+    transaction_count: usize,
+    event_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Class {
+    hash: ClassHash,
+    declared_at: BlockNumber,
+    /// Base64 encoded class definition.
+    definition: String,
+    /// Only present for Sierra classes.
+    casm: Option<Casm>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Casm {
+    hash: CasmHash,
+    /// Base64 encoded compiled class definition.
+    definition: String,
+}
+
+/// The state of a contract. Contracts with a lot of storage are split into multiple consecutive
+/// entries, each containing part of the storage.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Contract {
+    address: ContractAddress,
+    /// `None` for system contracts, which have no class.
+    class_hash: Option<ClassHash>,
+    nonce: ContractNonce,
+    storage: Vec<(StorageAddress, StorageValue)>,
+}
+
+pub fn export(config: DbExportSnapshotConfig) -> anyhow::Result<()> {
+    let storage = Storage::open_read_only(config.database.clone(), 1)
+        .context("Opening database")?
+        .create_pool(NonZeroU32::new(1).unwrap())
+        .context("Creating database connection pool")?;
+    let mut db = storage
+        .connection()
+        .context("Opening database connection")?;
+    let tx = db.transaction().context("Creating database transaction")?;
+
+    let block_id = match config.block {
+        Some(number) => BlockNumber::new(number)
+            .context("Block number out of range")?
+            .into(),
+        None => BlockId::Latest,
+    };
+    let header = tx
+        .block_header(block_id)?
+        .context("Block does not exist in the database")?;
+    let block = header.number;
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&config.output)
+        .with_context(|| format!("Creating snapshot file {}", config.output.display()))?;
+    let mut writer = Writer::new(BufWriter::new(file))?;
+    writer.write(&Chunk::Manifest(Manifest {
+        block_number: block,
+        block_hash: header.hash,
+        state_commitment: header.state_commitment,
+    }))?;
+
+    tracing::info!(%block, "Exporting headers");
+    let mut headers = Vec::with_capacity(HEADERS_PER_CHUNK);
+    for number in 0..=block.get() {
+        let header = tx
+            .block_header(BlockNumber::new_or_panic(number).into())?
+            .with_context(|| format!("Header of block {number} is missing"))?;
+        headers.push(header.into());
+
+        if headers.len() == HEADERS_PER_CHUNK || number == block.get() {
+            writer.write(&Chunk::Headers(std::mem::take(&mut headers)))?;
+        }
+    }
+
+    let classes = tx
+        .declared_classes(block)
+        .context("Querying declared classes")?;
+    tracing::info!(count=%classes.len(), "Exporting classes");
+    for batch in classes.chunks(CLASSES_PER_CHUNK) {
+        let batch = batch
+            .iter()
+            .map(|&(hash, declared_at)| export_class(&tx, hash, declared_at))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        writer.write(&Chunk::Classes(batch))?;
+    }
+
+    let contracts = tx
+        .deployed_contracts(block)
+        .context("Querying deployed contracts")?;
+    tracing::info!(count=%contracts.len(), "Exporting contracts");
+    let contracts = std::iter::once((ContractAddress::ONE, None)).chain(
+        contracts
+            .into_iter()
+            .map(|(address, class)| (address, Some(class))),
+    );
+
+    let mut batch = Vec::new();
+    let mut batch_entries = 0;
+    let mut last_logged = Instant::now();
+    for (address, class_hash) in contracts {
+        let nonce = tx
+            .contract_nonce(address, block.into())
+            .context("Querying contract nonce")?
+            .unwrap_or_default();
+        let mut storage = tx
+            .contract_storage(block, address)
+            .context("Querying contract storage")?;
+
+        // The system contract only exists once it has storage.
+        if class_hash.is_none() && storage.is_empty() {
+            continue;
+        }
+
+        loop {
+            let rest =
+                storage.split_off(storage.len().min(STORAGE_ENTRIES_PER_CHUNK - batch_entries));
+            batch_entries += storage.len();
+            batch.push(Contract {
+                address,
+                class_hash,
+                nonce,
+                storage,
+            });
+
+            if batch_entries == STORAGE_ENTRIES_PER_CHUNK || batch.len() == CONTRACTS_PER_CHUNK {
+                writer.write(&Chunk::Contracts(std::mem::take(&mut batch)))?;
+                batch_entries = 0;
+            }
+
+            if rest.is_empty() {
+                break;
+            }
+            storage = rest;
+        }
+
+        if last_logged.elapsed() >= PROGRESS_LOG_INTERVAL {
+            tracing::info!(%address, "Exporting contracts");
+            last_logged = Instant::now();
+        }
+    }
+    if !batch.is_empty() {
+        writer.write(&Chunk::Contracts(batch))?;
+    }
+
+    writer.finish()?;
+    tracing::info!(%block, path=%config.output.display(), "Snapshot exported");
+
+    Ok(())
+}
+
+fn export_class(
+    tx: &Transaction<'_>,
+    hash: ClassHash,
+    declared_at: BlockNumber,
+) -> anyhow::Result<Class> {
+    let definition = tx
+        .class_definition(hash)?
+        .with_context(|| format!("Definition of class {hash} is missing"))?;
+
+    let casm = match tx.casm_hash(hash)? {
+        Some(casm_hash) => {
+            let definition = tx
+                .casm_definition(hash)?
+                .with_context(|| format!("Compiled definition of class {hash} is missing"))?;
+            Some(Casm {
+                hash: casm_hash,
+                definition: base64::encode(definition),
+            })
+        }
+        None => None,
+    };
+
+    Ok(Class {
+        hash,
+        declared_at,
+        definition: base64::encode(definition),
+        casm,
+    })
+}
+
+pub fn import(config: DbImportSnapshotConfig) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !config.database.exists(),
+        "Database {} already exists, snapshots can only be imported into a new database",
+        config.database.display()
+    );
+
+    let result = import_into(&config.snapshot, &config.database);
+    if result.is_err() {
+        // Don't leave a partially initialized database behind.
+        for suffix in ["", "-journal", "-wal", "-shm"] {
+            let mut path = config.database.as_os_str().to_owned();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    let block = result?;
+
+    tracing::info!(%block, path=%config.database.display(), "Snapshot imported");
+
+    Ok(())
+}
+
+fn import_into(snapshot: &Path, database: &Path) -> anyhow::Result<BlockNumber> {
+    let file = File::open(snapshot)
+        .with_context(|| format!("Opening snapshot file {}", snapshot.display()))?;
+    let mut reader = Reader::new(BufReader::new(file))?;
+    let Chunk::Manifest(manifest) = reader.read()? else {
+        anyhow::bail!("Snapshot does not start with a manifest");
+    };
+    let block = manifest.block_number;
+
+    let storage = Storage::migrate(database.to_owned(), JournalMode::Rollback, 1)
+        .context("Creating database")?
+        .create_pool(NonZeroU32::new(1).unwrap())
+        .context("Creating database connection pool")?;
+    let mut db = storage
+        .connection()
+        .context("Opening database connection")?;
+    let tx = db.transaction().context("Creating database transaction")?;
+
+    let mut state = StateImporter::new(&tx, block);
+    let mut headers_verifier = HeadersVerifier::default();
+    let mut last_logged = Instant::now();
+    loop {
+        match reader.read()? {
+            Chunk::Manifest(_) => anyhow::bail!("Snapshot contains more than one manifest"),
+            Chunk::Headers(headers) => {
+                for header in headers {
+                    let header = BlockHeader::from(header);
+                    headers_verifier.verify(&header)?;
+                    tx.insert_block_header(&header)
+                        .context("Inserting block header")?;
+                }
+            }
+            Chunk::Classes(classes) => state.insert_classes(classes)?,
+            Chunk::Contracts(contracts) => state.insert_contracts(contracts)?,
+            Chunk::End { .. } => break,
+        }
+
+        if last_logged.elapsed() >= PROGRESS_LOG_INTERVAL {
+            tracing::info!(chunks=%reader.chunks, "Importing snapshot");
+            last_logged = Instant::now();
+        }
+    }
+    let (storage_commitment, class_commitment) = state.finish()?;
+
+    let header = tx
+        .block_header(block.into())?
+        .with_context(|| format!("Snapshot is missing the header of block {block}"))?;
+    anyhow::ensure!(
+        header.hash == manifest.block_hash,
+        "Block hash mismatch: header has {} but the manifest {}",
+        header.hash,
+        manifest.block_hash
+    );
+    if !headers_verifier.verified(block) {
+        tracing::warn!(%block, "The block hash cannot be verified on this chain");
+    }
+
+    let state_commitment = StateCommitment::calculate(storage_commitment, class_commitment);
+    anyhow::ensure!(
+        state_commitment == header.state_commitment,
+        "State commitment mismatch: computed {} but block {block} has {}",
+        state_commitment,
+        header.state_commitment
+    );
+
+    // Only the state of the snapshot's block is available, older blocks have headers only.
+    tx.set_first_unpruned_block_body(block + 1)
+        .context("Marking block bodies as pruned")?;
+    tx.set_first_block_with_state(block)
+        .context("Marking older state as unavailable")?;
+    tx.set_first_block_with_l1_handler_links(block + 1)
+        .context("Marking older L1 handler transactions as unlinked")?;
+
+    tx.commit().context("Committing database transaction")?;
+
+    Ok(block)
+}
+
+/// Checks that the imported headers form a chain starting at genesis, and that their hashes match
+/// their content.
+#[derive(Default)]
+struct HeadersVerifier {
+    parent: Option<(BlockNumber, BlockHash)>,
+    /// Identified from the genesis block's hash.
+    chain: Option<(&'static BlockHashMetaInfo, ChainId)>,
+    /// The last block whose hash was verified.
+    last_verified: Option<BlockNumber>,
+}
+
+impl HeadersVerifier {
+    fn verify(&mut self, header: &BlockHeader) -> anyhow::Result<()> {
+        match self.parent {
+            Some((number, hash)) => {
+                anyhow::ensure!(
+                    header.number == number + 1,
+                    "Expected the header of block {} but got block {}",
+                    number + 1,
+                    header.number
+                );
+                anyhow::ensure!(
+                    header.parent_hash == hash,
+                    "Parent hash {} of block {} does not match the hash {hash} of the previous block",
+                    header.parent_hash,
+                    header.number
+                );
+            }
+            None => {
+                anyhow::ensure!(
+                    header.number == BlockNumber::GENESIS,
+                    "Snapshot does not start with the genesis header"
+                );
+                let chain = super::verify::chain_from_genesis(header.hash);
+                self.chain = Some(super::verify::block_hash_meta_info(chain));
+            }
+        }
+        self.parent = Some((header.number, header.hash));
+
+        let (meta_info, chain_id) = self.chain.expect("Set by the genesis header");
+        match verify_header_hash(header, meta_info, chain_id) {
+            VerifyResult::Match(_) => self.last_verified = Some(header.number),
+            VerifyResult::Mismatch => anyhow::bail!(
+                "Hash {} of block {} does not match its header",
+                header.hash,
+                header.number
+            ),
+            VerifyResult::NotVerifiable => {}
+        }
+
+        Ok(())
+    }
+
+    fn verified(&self, block: BlockNumber) -> bool {
+        self.last_verified == Some(block)
+    }
+}
+
+/// Inserts the state at `block` into the database and builds the storage and class tries for it.
+struct StateImporter<'tx> {
+    tx: &'tx Transaction<'tx>,
+    block: BlockNumber,
+    storage_commitment_tree: StorageCommitmentTree<'tx>,
+    class_commitment_tree: ClassCommitmentTree<'tx>,
+    /// The contract being read, whose storage may still continue in the next entries.
+    contract: Option<Contract>,
+}
+
+impl<'tx> StateImporter<'tx> {
+    fn new(tx: &'tx Transaction<'tx>, block: BlockNumber) -> Self {
+        Self {
+            tx,
+            block,
+            storage_commitment_tree: StorageCommitmentTree::empty(tx),
+            class_commitment_tree: ClassCommitmentTree::empty(tx),
+            contract: None,
+        }
+    }
+
+    fn insert_classes(&mut self, classes: Vec<Class>) -> anyhow::Result<()> {
+        for class in classes {
+            let definition =
+                base64::decode(&class.definition).context("Decoding class definition")?;
+            verify_class(&class, &definition)?;
+
+            let declaration = match class.casm {
+                Some(casm) => {
+                    let casm_definition = base64::decode(&casm.definition)
+                        .context("Decoding compiled class definition")?;
+                    let casm_hash = casm_hash(&casm_definition).with_context(|| {
+                        format!("Computing compiled class hash of class {}", class.hash)
+                    })?;
+                    anyhow::ensure!(
+                        casm_hash == casm.hash,
+                        "Compiled class hash mismatch for class {}: computed {casm_hash} but the snapshot has {}",
+                        class.hash,
+                        casm.hash
+                    );
+                    let sierra_hash = SierraHash(class.hash.0);
+                    self.tx
+                        .insert_sierra_class(
+                            &sierra_hash,
+                            &definition,
+                            &casm.hash,
+                            &casm_definition,
+                        )
+                        .context("Inserting sierra class")?;
+
+                    let leaf_hash =
+                        pathfinder_common::calculate_class_commitment_leaf_hash(casm.hash);
+                    self.tx
+                        .insert_class_commitment_leaf(class.declared_at, &leaf_hash, &casm.hash)
+                        .context("Inserting class commitment leaf")?;
+                    self.class_commitment_tree
+                        .set(sierra_hash, leaf_hash)
+                        .context("Updating class commitment tree")?;
+
+                    StateUpdate::default().with_declared_sierra_class(sierra_hash, casm.hash)
+                }
+                None => {
+                    self.tx
+                        .insert_cairo_class(class.hash, &definition)
+                        .context("Inserting cairo class")?;
+
+                    StateUpdate::default().with_declared_cairo_class(class.hash)
+                }
+            };
+
+            // Records the block in which the class was declared.
+            self.tx
+                .insert_state_update(class.declared_at, &declaration)
+                .context("Inserting class declaration")?;
+        }
+
+        Ok(())
+    }
+
+    fn insert_contracts(&mut self, contracts: Vec<Contract>) -> anyhow::Result<()> {
+        for contract in contracts {
+            match &mut self.contract {
+                Some(current) if current.address == contract.address => {
+                    current.storage.extend(contract.storage);
+                }
+                _ => {
+                    if let Some(complete) = self.contract.replace(contract) {
+                        self.insert_contract(complete)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn insert_contract(&mut self, contract: Contract) -> anyhow::Result<()> {
+        let Contract {
+            address,
+            class_hash,
+            nonce,
+            storage,
+        } = contract;
+        let storage: HashMap<_, _> = storage.into_iter().collect();
+
+        let result = update_contract_state(
+            address,
+            &storage,
+            Some(nonce),
+            class_hash,
+            self.tx,
+            false,
+            self.block,
+        )
+        .with_context(|| format!("Updating state of contract {address}"))?;
+        self.storage_commitment_tree
+            .set(address, result.state_hash)
+            .context("Updating storage commitment tree")?;
+        result
+            .insert(self.block, self.tx)
+            .context("Inserting contract state")?;
+
+        let mut state_update = StateUpdate::default();
+        match class_hash {
+            Some(class_hash) => {
+                state_update.contract_updates.insert(
+                    address,
+                    ContractUpdate {
+                        storage,
+                        class: Some(ContractClassUpdate::Deploy(class_hash)),
+                        nonce: (nonce != ContractNonce::ZERO).then_some(nonce),
+                    },
+                );
+            }
+            None => {
+                state_update
+                    .system_contract_updates
+                    .insert(address, SystemContractUpdate { storage });
+            }
+        }
+        self.tx
+            .insert_state_update(self.block, &state_update)
+            .context("Inserting contract state")
+    }
+
+    fn finish(mut self) -> anyhow::Result<(StorageCommitment, ClassCommitment)> {
+        if let Some(contract) = self.contract.take() {
+            self.insert_contract(contract)?;
+        }
+
+        let (storage_commitment, trie_update) = self
+            .storage_commitment_tree
+            .commit()
+            .context("Committing storage commitment tree")?;
+        let root_idx = if !storage_commitment.0.is_zero() {
+            let root_idx = self
+                .tx
+                .insert_storage_trie(self.block, storage_commitment, &trie_update.nodes)
+                .context("Persisting storage trie")?;
+            Some(root_idx)
+        } else {
+            None
+        };
+        self.tx
+            .insert_storage_root(self.block, root_idx)
+            .context("Inserting storage root index")?;
+
+        let (class_commitment, trie_update) = self
+            .class_commitment_tree
+            .commit()
+            .context("Committing class commitment tree")?;
+        let root_idx = if !class_commitment.0.is_zero() {
+            let root_idx = self
+                .tx
+                .insert_class_trie(self.block, class_commitment, &trie_update.nodes)
+                .context("Persisting class trie")?;
+            Some(root_idx)
+        } else {
+            None
+        };
+        self.tx
+            .insert_class_root(self.block, root_idx)
+            .context("Inserting class root index")?;
+
+        Ok((storage_commitment, class_commitment))
+    }
+}
+
+/// Checks that the class definition hashes to the class hash, and that only Sierra classes come
+/// with a compiled definition.
+///
+/// The class hashes themselves are verified by the class commitment, and the compiled class
+/// hashes by the class commitment leaves.
+fn verify_class(class: &Class, definition: &[u8]) -> anyhow::Result<()> {
+    let computed = compute_class_hash(definition)
+        .with_context(|| format!("Computing hash of class {}", class.hash))?;
+    match (&computed, &class.casm) {
+        (ComputedClassHash::Cairo(_), Some(_)) => {
+            anyhow::bail!("Cairo class {} has a compiled definition", class.hash)
+        }
+        (ComputedClassHash::Sierra(_), None) => {
+            anyhow::bail!(
+                "Sierra class {} is missing its compiled definition",
+                class.hash
+            )
+        }
+        _ => {}
+    }
+    anyhow::ensure!(
+        computed.hash() == class.hash,
+        "Class hash mismatch: computed {} but the snapshot has {}",
+        computed.hash(),
+        class.hash
+    );
+
+    Ok(())
+}
+
+fn casm_hash(casm_definition: &[u8]) -> anyhow::Result<CasmHash> {
+    use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+
+    let casm: CasmContractClass =
+        serde_json::from_slice(casm_definition).context("Parsing compiled class definition")?;
+    let casm_hash = Felt::from_be_bytes(casm.compiled_class_hash().to_be_bytes())
+        .context("Compiled class hash out of range")?;
+
+    Ok(CasmHash(casm_hash))
+}
+
+struct Writer<W: Write> {
+    inner: W,
+    chunks: u64,
+}
+
+impl<W: Write> Writer<W> {
+    fn new(mut inner: W) -> anyhow::Result<Self> {
+        inner.write_all(MAGIC).context("Writing snapshot")?;
+        Ok(Self { inner, chunks: 0 })
+    }
+
+    fn write(&mut self, chunk: &Chunk) -> anyhow::Result<()> {
+        let json = serde_json::to_vec(chunk).context("Serializing chunk")?;
+
+        let mut encoder = zstd::stream::Encoder::new(Vec::new(), COMPRESSION_LEVEL)
+            .context("Creating zstd encoder")?;
+        encoder
+            .include_checksum(true)
+            .context("Enabling zstd checksum")?;
+        encoder.write_all(&json).context("Compressing chunk")?;
+        let frame = encoder.finish().context("Compressing chunk")?;
+
+        self.inner
+            .write_all(&(frame.len() as u64).to_le_bytes())
+            .context("Writing snapshot")?;
+        self.inner.write_all(&frame).context("Writing snapshot")?;
+        self.chunks += 1;
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> anyhow::Result<W> {
+        self.write(&Chunk::End {
+            chunks: self.chunks,
+        })?;
+        self.inner.flush().context("Writing snapshot")?;
+        Ok(self.inner)
+    }
+}
+
+struct Reader<R: Read> {
+    inner: R,
+    chunks: u64,
+}
+
+impl<R: Read> Reader<R> {
+    fn new(mut inner: R) -> anyhow::Result<Self> {
+        let mut magic = [0u8; MAGIC.len()];
+        inner.read_exact(&mut magic).context("Reading snapshot")?;
+        anyhow::ensure!(&magic == MAGIC, "Not a snapshot, or an unsupported version");
+
+        Ok(Self { inner, chunks: 0 })
+    }
+
+    fn read(&mut self) -> anyhow::Result<Chunk> {
+        let mut length = [0u8; 8];
+        self.inner
+            .read_exact(&mut length)
+            .context("Reading snapshot, it may be truncated")?;
+        let length = u64::from_le_bytes(length);
+        anyhow::ensure!(
+            length <= MAX_CHUNK_SIZE,
+            "Chunk {} is {length} bytes long, the snapshot is corrupted",
+            self.chunks
+        );
+
+        let mut frame = Vec::new();
+        (&mut self.inner)
+            .take(length)
+            .read_to_end(&mut frame)
+            .context("Reading snapshot")?;
+        anyhow::ensure!(
+            frame.len() as u64 == length,
+            "Reading snapshot, it is truncated"
+        );
+        // The frame's checksum is verified while decompressing.
+        let json = zstd::stream::decode_all(frame.as_slice()).with_context(|| {
+            format!(
+                "Decompressing chunk {}, the snapshot is corrupted",
+                self.chunks
+            )
+        })?;
+        let chunk = serde_json::from_slice(&json).context("Parsing chunk")?;
+
+        if let Chunk::End { chunks } = chunk {
+            anyhow::ensure!(
+                chunks == self.chunks,
+                "Snapshot should contain {chunks} chunks but has {}",
+                self.chunks
+            );
+        }
+        self.chunks += 1;
+
+        Ok(chunk)
+    }
+}
+
+impl From<BlockHeader> for Header {
+    fn from(header: BlockHeader) -> Self {
+        let BlockHeader {
+            hash,
+            parent_hash,
+            number,
+            timestamp,
+            eth_l1_gas_price,
+            strk_l1_gas_price,
+            sequencer_address,
+            starknet_version,
+            class_commitment,
+            event_commitment,
+            state_commitment,
+            storage_commitment,
+            transaction_commitment,
+            transaction_count,
+            event_count,
+        } = header;
+
+        Self {
+            hash,
+            parent_hash,
+            number,
+            timestamp,
+            eth_l1_gas_price,
+            strk_l1_gas_price,
+            sequencer_address,
+            starknet_version,
+            class_commitment,
+            event_commitment,
+            state_commitment,
+            storage_commitment,
+            transaction_commitment,
+            transaction_count,
+            event_count,
+        }
+    }
+}
+
+impl From<Header> for BlockHeader {
+    fn from(header: Header) -> Self {
+        let Header {
+            hash,
+            parent_hash,
+            number,
+            timestamp,
+            eth_l1_gas_price,
+            strk_l1_gas_price,
+            sequencer_address,
+            starknet_version,
+            class_commitment,
+            event_commitment,
+            state_commitment,
+            storage_commitment,
+            transaction_commitment,
+            transaction_count,
+            event_count,
+        } = header;
+
+        Self {
+            hash,
+            parent_hash,
+            number,
+            timestamp,
+            eth_l1_gas_price,
+            strk_l1_gas_price,
+            sequencer_address,
+            starknet_version,
+            class_commitment,
+            event_commitment,
+            state_commitment,
+            storage_commitment,
+            transaction_commitment,
+            transaction_count,
+            event_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+
+    use super::*;
+
+    fn snapshot() -> (Vec<Chunk>, Vec<u8>) {
+        let chunks = vec![
+            Chunk::Manifest(Manifest {
+                block_number: BlockNumber::GENESIS,
+                block_hash: block_hash_bytes!(b"genesis"),
+                state_commitment: state_commitment_bytes!(b"state commitment"),
+            }),
+            Chunk::Contracts(vec![Contract {
+                address: contract_address_bytes!(b"contract"),
+                class_hash: Some(class_hash_bytes!(b"class")),
+                nonce: contract_nonce_bytes!(b"nonce"),
+                storage: vec![(
+                    storage_address_bytes!(b"key"),
+                    storage_value_bytes!(b"value"),
+                )],
+            }]),
+        ];
+
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        for chunk in &chunks {
+            writer.write(chunk).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+
+        (chunks, bytes)
+    }
+
+    #[test]
+    fn round_trip() {
+        let (chunks, bytes) = snapshot();
+
+        let mut reader = Reader::new(bytes.as_slice()).unwrap();
+        for chunk in chunks {
+            assert_eq!(reader.read().unwrap(), chunk);
+        }
+        assert_eq!(reader.read().unwrap(), Chunk::End { chunks: 2 });
+    }
+
+    #[test]
+    fn truncation_is_detected() {
+        let (_, mut bytes) = snapshot();
+        bytes.truncate(bytes.len() - 1);
+
+        let mut reader = Reader::new(bytes.as_slice()).unwrap();
+        reader.read().unwrap();
+        reader.read().unwrap();
+        reader.read().unwrap_err();
+    }
+
+    #[test]
+    fn corruption_is_detected() {
+        let (_, mut bytes) = snapshot();
+        // Flip a bit in the compressed content of the last chunk.
+        let last = bytes.len() - 6;
+        bytes[last] ^= 1;
+
+        let mut reader = Reader::new(bytes.as_slice()).unwrap();
+        reader.read().unwrap();
+        reader.read().unwrap();
+        reader.read().unwrap_err();
+    }
+
+    /// Rewrites the snapshot at `path`, modifying its chunks with `f`.
+    fn tamper(path: &Path, mut f: impl FnMut(&mut Chunk)) {
+        let bytes = std::fs::read(path).unwrap();
+        let mut reader = Reader::new(bytes.as_slice()).unwrap();
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        loop {
+            let mut chunk = reader.read().unwrap();
+            if matches!(chunk, Chunk::End { .. }) {
+                break;
+            }
+            f(&mut chunk);
+            writer.write(&chunk).unwrap();
+        }
+        std::fs::write(path, writer.finish().unwrap()).unwrap();
+    }
+
+    /// Exports block 1 of a database with three blocks.
+    fn export_snapshot(dir: &Path) -> (Vec<BlockHeader>, std::path::PathBuf) {
+        let (_, headers) = crate::db::test_utils::create_database(&dir.join("source.sqlite"), 3);
+
+        let output = dir.join("snapshot");
+        export(DbExportSnapshotConfig {
+            database: dir.join("source.sqlite"),
+            block: Some(1),
+            output: output.clone(),
+        })
+        .unwrap();
+
+        (headers, output)
+    }
+
+    #[test]
+    fn export_and_import() {
+        use crate::db::test_utils::{sierra_class, CONTRACT, SIERRA_DEFINITION};
+
+        let dir = tempfile::tempdir().unwrap();
+        let (headers, snapshot) = export_snapshot(dir.path());
+
+        let database = dir.path().join("imported.sqlite");
+        import(DbImportSnapshotConfig {
+            database: database.clone(),
+            snapshot,
+        })
+        .unwrap();
+
+        let storage = Storage::migrate(database, JournalMode::WAL, 1)
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let block = headers[1].number;
+        assert_eq!(
+            tx.block_header(BlockId::Latest).unwrap().unwrap(),
+            headers[1]
+        );
+        assert_eq!(
+            tx.block_header(BlockNumber::GENESIS.into())
+                .unwrap()
+                .unwrap(),
+            headers[0]
+        );
+
+        let key = |n: u64| StorageAddress::new_or_panic(Felt::from_u64(n));
+        assert_eq!(
+            tx.storage_value(block.into(), CONTRACT, key(0)).unwrap(),
+            Some(StorageValue(Felt::from_u64(1)))
+        );
+        assert_eq!(
+            tx.storage_value(block.into(), CONTRACT, key(1)).unwrap(),
+            Some(StorageValue(Felt::from_u64(2)))
+        );
+        assert_eq!(
+            tx.storage_value(block.into(), CONTRACT, key(2)).unwrap(),
+            None
+        );
+        assert!(tx.contract_exists(CONTRACT, block.into()).unwrap());
+        let sierra_class = ClassHash(sierra_class().0);
+        assert_eq!(
+            tx.class_definition(sierra_class).unwrap(),
+            Some(SIERRA_DEFINITION.to_vec())
+        );
+        assert!(tx.casm_definition(sierra_class).unwrap().is_some());
+
+        // Only the snapshot's block has state, and no block has a body.
+        tx.storage_value(BlockNumber::GENESIS.into(), CONTRACT, key(0))
+            .unwrap_err();
+        assert_eq!(tx.first_block_with_state().unwrap(), block);
+        assert!(tx.block_body_pruned(block).unwrap());
+    }
+
+    #[test]
+    fn state_commitment_mismatch_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, snapshot) = export_snapshot(dir.path());
+        tamper(&snapshot, |chunk| {
+            if let Chunk::Contracts(contracts) = chunk {
+                contracts[0]
+                    .storage
+                    .push((storage_address!("0x1234"), storage_value!("0x1")));
+            }
+        });
+
+        let database = dir.path().join("imported.sqlite");
+        let error = import(DbImportSnapshotConfig {
+            database: database.clone(),
+            snapshot,
+        })
+        .unwrap_err();
+
+        assert!(
+            format!("{error:#}").contains("State commitment mismatch"),
+            "{error:#}"
+        );
+        assert!(!database.exists());
+    }
+
+    #[test]
+    fn block_hash_mismatch_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, snapshot) = export_snapshot(dir.path());
+        tamper(&snapshot, |chunk| {
+            if let Chunk::Headers(headers) = chunk {
+                headers[0].timestamp = BlockTimestamp::new_or_panic(1);
+            }
+        });
+
+        let database = dir.path().join("imported.sqlite");
+        let error = import(DbImportSnapshotConfig {
+            database: database.clone(),
+            snapshot,
+        })
+        .unwrap_err();
+
+        assert!(
+            format!("{error:#}").contains("does not match its header"),
+            "{error:#}"
+        );
+        assert!(!database.exists());
+    }
+
+    #[test]
+    fn class_hash_mismatch_is_rejected() {
+        use starknet_gateway_test_fixtures::class_definitions::DUMMY_ACCOUNT;
+
+        let dir = tempfile::tempdir().unwrap();
+        let (_, snapshot) = export_snapshot(dir.path());
+        tamper(&snapshot, |chunk| {
+            if let Chunk::Classes(classes) = chunk {
+                let class = classes.iter_mut().find(|c| c.casm.is_none()).unwrap();
+                class.definition = base64::encode(DUMMY_ACCOUNT);
+            }
+        });
+
+        let database = dir.path().join("imported.sqlite");
+        let error = import(DbImportSnapshotConfig {
+            database: database.clone(),
+            snapshot,
+        })
+        .unwrap_err();
+
+        assert!(
+            format!("{error:#}").contains("Class hash mismatch"),
+            "{error:#}"
+        );
+        assert!(!database.exists());
+    }
+}
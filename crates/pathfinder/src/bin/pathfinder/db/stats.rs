@@ -0,0 +1,382 @@
+//! Reports the space used by each table and category of data.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::num::NonZeroU32;
+
+use anyhow::Context;
+use pathfinder_common::BlockNumber;
+use pathfinder_storage::{BlockId, DataCategory, DatabaseStats, Storage};
+
+use crate::config::{DbStatsConfig, DbStatsFormat};
+
+#[derive(Debug, serde::Serialize)]
+struct Report {
+    size: u64,
+    page_size: u64,
+    categories: BTreeMap<&'static str, CategoryReport>,
+    tables: Vec<TableReport>,
+    savings: Savings,
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+struct CategoryReport {
+    size: u64,
+    rows: u64,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct TableReport {
+    name: String,
+    category: &'static str,
+    rows: u64,
+    size: u64,
+    average_row_size: u64,
+    unused: u64,
+}
+
+/// Estimated space freed by maintenance operations.
+#[derive(Debug, serde::Serialize)]
+struct Savings {
+    /// Free pages reclaimed by `VACUUM`.
+    vacuum: u64,
+    /// Transactions, receipts and events deleted when keeping only the configured number of
+    /// block bodies, assuming all blocks are of average size.
+    block_body_pruning: Option<u64>,
+}
+
+pub fn run(config: DbStatsConfig) -> anyhow::Result<()> {
+    let storage = Storage::open_read_only(config.database.clone(), 1)
+        .context("Opening database")?
+        .create_pool(NonZeroU32::new(1).unwrap())
+        .context("Creating database connection pool")?;
+    let mut db = storage
+        .connection()
+        .context("Opening database connection")?;
+    let tx = db.transaction().context("Creating database transaction")?;
+
+    tracing::info!("Reading database statistics, this reads the entire database");
+    let stats = tx.database_stats().context("Reading database statistics")?;
+
+    let block_body_pruning = match config.block_body_history {
+        Some(history) => {
+            let latest = tx
+                .block_id(BlockId::Latest)?
+                .map(|(number, _)| number)
+                .unwrap_or_default();
+            let first_unpruned = tx.first_unpruned_block_body()?;
+            Some(block_body_pruning_savings(
+                &stats,
+                latest,
+                first_unpruned,
+                history,
+            ))
+        }
+        None => None,
+    };
+
+    let report = Report::new(stats, block_body_pruning);
+    let output = match config.format {
+        DbStatsFormat::Table => report.to_table(),
+        DbStatsFormat::Json => {
+            serde_json::to_string_pretty(&report).context("Serializing report")? + "\n"
+        }
+        DbStatsFormat::Prometheus => report.to_prometheus(),
+    };
+    print!("{output}");
+
+    Ok(())
+}
+
+/// Estimates the space freed by pruning all but the latest `history` block bodies, by assuming
+/// that all blocks whose bodies are still stored are of the same size.
+fn block_body_pruning_savings(
+    stats: &DatabaseStats,
+    latest: BlockNumber,
+    first_unpruned: BlockNumber,
+    history: u64,
+) -> u64 {
+    let stored = (latest.get() + 1).saturating_sub(first_unpruned.get());
+    let pruned = stored.saturating_sub(history + 1);
+    if pruned == 0 {
+        return 0;
+    }
+
+    let size: u64 = stats
+        .tables
+        .iter()
+        .filter(|table| {
+            matches!(
+                table.category,
+                DataCategory::Transactions | DataCategory::Events
+            )
+        })
+        .map(|table| table.size)
+        .sum();
+
+    (size as u128 * pruned as u128 / stored as u128) as u64
+}
+
+fn category_name(category: DataCategory) -> &'static str {
+    match category {
+        DataCategory::Blocks => "blocks",
+        DataCategory::Transactions => "transactions",
+        DataCategory::Events => "events",
+        DataCategory::StateDiffs => "state_diffs",
+        DataCategory::Classes => "classes",
+        DataCategory::Tries => "tries",
+        DataCategory::Other => "other",
+    }
+}
+
+impl Report {
+    fn new(stats: DatabaseStats, block_body_pruning: Option<u64>) -> Self {
+        let mut categories = BTreeMap::<_, CategoryReport>::new();
+        for table in &stats.tables {
+            let category = categories.entry(category_name(table.category)).or_default();
+            category.size += table.size;
+            category.rows += table.rows;
+        }
+
+        let tables = stats
+            .tables
+            .iter()
+            .map(|table| TableReport {
+                name: table.name.clone(),
+                category: category_name(table.category),
+                rows: table.rows,
+                size: table.size,
+                average_row_size: table.average_row_size(),
+                unused: table.unused,
+            })
+            .collect();
+
+        Self {
+            size: stats.size(),
+            page_size: stats.page_size,
+            categories,
+            tables,
+            savings: Savings {
+                vacuum: stats.vacuum_savings(),
+                block_body_pruning,
+            },
+        }
+    }
+
+    fn to_table(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "Database size: {}", human_size(self.size));
+        let _ = writeln!(out);
+        let _ = writeln!(out, "{:<16} {:>12} {:>16}", "CATEGORY", "SIZE", "ROWS");
+        let mut categories = self.categories.iter().collect::<Vec<_>>();
+        categories.sort_by_key(|(_, category)| std::cmp::Reverse(category.size));
+        for (name, category) in categories {
+            let _ = writeln!(
+                out,
+                "{:<16} {:>12} {:>16}",
+                name,
+                human_size(category.size),
+                category.rows
+            );
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "{:<32} {:<16} {:>12} {:>16} {:>12} {:>12}",
+            "TABLE", "CATEGORY", "SIZE", "ROWS", "AVG ROW", "UNUSED"
+        );
+        for table in &self.tables {
+            let _ = writeln!(
+                out,
+                "{:<32} {:<16} {:>12} {:>16} {:>12} {:>12}",
+                table.name,
+                table.category,
+                human_size(table.size),
+                table.rows,
+                human_size(table.average_row_size),
+                human_size(table.unused)
+            );
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "Estimated savings from VACUUM: {}",
+            human_size(self.savings.vacuum)
+        );
+        if let Some(savings) = self.savings.block_body_pruning {
+            let _ = writeln!(
+                out,
+                "Estimated savings from block body pruning: {}",
+                human_size(savings)
+            );
+        }
+
+        out
+    }
+
+    /// Formats the report in Prometheus' text exposition format, e.g. for node exporter's
+    /// textfile collector.
+    fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        let mut gauge =
+            |name: &str, help: &str, values: &mut dyn Iterator<Item = (String, u64)>| {
+                let _ = writeln!(out, "# HELP {name} {help}");
+                let _ = writeln!(out, "# TYPE {name} gauge");
+                for (labels, value) in values {
+                    let _ = writeln!(out, "{name}{labels} {value}");
+                }
+            };
+
+        gauge(
+            "pathfinder_db_size_bytes",
+            "Size of the database file",
+            &mut std::iter::once((String::new(), self.size)),
+        );
+        gauge(
+            "pathfinder_db_category_size_bytes",
+            "Size of the tables of a data category, including indexes",
+            &mut self
+                .categories
+                .iter()
+                .map(|(name, category)| (format!("{{category=\"{name}\"}}"), category.size)),
+        );
+        gauge(
+            "pathfinder_db_table_size_bytes",
+            "Size of a table, including indexes",
+            &mut self
+                .tables
+                .iter()
+                .map(|table| (table_labels(table), table.size)),
+        );
+        gauge(
+            "pathfinder_db_table_rows",
+            "Number of rows in a table",
+            &mut self
+                .tables
+                .iter()
+                .map(|table| (table_labels(table), table.rows)),
+        );
+        gauge(
+            "pathfinder_db_table_unused_bytes",
+            "Unused space within the pages of a table and its indexes",
+            &mut self
+                .tables
+                .iter()
+                .map(|table| (table_labels(table), table.unused)),
+        );
+        gauge(
+            "pathfinder_db_vacuum_savings_bytes",
+            "Estimated space freed by VACUUM",
+            &mut std::iter::once((String::new(), self.savings.vacuum)),
+        );
+        if let Some(savings) = self.savings.block_body_pruning {
+            gauge(
+                "pathfinder_db_block_body_pruning_savings_bytes",
+                "Estimated space freed by block body pruning",
+                &mut std::iter::once((String::new(), savings)),
+            );
+        }
+
+        out
+    }
+}
+
+fn table_labels(table: &TableReport) -> String {
+    format!(
+        "{{table=\"{}\",category=\"{}\"}}",
+        table.name, table.category
+    )
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{size:.1} {}", UNITS[unit]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_storage::TableStats;
+
+    use super::*;
+
+    fn stats() -> DatabaseStats {
+        let table = |name: &str, category, size| TableStats {
+            name: name.to_owned(),
+            category,
+            rows: 10,
+            size,
+            payload: size / 2,
+            unused: 0,
+        };
+
+        DatabaseStats {
+            page_size: 4096,
+            page_count: 100,
+            freelist_count: 10,
+            tables: vec![
+                table("trie_storage", DataCategory::Tries, 200_000),
+                table("starknet_transactions", DataCategory::Transactions, 100_000),
+                table("starknet_events_filters", DataCategory::Events, 20_000),
+            ],
+        }
+    }
+
+    #[test]
+    fn block_body_pruning_estimate() {
+        let stats = stats();
+
+        // 100 blocks stored, 90 would be pruned.
+        let savings = block_body_pruning_savings(
+            &stats,
+            BlockNumber::new_or_panic(99),
+            BlockNumber::GENESIS,
+            9,
+        );
+        assert_eq!(savings, 108_000);
+
+        // Already pruned down to the history.
+        let savings = block_body_pruning_savings(
+            &stats,
+            BlockNumber::new_or_panic(99),
+            BlockNumber::new_or_panic(90),
+            9,
+        );
+        assert_eq!(savings, 0);
+    }
+
+    #[test]
+    fn prometheus_format() {
+        let report = Report::new(stats(), None);
+        let output = report.to_prometheus();
+
+        assert!(output.contains("# TYPE pathfinder_db_table_size_bytes gauge\n"));
+        assert!(output.contains(
+            "pathfinder_db_table_size_bytes{table=\"trie_storage\",category=\"tries\"} 200000\n"
+        ));
+        assert!(output.contains("pathfinder_db_category_size_bytes{category=\"events\"} 20000\n"));
+        assert!(output.contains("pathfinder_db_vacuum_savings_bytes 40960\n"));
+        assert!(!output.contains("block_body_pruning"));
+    }
+
+    #[test]
+    fn human_sizes() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
@@ -0,0 +1,151 @@
+//! Helpers for testing the database commands against a realistic database.
+
+use std::num::NonZeroU32;
+use std::path::Path;
+
+use pathfinder_common::macro_prelude::*;
+use pathfinder_common::{
+    BlockHash, BlockHeader, BlockNumber, BlockTimestamp, CasmHash, ClassHash, ContractAddress,
+    SequencerAddress, SierraHash, StarknetVersion, StateCommitment, StateUpdate, StorageAddress,
+    StorageValue,
+};
+use pathfinder_crypto::Felt;
+use pathfinder_lib::state::block_hash::compute_block_hash;
+use pathfinder_lib::state::update_starknet_state;
+use pathfinder_storage::{JournalMode, Storage};
+use starknet_gateway_test_fixtures::class_definitions::{
+    CAIRO_1_1_0_RC0_SIERRA, ERC20_CONTRACT_DEFINITION, ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+};
+use starknet_gateway_types::class_hash::compute_class_hash;
+use starknet_gateway_types::reply::{Block, Status};
+
+/// The contract whose storage is updated by every block.
+pub const CONTRACT: ContractAddress = contract_address!("0xc0");
+
+/// The Cairo class declared in the genesis block.
+pub const CAIRO_CLASS: ClassHash = ERC20_CONTRACT_DEFINITION_CLASS_HASH;
+
+/// The definition of the Sierra class declared in the genesis block.
+pub const SIERRA_DEFINITION: &[u8] = CAIRO_1_1_0_RC0_SIERRA;
+
+/// The hash of [SIERRA_DEFINITION].
+pub fn sierra_class() -> SierraHash {
+    SierraHash(compute_class_hash(SIERRA_DEFINITION).unwrap().hash().0)
+}
+
+/// Creates a database at `path` containing `num_blocks` blocks with valid state tries, state
+/// commitments and block hashes.
+///
+/// Block `n` deploys a new contract and sets storage key `n` of [CONTRACT], which is deployed in
+/// the genesis block along with [CAIRO_CLASS] and the Sierra class [SIERRA_DEFINITION]. The class definitions are real,
+/// so their hashes can be verified.
+pub fn create_database(path: &Path, num_blocks: u64) -> (Storage, Vec<BlockHeader>) {
+    let storage = Storage::migrate(path.to_owned(), JournalMode::WAL, 1)
+        .unwrap()
+        .create_pool(NonZeroU32::new(4).unwrap())
+        .unwrap();
+    let mut connection = storage.connection().unwrap();
+
+    let cairo_class = CAIRO_CLASS;
+    let sierra_class = sierra_class();
+    let casm_definition =
+        pathfinder_compiler::compile_to_casm_with_latest_compiler(SIERRA_DEFINITION).unwrap();
+    let casm_hash = casm_hash(&casm_definition);
+
+    let mut headers: Vec<BlockHeader> = Vec::new();
+    for number in 0..num_blocks {
+        let tx = connection.transaction().unwrap();
+        let number = BlockNumber::new_or_panic(number);
+
+        let mut state_update = StateUpdate::default()
+            .with_deployed_contract(
+                ContractAddress::new_or_panic(Felt::from_u64(0x1000 + number.get())),
+                cairo_class,
+            )
+            .with_storage_update(
+                CONTRACT,
+                StorageAddress::new_or_panic(Felt::from_u64(number.get())),
+                StorageValue(Felt::from_u64(number.get() + 1)),
+            );
+        if number == BlockNumber::GENESIS {
+            tx.insert_cairo_class(cairo_class, ERC20_CONTRACT_DEFINITION)
+                .unwrap();
+            tx.insert_sierra_class(
+                &sierra_class,
+                SIERRA_DEFINITION,
+                &casm_hash,
+                &casm_definition,
+            )
+            .unwrap();
+            state_update = state_update
+                .with_declared_cairo_class(cairo_class)
+                .with_declared_sierra_class(sierra_class, casm_hash)
+                .with_deployed_contract(CONTRACT, cairo_class);
+        }
+
+        let (storage_commitment, class_commitment) =
+            update_starknet_state(&tx, &state_update, true, number, storage.clone()).unwrap();
+        let state_commitment = StateCommitment::calculate(storage_commitment, class_commitment);
+
+        let parent_hash = headers.last().map(|h| h.hash).unwrap_or(BlockHash::ZERO);
+        let timestamp = BlockTimestamp::new_or_panic(1_700_000_000 + number.get());
+        let sequencer_address = SequencerAddress(Felt::from_u64(0x5e9));
+        let starknet_version = StarknetVersion::from("0.13.0".to_owned());
+        let (hash, transaction_commitment, event_commitment) = compute_block_hash(&Block {
+            block_hash: BlockHash::ZERO,
+            block_number: number,
+            eth_l1_gas_price: None,
+            strk_l1_gas_price: None,
+            parent_block_hash: parent_hash,
+            sequencer_address: Some(sequencer_address),
+            state_commitment,
+            status: Status::AcceptedOnL2,
+            timestamp,
+            transaction_receipts: vec![],
+            transactions: vec![],
+            starknet_version: starknet_version.clone(),
+        })
+        .unwrap();
+
+        let header = BlockHeader {
+            hash,
+            parent_hash,
+            number,
+            timestamp,
+            sequencer_address,
+            starknet_version,
+            class_commitment,
+            event_commitment,
+            state_commitment,
+            storage_commitment,
+            transaction_commitment,
+            ..Default::default()
+        };
+        tx.insert_block_header(&header).unwrap();
+        tx.insert_transaction_data(header.hash, header.number, &[])
+            .unwrap();
+        let state_update = state_update
+            .with_block_hash(hash)
+            .with_state_commitment(state_commitment)
+            .with_parent_state_commitment(
+                headers
+                    .last()
+                    .map(|h| h.state_commitment)
+                    .unwrap_or_default(),
+            );
+        tx.insert_state_update(header.number, &state_update)
+            .unwrap();
+        tx.commit().unwrap();
+
+        headers.push(header);
+    }
+
+    (storage, headers)
+}
+
+fn casm_hash(casm_definition: &[u8]) -> CasmHash {
+    use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+
+    let casm: CasmContractClass = serde_json::from_slice(casm_definition).unwrap();
+    CasmHash(Felt::from_be_bytes(casm.compiled_class_hash().to_be_bytes()).unwrap())
+}
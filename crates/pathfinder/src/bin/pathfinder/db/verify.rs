@@ -0,0 +1,778 @@
+//! Checks the consistency of the data stored for a range of blocks.
+//!
+//! Every inconsistency found is written to the report as a JSON object on its own line, instead
+//! of aborting at the first one, so that the extent of any damage is known up front.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::num::NonZeroU32;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use pathfinder_common::{
+    BlockHash, BlockHeader, BlockNumber, Chain, ChainId, ClassCommitment, ClassHash,
+    ContractAddress, StateCommitment, StorageCommitment,
+};
+use pathfinder_crypto::Felt;
+use pathfinder_lib::state::block_hash::{
+    calculate_event_commitment, calculate_transaction_commitment, verify_block_hash,
+    verify_header_hash, BlockHashMetaInfo, TransactionCommitmentFinalHashType, VerifyResult,
+};
+use pathfinder_merkle_tree::contract_state::calculate_contract_state_hash;
+use pathfinder_merkle_tree::{ClassCommitmentTree, ContractsStorageTree, StorageCommitmentTree};
+use pathfinder_storage::{BlockId, Storage, Transaction, TriePruneMode};
+use rayon::prelude::*;
+use starknet_gateway_types::reply::{Block, Status};
+
+use crate::config::DbVerifyConfig;
+
+/// How often the progress of the verification is logged.
+const PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Check {
+    CanonicalChain,
+    BlockBody,
+    TransactionHash,
+    TransactionCommitment,
+    EventCommitment,
+    BlockHash,
+    StateUpdate,
+    Classes,
+    StateCommitment,
+    Trie,
+}
+
+impl Check {
+    fn hint(self) -> &'static str {
+        match self {
+            Check::CanonicalChain => {
+                "The chain is broken at this block. Restore the database from a backup taken before this block, or resync it."
+            }
+            Check::BlockBody
+            | Check::TransactionHash
+            | Check::TransactionCommitment
+            | Check::EventCommitment
+            | Check::BlockHash => {
+                "The stored block data is corrupted. Restore the database from a backup taken before this block, or resync it."
+            }
+            Check::StateUpdate => {
+                "The state diff of this block is missing, so the state of all later blocks is unreliable. Restore the database from a backup taken before this block, or resync it."
+            }
+            Check::Classes => {
+                "Class definitions are downloaded separately from blocks. Restarting the node against this database may fetch missing classes, otherwise resync from before this block."
+            }
+            Check::StateCommitment | Check::Trie => {
+                "Trie nodes cannot be repaired in place. Restore the database from a backup taken before this block, or resync it."
+            }
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Issue {
+    block: BlockNumber,
+    check: Check,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<&'static str>,
+}
+
+struct Report {
+    output: BufWriter<File>,
+    hints: bool,
+    issues: usize,
+}
+
+impl Report {
+    fn add(&mut self, block: BlockNumber, check: Check, message: String) -> anyhow::Result<()> {
+        let issue = Issue {
+            block,
+            check,
+            message,
+            hint: self.hints.then(|| check.hint()),
+        };
+
+        tracing::warn!(%block, ?check, message=%issue.message, "Inconsistency found");
+        serde_json::to_writer(&mut self.output, &issue).context("Writing report")?;
+        self.output.write_all(b"\n").context("Writing report")?;
+        self.issues += 1;
+
+        Ok(())
+    }
+}
+
+pub fn run(config: DbVerifyConfig) -> anyhow::Result<()> {
+    let storage = Storage::open_read_only(config.database.clone(), 1)
+        .context("Opening database")?
+        .create_pool(NonZeroU32::new(1).unwrap())
+        .context("Creating database connection pool")?;
+    let mut db = storage
+        .connection()
+        .context("Opening database connection")?;
+
+    let (latest, chain) = {
+        let tx = db.transaction().context("Creating database transaction")?;
+        let (latest, _) = tx
+            .block_id(BlockId::Latest)?
+            .context("Database contains no blocks")?;
+        (latest, chain(&tx)?)
+    };
+    if chain.is_none() {
+        tracing::warn!("Unknown chain, transaction hashes are not verified");
+    }
+
+    let from = BlockNumber::new(config.from).context("Block number out of range")?;
+    let to = match config.to {
+        Some(to) => BlockNumber::new(to).context("Block number out of range")?,
+        None => latest,
+    };
+    anyhow::ensure!(
+        to <= latest,
+        "Last block {to} is past the latest block in the database {latest}"
+    );
+    anyhow::ensure!(from <= to, "Block range {from}..={to} is empty");
+
+    // Only the tries of the most recent blocks are kept when pruning.
+    let first_trie = match storage.trie_prune_mode() {
+        TriePruneMode::Archive => BlockNumber::GENESIS,
+        TriePruneMode::Prune { num_blocks_kept } => {
+            BlockNumber::new_or_panic(latest.get().saturating_sub(num_blocks_kept))
+        }
+    };
+
+    let output = File::create(&config.output)
+        .with_context(|| format!("Creating report file {}", config.output.display()))?;
+    let mut report = Report {
+        output: BufWriter::new(output),
+        hints: config.hints,
+        issues: 0,
+    };
+
+    let mut parent = match from.parent() {
+        Some(parent) => {
+            let tx = db.transaction().context("Creating database transaction")?;
+            tx.block_header(parent.into())
+                .context("Fetching parent header")?
+        }
+        None => None,
+    };
+
+    tracing::info!(%from, %to, "Verifying database");
+    let mut last_logged = Instant::now();
+    for number in from.get()..=to.get() {
+        let block = BlockNumber::new_or_panic(number);
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let header = verify_canonical_chain(&tx, block, parent.as_ref(), &mut report)?;
+        if let Some(header) = &header {
+            verify_block_body(&tx, header, chain, &mut report)?;
+            verify_state_commitment(&tx, header, block >= first_trie, &mut report)?;
+        }
+        verify_state_update(&tx, block, &mut report)?;
+
+        parent = header;
+
+        if last_logged.elapsed() >= PROGRESS_LOG_INTERVAL {
+            tracing::info!(%block, issues=%report.issues, "Verifying database");
+            last_logged = Instant::now();
+        }
+    }
+
+    if config.tries {
+        if to >= first_trie {
+            let tx = db.transaction().context("Creating database transaction")?;
+            verify_tries(&tx, to, &mut report)?;
+        } else {
+            tracing::warn!(block=%to, "Tries have been pruned, skipping full trie verification");
+        }
+    }
+
+    report.output.flush().context("Writing report")?;
+
+    anyhow::ensure!(
+        report.issues == 0,
+        "Found {} inconsistencies in blocks {from}..={to}, see {}",
+        report.issues,
+        config.output.display()
+    );
+    tracing::info!(%from, %to, "No inconsistencies found");
+
+    Ok(())
+}
+
+/// Checks that the block is part of the canonical chain and links to its parent. Returns the
+/// block's header if it exists.
+fn verify_canonical_chain(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+    parent: Option<&BlockHeader>,
+    report: &mut Report,
+) -> anyhow::Result<Option<BlockHeader>> {
+    let header = tx
+        .block_header(block.into())
+        .context("Fetching block header")?;
+    let canonical = tx.block_id(block.into()).context("Fetching block id")?;
+
+    let header = match (header, canonical) {
+        (Some(header), Some((_, hash))) => {
+            if header.hash != hash {
+                report.add(
+                    block,
+                    Check::CanonicalChain,
+                    format!(
+                        "Canonical chain has hash {hash} but the header has {}",
+                        header.hash
+                    ),
+                )?;
+            }
+            header
+        }
+        (Some(header), None) => {
+            report.add(
+                block,
+                Check::CanonicalChain,
+                "Block is missing from the canonical chain".to_owned(),
+            )?;
+            header
+        }
+        (None, _) => {
+            report.add(
+                block,
+                Check::CanonicalChain,
+                "Block header is missing".to_owned(),
+            )?;
+            return Ok(None);
+        }
+    };
+
+    if let Some(parent) = parent {
+        if header.parent_hash != parent.hash {
+            report.add(
+                block,
+                Check::CanonicalChain,
+                format!(
+                    "Parent hash {} does not match the hash {} of the previous block",
+                    header.parent_hash, parent.hash
+                ),
+            )?;
+        }
+    }
+
+    Ok(Some(header))
+}
+
+/// Verifies the transaction hashes, commitments and the block hash.
+fn verify_block_body(
+    tx: &Transaction<'_>,
+    header: &BlockHeader,
+    chain: Option<(Chain, ChainId)>,
+    report: &mut Report,
+) -> anyhow::Result<()> {
+    let block = header.number;
+    let (meta_info, chain_id) = block_hash_meta_info(chain);
+
+    if tx.block_body_pruned(block)? {
+        // Without the body, the hash can only be verified against the header's commitments.
+        if verify_header_hash(header, meta_info, chain_id) == VerifyResult::Mismatch {
+            report.add(
+                block,
+                Check::BlockHash,
+                format!(
+                    "Block hash {} does not match the block's header",
+                    header.hash
+                ),
+            )?;
+        }
+        return Ok(());
+    }
+
+    let Some(transactions) = tx
+        .transaction_data_for_block(block.into())
+        .context("Fetching transaction data")?
+    else {
+        report.add(
+            block,
+            Check::BlockBody,
+            "Transactions and receipts are missing".to_owned(),
+        )?;
+        return Ok(());
+    };
+    let (transactions, receipts): (Vec<_>, Vec<_>) = transactions.into_iter().unzip();
+
+    let event_count = receipts.iter().map(|r| r.events.len()).sum::<usize>();
+    if transactions.len() != header.transaction_count || event_count != header.event_count {
+        report.add(
+            block,
+            Check::BlockBody,
+            format!(
+                "Header has {} transactions and {} events, but {} transactions and {} events are stored",
+                header.transaction_count,
+                header.event_count,
+                transactions.len(),
+                event_count
+            ),
+        )?;
+    }
+
+    // Transaction hashes include the chain id, which is unknown for custom networks.
+    if chain.is_some() {
+        let mismatches = transactions
+            .par_iter()
+            .filter_map(|txn| {
+                match starknet_gateway_types::transaction_hash::verify(txn, chain_id) {
+                    starknet_gateway_types::transaction_hash::VerifyResult::Match => None,
+                    starknet_gateway_types::transaction_hash::VerifyResult::Mismatch(
+                        calculated,
+                    ) => Some((txn.hash(), calculated)),
+                }
+            })
+            .collect::<Vec<_>>();
+        for (hash, calculated) in mismatches {
+            report.add(
+                block,
+                Check::TransactionHash,
+                format!("Transaction {hash} hashes to {calculated}"),
+            )?;
+        }
+    }
+
+    let block_data = Block {
+        block_hash: header.hash,
+        block_number: block,
+        eth_l1_gas_price: Some(header.eth_l1_gas_price),
+        strk_l1_gas_price: Some(header.strk_l1_gas_price),
+        parent_block_hash: header.parent_hash,
+        sequencer_address: Some(header.sequencer_address),
+        state_commitment: header.state_commitment,
+        status: Status::AcceptedOnL2,
+        timestamp: header.timestamp,
+        transaction_receipts: receipts,
+        transactions,
+        starknet_version: header.starknet_version.clone(),
+    };
+    let result = verify_block_hash(&block_data, meta_info, chain_id, header.hash)
+        .context("Verifying block hash")?;
+
+    match result {
+        VerifyResult::Match((transaction_commitment, event_commitment)) => {
+            compare_commitments(header, transaction_commitment, event_commitment, report)
+        }
+        VerifyResult::Mismatch => {
+            report.add(
+                block,
+                Check::BlockHash,
+                format!(
+                    "Block hash {} does not match the block's content",
+                    header.hash
+                ),
+            )?;
+            // Narrow down the cause of the mismatch.
+            verify_commitments(
+                header,
+                &block_data.transactions,
+                &block_data.transaction_receipts,
+                report,
+            )
+        }
+        VerifyResult::NotVerifiable => Ok(()),
+    }
+}
+
+fn verify_commitments(
+    header: &BlockHeader,
+    transactions: &[starknet_gateway_types::reply::transaction::Transaction],
+    receipts: &[starknet_gateway_types::reply::transaction::Receipt],
+    report: &mut Report,
+) -> anyhow::Result<()> {
+    let final_hash_type =
+        TransactionCommitmentFinalHashType::for_version(&header.starknet_version)?;
+    let transaction_commitment = calculate_transaction_commitment(transactions, final_hash_type)
+        .context("Calculating transaction commitment")?;
+    let event_commitment =
+        calculate_event_commitment(receipts).context("Calculating event commitment")?;
+
+    compare_commitments(header, transaction_commitment, event_commitment, report)
+}
+
+fn compare_commitments(
+    header: &BlockHeader,
+    transaction_commitment: pathfinder_common::TransactionCommitment,
+    event_commitment: pathfinder_common::EventCommitment,
+    report: &mut Report,
+) -> anyhow::Result<()> {
+    // Commitments are not stored for blocks whose hash could not be verified while syncing.
+    if header.transaction_commitment != Default::default()
+        && header.transaction_commitment != transaction_commitment
+    {
+        report.add(
+            header.number,
+            Check::TransactionCommitment,
+            format!(
+                "Header has transaction commitment {} but the transactions commit to {}",
+                header.transaction_commitment, transaction_commitment
+            ),
+        )?;
+    }
+
+    if header.event_commitment != Default::default() && header.event_commitment != event_commitment
+    {
+        report.add(
+            header.number,
+            Check::EventCommitment,
+            format!(
+                "Header has event commitment {} but the events commit to {}",
+                header.event_commitment, event_commitment
+            ),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Checks that the state diff exists, and that all classes it refers to have been downloaded.
+fn verify_state_update(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+    report: &mut Report,
+) -> anyhow::Result<()> {
+    let Some(state_update) = tx
+        .state_update(block.into())
+        .context("Fetching state update")?
+    else {
+        return report.add(
+            block,
+            Check::StateUpdate,
+            "State update is missing".to_owned(),
+        );
+    };
+
+    let mut classes = state_update
+        .declared_sierra_classes
+        .keys()
+        .map(|sierra| ClassHash(sierra.0))
+        .chain(state_update.declared_cairo_classes.iter().copied())
+        .chain(
+            state_update
+                .contract_updates
+                .values()
+                .filter_map(|update| update.class.as_ref().map(|class| class.class_hash())),
+        )
+        .collect::<Vec<_>>();
+    classes.sort();
+    classes.dedup();
+
+    let exist = tx
+        .class_definitions_exist(&classes)
+        .context("Querying class definitions")?;
+    for (class, exists) in classes.into_iter().zip(exist) {
+        if !exists {
+            report.add(
+                block,
+                Check::Classes,
+                format!("Definition of class {class} is missing"),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the stored trie roots match the header's commitments, and that those make up the
+/// state commitment.
+fn verify_state_commitment(
+    tx: &Transaction<'_>,
+    header: &BlockHeader,
+    tries_available: bool,
+    report: &mut Report,
+) -> anyhow::Result<()> {
+    let block = header.number;
+
+    let state_commitment =
+        StateCommitment::calculate(header.storage_commitment, header.class_commitment);
+    if state_commitment != header.state_commitment {
+        report.add(
+            block,
+            Check::StateCommitment,
+            format!(
+                "Header has state commitment {} but its storage and class commitments hash to {}",
+                header.state_commitment, state_commitment
+            ),
+        )?;
+    }
+
+    if !tries_available {
+        return Ok(());
+    }
+
+    let storage_root = match tx
+        .storage_root_index(block)
+        .context("Querying storage root index")?
+    {
+        Some(index) => tx
+            .storage_trie_node_hash(index)
+            .context("Querying storage root hash")?
+            .map(StorageCommitment),
+        None => Some(StorageCommitment::ZERO),
+    };
+    if storage_root != Some(header.storage_commitment) {
+        report.add(
+            block,
+            Check::Trie,
+            format!(
+                "Header has storage commitment {} but the storage trie root is {}",
+                header.storage_commitment,
+                storage_root.map_or("missing".to_owned(), |root| root.to_string())
+            ),
+        )?;
+    }
+
+    let class_root = match tx
+        .class_root_index(block)
+        .context("Querying class root index")?
+    {
+        Some(index) => tx
+            .class_trie_node_hash(index)
+            .context("Querying class root hash")?
+            .map(ClassCommitment),
+        None => Some(ClassCommitment::ZERO),
+    };
+    if class_root != Some(header.class_commitment) {
+        report.add(
+            block,
+            Check::Trie,
+            format!(
+                "Header has class commitment {} but the class trie root is {}",
+                header.class_commitment,
+                class_root.map_or("missing".to_owned(), |root| root.to_string())
+            ),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Recomputes the storage and class tries of `block`, and the storage tries of all contracts,
+/// from their leaves.
+fn verify_tries(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+    report: &mut Report,
+) -> anyhow::Result<()> {
+    let header = tx
+        .block_header(block.into())
+        .context("Fetching block header")?
+        .context("Block header is missing")?;
+
+    tracing::info!(%block, "Verifying storage trie");
+    match StorageCommitmentTree::verify(tx, block) {
+        Ok(commitment) if commitment != header.storage_commitment => report.add(
+            block,
+            Check::Trie,
+            format!(
+                "Storage trie hashes to {commitment} but the header has {}",
+                header.storage_commitment
+            ),
+        )?,
+        Ok(_) => {}
+        Err(error) => report.add(block, Check::Trie, format!("Storage trie: {error:#}"))?,
+    }
+
+    tracing::info!(%block, "Verifying contract storage tries");
+    let contracts = tx
+        .deployed_contracts(block)
+        .context("Querying deployed contracts")?;
+    // The system contract has no class, and only exists once it has storage.
+    let system_contract = tx
+        .contract_state_hash(block, ContractAddress::ONE)
+        .context("Querying system contract state hash")?
+        .map(|_| (ContractAddress::ONE, ClassHash::ZERO));
+    for (contract, class_hash) in system_contract.into_iter().chain(contracts) {
+        if let Err(error) = verify_contract_state(tx, block, contract, class_hash) {
+            report.add(
+                block,
+                Check::Trie,
+                format!("Contract {contract}: {error:#}"),
+            )?;
+        }
+    }
+
+    tracing::info!(%block, "Verifying class trie");
+    match ClassCommitmentTree::verify(tx, block) {
+        Ok(commitment) if commitment != header.class_commitment => report.add(
+            block,
+            Check::Trie,
+            format!(
+                "Class trie hashes to {commitment} but the header has {}",
+                header.class_commitment
+            ),
+        )?,
+        Ok(_) => {}
+        Err(error) => report.add(block, Check::Trie, format!("Class trie: {error:#}"))?,
+    }
+
+    Ok(())
+}
+
+/// Recomputes the storage root of a contract from its storage, and checks that its state hash,
+/// which is the storage trie's leaf, commits to it.
+fn verify_contract_state(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+    contract: ContractAddress,
+    class_hash: ClassHash,
+) -> anyhow::Result<()> {
+    let root = ContractsStorageTree::verify(tx, contract, block)?;
+    let nonce = tx
+        .contract_nonce(contract, block.into())
+        .context("Querying nonce")?
+        .unwrap_or_default();
+    let state_hash = calculate_contract_state_hash(class_hash, root, nonce);
+
+    let stored = tx
+        .contract_state_hash(block, contract)
+        .context("Querying state hash")?
+        .context("State hash is missing")?;
+    anyhow::ensure!(
+        stored == state_hash,
+        "State hash is {stored} but the contract's class, storage and nonce hash to {state_hash}"
+    );
+
+    Ok(())
+}
+
+/// Returns the parameters for verifying the block hashes of `chain`, which is `None` for custom
+/// networks.
+pub(super) fn block_hash_meta_info(
+    chain: Option<(Chain, ChainId)>,
+) -> (&'static BlockHashMetaInfo, ChainId) {
+    match chain {
+        Some((chain, chain_id)) => (BlockHashMetaInfo::for_chain(chain), chain_id),
+        // Only the pre 0.7 algorithm, which custom networks never used, hashes the chain id.
+        None => (
+            BlockHashMetaInfo::for_chain(Chain::Custom),
+            ChainId(Felt::ZERO),
+        ),
+    }
+}
+
+/// Identifies the chain from the genesis block, or `None` for custom networks.
+fn chain(tx: &Transaction<'_>) -> anyhow::Result<Option<(Chain, ChainId)>> {
+    let (_, genesis_hash) = tx
+        .block_id(BlockNumber::GENESIS.into())?
+        .context("Getting genesis hash")?;
+
+    Ok(chain_from_genesis(genesis_hash))
+}
+
+/// Identifies the chain from the hash of its genesis block, or `None` for custom networks.
+pub(super) fn chain_from_genesis(genesis_hash: BlockHash) -> Option<(Chain, ChainId)> {
+    use pathfinder_common::consts::{
+        GOERLI_INTEGRATION_GENESIS_HASH, GOERLI_TESTNET_GENESIS_HASH, MAINNET_GENESIS_HASH,
+        SEPOLIA_INTEGRATION_GENESIS_HASH, SEPOLIA_TESTNET_GENESIS_HASH,
+    };
+
+    match genesis_hash {
+        MAINNET_GENESIS_HASH => Some((Chain::Mainnet, ChainId::MAINNET)),
+        GOERLI_TESTNET_GENESIS_HASH => Some((Chain::GoerliTestnet, ChainId::GOERLI_TESTNET)),
+        GOERLI_INTEGRATION_GENESIS_HASH => {
+            Some((Chain::GoerliIntegration, ChainId::GOERLI_INTEGRATION))
+        }
+        SEPOLIA_TESTNET_GENESIS_HASH => Some((Chain::SepoliaTestnet, ChainId::SEPOLIA_TESTNET)),
+        SEPOLIA_INTEGRATION_GENESIS_HASH => {
+            Some((Chain::SepoliaIntegration, ChainId::SEPOLIA_INTEGRATION))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::db::test_utils::{create_database, CONTRACT};
+
+    fn verify(database: &Path, tries: bool) -> (anyhow::Result<()>, String) {
+        let output = database.with_extension("report");
+        let result = run(DbVerifyConfig {
+            database: database.to_owned(),
+            from: 0,
+            to: None,
+            output: output.clone(),
+            tries,
+            hints: false,
+        });
+        let report = std::fs::read_to_string(output).unwrap();
+
+        (result, report)
+    }
+
+    fn corrupt(database: &Path, sql: &str, params: impl rusqlite::Params) {
+        let connection = rusqlite::Connection::open(database).unwrap();
+        let rows = connection.execute(sql, params).unwrap();
+        assert_eq!(rows, 1);
+    }
+
+    #[test]
+    fn consistent_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("pathfinder.sqlite");
+        create_database(&database, 3);
+
+        let (result, report) = verify(&database, true);
+        result.unwrap();
+        assert_eq!(report, "");
+    }
+
+    #[test]
+    fn corrupted_trie_node() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("pathfinder.sqlite");
+        create_database(&database, 3);
+
+        corrupt(
+            &database,
+            "UPDATE trie_contracts SET hash = ? WHERE idx = (
+                SELECT root_index FROM contract_roots WHERE contract_address = ?
+                ORDER BY block_number DESC LIMIT 1
+            )",
+            rusqlite::params![
+                Felt::from_u64(1).to_be_bytes().to_vec(),
+                CONTRACT.0.to_be_bytes().to_vec()
+            ],
+        );
+
+        // Contract storage tries are only checked when recomputing the tries.
+        let (result, _) = verify(&database, false);
+        result.unwrap();
+
+        let (result, report) = verify(&database, true);
+        result.unwrap_err();
+        assert_eq!(report.lines().count(), 1, "{report}");
+        assert!(report.contains(r#""block":2,"check":"trie""#), "{report}");
+        assert!(report.contains(&CONTRACT.to_string()), "{report}");
+    }
+
+    #[test]
+    fn corrupted_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("pathfinder.sqlite");
+        create_database(&database, 3);
+
+        corrupt(
+            &database,
+            "UPDATE block_headers SET timestamp = 1 WHERE number = 1",
+            [],
+        );
+
+        let (result, report) = verify(&database, false);
+        result.unwrap_err();
+        assert_eq!(report.lines().count(), 1, "{report}");
+        assert!(
+            report.contains(r#""block":1,"check":"block_hash""#),
+            "{report}"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use std::num::NonZeroU32;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use anyhow::Context;
+use pathfinder_common::{Chain, ChainId};
+use pathfinder_crypto::Felt;
+use pathfinder_executor::ForkState;
+use pathfinder_lib::chain_config::ChainConfig;
+use pathfinder_lib::devnet::{Devnet, Fork, Genesis};
+use pathfinder_rpc::context::WebsocketContext;
+use pathfinder_rpc::SyncState;
+use pathfinder_storage::Storage;
+use reqwest::Url;
+use tracing::info;
+
+use crate::config::{Config, DevnetSource, NetworkConfig};
+
+/// Runs a local development network, for which pathfinder produces its own blocks instead of
+/// syncing them from a Starknet network.
+pub async fn run(config: Config, readiness: Arc<AtomicBool>) -> anyhow::Result<()> {
+    let Some(NetworkConfig::Devnet {
+        source,
+        chain_id,
+        chain_config,
+        block_time,
+    }) = config.network
+    else {
+        anyhow::bail!("Devnet network configuration is required");
+    };
+
+    let chain_id = ChainId(Felt::from_be_slice(chain_id.as_bytes()).context("Parsing chain ID")?);
+    let execution_config = match chain_config {
+        Some(path) => ChainConfig::load(Chain::Custom, &path)?,
+        None => ChainConfig::for_chain(Chain::Custom),
+    }
+    .execution;
+    let execution_config = Arc::new(execution_config);
+
+    if let Some(address) = config.monitor_address {
+        crate::spawn_monitoring("devnet", address, readiness.clone())
+            .await
+            .context("Starting monitoring task")?;
+    }
+
+    let available_parallelism = std::thread::available_parallelism()?;
+    let database = config.data_directory.join("devnet.sqlite");
+
+    let storage_manager = Storage::migrate(
+        database.clone(),
+        config.sqlite_wal,
+        config.event_bloom_filter_cache_size.get(),
+    )
+    .context("Migrating database")?;
+    let storage_manager = match config.state_tries {
+        Some(mode) => storage_manager
+            .with_trie_prune_mode(mode)
+            .context("Configuring state trie pruning")?,
+        None => storage_manager,
+    };
+    let storage_manager = storage_manager.with_block_body_history(config.block_body_history);
+    let sequencer_storage = storage_manager
+        // Block production uses the rayon thread pool to update the state tries.
+        .create_pool(NonZeroU32::new(5 + available_parallelism.get() as u32).unwrap())
+        .context("Creating database connection pool for block production")?;
+
+    let max_rpc_connections: u32 = config
+        .max_rpc_connections
+        .get()
+        .try_into()
+        .expect("usize should cast to u32");
+    let rpc_storage = std::cmp::max(10, max_rpc_connections / 8);
+    let rpc_storage = NonZeroU32::new(rpc_storage).expect("A non-zero minimum is set");
+    let rpc_storage = storage_manager
+        .create_pool(rpc_storage)
+        .context("Creating database connection pool for RPC")?;
+
+    let execution_storage_pool_size = config.execution_concurrency.unwrap_or_else(|| {
+        std::num::NonZeroU32::new(available_parallelism.get() as u32)
+            .expect("The number of CPU cores should be non-zero")
+    });
+    let execution_storage = storage_manager
+        .create_pool(execution_storage_pool_size)
+        .context("Creating database connection pool for execution")?;
+
+    info!(location=?database, "Database migrated.");
+
+    let fork = match source {
+        DevnetSource::Genesis(path) => {
+            let genesis = Genesis::load(&path).context("Loading devnet genesis")?;
+            let storage = sequencer_storage.clone();
+            let execution_config = execution_config.clone();
+            tokio::task::spawn_blocking(move || genesis.initialize(storage, &execution_config))
+                .await
+                .context("Joining genesis task")?
+                .context("Creating genesis block")?;
+            None
+        }
+        DevnetSource::Fork {
+            feeder_gateway,
+            block,
+        } => {
+            // Only the feeder gateway is queried, transactions are never forwarded.
+            let gateway =
+                starknet_gateway_client::Client::with_urls(feeder_gateway.clone(), feeder_gateway)
+                    .context("Creating fork gateway client")?;
+            // The fork caches remote state in the database. While a block is sealed its writes
+            // are recorded in the block's database transaction instead.
+            let storage = storage_manager
+                .create_pool(NonZeroU32::new(1).unwrap())
+                .context("Creating database connection pool for fork")?;
+            let fork = Fork::new(Box::new(gateway), block, storage);
+            fork.initialize().await.context("Creating fork block")?;
+            Some(Arc::new(fork))
+        }
+    };
+
+    let (devnet, producer) = Devnet::new(chain_id, execution_config.clone(), fork.clone());
+
+    // There is no pending block, transactions are only visible to the mempool until sealed.
+    let (_tx_pending, rx_pending) = tokio::sync::watch::channel(Default::default());
+
+    // A devnet has no upstream gateway. Transactions are submitted to the local sequencer instead,
+    // and all blocks are recent enough to never require proxying traces.
+    let unreachable = Url::parse("http://localhost:0/").expect("Valid URL");
+    let gateway = starknet_gateway_client::Client::with_urls(unreachable.clone(), unreachable)
+        .context("Creating gateway client")?;
+
+    let rpc_config = pathfinder_rpc::context::RpcConfig {
+        batch_concurrency_limit: config.rpc_batch_concurrency_limit,
+        get_events_max_blocks_to_scan: config.get_events_max_blocks_to_scan,
+        get_events_max_uncached_bloom_filters_to_load: config
+            .get_events_max_uncached_bloom_filters_to_load,
+        backup_directory: config.rpc_backup_directory.clone(),
+    };
+
+    let context = pathfinder_rpc::context::RpcContext::new(
+        rpc_storage,
+        execution_storage,
+        Arc::new(SyncState::default()),
+        chain_id,
+        gateway,
+        rx_pending,
+        rpc_config,
+    )
+    .with_local_sequencer(Arc::new(devnet))
+    .with_execution_config(execution_config);
+
+    let context = match fork {
+        Some(fork) => context.with_fork(fork as Arc<dyn ForkState>),
+        None => context,
+    };
+
+    let context = if config.websocket.enabled {
+        context.with_websockets(WebsocketContext::new(
+            config.websocket.socket_buffer_capacity,
+            config.websocket.topic_sender_capacity,
+        ))
+    } else {
+        context
+    };
+
+    let default_version = match config.rpc_root_version {
+        crate::config::RpcVersion::V05 => pathfinder_rpc::DefaultVersion::V05,
+        crate::config::RpcVersion::V06 => pathfinder_rpc::DefaultVersion::V06,
+    };
+
+    let rpc_server = pathfinder_rpc::RpcServer::new(config.rpc_address, context, default_version);
+    let rpc_server = match config.rpc_cors_domains {
+        Some(allowed_origins) => rpc_server.with_cors(allowed_origins),
+        None => rpc_server,
+    };
+
+    let producer_handle = tokio::spawn(producer.run(
+        sequencer_storage,
+        block_time,
+        rpc_server.get_topic_broadcasters().cloned(),
+    ));
+
+    let (rpc_handle, local_addr) = rpc_server
+        .with_max_connections(config.max_rpc_connections.get())
+        .spawn()
+        .context("Starting the RPC server")?;
+    info!("📡 HTTP-RPC server started on: {}", local_addr);
+
+    readiness.store(true, std::sync::atomic::Ordering::Relaxed);
+
+    tokio::select! {
+        result = producer_handle => {
+            match result {
+                Ok(task_result) => tracing::error!("Block production ended unexpected with: {:?}", task_result),
+                Err(err) => tracing::error!("Block production ended unexpected; failed to join task handle: {:?}", err),
+            }
+        }
+        result = rpc_handle => {
+            match result {
+                Ok(_) => tracing::error!("RPC server process ended unexpectedly"),
+                Err(err) => tracing::error!(error=%err, "RPC server process ended unexpectedly"),
+            }
+        }
+    }
+
+    anyhow::bail!("Unexpected shutdown");
+}
@@ -5,7 +5,7 @@ use metrics_exporter_prometheus::PrometheusBuilder;
 use mimalloc::MiMalloc;
 
 use pathfinder_common::{consts::VERGEN_GIT_DESCRIBE, BlockNumber, Chain, ChainId, EthereumChain};
-use pathfinder_ethereum::{EthereumApi, EthereumClient};
+use pathfinder_ethereum::{EthereumApi, FailoverClient};
 use pathfinder_lib::state::SyncContext;
 use pathfinder_lib::{
     monitoring::{self},
@@ -17,7 +17,7 @@ use pathfinder_storage::Storage;
 use primitive_types::H160;
 use starknet_gateway_client::GatewayApi;
 use std::net::SocketAddr;
-use std::num::NonZeroU32;
+use std::num::{NonZeroU32, NonZeroUsize};
 use std::path::PathBuf;
 use std::sync::{atomic::AtomicBool, Arc};
 use tracing::info;
@@ -25,6 +25,10 @@ use tracing::info;
 use crate::config::NetworkConfig;
 
 mod config;
+mod db;
+mod devnet;
+mod recompress;
+mod replay;
 mod update;
 
 #[global_allocator]
@@ -45,7 +49,13 @@ async fn async_main() -> anyhow::Result<()> {
         std::env::set_var("RUST_LOG", "pathfinder=info");
     }
 
-    let config = config::Config::parse();
+    let config = match config::Command::parse() {
+        config::Command::Node(config) => *config,
+        config::Command::Subcommand { subcommand, color } => {
+            setup_tracing(color, false);
+            return run_subcommand(subcommand).await;
+        }
+    };
 
     setup_tracing(config.color, config.debug.pretty_log);
 
@@ -55,7 +65,10 @@ async fn async_main() -> anyhow::Result<()> {
         "🏁 Starting node."
     );
 
-    permission_check(&config.data_directory)?;
+    // A read-only node does not write to the data directory.
+    if !config.read_only {
+        permission_check(&config.data_directory)?;
+    }
 
     let available_parallelism = std::thread::available_parallelism()?;
 
@@ -67,16 +80,33 @@ async fn async_main() -> anyhow::Result<()> {
     // A readiness flag which is used to indicate that pathfinder is ready via monitoring.
     let readiness = Arc::new(AtomicBool::new(false));
 
-    let ethereum = EthereumContext::setup(config.ethereum.url, config.ethereum.password)
-        .await
-        .context("Creating Ethereum context")?;
+    if let Some(NetworkConfig::Devnet { .. }) = &config.network {
+        anyhow::ensure!(
+            !config.read_only,
+            "A devnet cannot be run in read-only mode"
+        );
+        return devnet::run(config, readiness).await;
+    }
+
+    // A read-only node follows the database of another node, so it does not use Ethereum.
+    let ethereum = match config.ethereum {
+        Some(ethereum) if !config.read_only => Some(
+            EthereumContext::setup(ethereum.urls, ethereum.password, ethereum.quorum)
+                .await
+                .context("Creating Ethereum context")?,
+        ),
+        Some(_) => None,
+        None if config.read_only => None,
+        None => anyhow::bail!("Ethereum is required unless running a devnet or in read-only mode"),
+    };
 
     // Use the default starknet network if none was configured.
-    let network = match config.network {
-        Some(network) => network,
-        None => ethereum
+    let network = match (config.network, &ethereum) {
+        (Some(network), _) => network,
+        (None, Some(ethereum)) => ethereum
             .default_network()
             .context("Using default Starknet network based on Ethereum configuration")?,
+        (None, None) => anyhow::bail!("A network is required in read-only mode"),
     };
 
     // Spawn monitoring if configured.
@@ -88,11 +118,15 @@ async fn async_main() -> anyhow::Result<()> {
             NetworkConfig::SepoliaTestnet => "testnet-sepolia",
             NetworkConfig::SepoliaIntegration => "integration-sepolia",
             NetworkConfig::Custom { .. } => "custom",
+            NetworkConfig::Devnet { .. } => "devnet",
         };
         spawn_monitoring(network_label, address, readiness.clone())
             .await
             .context("Starting monitoring task")?;
     }
+    if let Some(ethereum) = &ethereum {
+        ethereum.client.register_metrics();
+    }
 
     let pathfinder_context = PathfinderContext::configure_and_proxy_check(
         network,
@@ -102,16 +136,36 @@ async fn async_main() -> anyhow::Result<()> {
     .await
     .context("Configuring pathfinder")?;
 
-    verify_networks(pathfinder_context.network, ethereum.chain)?;
+    if let Some(ethereum) = &ethereum {
+        verify_networks(pathfinder_context.network, ethereum.chain)?;
+    }
 
     // Setup and verify database
 
-    let storage_manager = Storage::migrate(
-        pathfinder_context.database.clone(),
-        config.sqlite_wal,
-        config.event_bloom_filter_cache_size.get(),
-    )
-    .unwrap();
+    let storage_manager = if config.read_only {
+        if config.state_tries.is_some() || config.block_body_history.is_some() {
+            tracing::warn!("Storage options are set by the node owning the database and are ignored in read-only mode");
+        }
+        Storage::open_read_only(
+            pathfinder_context.database.clone(),
+            config.event_bloom_filter_cache_size.get(),
+        )
+        .context("Opening database in read-only mode")?
+    } else {
+        let storage_manager = Storage::migrate(
+            pathfinder_context.database.clone(),
+            config.sqlite_wal,
+            config.event_bloom_filter_cache_size.get(),
+        )
+        .unwrap();
+        let storage_manager = match config.state_tries {
+            Some(mode) => storage_manager
+                .with_trie_prune_mode(mode)
+                .context("Configuring state trie pruning")?,
+            None => storage_manager,
+        };
+        storage_manager.with_block_body_history(config.block_body_history)
+    };
     let sync_storage = storage_manager
         // 5 is enough for normal sync operations, and then `available_parallelism` for
         // the rayon thread pool workers to use.
@@ -157,24 +211,44 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
       Try increasing the file limit to using `ulimit` or similar tooling.",
         )?;
 
-    info!(location=?pathfinder_context.database, "Database migrated.");
-    verify_database(
-        &sync_storage,
-        pathfinder_context.network,
-        &pathfinder_context.gateway,
-    )
-    .await
-    .context("Verifying database")?;
+    let recompress_storage = storage_manager
+        .create_pool(NonZeroU32::new(1).unwrap())
+        .context(
+            r"Creating database connection pool for recompression
+
+Hint: This is usually caused by exceeding the file descriptor limit of your system.
+      Try increasing the file limit to using `ulimit` or similar tooling.",
+        )?;
+
+    // The node owning the database migrates and verifies it.
+    if !config.read_only {
+        info!(location=?pathfinder_context.database, "Database migrated.");
+        verify_database(
+            &sync_storage,
+            pathfinder_context.network,
+            &pathfinder_context.gateway,
+        )
+        .await
+        .context("Verifying database")?;
+    }
 
     let sync_state = Arc::new(SyncState::default());
 
     let (tx_pending, rx_pending) = tokio::sync::watch::channel(Default::default());
 
+    if config.rpc_backup_directory.is_some() && !config.rpc_address.ip().is_loopback() {
+        tracing::warn!(
+            address=%config.rpc_address,
+            "The pathfinder_backupDatabase RPC method is enabled on a non-local address, anyone who can reach it can write database backups"
+        );
+    }
+
     let rpc_config = pathfinder_rpc::context::RpcConfig {
         batch_concurrency_limit: config.rpc_batch_concurrency_limit,
         get_events_max_blocks_to_scan: config.get_events_max_blocks_to_scan,
         get_events_max_uncached_bloom_filters_to_load: config
             .get_events_max_uncached_bloom_filters_to_load,
+        backup_directory: config.rpc_backup_directory.clone(),
     };
 
     let context = pathfinder_rpc::context::RpcContext::new(
@@ -185,7 +259,8 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
         pathfinder_context.gateway.clone(),
         rx_pending,
         rpc_config,
-    );
+    )
+    .with_execution_config(Arc::new(pathfinder_context.chain_config.execution.clone()));
 
     let context = if config.websocket.enabled {
         context.with_websockets(WebsocketContext::new(
@@ -207,36 +282,79 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
         None => rpc_server,
     };
 
-    let (p2p_handle, sequencer) = start_p2p(
-        pathfinder_context.network_id,
-        p2p_storage,
+    let sequencer = starknet_gateway_client::MultiSourceClient::new(
         pathfinder_context.gateway,
-        config.p2p,
+        &config.feeder_gateway_mirrors,
     )
-    .await?;
-
-    let sync_context = SyncContext {
-        storage: sync_storage,
-        ethereum: ethereum.client,
-        chain: pathfinder_context.network,
-        chain_id: pathfinder_context.network_id,
-        core_address: pathfinder_context.l1_core_address,
-        sequencer,
-        state: sync_state.clone(),
-        head_poll_interval: config.poll_interval,
-        pending_data: tx_pending,
-        // Currently p2p does not perform block hash and state commitment verification if p2p header lacks state commitment
-        block_validation_mode: state::l2::BlockValidationMode::Strict,
-        websocket_txs: rpc_server.get_topic_broadcasters().cloned(),
-        block_cache_size: 1_000,
-        restart_delay: config.debug.restart_delay,
-        verify_tree_hashes: config.verify_tree_hashes,
-    };
+    .context("Creating feeder gateway mirror clients")?
+    .with_cross_check(config.cross_check_mirrors);
 
-    let sync_handle = if config.is_sync_enabled {
-        tokio::spawn(state::sync(sync_context, state::l1::sync, state::l2::sync))
-    } else {
-        tokio::spawn(std::future::pending())
+    let (p2p_handle, sync_handle) = match ethereum {
+        // A read-only node neither syncs nor joins the p2p network.
+        None => {
+            let replica_context = state::replica::ReplicaContext {
+                storage: sync_storage,
+                sequencer,
+                state: sync_state.clone(),
+                pending_data: tx_pending,
+                websocket_txs: rpc_server.get_topic_broadcasters().cloned(),
+                poll_interval: config.poll_interval,
+                restart_delay: config.debug.restart_delay,
+            };
+            (
+                tokio::spawn(std::future::pending()),
+                tokio::spawn(state::replica::follow(replica_context)),
+            )
+        }
+        Some(ethereum) => {
+            let (p2p_handle, sequencer) = start_p2p(
+                pathfinder_context.network_id,
+                p2p_storage,
+                sequencer,
+                config.p2p,
+            )
+            .await?;
+
+            let sync_handle = if config.is_sync_enabled {
+                if pathfinder_context
+                    .chain_config
+                    .sequencer_public_key
+                    .is_none()
+                {
+                    tracing::warn!("The sequencer public key of this network is unknown, block signatures will not be verified");
+                }
+
+                let sync_context = SyncContext {
+                    storage: sync_storage,
+                    ethereum: ethereum.client,
+                    chain: pathfinder_context.network,
+                    chain_id: pathfinder_context.network_id,
+                    block_hash_meta_info: pathfinder_context.chain_config.block_hash.clone(),
+                    core_address: pathfinder_context.l1_core_address,
+                    sequencer,
+                    state: sync_state.clone(),
+                    head_poll_interval: config.poll_interval,
+                    pending_data: tx_pending,
+                    // Currently p2p does not perform block hash and state commitment verification if p2p header lacks state commitment
+                    block_validation_mode: state::l2::BlockValidationMode::Strict,
+                    websocket_txs: rpc_server.get_topic_broadcasters().cloned(),
+                    block_cache_size: 1_000,
+                    restart_delay: config.debug.restart_delay,
+                    verify_tree_hashes: config.verify_tree_hashes,
+                    download_window: config.download_window,
+                    stop_at_block: config.stop_at_block,
+                    sequencer_public_key: pathfinder_context.chain_config.sequencer_public_key,
+                    execute_pending: config
+                        .execute_pending
+                        .then(|| Arc::new(pathfinder_context.chain_config.execution.clone())),
+                };
+                tokio::spawn(state::sync(sync_context, state::l1::sync, state::l2::sync))
+            } else {
+                tokio::spawn(std::future::pending())
+            };
+
+            (p2p_handle, sync_handle)
+        }
     };
 
     let rpc_handle = if config.is_rpc_enabled {
@@ -251,6 +369,9 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
     };
 
     tokio::spawn(update::poll_github_for_releases());
+    if !config.read_only {
+        tokio::task::spawn_blocking(move || recompress::recompress(recompress_storage));
+    }
 
     // We are now ready.
     readiness.store(true, std::sync::atomic::Ordering::Relaxed);
@@ -280,6 +401,19 @@ Hint: This is usually caused by exceeding the file descriptor limit of your syst
     anyhow::bail!("Unexpected shutdown");
 }
 
+async fn run_subcommand(subcommand: config::Subcommand) -> anyhow::Result<()> {
+    match subcommand {
+        config::Subcommand::Replay(config) => {
+            tokio::task::spawn_blocking(move || replay::run(config))
+                .await
+                .context("Joining replay task")?
+        }
+        config::Subcommand::Db(command) => tokio::task::spawn_blocking(move || db::run(command))
+            .await
+            .context("Joining database task")?,
+    }
+}
+
 #[cfg(feature = "tokio-console")]
 fn setup_tracing(color: config::Color, pretty_log: bool) {
     use tracing_subscriber::prelude::*;
@@ -339,7 +473,7 @@ fn permission_check(base: &std::path::Path) -> Result<(), anyhow::Error> {
 async fn start_p2p(
     chain_id: ChainId,
     storage: Storage,
-    sequencer: starknet_gateway_client::Client,
+    sequencer: starknet_gateway_client::MultiSourceClient,
     config: config::P2PConfig,
 ) -> anyhow::Result<(
     tokio::task::JoinHandle<()>,
@@ -408,9 +542,12 @@ async fn start_p2p(
 async fn start_p2p(
     _: ChainId,
     _: Storage,
-    sequencer: starknet_gateway_client::Client,
+    sequencer: starknet_gateway_client::MultiSourceClient,
     _: config::P2PConfig,
-) -> anyhow::Result<(tokio::task::JoinHandle<()>, starknet_gateway_client::Client)> {
+) -> anyhow::Result<(
+    tokio::task::JoinHandle<()>,
+    starknet_gateway_client::MultiSourceClient,
+)> {
     let join_handle = tokio::task::spawn(async move { futures::future::pending().await });
 
     Ok((join_handle, sequencer))
@@ -435,18 +572,19 @@ async fn spawn_monitoring(
 
 /// Convenience bundle for an Ethereum transport and chain.
 struct EthereumContext {
-    client: EthereumClient,
+    client: FailoverClient,
     chain: EthereumChain,
 }
 
 impl EthereumContext {
     /// Configure an [EthereumContext]'s transport and read the chain ID using it.
-    async fn setup(url: reqwest::Url, password: Option<String>) -> anyhow::Result<Self> {
-        let client = if let Some(password) = password.as_ref() {
-            EthereumClient::with_password(url, password).context("Creating Ethereum client")?
-        } else {
-            EthereumClient::new(url).context("Creating Ethereum client")?
-        };
+    async fn setup(
+        urls: Vec<reqwest::Url>,
+        password: Option<String>,
+        quorum: NonZeroUsize,
+    ) -> anyhow::Result<Self> {
+        let client = FailoverClient::new(urls, password.as_deref(), quorum.get())
+            .context("Creating Ethereum client")?;
 
         let chain = client.get_chain().await.context(
             r"Determining Ethereum chain.
@@ -483,12 +621,14 @@ struct PathfinderContext {
     gateway: starknet_gateway_client::Client,
     database: PathBuf,
     l1_core_address: H160,
+    chain_config: pathfinder_lib::chain_config::ChainConfig,
 }
 
 /// Used to hide private fn's for [PathfinderContext].
 mod pathfinder_context {
     use super::PathfinderContext;
     use crate::config::NetworkConfig;
+    use pathfinder_lib::chain_config::ChainConfig;
 
     use std::path::PathBuf;
 
@@ -512,6 +652,7 @@ mod pathfinder_context {
                     gateway: GatewayClient::mainnet().with_api_key(api_key),
                     database: data_directory.join("mainnet.sqlite"),
                     l1_core_address: H160::from(core_addr::MAINNET),
+                    chain_config: ChainConfig::for_chain(Chain::Mainnet),
                 },
                 NetworkConfig::GoerliTestnet => Self {
                     network: Chain::GoerliTestnet,
@@ -519,6 +660,7 @@ mod pathfinder_context {
                     gateway: GatewayClient::goerli_testnet().with_api_key(api_key),
                     database: data_directory.join("goerli.sqlite"),
                     l1_core_address: H160::from(core_addr::GOERLI_TESTNET),
+                    chain_config: ChainConfig::for_chain(Chain::GoerliTestnet),
                 },
                 NetworkConfig::GoerliIntegration => Self {
                     network: Chain::GoerliIntegration,
@@ -526,6 +668,7 @@ mod pathfinder_context {
                     gateway: GatewayClient::goerli_integration().with_api_key(api_key),
                     database: data_directory.join("integration.sqlite"),
                     l1_core_address: H160::from(core_addr::GOERLI_INTEGRATION),
+                    chain_config: ChainConfig::for_chain(Chain::GoerliIntegration),
                 },
                 NetworkConfig::SepoliaTestnet => Self {
                     network: Chain::SepoliaTestnet,
@@ -533,6 +676,7 @@ mod pathfinder_context {
                     gateway: GatewayClient::sepolia_testnet().with_api_key(api_key),
                     database: data_directory.join("testnet-sepolia.sqlite"),
                     l1_core_address: H160::from(core_addr::SEPOLIA_TESTNET),
+                    chain_config: ChainConfig::for_chain(Chain::SepoliaTestnet),
                 },
                 NetworkConfig::SepoliaIntegration => Self {
                     network: Chain::SepoliaIntegration,
@@ -540,20 +684,26 @@ mod pathfinder_context {
                     gateway: GatewayClient::sepolia_integration().with_api_key(api_key),
                     database: data_directory.join("integration-sepolia.sqlite"),
                     l1_core_address: H160::from(core_addr::SEPOLIA_INTEGRATION),
+                    chain_config: ChainConfig::for_chain(Chain::SepoliaIntegration),
                 },
                 NetworkConfig::Custom {
                     gateway,
                     feeder_gateway,
                     chain_id,
+                    chain_config,
                 } => Self::configure_custom(
                     gateway,
                     feeder_gateway,
                     chain_id,
+                    chain_config,
                     data_directory,
                     api_key,
                 )
                 .await
                 .context("Configuring custom network")?,
+                NetworkConfig::Devnet { .. } => {
+                    anyhow::bail!("A devnet does not sync from a Starknet network")
+                }
             };
 
             Ok(context)
@@ -566,6 +716,7 @@ mod pathfinder_context {
             gateway: Url,
             feeder: Url,
             chain_id: String,
+            chain_config: Option<PathBuf>,
             data_directory: PathBuf,
             api_key: Option<String>,
         ) -> anyhow::Result<Self> {
@@ -600,12 +751,18 @@ mod pathfinder_context {
                 tracing::info!(%network, "Proxy gateway detected");
             }
 
+            let chain_config = match chain_config {
+                Some(path) => ChainConfig::load(network, &path)?,
+                None => ChainConfig::for_chain(network),
+            };
+
             let context = Self {
                 network,
                 network_id,
                 gateway,
                 database: data_directory.join("custom.sqlite"),
                 l1_core_address,
+                chain_config,
             };
 
             Ok(context)
@@ -0,0 +1,106 @@
+//! Recompresses class definitions and transactions using trained zstd dictionaries.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Context;
+use pathfinder_storage::{DictionaryKind, Storage, TransactionBehavior};
+
+/// The number of rows recompressed per database transaction.
+const BATCH_SIZE: usize = 1_000;
+/// Pause between batches, to leave room for sync to write to the database.
+const BATCH_DELAY: Duration = Duration::from_millis(100);
+/// How often to check whether a table has grown enough to train a new dictionary.
+const TRAIN_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Trains a compression dictionary for each kind of data once enough of it is stored, and again
+/// each time it has grown considerably since, and recompresses the rows stored before then with
+/// the latest dictionary.
+///
+/// This is a blocking function, which is meant to run in the background for the lifetime of
+/// the node.
+pub fn recompress(storage: Storage) {
+    // The latest dictionary of each kind which all rows have been recompressed with.
+    let mut recompressed = HashMap::new();
+    loop {
+        for kind in DictionaryKind::ALL {
+            let done = recompressed.get(&kind).copied();
+            match recompress_kind(&storage, kind, done) {
+                Ok(Some(id)) => {
+                    recompressed.insert(kind, id);
+                }
+                Ok(None) => {}
+                Err(error) => tracing::warn!(?kind, ?error, "Recompressing data failed"),
+            }
+        }
+
+        std::thread::sleep(TRAIN_INTERVAL);
+    }
+}
+
+/// Trains a new dictionary if there is enough new data, and recompresses all rows with the latest
+/// dictionary unless that is `recompressed` already. Returns the id of the latest dictionary, if
+/// there is one.
+fn recompress_kind(
+    storage: &Storage,
+    kind: DictionaryKind,
+    recompressed: Option<i64>,
+) -> anyhow::Result<Option<i64>> {
+    let mut db = storage
+        .connection()
+        .context("Opening database connection")?;
+
+    // Training only reads the database, so it does not block sync while it takes place.
+    let tx = db.transaction().context("Creating database transaction")?;
+    let dictionary = tx
+        .train_compression_dictionary(kind)
+        .context("Training compression dictionary")?;
+    let latest = tx.compression_dictionary_id(kind)?;
+    drop(tx);
+
+    let latest = match dictionary {
+        Some(dictionary) => {
+            let tx = db
+                .transaction_with_behavior(TransactionBehavior::Immediate)
+                .context("Creating database transaction")?;
+            let id = tx
+                .insert_compression_dictionary(kind, &dictionary)
+                .context("Inserting compression dictionary")?;
+            tx.commit().context("Committing database transaction")?;
+            tracing::info!(?kind, %id, size=%dictionary.size(), "Trained compression dictionary, recompressing existing data");
+            id
+        }
+        None => match latest {
+            Some(id) => id,
+            None => return Ok(None),
+        },
+    };
+    if recompressed == Some(latest) {
+        return Ok(Some(latest));
+    }
+
+    let mut after = 0;
+    loop {
+        let tx = db
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .context("Creating database transaction")?;
+        let Some(last) = tx
+            .recompress_rows(kind, after, BATCH_SIZE)
+            .context("Recompressing rows")?
+        else {
+            break;
+        };
+        tx.commit().context("Committing database transaction")?;
+
+        after = last;
+        tracing::trace!(?kind, rowid=%after, "Recompressed rows");
+
+        std::thread::sleep(BATCH_DELAY);
+    }
+
+    if after > 0 {
+        tracing::info!(?kind, "Recompressed existing data");
+    }
+
+    Ok(Some(latest))
+}
@@ -0,0 +1,909 @@
+//! Re-executes historical blocks and compares the results against the stored receipts.
+//!
+//! Blocks are executed concurrently, but the report is written in block order. This lets
+//! the progress checkpoint be a single block number, below which every block has been
+//! replayed and reported.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Seek, Write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use pathfinder_common::event::Event;
+use pathfinder_common::{
+    BlockNumber, Chain, ChainId, ContractAddress, EventData, EventKey, TransactionHash,
+};
+use pathfinder_executor::types::{
+    ExecuteInvocation, ExecutionResources, FunctionInvocation, TransactionSimulation,
+    TransactionTrace,
+};
+use pathfinder_executor::{ExecutionConfig, ExecutionState};
+use pathfinder_lib::chain_config::ChainConfig;
+use pathfinder_storage::{BlockId, Storage};
+use starknet_gateway_types::reply::transaction::{ExecutionStatus, Receipt, Transaction};
+
+use crate::config::{ReplayConfig, TransactionType};
+
+/// How often progress is checkpointed to disk.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many blocks each job may replay ahead of the oldest block which has not been written to
+/// the report yet.
+const BLOCKS_AHEAD_PER_JOB: u64 = 8;
+
+pub fn run(config: ReplayConfig) -> anyhow::Result<()> {
+    let jobs = match config.jobs {
+        Some(jobs) => jobs,
+        None => std::thread::available_parallelism()?,
+    };
+    let pool_size = NonZeroU32::new(jobs.get() as u32 + 1).expect("Non-zero pool size");
+
+    let storage = Storage::open_read_only(config.database.clone(), 1)
+        .context("Opening database")?
+        .create_pool(pool_size)
+        .context("Creating database connection pool")?;
+
+    let (latest, chain) = {
+        let mut db = storage
+            .connection()
+            .context("Opening database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+        let (latest, _) = tx
+            .block_id(BlockId::Latest)?
+            .context("Database contains no blocks")?;
+        (latest.get(), chain(&tx, config.chain_id.as_deref())?)
+    };
+    let (chain, chain_id) = chain;
+    let chain_config = match &config.chain_config {
+        Some(path) => ChainConfig::load(chain, path)?,
+        None => ChainConfig::for_chain(chain),
+    };
+    let execution_config = Arc::new(chain_config.execution);
+
+    let from = config.from;
+    let to = config.to.unwrap_or(latest);
+    anyhow::ensure!(
+        to <= latest,
+        "Last block {to} is past the latest block in the database {latest}"
+    );
+    anyhow::ensure!(from <= to, "Block range {from}..={to} is empty");
+
+    let checkpoint_path = checkpoint_path(&config.output);
+    let checkpoint = match config.resume {
+        true => Checkpoint::load(&checkpoint_path)?,
+        false => None,
+    };
+    let checkpoint = match checkpoint {
+        Some(checkpoint) => {
+            anyhow::ensure!(
+                checkpoint.from == from && checkpoint.to == to,
+                "Checkpoint is for block range {}..={} but {from}..={to} was requested",
+                checkpoint.from,
+                checkpoint.to
+            );
+            checkpoint
+        }
+        None => Checkpoint {
+            from,
+            to,
+            next_block: from,
+            report_len: 0,
+        },
+    };
+
+    // Discard anything written after the checkpoint, since those blocks will be replayed again.
+    let mut report = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&config.output)
+        .context("Opening report file")?;
+    report
+        .set_len(checkpoint.report_len)
+        .context("Truncating report file to checkpoint")?;
+    report
+        .seek(std::io::SeekFrom::End(0))
+        .context("Seeking to end of report file")?;
+    let report = BufWriter::new(report);
+
+    let filter = Filter {
+        contracts: config.contracts.into_iter().collect(),
+        transaction_types: config.transaction_types.into_iter().collect(),
+    };
+
+    tracing::info!(%from, %to, next_block=%checkpoint.next_block, %jobs, "Replaying blocks");
+
+    let start_time = Instant::now();
+    let next_block = AtomicU64::new(checkpoint.next_block);
+    let window = Window::new(
+        checkpoint.next_block,
+        jobs.get() as u64 * BLOCKS_AHEAD_PER_JOB,
+    );
+    let (result_tx, result_rx) = std::sync::mpsc::channel();
+
+    let summary = std::thread::scope(|scope| {
+        for _ in 0..jobs.get() {
+            let result_tx = result_tx.clone();
+            let storage = &storage;
+            let filter = &filter;
+            let next_block = &next_block;
+            let window = &window;
+            let execution_config = &execution_config;
+
+            scope.spawn(move || loop {
+                let block = next_block.fetch_add(1, Ordering::Relaxed);
+                if block > to {
+                    break;
+                }
+                window.wait_for(block);
+
+                let block_number = BlockNumber::new_or_panic(block);
+                let report = replay_block(
+                    storage,
+                    chain_id,
+                    execution_config.clone(),
+                    block_number,
+                    filter,
+                );
+
+                if result_tx.send((block, report)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(result_tx);
+
+        let writer = ReportWriter {
+            report,
+            checkpoint,
+            checkpoint_path,
+            last_checkpoint: Instant::now(),
+            summary: Summary::default(),
+        };
+
+        // Consuming the receiver here means the workers stop early if writing fails.
+        let summary = writer.write_results(result_rx, &window);
+        // Release any workers still waiting, whether writing finished or failed.
+        window.advance(u64::MAX);
+        summary
+    })?;
+
+    tracing::info!(
+        blocks=%summary.blocks,
+        transactions=%summary.transactions,
+        mismatches=%summary.mismatches,
+        failed_blocks=%summary.failed_blocks,
+        elapsed=?start_time.elapsed(),
+        report=%config.output.display(),
+        "Replay finished"
+    );
+
+    Ok(())
+}
+
+/// Identifies the chain from its genesis block, falling back to a custom network with `chain_id`.
+fn chain(
+    tx: &pathfinder_storage::Transaction<'_>,
+    chain_id: Option<&str>,
+) -> anyhow::Result<(Chain, ChainId)> {
+    use pathfinder_common::consts::{
+        GOERLI_INTEGRATION_GENESIS_HASH, GOERLI_TESTNET_GENESIS_HASH, MAINNET_GENESIS_HASH,
+        SEPOLIA_INTEGRATION_GENESIS_HASH, SEPOLIA_TESTNET_GENESIS_HASH,
+    };
+
+    let (_, genesis_hash) = tx
+        .block_id(BlockNumber::GENESIS.into())?
+        .context("Getting genesis hash")?;
+
+    let chain = match genesis_hash {
+        MAINNET_GENESIS_HASH => (Chain::Mainnet, ChainId::MAINNET),
+        GOERLI_TESTNET_GENESIS_HASH => (Chain::GoerliTestnet, ChainId::GOERLI_TESTNET),
+        GOERLI_INTEGRATION_GENESIS_HASH => (Chain::GoerliIntegration, ChainId::GOERLI_INTEGRATION),
+        SEPOLIA_TESTNET_GENESIS_HASH => (Chain::SepoliaTestnet, ChainId::SEPOLIA_TESTNET),
+        SEPOLIA_INTEGRATION_GENESIS_HASH => {
+            (Chain::SepoliaIntegration, ChainId::SEPOLIA_INTEGRATION)
+        }
+        _ => {
+            let chain_id = chain_id.with_context(|| {
+                format!("Unknown chain with genesis block hash {genesis_hash}, use --chain-id")
+            })?;
+            let chain_id = ChainId(
+                pathfinder_crypto::Felt::from_be_slice(chain_id.as_bytes())
+                    .context("Parsing chain ID")?,
+            );
+            (Chain::Custom, chain_id)
+        }
+    };
+
+    Ok(chain)
+}
+
+fn checkpoint_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".checkpoint");
+    path.into()
+}
+
+/// Replay progress. All blocks in `from..next_block` have been replayed and their results
+/// make up the first `report_len` bytes of the report.
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Checkpoint {
+    from: u64,
+    to: u64,
+    next_block: u64,
+    report_len: u64,
+}
+
+impl Checkpoint {
+    fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .context("Parsing checkpoint"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context("Reading checkpoint"),
+        }
+    }
+
+    /// Writes the checkpoint via a temporary file so that an interruption cannot corrupt it.
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?).context("Writing checkpoint")?;
+        std::fs::rename(&tmp, path).context("Replacing checkpoint")
+    }
+}
+
+/// Limits how far ahead of the report the workers may replay, which bounds the number of results
+/// buffered until they can be written in block order.
+struct Window {
+    /// The next block to be written to the report.
+    written: Mutex<u64>,
+    advanced: Condvar,
+    size: u64,
+}
+
+impl Window {
+    fn new(next_block: u64, size: u64) -> Self {
+        Self {
+            written: Mutex::new(next_block),
+            advanced: Condvar::new(),
+            size,
+        }
+    }
+
+    /// Blocks until `block` is within the window.
+    ///
+    /// This cannot deadlock, as every block before `block` has already been claimed by a worker
+    /// and the oldest of them is always within the window.
+    fn wait_for(&self, block: u64) {
+        let mut written = self.written.lock().unwrap();
+        while block >= written.saturating_add(self.size) {
+            written = self.advanced.wait(written).unwrap();
+        }
+    }
+
+    fn advance(&self, next_block: u64) {
+        *self.written.lock().unwrap() = next_block;
+        self.advanced.notify_all();
+    }
+}
+
+#[derive(Default)]
+struct Summary {
+    blocks: u64,
+    transactions: usize,
+    mismatches: usize,
+    failed_blocks: usize,
+}
+
+struct ReportWriter {
+    report: BufWriter<std::fs::File>,
+    checkpoint: Checkpoint,
+    checkpoint_path: PathBuf,
+    last_checkpoint: Instant,
+    summary: Summary,
+}
+
+impl ReportWriter {
+    fn write_results(
+        mut self,
+        results: std::sync::mpsc::Receiver<(u64, anyhow::Result<BlockReport>)>,
+        window: &Window,
+    ) -> anyhow::Result<Summary> {
+        // Results arrive out of order, buffer them until they can be written in order. The
+        // window keeps the workers from getting too far ahead.
+        let mut buffered = BTreeMap::new();
+        for (block, report) in results.iter() {
+            buffered.insert(block, report);
+
+            while let Some(report) = buffered.remove(&self.checkpoint.next_block) {
+                let report = report
+                    .with_context(|| format!("Replaying block {}", self.checkpoint.next_block))?;
+                self.write(report)?;
+            }
+            window.advance(self.checkpoint.next_block);
+
+            if self.last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                self.save_checkpoint()?;
+            }
+        }
+
+        self.save_checkpoint()?;
+
+        Ok(self.summary)
+    }
+
+    fn write(&mut self, report: BlockReport) -> anyhow::Result<()> {
+        self.summary.blocks += 1;
+        self.summary.transactions += report.replayed_transactions;
+        self.summary.mismatches += report.transactions.len();
+
+        if let Some(error) = &report.error {
+            self.summary.failed_blocks += 1;
+            tracing::warn!(block_number=%report.block_number, %error, "Block replay failed");
+        }
+
+        if report.has_differences() {
+            serde_json::to_writer(&mut self.report, &report).context("Writing report")?;
+            self.report.write_all(b"\n").context("Writing report")?;
+        }
+
+        self.checkpoint.next_block += 1;
+
+        Ok(())
+    }
+
+    fn save_checkpoint(&mut self) -> anyhow::Result<()> {
+        self.report.flush().context("Flushing report")?;
+        self.checkpoint.report_len = self
+            .report
+            .get_ref()
+            .metadata()
+            .context("Reading report file size")?
+            .len();
+        self.checkpoint.save(&self.checkpoint_path)?;
+        self.last_checkpoint = Instant::now();
+
+        tracing::info!(next_block=%self.checkpoint.next_block, mismatches=%self.summary.mismatches, "Replay progress");
+
+        Ok(())
+    }
+}
+
+struct Filter {
+    contracts: HashSet<ContractAddress>,
+    transaction_types: HashSet<TransactionType>,
+}
+
+impl Filter {
+    fn matches_type(&self, transaction: &Transaction) -> bool {
+        self.transaction_types.is_empty()
+            || self
+                .transaction_types
+                .contains(&transaction_type(transaction))
+    }
+
+    /// Whether the transaction was sent by one of the filtered contracts, or called into one.
+    fn matches_contract(&self, transaction: &Transaction, trace: &TransactionTrace) -> bool {
+        fn calls_into(
+            invocation: &FunctionInvocation,
+            contracts: &HashSet<ContractAddress>,
+        ) -> bool {
+            contracts.contains(&invocation.contract_address)
+                || invocation
+                    .internal_calls
+                    .iter()
+                    .any(|call| calls_into(call, contracts))
+        }
+
+        self.contracts.is_empty()
+            || self.contracts.contains(&transaction.contract_address())
+            || top_level_invocations(trace)
+                .into_iter()
+                .any(|invocation| calls_into(invocation, &self.contracts))
+    }
+}
+
+fn transaction_type(transaction: &Transaction) -> TransactionType {
+    match transaction {
+        Transaction::Declare(_) => TransactionType::Declare,
+        Transaction::Deploy(_) => TransactionType::Deploy,
+        Transaction::DeployAccount(_) => TransactionType::DeployAccount,
+        Transaction::Invoke(_) => TransactionType::Invoke,
+        Transaction::L1Handler(_) => TransactionType::L1Handler,
+    }
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+struct BlockReport {
+    block_number: BlockNumber,
+    #[serde(skip)]
+    replayed_transactions: usize,
+    /// Set if the block could not be executed at all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    transactions: Vec<TransactionReport>,
+}
+
+impl BlockReport {
+    fn has_differences(&self) -> bool {
+        self.error.is_some() || !self.transactions.is_empty()
+    }
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+struct TransactionReport {
+    transaction_hash: TransactionHash,
+    transaction_index: usize,
+    /// Revert reasons, if the transaction reverted in only one of the executions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revert_reason: Option<Diff<Option<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fee: Option<Diff<u128>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    resources: BTreeMap<&'static str, Diff<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    events: Option<EventsDiff>,
+}
+
+/// The value from the stored receipt, and the value from re-execution.
+#[derive(Debug, PartialEq, serde::Serialize)]
+struct Diff<T> {
+    expected: T,
+    actual: T,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+struct EventsDiff {
+    expected_count: usize,
+    actual_count: usize,
+    /// Index of the first event that differs.
+    first_mismatch: usize,
+}
+
+/// The parts of a re-executed transaction which are compared against its receipt.
+struct Executed {
+    fee: u128,
+    resources: ExecutionResources,
+    events: Vec<Event>,
+    revert_reason: Option<String>,
+}
+
+impl From<&TransactionSimulation> for Executed {
+    fn from(simulation: &TransactionSimulation) -> Self {
+        let events = top_level_invocations(&simulation.trace)
+            .into_iter()
+            .flat_map(emitted_events)
+            .collect();
+
+        Self {
+            fee: simulation.fee_estimation.overall_fee.as_u128(),
+            resources: simulation.execution_resources.clone(),
+            events,
+            revert_reason: simulation.revert_reason().map(ToOwned::to_owned),
+        }
+    }
+}
+
+fn replay_block(
+    storage: &Storage,
+    chain_id: ChainId,
+    execution_config: Arc<ExecutionConfig>,
+    block_number: BlockNumber,
+    filter: &Filter,
+) -> anyhow::Result<BlockReport> {
+    let mut connection = storage
+        .connection()
+        .context("Opening database connection")?;
+    let db_tx = connection
+        .transaction()
+        .context("Creating database transaction")?;
+
+    let block_id = BlockId::Number(block_number);
+    let header = db_tx
+        .block_header(block_id)?
+        .context("Block header missing")?;
+    let mut transactions = db_tx
+        .transaction_data_for_block(block_id)?
+        .context("Transaction data missing")?;
+
+    let mut report = BlockReport {
+        block_number,
+        ..Default::default()
+    };
+
+    // Transactions after the last one of interest don't affect the result.
+    let Some(last) = transactions
+        .iter()
+        .rposition(|(tx, _)| filter.matches_type(tx))
+    else {
+        return Ok(report);
+    };
+    transactions.truncate(last + 1);
+
+    let executor_transactions = transactions
+        .iter()
+        .map(|(tx, _)| pathfinder_rpc::compose_executor_transaction(tx, &db_tx))
+        .collect::<Result<Vec<_>, _>>();
+    let executor_transactions = match executor_transactions {
+        Ok(transactions) => transactions,
+        Err(error) => {
+            report.error = Some(format!("Transaction conversion failed: {error:#}"));
+            return Ok(report);
+        }
+    };
+
+    let execution_state =
+        ExecutionState::trace(&db_tx, chain_id, header, None).with_config(execution_config);
+    let simulations =
+        match pathfinder_executor::simulate(execution_state, executor_transactions, false, false) {
+            Ok(simulations) => simulations,
+            Err(error) => {
+                report.error = Some(format!("Execution failed: {error:?}"));
+                return Ok(report);
+            }
+        };
+
+    for (index, ((transaction, receipt), simulation)) in
+        transactions.iter().zip(simulations.iter()).enumerate()
+    {
+        if !filter.matches_type(transaction)
+            || !filter.matches_contract(transaction, &simulation.trace)
+        {
+            continue;
+        }
+
+        report.replayed_transactions += 1;
+
+        if let Some(diff) = diff_transaction(index, receipt, &simulation.into()) {
+            report.transactions.push(diff);
+        }
+    }
+
+    Ok(report)
+}
+
+fn diff_transaction(
+    index: usize,
+    receipt: &Receipt,
+    executed: &Executed,
+) -> Option<TransactionReport> {
+    let expected_revert = match receipt.execution_status {
+        ExecutionStatus::Succeeded => None,
+        ExecutionStatus::Reverted => Some(receipt.revert_error.clone().unwrap_or_default()),
+    };
+    let revert_reason =
+        (expected_revert.is_some() != executed.revert_reason.is_some()).then(|| Diff {
+            expected: expected_revert,
+            actual: executed.revert_reason.clone(),
+        });
+
+    // Old receipts have no fee and L1 handlers used to be free, so there is nothing to compare to.
+    let expected_fee = receipt
+        .actual_fee
+        .map(|fee| u128::from_be_bytes(fee.0.to_be_bytes()[16..].try_into().unwrap()))
+        .filter(|fee| *fee != 0);
+    let fee = expected_fee
+        .filter(|expected| *expected != executed.fee)
+        .map(|expected| Diff {
+            expected,
+            actual: executed.fee,
+        });
+
+    let mut resources = BTreeMap::new();
+    if let Some(expected) = &receipt.execution_resources {
+        let builtins = &expected.builtin_instance_counter;
+        let actual = &executed.resources;
+
+        for (name, expected, actual) in [
+            ("steps", expected.n_steps, actual.steps),
+            (
+                "pedersen_builtin",
+                builtins.pedersen_builtin,
+                actual.pedersen_builtin_applications,
+            ),
+            (
+                "range_check_builtin",
+                builtins.range_check_builtin,
+                actual.range_check_builtin_applications,
+            ),
+            (
+                "ecdsa_builtin",
+                builtins.ecdsa_builtin,
+                actual.ecdsa_builtin_applications,
+            ),
+            (
+                "bitwise_builtin",
+                builtins.bitwise_builtin,
+                actual.bitwise_builtin_applications,
+            ),
+            (
+                "ec_op_builtin",
+                builtins.ec_op_builtin,
+                actual.ec_op_builtin_applications,
+            ),
+            (
+                "keccak_builtin",
+                builtins.keccak_builtin,
+                actual.keccak_builtin_applications,
+            ),
+            (
+                "poseidon_builtin",
+                builtins.poseidon_builtin,
+                actual.poseidon_builtin_applications,
+            ),
+            (
+                "segment_arena_builtin",
+                builtins.segment_arena_builtin,
+                actual.segment_arena_builtin,
+            ),
+        ] {
+            let actual = actual as u64;
+            if expected != actual {
+                resources.insert(name, Diff { expected, actual });
+            }
+        }
+    }
+
+    let events = receipt
+        .events
+        .iter()
+        .zip(executed.events.iter())
+        .position(|(expected, actual)| expected != actual)
+        .or_else(|| {
+            (receipt.events.len() != executed.events.len())
+                .then(|| receipt.events.len().min(executed.events.len()))
+        })
+        .map(|first_mismatch| EventsDiff {
+            expected_count: receipt.events.len(),
+            actual_count: executed.events.len(),
+            first_mismatch,
+        });
+
+    if revert_reason.is_none() && fee.is_none() && resources.is_empty() && events.is_none() {
+        return None;
+    }
+
+    Some(TransactionReport {
+        transaction_hash: receipt.transaction_hash,
+        transaction_index: index,
+        revert_reason,
+        fee,
+        resources,
+        events,
+    })
+}
+
+/// The top-level calls of a transaction, in the order in which their events are emitted.
+fn top_level_invocations(trace: &TransactionTrace) -> Vec<&FunctionInvocation> {
+    match trace {
+        TransactionTrace::Declare(trace) => [
+            trace.validate_invocation.as_ref(),
+            trace.fee_transfer_invocation.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+        TransactionTrace::DeployAccount(trace) => [
+            trace.constructor_invocation.as_ref(),
+            trace.validate_invocation.as_ref(),
+            trace.fee_transfer_invocation.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+        TransactionTrace::Invoke(trace) => {
+            let execute = match &trace.execute_invocation {
+                ExecuteInvocation::FunctionInvocation(invocation) => invocation.as_ref(),
+                ExecuteInvocation::RevertedReason(_) => None,
+            };
+
+            [
+                trace.validate_invocation.as_ref(),
+                execute,
+                trace.fee_transfer_invocation.as_ref(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect()
+        }
+        TransactionTrace::L1Handler(trace) => trace.function_invocation.iter().collect(),
+    }
+}
+
+/// Events emitted by the invocation and its internal calls, in emission order.
+fn emitted_events(invocation: &FunctionInvocation) -> Vec<Event> {
+    fn collect(invocation: &FunctionInvocation, events: &mut Vec<(i64, Event)>) {
+        for event in &invocation.events {
+            events.push((
+                event.order,
+                Event {
+                    data: event.data.iter().copied().map(EventData).collect(),
+                    from_address: invocation.contract_address,
+                    keys: event.keys.iter().copied().map(EventKey).collect(),
+                },
+            ));
+        }
+
+        for call in &invocation.internal_calls {
+            collect(call, events);
+        }
+    }
+
+    let mut events = Vec::new();
+    collect(invocation, &mut events);
+    events.sort_by_key(|(order, _)| *order);
+
+    events.into_iter().map(|(_, event)| event).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fake::{Fake, Faker};
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::Fee;
+    use pathfinder_crypto::Felt;
+    use starknet_gateway_types::reply::transaction::{
+        BuiltinCounters, ExecutionResources as ReceiptResources,
+    };
+
+    fn receipt() -> Receipt {
+        Receipt {
+            actual_fee: Some(Fee(Felt::from_u64(1000))),
+            events: vec![Event {
+                data: vec![event_data!("0x1")],
+                from_address: contract_address!("0xabc"),
+                keys: vec![event_key!("0x2")],
+            }],
+            execution_resources: Some(ReceiptResources {
+                builtin_instance_counter: BuiltinCounters {
+                    pedersen_builtin: 3,
+                    ..Default::default()
+                },
+                n_steps: 100,
+                n_memory_holes: 5,
+            }),
+            execution_status: ExecutionStatus::Succeeded,
+            revert_error: None,
+            ..Faker.fake()
+        }
+    }
+
+    fn executed() -> Executed {
+        Executed {
+            fee: 1000,
+            resources: ExecutionResources {
+                steps: 100,
+                memory_holes: 0,
+                range_check_builtin_applications: 0,
+                pedersen_builtin_applications: 3,
+                poseidon_builtin_applications: 0,
+                ec_op_builtin_applications: 0,
+                ecdsa_builtin_applications: 0,
+                bitwise_builtin_applications: 0,
+                keccak_builtin_applications: 0,
+                segment_arena_builtin: 0,
+            },
+            events: vec![Event {
+                data: vec![event_data!("0x1")],
+                from_address: contract_address!("0xabc"),
+                keys: vec![event_key!("0x2")],
+            }],
+            revert_reason: None,
+        }
+    }
+
+    #[test]
+    fn identical_execution_has_no_diff() {
+        assert_eq!(diff_transaction(0, &receipt(), &executed()), None);
+    }
+
+    #[test]
+    fn fee_and_resource_differences_are_reported() {
+        let receipt = receipt();
+        let mut executed = executed();
+        executed.fee = 1200;
+        executed.resources.steps = 110;
+
+        let diff = diff_transaction(3, &receipt, &executed).unwrap();
+
+        assert_eq!(
+            diff,
+            TransactionReport {
+                transaction_hash: receipt.transaction_hash,
+                transaction_index: 3,
+                revert_reason: None,
+                fee: Some(Diff {
+                    expected: 1000,
+                    actual: 1200
+                }),
+                resources: BTreeMap::from([(
+                    "steps",
+                    Diff {
+                        expected: 100,
+                        actual: 110
+                    }
+                )]),
+                events: None,
+            }
+        );
+    }
+
+    #[test]
+    fn missing_events_are_reported() {
+        let receipt = receipt();
+        let mut executed = executed();
+        executed.events.clear();
+
+        let diff = diff_transaction(0, &receipt, &executed).unwrap();
+
+        assert_eq!(
+            diff.events,
+            Some(EventsDiff {
+                expected_count: 1,
+                actual_count: 0,
+                first_mismatch: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn revert_mismatch_is_reported() {
+        let receipt = receipt();
+        let mut executed = executed();
+        executed.revert_reason = Some("out of gas".to_owned());
+
+        let diff = diff_transaction(0, &receipt, &executed).unwrap();
+
+        assert_eq!(
+            diff.revert_reason,
+            Some(Diff {
+                expected: None,
+                actual: Some("out of gas".to_owned())
+            })
+        );
+    }
+
+    #[test]
+    fn window_waits_for_written_blocks() {
+        let window = Arc::new(Window::new(10, 2));
+        window.wait_for(11);
+
+        let waiting = {
+            let window = window.clone();
+            std::thread::spawn(move || window.wait_for(12))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!waiting.is_finished());
+
+        window.advance(11);
+        waiting.join().unwrap();
+    }
+
+    #[test]
+    fn checkpoint_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = checkpoint_path(&dir.path().join("report.json"));
+        assert_eq!(path, dir.path().join("report.json.checkpoint"));
+
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+
+        let checkpoint = Checkpoint {
+            from: 10,
+            to: 20,
+            next_block: 15,
+            report_len: 123,
+        };
+        checkpoint.save(&path).unwrap();
+
+        assert_eq!(Checkpoint::load(&path).unwrap(), Some(checkpoint));
+    }
+}
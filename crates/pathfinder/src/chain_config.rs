@@ -0,0 +1,236 @@
+//! Chain specific parameters, which can be configured for custom Starknet networks.
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::Context;
+use pathfinder_common::macro_prelude::*;
+use pathfinder_common::{BlockNumber, Chain, ContractAddress, PublicKey, SequencerAddress};
+use pathfinder_executor::ExecutionConfig;
+
+use crate::state::block_hash::BlockHashMetaInfo;
+
+/// Parameters which differ between Starknet chains.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainConfig {
+    pub execution: ExecutionConfig,
+    pub block_hash: BlockHashMetaInfo,
+    /// The key which the sequencer signs blocks with. Signatures are not verified if unknown.
+    pub sequencer_public_key: Option<PublicKey>,
+}
+
+/// Overrides of a custom network's [ChainConfig], loaded from a JSON file.
+///
+/// All fields are optional and default to those of the chain being overridden.
+///
+/// ```json
+/// {
+///   "fee_token_addresses": { "eth": "0x...", "strk": "0x..." },
+///   "versioned_constants": {
+///     "invoke_tx_max_n_steps": 3000000,
+///     "validate_max_n_steps": 1000000,
+///     "max_recursion_depth": 50,
+///     "vm_resource_fee_costs": { "n_steps": 0.005, "pedersen_builtin": 0.16 }
+///   },
+///   "block_hash": {
+///     "first_0_7_block": 0,
+///     "not_verifiable_range": { "start": 0, "end": 100 },
+///     "fallback_sequencer_address": "0x..."
+///   },
+///   "sequencer_public_key": "0x..."
+/// }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChainConfigFile {
+    #[serde(default)]
+    fee_token_addresses: FeeTokenAddresses,
+    #[serde(default)]
+    versioned_constants: VersionedConstants,
+    #[serde(default)]
+    block_hash: BlockHashParameters,
+    sequencer_public_key: Option<PublicKey>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FeeTokenAddresses {
+    eth: Option<ContractAddress>,
+    strk: Option<ContractAddress>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VersionedConstants {
+    invoke_tx_max_n_steps: Option<u32>,
+    validate_max_n_steps: Option<u32>,
+    max_recursion_depth: Option<usize>,
+    /// Merged into the default fee weights, so only the differing resources need to be listed.
+    #[serde(default)]
+    vm_resource_fee_costs: HashMap<String, f64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlockHashParameters {
+    first_0_7_block: Option<BlockNumber>,
+    not_verifiable_range: Option<Range<BlockNumber>>,
+    fallback_sequencer_address: Option<SequencerAddress>,
+}
+
+impl ChainConfig {
+    pub fn for_chain(chain: Chain) -> Self {
+        Self {
+            execution: ExecutionConfig::default(),
+            block_hash: BlockHashMetaInfo::for_chain(chain).clone(),
+            sequencer_public_key: match chain {
+                // https://alpha-mainnet.starknet.io/feeder_gateway/get_public_key
+                Chain::Mainnet => Some(public_key!(
+                    "0x48253ff2c3bed7af18bde0b611b083b39445959102d4947c51c4db6aa4f4e58"
+                )),
+                // https://alpha-sepolia.starknet.io/feeder_gateway/get_public_key
+                Chain::SepoliaTestnet => Some(public_key!(
+                    "0x1252b6bce1351844c677869c6327e80eae1535755b611c66b8f46e595b40eea"
+                )),
+                // https://integration-sepolia.starknet.io/feeder_gateway/get_public_key
+                Chain::SepoliaIntegration => Some(public_key!(
+                    "0x4e4856eb36dbd5f4a7dca29f7bb5232974ef1fb7eb5b597c58077174c294da1"
+                )),
+                // The Goerli networks are deprecated and custom networks provide their own key.
+                Chain::GoerliTestnet | Chain::GoerliIntegration | Chain::Custom => None,
+            },
+        }
+    }
+
+    /// Reads the configuration of a custom network from `path`, applied on top of the defaults
+    /// of `chain`. This is [Chain::Custom] unless the network proxies one of the public ones.
+    pub fn load(chain: Chain, path: &Path) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Opening chain config file {}", path.display()))?;
+        let file: ChainConfigFile =
+            serde_json::from_reader(file).context("Parsing chain config file")?;
+
+        Ok(Self::for_chain(chain).with_overrides(file))
+    }
+
+    fn with_overrides(mut self, overrides: ChainConfigFile) -> Self {
+        let ChainConfigFile {
+            fee_token_addresses,
+            versioned_constants,
+            block_hash,
+            sequencer_public_key,
+        } = overrides;
+
+        let execution = &mut self.execution;
+        if let Some(eth) = fee_token_addresses.eth {
+            execution.eth_fee_token_address = eth;
+        }
+        if let Some(strk) = fee_token_addresses.strk {
+            execution.strk_fee_token_address = strk;
+        }
+        if let Some(steps) = versioned_constants.invoke_tx_max_n_steps {
+            execution.invoke_tx_max_n_steps = steps;
+        }
+        if let Some(steps) = versioned_constants.validate_max_n_steps {
+            execution.validate_max_n_steps = steps;
+        }
+        if let Some(depth) = versioned_constants.max_recursion_depth {
+            execution.max_recursion_depth = depth;
+        }
+        execution
+            .vm_resource_fee_costs
+            .extend(versioned_constants.vm_resource_fee_costs);
+
+        if let Some(block) = block_hash.first_0_7_block {
+            self.block_hash.first_0_7_block = block;
+        }
+        if let Some(range) = block_hash.not_verifiable_range {
+            self.block_hash.not_verifiable_range = Some(range);
+        }
+        if let Some(address) = block_hash.fallback_sequencer_address {
+            self.block_hash.fallback_sequencer_address = Some(address);
+        }
+        if let Some(key) = sequencer_public_key {
+            self.sequencer_public_key = Some(key);
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(json: serde_json::Value) -> anyhow::Result<ChainConfig> {
+        load_for(Chain::Custom, json)
+    }
+
+    fn load_for(chain: Chain, json: serde_json::Value) -> anyhow::Result<ChainConfig> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chain.json");
+        std::fs::write(&path, serde_json::to_vec(&json).unwrap()).unwrap();
+        ChainConfig::load(chain, &path)
+    }
+
+    #[test]
+    fn empty_file_uses_custom_defaults() {
+        let config = load(serde_json::json!({})).unwrap();
+        assert_eq!(config, ChainConfig::for_chain(Chain::Custom));
+    }
+
+    #[test]
+    fn overrides_apply_on_top_of_the_detected_chain() {
+        let config = load_for(
+            Chain::Mainnet,
+            serde_json::json!({ "versioned_constants": { "max_recursion_depth": 20 } }),
+        )
+        .unwrap();
+
+        let mut expected = ChainConfig::for_chain(Chain::Mainnet);
+        expected.execution.max_recursion_depth = 20;
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn overrides() {
+        let config = load(serde_json::json!({
+            "fee_token_addresses": { "eth": "0x1", "strk": "0x2" },
+            "versioned_constants": {
+                "invoke_tx_max_n_steps": 10,
+                "max_recursion_depth": 20,
+                "vm_resource_fee_costs": { "n_steps": 1.0 }
+            },
+            "block_hash": {
+                "first_0_7_block": 5,
+                "not_verifiable_range": { "start": 5, "end": 8 },
+                "fallback_sequencer_address": "0x3"
+            },
+            "sequencer_public_key": "0x4"
+        }))
+        .unwrap();
+
+        let mut expected = ChainConfig::for_chain(Chain::Custom);
+        expected.execution.eth_fee_token_address = contract_address!("0x1");
+        expected.execution.strk_fee_token_address = contract_address!("0x2");
+        expected.execution.invoke_tx_max_n_steps = 10;
+        expected.execution.max_recursion_depth = 20;
+        expected
+            .execution
+            .vm_resource_fee_costs
+            .insert("n_steps".to_owned(), 1.0);
+        expected.block_hash = BlockHashMetaInfo {
+            first_0_7_block: BlockNumber::new_or_panic(5),
+            not_verifiable_range: Some(BlockNumber::new_or_panic(5)..BlockNumber::new_or_panic(8)),
+            fallback_sequencer_address: Some(sequencer_address!("0x3")),
+        };
+        expected.sequencer_public_key = Some(public_key!("0x4"));
+
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        load(serde_json::json!({ "fee_token_address": "0x1" })).unwrap_err();
+    }
+}
@@ -0,0 +1,103 @@
+//! Local development network.
+//!
+//! Instead of syncing from a Starknet network, pathfinder acts as its own sequencer: transactions
+//! submitted via the RPC API are kept in a [mempool](mempool::Mempool) and executed once a block is
+//! sealed, either on demand via `pathfinder_createBlock` or at a fixed interval.
+//!
+//! The devnet either starts from a [Genesis] block, or is [forked](Fork) from a remote network's
+//! state at a given block.
+use std::sync::Arc;
+
+use anyhow::Context;
+use pathfinder_common::{BlockNumber, CasmHash, ChainId};
+use pathfinder_crypto::Felt;
+use pathfinder_executor::ExecutionConfig;
+use pathfinder_rpc::context::LocalSequencer;
+use starknet_gateway_client::GatewayApi;
+use tokio::sync::{mpsc, oneshot};
+
+mod fork;
+mod genesis;
+mod mempool;
+mod producer;
+
+pub use fork::Fork;
+pub use genesis::Genesis;
+pub use producer::BlockProducer;
+
+use mempool::Mempool;
+
+/// Handle to the devnet sequencer, used by the RPC API to submit transactions and seal blocks.
+#[derive(Clone)]
+pub struct Devnet {
+    mempool: Arc<Mempool>,
+    requests: mpsc::Sender<producer::SealRequest>,
+}
+
+impl Devnet {
+    /// Creates the sequencer handle along with the [BlockProducer] which must be run for
+    /// blocks to be sealed.
+    ///
+    /// Blocks are executed with the chain specific parameters in `config`. Execution falls back to
+    /// `fork` for state which is missing locally.
+    pub fn new(
+        chain_id: ChainId,
+        config: Arc<ExecutionConfig>,
+        fork: Option<Arc<Fork>>,
+    ) -> (Self, BlockProducer) {
+        let mempool = Arc::new(Mempool::new(chain_id));
+        let (tx, rx) = mpsc::channel(1);
+
+        let devnet = Self {
+            mempool: mempool.clone(),
+            requests: tx,
+        };
+        let producer = BlockProducer {
+            chain_id,
+            config,
+            mempool,
+            requests: rx,
+            fork,
+        };
+
+        (devnet, producer)
+    }
+}
+
+#[async_trait::async_trait]
+impl LocalSequencer for Devnet {
+    fn gateway(&self) -> &(dyn GatewayApi + Send + Sync) {
+        self.mempool.as_ref()
+    }
+
+    async fn create_block(&self) -> anyhow::Result<BlockNumber> {
+        let (tx, rx) = oneshot::channel();
+        self.requests
+            .send(tx)
+            .await
+            .context("Block producer has stopped")?;
+        rx.await.context("Block producer has stopped")?
+    }
+}
+
+/// Compiles a Sierra class definition, returning the CASM definition and its hash.
+fn compile_sierra_class(definition: &[u8]) -> anyhow::Result<(Vec<u8>, CasmHash)> {
+    let casm_definition = pathfinder_compiler::compile_to_casm_with_latest_compiler(definition)
+        .context("Compiling Sierra class")?;
+
+    let casm_hash = casm_hash(&casm_definition)?;
+
+    Ok((casm_definition, casm_hash))
+}
+
+/// Computes the hash of a CASM class definition.
+fn casm_hash(casm_definition: &[u8]) -> anyhow::Result<CasmHash> {
+    use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+
+    let casm: CasmContractClass =
+        serde_json::from_slice(casm_definition).context("Parsing CASM definition")?;
+    let casm_hash = Felt::from_be_bytes(casm.compiled_class_hash().to_be_bytes())
+        .context("CASM hash out of range")?;
+
+    Ok(CasmHash(casm_hash))
+}
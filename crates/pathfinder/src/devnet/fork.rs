@@ -0,0 +1,585 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use pathfinder_common::{
+    BlockHash, BlockHeader, BlockNumber, ClassHash, ContractAddress, ContractNonce, SierraHash,
+    StateUpdate, StorageAddress, StorageValue,
+};
+use pathfinder_executor::{ForkClass, ForkState};
+use pathfinder_storage::{BlockId, Storage, Transaction, TransactionBehavior};
+use starknet_gateway_client::GatewayApi;
+use starknet_gateway_types::class_hash::{compute_class_hash, ComputedClassHash};
+use starknet_gateway_types::error::{KnownStarknetErrorCode, SequencerError};
+
+/// Remote state the devnet is forked from.
+///
+/// State missing from the local database is fetched from the feeder gateway as of the fork block
+/// and cached, both in memory and in the database. The database cache is recorded as part of the
+/// fork block, so that blocks produced on top of it find the state like any other.
+pub struct Fork {
+    gateway: Box<dyn GatewayApi + Send + Sync>,
+    block: BlockNumber,
+    storage: Storage,
+    runtime: tokio::runtime::Handle,
+    cache: Mutex<Cache>,
+    /// State fetched while writes are [deferred](Fork::defer_writes).
+    deferred: Mutex<Option<Vec<Fetched>>>,
+}
+
+/// Remote state to be recorded as part of the fork block.
+struct Fetched {
+    state_update: StateUpdate,
+    class: Option<(ClassHash, Vec<u8>, ForkClass)>,
+}
+
+#[derive(Default)]
+struct Cache {
+    storage: HashMap<(ContractAddress, StorageAddress), StorageValue>,
+    nonces: HashMap<ContractAddress, ContractNonce>,
+    class_hashes: HashMap<ContractAddress, Option<ClassHash>>,
+    classes: HashMap<ClassHash, Option<ForkClass>>,
+    block_hashes: HashMap<BlockNumber, Option<BlockHash>>,
+}
+
+impl Fork {
+    /// Must be called from within a tokio runtime, which is used to drive the gateway requests.
+    pub fn new(
+        gateway: Box<dyn GatewayApi + Send + Sync>,
+        block: BlockNumber,
+        storage: Storage,
+    ) -> Self {
+        Self {
+            gateway,
+            block,
+            storage,
+            runtime: tokio::runtime::Handle::current(),
+            cache: Default::default(),
+            deferred: Default::default(),
+        }
+    }
+
+    /// Stores the header of the fork block, which the devnet's blocks are built on, unless the
+    /// database already contains blocks.
+    pub async fn initialize(&self) -> anyhow::Result<()> {
+        let block = self
+            .gateway
+            .block(self.block.into())
+            .await
+            .context("Fetching fork block")?
+            .as_block()
+            .context("Fork block is pending")?;
+
+        let storage = self.storage.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = storage
+                .connection()
+                .context("Creating database connection")?;
+            let db = connection
+                .transaction_with_behavior(TransactionBehavior::Immediate)
+                .context("Create database transaction")?;
+
+            if let Some(latest) = db
+                .block_header(BlockId::Latest)
+                .context("Fetching latest block header")?
+            {
+                tracing::info!(number=%latest.number, "Resuming forked devnet from existing database");
+                return Ok(());
+            }
+
+            // The local state tries start out empty, so only the state commitment is known.
+            let header = BlockHeader {
+                hash: block.block_hash,
+                parent_hash: block.parent_block_hash,
+                number: block.block_number,
+                timestamp: block.timestamp,
+                eth_l1_gas_price: block.eth_l1_gas_price.unwrap_or_default(),
+                strk_l1_gas_price: block.strk_l1_gas_price.unwrap_or_default(),
+                sequencer_address: block.sequencer_address.unwrap_or_default(),
+                starknet_version: block.starknet_version,
+                state_commitment: block.state_commitment,
+                ..Default::default()
+            };
+            db.insert_block_header(&header)
+                .context("Inserting fork block header")?;
+            db.commit().context("Commit database transaction")?;
+
+            tracing::info!(number=%header.number, hash=%header.hash, "Forked devnet from remote block");
+
+            Ok(())
+        })
+        .await
+        .context("Joining fork initialization task")?
+    }
+
+    /// Runs a gateway request to completion.
+    ///
+    /// Execution runs on blocking threads, so blocking on the runtime here is fine.
+    fn fetch<T>(
+        &self,
+        request: impl Future<Output = Result<T, SequencerError>>,
+    ) -> Result<T, SequencerError> {
+        self.runtime.block_on(request)
+    }
+
+    /// Holds back the state fetched from now on until [Fork::write_deferred], instead of writing
+    /// it to the database right away.
+    ///
+    /// Block production holds the database's write lock while executing, and records the fetched
+    /// state as part of its own database transaction.
+    pub(super) fn defer_writes(&self) {
+        self.deferred.lock().unwrap().get_or_insert_with(Vec::new);
+    }
+
+    /// Writes the state fetched since [Fork::defer_writes] using `db`, and resumes writing fetched
+    /// state right away.
+    ///
+    /// Without `db` the deferred state is dropped, it is still cached in memory though.
+    pub(super) fn write_deferred(&self, db: Option<&Transaction<'_>>) -> anyhow::Result<()> {
+        let deferred = self.deferred.lock().unwrap().take().unwrap_or_default();
+        let Some(db) = db else {
+            return Ok(());
+        };
+
+        for fetched in deferred {
+            let class = fetched
+                .class
+                .as_ref()
+                .map(|(hash, definition, class)| (*hash, definition.as_slice(), class));
+            self.write(db, &fetched.state_update, class)?;
+        }
+
+        Ok(())
+    }
+
+    /// Records `state_update` as part of the fork block.
+    ///
+    /// Failing to do so only costs another remote request later on, so errors are merely logged.
+    fn persist(&self, state_update: StateUpdate, class: Option<(ClassHash, &[u8], &ForkClass)>) {
+        if let Some(deferred) = self.deferred.lock().unwrap().as_mut() {
+            deferred.push(Fetched {
+                state_update,
+                class: class
+                    .map(|(hash, definition, class)| (hash, definition.to_vec(), class.clone())),
+            });
+            return;
+        }
+
+        if let Err(error) = self.try_persist(state_update, class) {
+            tracing::warn!(?error, "Failed to cache forked state");
+        }
+    }
+
+    fn try_persist(
+        &self,
+        state_update: StateUpdate,
+        class: Option<(ClassHash, &[u8], &ForkClass)>,
+    ) -> anyhow::Result<()> {
+        let mut connection = self
+            .storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .context("Create database transaction")?;
+
+        self.write(&db, &state_update, class)?;
+        db.commit().context("Commit database transaction")
+    }
+
+    fn write(
+        &self,
+        db: &Transaction<'_>,
+        state_update: &StateUpdate,
+        class: Option<(ClassHash, &[u8], &ForkClass)>,
+    ) -> anyhow::Result<()> {
+        match class {
+            Some((hash, definition, ForkClass::Cairo(_))) => db
+                .insert_cairo_class(hash, definition)
+                .context("Inserting class definition")?,
+            Some((
+                hash,
+                definition,
+                ForkClass::Sierra {
+                    casm_definition,
+                    casm_hash,
+                },
+            )) => db
+                .insert_sierra_class(&SierraHash(hash.0), definition, casm_hash, casm_definition)
+                .context("Inserting class definition")?,
+            None => {}
+        }
+
+        db.insert_state_update(self.block, state_update)
+            .context("Inserting state update")
+    }
+}
+
+impl ForkState for Fork {
+    fn storage_value(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageAddress,
+    ) -> anyhow::Result<StorageValue> {
+        if let Some(value) = self
+            .cache
+            .lock()
+            .unwrap()
+            .storage
+            .get(&(contract_address, key))
+        {
+            return Ok(*value);
+        }
+
+        let value = self
+            .fetch(
+                self.gateway
+                    .storage_at(contract_address, key, self.block.into()),
+            )
+            .context("Fetching storage value from fork")?;
+
+        self.persist(
+            StateUpdate::default().with_storage_update(contract_address, key, value),
+            None,
+        );
+        self.cache
+            .lock()
+            .unwrap()
+            .storage
+            .insert((contract_address, key), value);
+
+        Ok(value)
+    }
+
+    fn contract_nonce(&self, contract_address: ContractAddress) -> anyhow::Result<ContractNonce> {
+        if let Some(nonce) = self.cache.lock().unwrap().nonces.get(&contract_address) {
+            return Ok(*nonce);
+        }
+
+        let nonce = self
+            .fetch(self.gateway.nonce_at(contract_address, self.block.into()))
+            .context("Fetching nonce from fork")?;
+
+        self.persist(
+            StateUpdate::default().with_contract_nonce(contract_address, nonce),
+            None,
+        );
+        self.cache
+            .lock()
+            .unwrap()
+            .nonces
+            .insert(contract_address, nonce);
+
+        Ok(nonce)
+    }
+
+    fn contract_class_hash(
+        &self,
+        contract_address: ContractAddress,
+    ) -> anyhow::Result<Option<ClassHash>> {
+        if let Some(class_hash) = self
+            .cache
+            .lock()
+            .unwrap()
+            .class_hashes
+            .get(&contract_address)
+        {
+            return Ok(*class_hash);
+        }
+
+        let class_hash = match self.fetch(
+            self.gateway
+                .class_hash_at(contract_address, self.block.into()),
+        ) {
+            Ok(class_hash) => {
+                self.persist(
+                    StateUpdate::default().with_deployed_contract(contract_address, class_hash),
+                    None,
+                );
+                Some(class_hash)
+            }
+            Err(SequencerError::StarknetError(e))
+                if e.code == KnownStarknetErrorCode::UninitializedContract.into() =>
+            {
+                None
+            }
+            Err(e) => return Err(e).context("Fetching class hash from fork"),
+        };
+
+        self.cache
+            .lock()
+            .unwrap()
+            .class_hashes
+            .insert(contract_address, class_hash);
+
+        Ok(class_hash)
+    }
+
+    fn class_definition(&self, class_hash: ClassHash) -> anyhow::Result<Option<ForkClass>> {
+        if let Some(class) = self.cache.lock().unwrap().classes.get(&class_hash) {
+            return Ok(class.clone());
+        }
+
+        let definition = match self.fetch(self.gateway.class_by_hash(class_hash, self.block.into()))
+        {
+            Ok(definition) => Some(definition),
+            Err(SequencerError::StarknetError(e))
+                if e.code == KnownStarknetErrorCode::UndeclaredClass.into() =>
+            {
+                None
+            }
+            Err(e) => return Err(e).context("Fetching class definition from fork"),
+        };
+
+        let class = match definition {
+            Some(definition) => {
+                let (class, state_update) = match compute_class_hash(&definition)
+                    .context("Computing class hash")?
+                {
+                    ComputedClassHash::Cairo(_) => (
+                        ForkClass::Cairo(definition.to_vec()),
+                        StateUpdate::default().with_declared_cairo_class(class_hash),
+                    ),
+                    ComputedClassHash::Sierra(_) => {
+                        let casm_definition = self
+                            .fetch(self.gateway.casm_by_hash(class_hash, self.block.into()))
+                            .context("Fetching CASM definition from fork")?
+                            .to_vec();
+                        let casm_hash = super::casm_hash(&casm_definition)?;
+                        (
+                            ForkClass::Sierra {
+                                casm_definition,
+                                casm_hash,
+                            },
+                            StateUpdate::default()
+                                .with_declared_sierra_class(SierraHash(class_hash.0), casm_hash),
+                        )
+                    }
+                };
+
+                self.persist(state_update, Some((class_hash, &definition, &class)));
+                Some(class)
+            }
+            None => None,
+        };
+
+        self.cache
+            .lock()
+            .unwrap()
+            .classes
+            .insert(class_hash, class.clone());
+
+        Ok(class)
+    }
+
+    fn block_hash(&self, block: BlockNumber) -> anyhow::Result<Option<BlockHash>> {
+        if let Some(hash) = self.cache.lock().unwrap().block_hashes.get(&block) {
+            return Ok(*hash);
+        }
+
+        let hash = match self.fetch(self.gateway.block_header(block.into())) {
+            Ok((_, hash)) => Some(hash),
+            Err(SequencerError::StarknetError(e))
+                if e.code == KnownStarknetErrorCode::BlockNotFound.into() =>
+            {
+                None
+            }
+            Err(e) => return Err(e).context("Fetching block hash from fork"),
+        };
+
+        self.cache.lock().unwrap().block_hashes.insert(block, hash);
+
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::BlockTimestamp;
+    use starknet_gateway_client::MockGatewayApi;
+    use starknet_gateway_test_fixtures::class_definitions::{
+        ERC20_CONTRACT_DEFINITION, ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+    };
+    use starknet_gateway_types::error::StarknetError;
+    use starknet_gateway_types::reply::{Block, Status};
+
+    use super::*;
+
+    const FORK_BLOCK: BlockNumber = BlockNumber::new_or_panic(100);
+
+    fn fork_block() -> Block {
+        Block {
+            block_hash: block_hash!("0xb100"),
+            block_number: FORK_BLOCK,
+            eth_l1_gas_price: None,
+            strk_l1_gas_price: None,
+            parent_block_hash: block_hash!("0xb99"),
+            sequencer_address: None,
+            state_commitment: state_commitment!("0x5c"),
+            status: Status::AcceptedOnL2,
+            timestamp: BlockTimestamp::new_or_panic(1000),
+            transaction_receipts: vec![],
+            transactions: vec![],
+            starknet_version: Default::default(),
+        }
+    }
+
+    async fn setup(gateway: MockGatewayApi) -> (Fork, Storage) {
+        let storage = Storage::in_memory().unwrap();
+        let fork = Fork::new(Box::new(gateway), FORK_BLOCK, storage.clone());
+        fork.initialize().await.unwrap();
+        (fork, storage)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn initialize() {
+        let mut gateway = MockGatewayApi::new();
+        gateway
+            .expect_block()
+            .times(2)
+            .returning(|_| Ok(fork_block().into()));
+
+        let (fork, storage) = setup(gateway).await;
+        // Resuming leaves the existing header untouched.
+        fork.initialize().await.unwrap();
+
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        let header = tx.block_header(BlockId::Latest).unwrap().unwrap();
+        assert_eq!(header.number, FORK_BLOCK);
+        assert_eq!(header.hash, block_hash!("0xb100"));
+        assert_eq!(header.state_commitment, state_commitment!("0x5c"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn remote_state_is_cached() {
+        let mut gateway = MockGatewayApi::new();
+        gateway
+            .expect_block()
+            .returning(|_| Ok(fork_block().into()));
+        gateway
+            .expect_storage_at()
+            .withf(|_, _, block| *block == pathfinder_common::BlockId::Number(FORK_BLOCK))
+            .times(1)
+            .returning(|_, _, _| Ok(storage_value!("0x99")));
+        gateway
+            .expect_nonce_at()
+            .times(1)
+            .returning(|_, _| Ok(contract_nonce!("0x3")));
+        gateway
+            .expect_class_hash_at()
+            .times(1)
+            .returning(|_, _| Ok(class_hash!("0xc1")));
+
+        let (fork, storage) = setup(gateway).await;
+        let contract = contract_address!("0x123");
+        let key = storage_address!("0x1");
+
+        let fork = tokio::task::spawn_blocking(move || {
+            for _ in 0..2 {
+                assert_eq!(
+                    fork.storage_value(contract, key).unwrap(),
+                    storage_value!("0x99")
+                );
+                assert_eq!(
+                    fork.contract_nonce(contract).unwrap(),
+                    contract_nonce!("0x3")
+                );
+                assert_eq!(
+                    fork.contract_class_hash(contract).unwrap(),
+                    Some(class_hash!("0xc1"))
+                );
+            }
+            fork
+        })
+        .await
+        .unwrap();
+        drop(fork);
+
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        let block = FORK_BLOCK.into();
+        assert_eq!(
+            tx.storage_value(block, contract, key).unwrap(),
+            Some(storage_value!("0x99"))
+        );
+        assert_eq!(
+            tx.contract_nonce(contract, block).unwrap(),
+            Some(contract_nonce!("0x3"))
+        );
+        assert_eq!(
+            tx.contract_class_hash(block, contract).unwrap(),
+            Some(class_hash!("0xc1"))
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn missing_remote_state() {
+        let mut gateway = MockGatewayApi::new();
+        gateway
+            .expect_block()
+            .returning(|_| Ok(fork_block().into()));
+        gateway.expect_class_hash_at().times(1).returning(|_, _| {
+            Err(SequencerError::StarknetError(StarknetError {
+                code: KnownStarknetErrorCode::UninitializedContract.into(),
+                message: String::new(),
+            }))
+        });
+        gateway.expect_class_by_hash().times(1).returning(|_, _| {
+            Err(SequencerError::StarknetError(StarknetError {
+                code: KnownStarknetErrorCode::UndeclaredClass.into(),
+                message: String::new(),
+            }))
+        });
+
+        let (fork, _storage) = setup(gateway).await;
+
+        tokio::task::spawn_blocking(move || {
+            for _ in 0..2 {
+                assert_eq!(
+                    fork.contract_class_hash(contract_address!("0x123"))
+                        .unwrap(),
+                    None
+                );
+                assert_eq!(fork.class_definition(class_hash!("0xc1")).unwrap(), None);
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn class_definition() {
+        let mut gateway = MockGatewayApi::new();
+        gateway
+            .expect_block()
+            .returning(|_| Ok(fork_block().into()));
+        gateway
+            .expect_class_by_hash()
+            .withf(|_, block| *block == pathfinder_common::BlockId::Number(FORK_BLOCK))
+            .times(1)
+            .returning(|_, _| Ok(bytes::Bytes::from_static(ERC20_CONTRACT_DEFINITION)));
+
+        let (fork, storage) = setup(gateway).await;
+
+        let class = tokio::task::spawn_blocking(move || {
+            fork.class_definition(ERC20_CONTRACT_DEFINITION_CLASS_HASH)
+                .unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            class,
+            Some(ForkClass::Cairo(ERC20_CONTRACT_DEFINITION.to_vec()))
+        );
+
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        assert!(tx
+            .class_definition_at(FORK_BLOCK.into(), ERC20_CONTRACT_DEFINITION_CLASS_HASH)
+            .unwrap()
+            .is_some());
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Context;
+use pathfinder_common::{
+    BlockHash, BlockHeader, BlockNumber, BlockTimestamp, ClassHash, ContractAddress, Fee, GasPrice,
+    SequencerAddress, SierraHash, StarknetVersion, StateUpdate, StorageAddress, StorageValue,
+};
+use pathfinder_crypto::Felt;
+use pathfinder_executor::ExecutionConfig;
+use pathfinder_storage::{BlockId, Storage, TransactionBehavior};
+use starknet_gateway_types::class_hash::{compute_class_hash, ComputedClassHash};
+
+/// The Starknet version devnet blocks are produced with.
+const STARKNET_VERSION: &str = "0.13.0";
+
+/// Description of the devnet's genesis block, loaded from a JSON file.
+///
+/// ```json
+/// {
+///   "classes": ["erc20.json", "account.json"],
+///   "fee_token_class": "0x...",
+///   "accounts": [
+///     { "address": "0x...", "class_hash": "0x...", "public_key": "0x...", "balance": "0x..." }
+///   ],
+///   "contracts": [
+///     { "address": "0x...", "class_hash": "0x...", "storage": { "0x...": "0x..." } }
+///   ],
+///   "sequencer_address": "0x...",
+///   "gas_price": 1000000000
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Genesis {
+    /// Class definitions to declare, relative to the genesis file.
+    #[serde(default)]
+    classes: Vec<PathBuf>,
+    /// Class of the fee token contracts, deployed at the chain's ETH and STRK fee token addresses.
+    fee_token_class: ClassHash,
+    /// Accounts to deploy and fund.
+    #[serde(default)]
+    accounts: Vec<Account>,
+    /// Arbitrary contracts to deploy.
+    #[serde(default)]
+    contracts: Vec<Contract>,
+    #[serde(default)]
+    sequencer_address: SequencerAddress,
+    /// Gas price of all devnet blocks, in both wei and fri.
+    #[serde(default = "default_gas_price")]
+    gas_price: GasPrice,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Account {
+    address: ContractAddress,
+    class_hash: ClassHash,
+    public_key: Felt,
+    /// Initial balance in both fee tokens.
+    balance: Fee,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Contract {
+    address: ContractAddress,
+    class_hash: ClassHash,
+    #[serde(default)]
+    storage: HashMap<StorageAddress, StorageValue>,
+}
+
+fn default_gas_price() -> GasPrice {
+    GasPrice(1_000_000_000)
+}
+
+impl Genesis {
+    /// Reads the genesis description from `path`.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Opening genesis file {}", path.display()))?;
+        let mut genesis: Self = serde_json::from_reader(file).context("Parsing genesis file")?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+        for class in &mut genesis.classes {
+            *class = base.join(&class);
+        }
+
+        Ok(genesis)
+    }
+
+    /// Creates the genesis block unless the database already contains blocks.
+    ///
+    /// The fee tokens are deployed at the addresses given by `config`.
+    pub fn initialize(&self, storage: Storage, config: &ExecutionConfig) -> anyhow::Result<()> {
+        let mut connection = storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .context("Create database transaction")?;
+
+        if let Some(latest) = db
+            .block_header(BlockId::Latest)
+            .context("Fetching latest block header")?
+        {
+            tracing::info!(number=%latest.number, "Resuming devnet from existing database");
+            return Ok(());
+        }
+
+        let mut state_update = StateUpdate::default();
+        let mut declared = HashSet::new();
+        for path in &self.classes {
+            let definition = std::fs::read(path)
+                .with_context(|| format!("Reading class definition {}", path.display()))?;
+
+            let hash = match compute_class_hash(&definition)
+                .with_context(|| format!("Computing class hash of {}", path.display()))?
+            {
+                ComputedClassHash::Cairo(hash) => {
+                    db.insert_cairo_class(hash, &definition)
+                        .context("Inserting class definition")?;
+                    state_update = state_update.with_declared_cairo_class(hash);
+                    hash
+                }
+                ComputedClassHash::Sierra(hash) => {
+                    let (casm_definition, casm_hash) = super::compile_sierra_class(&definition)
+                        .with_context(|| format!("Compiling {}", path.display()))?;
+                    let sierra_hash = SierraHash(hash.0);
+                    db.insert_sierra_class(&sierra_hash, &definition, &casm_hash, &casm_definition)
+                        .context("Inserting class definition")?;
+                    state_update = state_update.with_declared_sierra_class(sierra_hash, casm_hash);
+                    hash
+                }
+            };
+
+            tracing::debug!(class_hash=%hash, path=%path.display(), "Declared genesis class");
+            declared.insert(hash);
+        }
+
+        state_update = self.state_update(state_update, &declared, config)?;
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .context("System time is before the unix epoch")?
+            .as_secs();
+        let header = BlockHeader {
+            parent_hash: BlockHash::ZERO,
+            number: BlockNumber::GENESIS,
+            timestamp: BlockTimestamp::new_or_panic(now),
+            eth_l1_gas_price: self.gas_price,
+            strk_l1_gas_price: self.gas_price,
+            sequencer_address: self.sequencer_address,
+            starknet_version: StarknetVersion::from(STARKNET_VERSION.to_owned()),
+            ..Default::default()
+        };
+
+        let header = super::producer::insert_block(&db, storage, header, vec![], state_update)
+            .context("Inserting genesis block")?;
+
+        db.commit().context("Commit database transaction")?;
+
+        tracing::info!(hash=%header.hash, "Created devnet genesis block");
+
+        Ok(())
+    }
+
+    /// Adds the contracts described by the genesis file to `state_update`.
+    fn state_update(
+        &self,
+        mut state_update: StateUpdate,
+        declared: &HashSet<ClassHash>,
+        config: &ExecutionConfig,
+    ) -> anyhow::Result<StateUpdate> {
+        let fee_tokens = [config.eth_fee_token_address, config.strk_fee_token_address];
+
+        let ensure_declared = |class_hash: &ClassHash| {
+            anyhow::ensure!(
+                declared.contains(class_hash),
+                "Class {class_hash} is not declared in the genesis file"
+            );
+            Ok(())
+        };
+
+        ensure_declared(&self.fee_token_class)?;
+        for token in fee_tokens {
+            state_update = state_update.with_deployed_contract(token, self.fee_token_class);
+        }
+
+        for account in &self.accounts {
+            ensure_declared(&account.class_hash)?;
+            anyhow::ensure!(
+                account.balance.0.to_be_bytes()[..16]
+                    .iter()
+                    .all(|b| *b == 0),
+                "Balance of account {} does not fit into 128 bits",
+                account.address
+            );
+
+            state_update = state_update
+                .with_deployed_contract(account.address, account.class_hash)
+                .with_storage_update(
+                    account.address,
+                    StorageAddress::from_name(b"Account_public_key"),
+                    StorageValue(account.public_key),
+                );
+
+            // Balances are u256 values, of which we only set the low word.
+            let balance_key =
+                StorageAddress::from_map_name_and_key(b"ERC20_balances", account.address.0);
+            for token in fee_tokens {
+                state_update = state_update.with_storage_update(
+                    token,
+                    balance_key,
+                    StorageValue(account.balance.0),
+                );
+            }
+        }
+
+        for contract in &self.contracts {
+            ensure_declared(&contract.class_hash)?;
+
+            state_update =
+                state_update.with_deployed_contract(contract.address, contract.class_hash);
+            for (key, value) in &contract.storage {
+                state_update = state_update.with_storage_update(contract.address, *key, *value);
+            }
+        }
+
+        Ok(state_update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::StateCommitment;
+    use pathfinder_executor::ETH_FEE_TOKEN_ADDRESS;
+    use starknet_gateway_test_fixtures::class_definitions::{
+        DUMMY_ACCOUNT, DUMMY_ACCOUNT_CLASS_HASH, ERC20_CONTRACT_DEFINITION,
+        ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+    };
+
+    use super::*;
+
+    fn write_genesis(dir: &Path, genesis: serde_json::Value) -> PathBuf {
+        std::fs::write(dir.join("erc20.json"), ERC20_CONTRACT_DEFINITION).unwrap();
+        std::fs::write(dir.join("account.json"), DUMMY_ACCOUNT).unwrap();
+
+        let path = dir.join("genesis.json");
+        std::fs::write(&path, serde_json::to_vec(&genesis).unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn initialize() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_genesis(
+            dir.path(),
+            serde_json::json!({
+                "classes": ["erc20.json", "account.json"],
+                "fee_token_class": ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+                "accounts": [{
+                    "address": "0x123",
+                    "class_hash": DUMMY_ACCOUNT_CLASS_HASH,
+                    "public_key": "0xabc",
+                    "balance": "0x1000"
+                }],
+                "sequencer_address": "0x1000",
+            }),
+        );
+
+        let genesis = Genesis::load(&path).unwrap();
+        let storage = Storage::in_memory().unwrap();
+        genesis
+            .initialize(storage.clone(), &ExecutionConfig::default())
+            .unwrap();
+
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let header = tx.block_header(BlockId::Latest).unwrap().unwrap();
+        assert_eq!(header.number, BlockNumber::GENESIS);
+        assert_eq!(header.sequencer_address, sequencer_address!("0x1000"));
+        assert_eq!(header.eth_l1_gas_price, default_gas_price());
+        assert_ne!(header.state_commitment, StateCommitment::ZERO);
+
+        let account = contract_address!("0x123");
+        assert_eq!(
+            tx.contract_class_hash(BlockId::Latest, account).unwrap(),
+            Some(DUMMY_ACCOUNT_CLASS_HASH)
+        );
+        let balance = tx
+            .storage_value(
+                BlockId::Latest,
+                ETH_FEE_TOKEN_ADDRESS,
+                StorageAddress::from_map_name_and_key(b"ERC20_balances", account.0),
+            )
+            .unwrap();
+        assert_eq!(balance, Some(storage_value!("0x1000")));
+        drop(tx);
+
+        // Initializing an existing database leaves it untouched.
+        genesis
+            .initialize(storage.clone(), &ExecutionConfig::default())
+            .unwrap();
+        let tx = connection.transaction().unwrap();
+        assert_eq!(tx.block_header(BlockId::Latest).unwrap().unwrap(), header);
+    }
+
+    #[test]
+    fn undeclared_class_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_genesis(
+            dir.path(),
+            serde_json::json!({
+                "classes": ["erc20.json"],
+                "fee_token_class": ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+                "contracts": [{
+                    "address": "0x123",
+                    "class_hash": DUMMY_ACCOUNT_CLASS_HASH,
+                }],
+            }),
+        );
+
+        let genesis = Genesis::load(&path).unwrap();
+        let storage = Storage::in_memory().unwrap();
+        genesis
+            .initialize(storage.clone(), &ExecutionConfig::default())
+            .unwrap_err();
+
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        assert_eq!(tx.block_header(BlockId::Latest).unwrap(), None);
+    }
+}
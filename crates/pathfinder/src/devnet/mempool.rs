@@ -0,0 +1,634 @@
+use std::collections::HashSet;
+use std::io::Read;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use lru::LruCache;
+use pathfinder_common::{
+    CasmHash, ChainId, ClassHash, ContractAddress, TransactionHash, TransactionVersion,
+};
+use starknet_gateway_client::GatewayApi;
+use starknet_gateway_types::class_hash::{compute_class_hash, ComputedClassHash};
+use starknet_gateway_types::error::{KnownStarknetErrorCode, SequencerError, StarknetError};
+use starknet_gateway_types::reply::transaction::{
+    DeclareTransaction, DeclareTransactionV0V1, DeclareTransactionV2, DeclareTransactionV3,
+    DeployAccountTransaction, DeployAccountTransactionV0V1, DeployAccountTransactionV3,
+    InvokeTransaction, InvokeTransactionV0, InvokeTransactionV1, InvokeTransactionV3, Transaction,
+};
+use starknet_gateway_types::reply::transaction_status::{ExecutionStatus, FinalityStatus};
+use starknet_gateway_types::reply::{add_transaction, Status, TransactionStatus};
+use starknet_gateway_types::request::add_transaction::{
+    CairoContractDefinition, ContractDefinition, Declare, DeployAccount, DeployAccountV0V1,
+    InvokeFunction, SierraContractDefinition,
+};
+use starknet_gateway_types::transaction_hash::compute_transaction_hash;
+
+/// How many rejected transactions are remembered for status queries.
+const MAX_REJECTED: usize = 10_000;
+
+/// Transactions submitted to the devnet which have not been sealed into a block yet.
+///
+/// Implements the transaction submission part of [GatewayApi] so that the RPC
+/// `starknet_add*Transaction` methods can use it in place of the Starknet gateway.
+pub(super) struct Mempool {
+    chain_id: ChainId,
+    received: Mutex<Vec<ReceivedTransaction>>,
+    /// Transactions which were [taken](Mempool::take) and are being sealed into a block.
+    ///
+    /// Always locked after `received`.
+    sealing: Mutex<HashSet<TransactionHash>>,
+    /// The most recent transactions which failed execution, mapped to the reason.
+    rejected: Mutex<LruCache<TransactionHash, String>>,
+}
+
+pub(super) struct ReceivedTransaction {
+    pub transaction: Transaction,
+    /// The class being declared, for declare transactions.
+    pub class: Option<ClassDefinition>,
+}
+
+pub(super) enum ClassDefinition {
+    Cairo {
+        hash: ClassHash,
+        definition: Vec<u8>,
+    },
+    Sierra {
+        hash: ClassHash,
+        definition: Vec<u8>,
+        casm_hash: CasmHash,
+        casm_definition: Vec<u8>,
+    },
+}
+
+impl Mempool {
+    pub fn new(chain_id: ChainId) -> Self {
+        Self {
+            chain_id,
+            received: Default::default(),
+            sealing: Default::default(),
+            rejected: Mutex::new(LruCache::new(
+                NonZeroUsize::new(MAX_REJECTED).expect("Non-zero capacity"),
+            )),
+        }
+    }
+
+    /// Removes and returns all received transactions, in the order they were received.
+    ///
+    /// The transactions are still reported as received until they are [sealed](Mempool::sealed),
+    /// [rejected](Mempool::reject) or [restored](Mempool::restore).
+    pub fn take(&self) -> Vec<ReceivedTransaction> {
+        let mut received = self.received.lock().unwrap();
+        let transactions = std::mem::take(&mut *received);
+        *self.sealing.lock().unwrap() = transactions.iter().map(|r| r.transaction.hash()).collect();
+        transactions
+    }
+
+    /// Forgets the transactions which were [taken](Mempool::take), once they have been stored in
+    /// a block.
+    pub fn sealed(&self) {
+        self.sealing.lock().unwrap().clear();
+    }
+
+    /// Puts transactions which were [taken](Mempool::take) but could not be sealed back, ahead of
+    /// those received since.
+    pub fn restore(&self, transactions: Vec<ReceivedTransaction>) {
+        let mut received = self.received.lock().unwrap();
+        self.sealing.lock().unwrap().clear();
+        let newer = std::mem::replace(&mut *received, transactions);
+        for transaction in newer {
+            // The same transaction may have been submitted again while it was taken.
+            let hash = transaction.transaction.hash();
+            if !received.iter().any(|r| r.transaction.hash() == hash) {
+                received.push(transaction);
+            }
+        }
+    }
+
+    /// Marks the transaction as rejected, so that its status can be queried.
+    pub fn reject(&self, transaction_hash: TransactionHash, reason: String) {
+        self.sealing.lock().unwrap().remove(&transaction_hash);
+        self.rejected.lock().unwrap().put(transaction_hash, reason);
+    }
+
+    /// Computes the hash of the transaction and adds it to the mempool.
+    fn insert(
+        &self,
+        mut transaction: Transaction,
+        class: Option<ClassDefinition>,
+    ) -> Result<TransactionHash, SequencerError> {
+        let hash = compute_transaction_hash(&transaction, self.chain_id);
+        set_transaction_hash(&mut transaction, hash);
+
+        let mut received = self.received.lock().unwrap();
+        if received.iter().any(|r| r.transaction.hash() == hash) {
+            return Err(starknet_error(
+                KnownStarknetErrorCode::DuplicatedTransaction,
+                format!("Transaction {hash} is already in the mempool"),
+            ));
+        }
+
+        tracing::debug!(transaction_hash=%hash, "Transaction received");
+        received.push(ReceivedTransaction { transaction, class });
+
+        Ok(hash)
+    }
+}
+
+#[async_trait::async_trait]
+impl GatewayApi for Mempool {
+    async fn add_invoke_transaction(
+        &self,
+        invoke: InvokeFunction,
+    ) -> Result<add_transaction::InvokeResponse, SequencerError> {
+        let transaction = match invoke {
+            InvokeFunction::V0(tx) => {
+                let entry_point_selector = tx.entry_point_selector.ok_or_else(|| {
+                    starknet_error(
+                        KnownStarknetErrorCode::MalformedRequest,
+                        "Invoke v0 transactions require an entry point selector".to_owned(),
+                    )
+                })?;
+
+                InvokeTransaction::V0(InvokeTransactionV0 {
+                    calldata: tx.calldata,
+                    sender_address: tx.sender_address,
+                    entry_point_selector,
+                    entry_point_type: None,
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    transaction_hash: TransactionHash::ZERO,
+                })
+            }
+            InvokeFunction::V1(tx) => {
+                let nonce = tx.nonce.ok_or_else(|| {
+                    starknet_error(
+                        KnownStarknetErrorCode::MalformedRequest,
+                        "Invoke v1 transactions require a nonce".to_owned(),
+                    )
+                })?;
+
+                InvokeTransaction::V1(InvokeTransactionV1 {
+                    calldata: tx.calldata,
+                    sender_address: tx.sender_address,
+                    max_fee: tx.max_fee,
+                    signature: tx.signature,
+                    nonce,
+                    transaction_hash: TransactionHash::ZERO,
+                })
+            }
+            InvokeFunction::V3(tx) => InvokeTransaction::V3(InvokeTransactionV3 {
+                nonce: tx.nonce,
+                nonce_data_availability_mode: tx.nonce_data_availability_mode,
+                fee_data_availability_mode: tx.fee_data_availability_mode,
+                resource_bounds: tx.resource_bounds,
+                tip: tx.tip,
+                paymaster_data: tx.paymaster_data,
+                sender_address: tx.sender_address,
+                signature: tx.signature,
+                transaction_hash: TransactionHash::ZERO,
+                calldata: tx.calldata,
+                account_deployment_data: tx.account_deployment_data,
+            }),
+        };
+
+        let transaction_hash = self.insert(Transaction::Invoke(transaction), None)?;
+
+        Ok(add_transaction::InvokeResponse {
+            code: "TRANSACTION_RECEIVED".to_owned(),
+            transaction_hash,
+        })
+    }
+
+    async fn add_declare_transaction(
+        &self,
+        declare: Declare,
+        _token: Option<String>,
+    ) -> Result<add_transaction::DeclareResponse, SequencerError> {
+        let (transaction, class) = match declare {
+            Declare::V0(tx) | Declare::V1(tx) | Declare::V2(tx) => {
+                let class = match tx.contract_class {
+                    ContractDefinition::Cairo(class) => decode_cairo_class(class)?,
+                    ContractDefinition::Sierra(class) => {
+                        decode_sierra_class(class, tx.compiled_class_hash).await?
+                    }
+                };
+
+                let transaction = match (&class, tx.version.without_query_version()) {
+                    (ClassDefinition::Cairo { hash, .. }, version @ (0 | 1)) => {
+                        let tx = DeclareTransactionV0V1 {
+                            class_hash: *hash,
+                            max_fee: tx.max_fee,
+                            nonce: tx.nonce,
+                            sender_address: tx.sender_address,
+                            signature: tx.signature,
+                            transaction_hash: TransactionHash::ZERO,
+                        };
+                        if version == 0 {
+                            DeclareTransaction::V0(tx)
+                        } else {
+                            DeclareTransaction::V1(tx)
+                        }
+                    }
+                    (
+                        ClassDefinition::Sierra {
+                            hash, casm_hash, ..
+                        },
+                        2,
+                    ) => DeclareTransaction::V2(DeclareTransactionV2 {
+                        class_hash: *hash,
+                        max_fee: tx.max_fee,
+                        nonce: tx.nonce,
+                        sender_address: tx.sender_address,
+                        signature: tx.signature,
+                        transaction_hash: TransactionHash::ZERO,
+                        compiled_class_hash: *casm_hash,
+                    }),
+                    _ => {
+                        return Err(starknet_error(
+                            KnownStarknetErrorCode::InvalidContractClassVersion,
+                            "Class type does not match the declare transaction version".to_owned(),
+                        ))
+                    }
+                };
+
+                (transaction, class)
+            }
+            Declare::V3(tx) => {
+                let class =
+                    decode_sierra_class(tx.contract_class, Some(tx.compiled_class_hash)).await?;
+                let ClassDefinition::Sierra { hash, .. } = &class else {
+                    unreachable!("Sierra class was decoded");
+                };
+
+                let transaction = DeclareTransaction::V3(DeclareTransactionV3 {
+                    class_hash: *hash,
+                    nonce: tx.nonce,
+                    nonce_data_availability_mode: tx.nonce_data_availability_mode,
+                    fee_data_availability_mode: tx.fee_data_availability_mode,
+                    resource_bounds: tx.resource_bounds,
+                    tip: tx.tip,
+                    paymaster_data: tx.paymaster_data,
+                    sender_address: tx.sender_address,
+                    signature: tx.signature,
+                    transaction_hash: TransactionHash::ZERO,
+                    compiled_class_hash: tx.compiled_class_hash,
+                    account_deployment_data: tx.account_deployment_data,
+                });
+
+                (transaction, class)
+            }
+        };
+
+        let class_hash = match &class {
+            ClassDefinition::Cairo { hash, .. } | ClassDefinition::Sierra { hash, .. } => *hash,
+        };
+        let transaction_hash = self.insert(Transaction::Declare(transaction), Some(class))?;
+
+        Ok(add_transaction::DeclareResponse {
+            code: "TRANSACTION_RECEIVED".to_owned(),
+            transaction_hash,
+            class_hash,
+        })
+    }
+
+    async fn add_deploy_account(
+        &self,
+        deploy: DeployAccount,
+    ) -> Result<add_transaction::DeployAccountResponse, SequencerError> {
+        let transaction = match deploy {
+            DeployAccount::V0(tx) => deploy_account_v0v1(tx, TransactionVersion::ZERO),
+            DeployAccount::V1(tx) => deploy_account_v0v1(tx, TransactionVersion::ONE),
+            DeployAccount::V3(tx) => DeployAccountTransaction::V3(DeployAccountTransactionV3 {
+                nonce: tx.nonce,
+                nonce_data_availability_mode: tx.nonce_data_availability_mode,
+                fee_data_availability_mode: tx.fee_data_availability_mode,
+                resource_bounds: tx.resource_bounds,
+                tip: tx.tip,
+                paymaster_data: tx.paymaster_data,
+                sender_address: ContractAddress::deployed_contract_address(
+                    tx.constructor_calldata.iter().copied(),
+                    &tx.contract_address_salt,
+                    &tx.class_hash,
+                ),
+                signature: tx.signature,
+                transaction_hash: TransactionHash::ZERO,
+                version: TransactionVersion::THREE,
+                contract_address_salt: tx.contract_address_salt,
+                constructor_calldata: tx.constructor_calldata,
+                class_hash: tx.class_hash,
+            }),
+        };
+
+        let transaction_hash = self.insert(Transaction::DeployAccount(transaction), None)?;
+
+        Ok(add_transaction::DeployAccountResponse {
+            code: "TRANSACTION_RECEIVED".to_owned(),
+            transaction_hash,
+        })
+    }
+
+    async fn transaction(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionStatus, SequencerError> {
+        let received = {
+            let received = self.received.lock().unwrap();
+            received
+                .iter()
+                .any(|r| r.transaction.hash() == transaction_hash)
+                || self.sealing.lock().unwrap().contains(&transaction_hash)
+        };
+        if received {
+            return Ok(TransactionStatus {
+                status: Status::Received,
+                finality_status: FinalityStatus::Received,
+                execution_status: ExecutionStatus::Succeeded,
+            });
+        }
+
+        if self.rejected.lock().unwrap().contains(&transaction_hash) {
+            return Ok(TransactionStatus {
+                status: Status::Rejected,
+                finality_status: FinalityStatus::Received,
+                execution_status: ExecutionStatus::Rejected,
+            });
+        }
+
+        Ok(TransactionStatus {
+            status: Status::NotReceived,
+            finality_status: FinalityStatus::NotReceived,
+            execution_status: ExecutionStatus::Succeeded,
+        })
+    }
+}
+
+fn deploy_account_v0v1(
+    tx: DeployAccountV0V1,
+    version: TransactionVersion,
+) -> DeployAccountTransaction {
+    DeployAccountTransaction::V0V1(DeployAccountTransactionV0V1 {
+        contract_address: ContractAddress::deployed_contract_address(
+            tx.constructor_calldata.iter().copied(),
+            &tx.contract_address_salt,
+            &tx.class_hash,
+        ),
+        transaction_hash: TransactionHash::ZERO,
+        max_fee: tx.max_fee,
+        version,
+        signature: tx.signature,
+        nonce: tx.nonce,
+        contract_address_salt: tx.contract_address_salt,
+        constructor_calldata: tx.constructor_calldata,
+        class_hash: tx.class_hash,
+    })
+}
+
+fn set_transaction_hash(transaction: &mut Transaction, hash: TransactionHash) {
+    match transaction {
+        Transaction::Declare(DeclareTransaction::V0(tx) | DeclareTransaction::V1(tx)) => {
+            tx.transaction_hash = hash
+        }
+        Transaction::Declare(DeclareTransaction::V2(tx)) => tx.transaction_hash = hash,
+        Transaction::Declare(DeclareTransaction::V3(tx)) => tx.transaction_hash = hash,
+        Transaction::Deploy(tx) => tx.transaction_hash = hash,
+        Transaction::DeployAccount(DeployAccountTransaction::V0V1(tx)) => {
+            tx.transaction_hash = hash
+        }
+        Transaction::DeployAccount(DeployAccountTransaction::V3(tx)) => tx.transaction_hash = hash,
+        Transaction::Invoke(InvokeTransaction::V0(tx)) => tx.transaction_hash = hash,
+        Transaction::Invoke(InvokeTransaction::V1(tx)) => tx.transaction_hash = hash,
+        Transaction::Invoke(InvokeTransaction::V3(tx)) => tx.transaction_hash = hash,
+        Transaction::L1Handler(tx) => tx.transaction_hash = hash,
+    }
+}
+
+fn starknet_error(code: KnownStarknetErrorCode, message: String) -> SequencerError {
+    SequencerError::StarknetError(StarknetError {
+        code: code.into(),
+        message,
+    })
+}
+
+/// Decodes a gzip + base64 encoded program.
+fn decompress_program(program: &str) -> anyhow::Result<Vec<u8>> {
+    let compressed = base64::decode(program).context("Decoding base64")?;
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut decompressed)
+        .context("Decompressing program")?;
+    Ok(decompressed)
+}
+
+/// Converts the class into its feeder gateway representation, which is what we store.
+fn decode_cairo_class(class: CairoContractDefinition) -> Result<ClassDefinition, SequencerError> {
+    let invalid_class = |e: anyhow::Error| {
+        starknet_error(KnownStarknetErrorCode::InvalidContractClass, e.to_string())
+    };
+
+    let definition = decompress_program(&class.program)
+        .and_then(|program| {
+            let mut program: serde_json::Value =
+                serde_json::from_slice(&program).context("Parsing program")?;
+            // The executor requires `debug_info` to be present.
+            let program_object = program
+                .as_object_mut()
+                .context("Program is not an object")?;
+            program_object
+                .entry("debug_info")
+                .or_insert(serde_json::Value::Null);
+
+            let definition = serde_json::json!({
+                "program": program,
+                "entry_points_by_type": class.entry_points_by_type,
+                "abi": class.abi,
+            });
+            Ok(serde_json::to_vec(&definition)?)
+        })
+        .map_err(invalid_class)?;
+
+    let hash = match compute_class_hash(&definition).map_err(invalid_class)? {
+        ComputedClassHash::Cairo(hash) => hash,
+        ComputedClassHash::Sierra(_) => {
+            return Err(starknet_error(
+                KnownStarknetErrorCode::InvalidContractClass,
+                "Expected a Cairo class".to_owned(),
+            ))
+        }
+    };
+
+    Ok(ClassDefinition::Cairo { hash, definition })
+}
+
+/// Converts the class into its feeder gateway representation and compiles it to CASM.
+///
+/// Fails if `compiled_class_hash` does not match the result of the compilation.
+async fn decode_sierra_class(
+    class: SierraContractDefinition,
+    compiled_class_hash: Option<CasmHash>,
+) -> Result<ClassDefinition, SequencerError> {
+    let invalid_class = |e: anyhow::Error| {
+        starknet_error(KnownStarknetErrorCode::InvalidContractClass, e.to_string())
+    };
+
+    let definition = decompress_program(&class.sierra_program)
+        .and_then(|program| {
+            let program: Vec<serde_json::Value> =
+                serde_json::from_slice(&program).context("Parsing Sierra program")?;
+
+            let definition = serde_json::json!({
+                "sierra_program": program,
+                "contract_class_version": class.contract_class_version,
+                "entry_points_by_type": class.entry_points_by_type,
+                "abi": class.abi,
+            });
+            Ok(serde_json::to_vec(&definition)?)
+        })
+        .map_err(invalid_class)?;
+
+    let hash = match compute_class_hash(&definition).map_err(invalid_class)? {
+        ComputedClassHash::Sierra(hash) => hash,
+        ComputedClassHash::Cairo(_) => {
+            return Err(starknet_error(
+                KnownStarknetErrorCode::InvalidContractClass,
+                "Expected a Sierra class".to_owned(),
+            ))
+        }
+    };
+
+    let (definition, (casm_definition, casm_hash)) = tokio::task::spawn_blocking(move || {
+        super::compile_sierra_class(&definition).map(|casm| (definition, casm))
+    })
+    .await
+    .map_err(|e| starknet_error(KnownStarknetErrorCode::CompilationFailed, e.to_string()))?
+    .map_err(|e| starknet_error(KnownStarknetErrorCode::CompilationFailed, e.to_string()))?;
+
+    if let Some(expected) = compiled_class_hash {
+        if expected != casm_hash {
+            return Err(starknet_error(
+                KnownStarknetErrorCode::InvalidCompiledClassHash,
+                format!("Compiled class hash mismatch: expected {expected}, got {casm_hash}"),
+            ));
+        }
+    }
+
+    Ok(ClassDefinition::Sierra {
+        hash,
+        definition,
+        casm_hash,
+        casm_definition,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use starknet_gateway_types::request::add_transaction::InvokeFunctionV0V1;
+
+    use super::*;
+
+    fn invoke() -> InvokeFunction {
+        InvokeFunction::V1(InvokeFunctionV0V1 {
+            max_fee: fee!("0x1000"),
+            signature: vec![],
+            nonce: Some(transaction_nonce!("0x1")),
+            sender_address: contract_address!("0x123"),
+            entry_point_selector: None,
+            calldata: vec![call_param!("0x1")],
+        })
+    }
+
+    #[tokio::test]
+    async fn invoke_is_received() {
+        let mempool = Mempool::new(ChainId::SEPOLIA_TESTNET);
+
+        let response = mempool.add_invoke_transaction(invoke()).await.unwrap();
+
+        let status = mempool
+            .transaction(response.transaction_hash)
+            .await
+            .unwrap();
+        assert_eq!(status.status, Status::Received);
+
+        let received = mempool.take();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].transaction.hash(), response.transaction_hash);
+        assert_eq!(
+            compute_transaction_hash(&received[0].transaction, ChainId::SEPOLIA_TESTNET),
+            response.transaction_hash
+        );
+
+        // Still received while being sealed into a block.
+        let status = mempool
+            .transaction(response.transaction_hash)
+            .await
+            .unwrap();
+        assert_eq!(status.status, Status::Received);
+
+        // Once sealed, the transaction's status comes from the database instead.
+        mempool.sealed();
+        let status = mempool
+            .transaction(response.transaction_hash)
+            .await
+            .unwrap();
+        assert_eq!(status.status, Status::NotReceived);
+    }
+
+    #[tokio::test]
+    async fn duplicate_is_rejected() {
+        let mempool = Mempool::new(ChainId::SEPOLIA_TESTNET);
+
+        mempool.add_invoke_transaction(invoke()).await.unwrap();
+        let error = mempool.add_invoke_transaction(invoke()).await.unwrap_err();
+
+        assert_matches::assert_matches!(
+            error,
+            SequencerError::StarknetError(e) if e.code == KnownStarknetErrorCode::DuplicatedTransaction.into()
+        );
+    }
+
+    #[tokio::test]
+    async fn restored_transactions_precede_newer_ones() {
+        let mempool = Mempool::new(ChainId::SEPOLIA_TESTNET);
+
+        let first = mempool.add_invoke_transaction(invoke()).await.unwrap();
+        let taken = mempool.take();
+
+        let mut newer = invoke();
+        let InvokeFunction::V1(tx) = &mut newer else {
+            unreachable!()
+        };
+        tx.nonce = Some(transaction_nonce!("0x2"));
+        let second = mempool.add_invoke_transaction(newer).await.unwrap();
+        // Submitted again while taken for execution.
+        mempool.add_invoke_transaction(invoke()).await.unwrap();
+
+        mempool.restore(taken);
+
+        let hashes = mempool
+            .take()
+            .iter()
+            .map(|r| r.transaction.hash())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hashes,
+            vec![first.transaction_hash, second.transaction_hash]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_status() {
+        let mempool = Mempool::new(ChainId::SEPOLIA_TESTNET);
+
+        let response = mempool.add_invoke_transaction(invoke()).await.unwrap();
+        mempool.take();
+        mempool.reject(response.transaction_hash, "Out of gas".to_owned());
+
+        let status = mempool
+            .transaction(response.transaction_hash)
+            .await
+            .unwrap();
+        assert_eq!(status.status, Status::Rejected);
+        assert_eq!(status.execution_status, ExecutionStatus::Rejected);
+    }
+}
@@ -0,0 +1,433 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use pathfinder_common::{
+    BlockHash, BlockHeader, BlockNumber, BlockTimestamp, ChainId, ClassHash, ContractAddress,
+    SierraHash, StateCommitment, StateUpdate, StorageAddress, StorageValue,
+};
+use pathfinder_crypto::Felt;
+use pathfinder_executor::{ExecutionConfig, ExecutionState, ForkState, TransactionExecutionError};
+use pathfinder_rpc::TopicBroadcasters;
+use pathfinder_storage::{BlockId, Storage, Transaction, TransactionBehavior};
+use starknet_gateway_types::reply::transaction::Receipt;
+use starknet_gateway_types::reply::{Block, Status};
+use tokio::sync::{mpsc, oneshot};
+
+use super::mempool::{ClassDefinition, Mempool, ReceivedTransaction};
+use super::Fork;
+use crate::state::block_hash::compute_block_hash;
+use crate::state::execution::{merge_state_diff, receipt, state_diff};
+use crate::state::update_starknet_state;
+
+pub(super) type SealRequest = oneshot::Sender<anyhow::Result<BlockNumber>>;
+
+/// Seals the transactions in the mempool into blocks.
+///
+/// Blocks are produced on request via [Devnet](super::Devnet), and optionally at a fixed interval.
+pub struct BlockProducer {
+    pub(super) chain_id: ChainId,
+    pub(super) config: Arc<ExecutionConfig>,
+    pub(super) mempool: Arc<Mempool>,
+    pub(super) requests: mpsc::Receiver<SealRequest>,
+    pub(super) fork: Option<Arc<Fork>>,
+}
+
+impl BlockProducer {
+    /// Produces blocks until all [Devnet](super::Devnet) handles are dropped.
+    pub async fn run(
+        mut self,
+        storage: Storage,
+        block_time: Option<Duration>,
+        mut websocket_txs: Option<TopicBroadcasters>,
+    ) -> anyhow::Result<()> {
+        let mut interval = block_time
+            .map(|period| tokio::time::interval_at(tokio::time::Instant::now() + period, period));
+
+        loop {
+            let reply = tokio::select! {
+                _ = tick(&mut interval) => None,
+                request = self.requests.recv() => match request {
+                    Some(reply) => Some(reply),
+                    None => return Ok(()),
+                },
+            };
+
+            let result = tokio::task::block_in_place(|| self.seal_block(storage.clone()));
+
+            match &result {
+                Ok(header) => {
+                    tracing::info!(number=%header.number, hash=%header.hash, transactions=%header.transaction_count, "Block produced");
+
+                    if let Some(sender) = &websocket_txs {
+                        if let Err(e) = sender.new_head.send_if_receiving(header.clone().into()) {
+                            tracing::error!(error=?e, "Failed to send header over websocket broadcaster.");
+                            websocket_txs = None;
+                        }
+                    }
+                }
+                Err(e) => tracing::error!(error=?e, "Failed to produce block"),
+            }
+
+            if let Some(reply) = reply {
+                // The requester may have gone away, which is fine.
+                let _ = reply.send(result.map(|header| header.number));
+            }
+        }
+    }
+
+    /// Executes all transactions in the mempool and stores the resulting block.
+    ///
+    /// Transactions which fail execution are rejected and left out of the block. If the block
+    /// cannot be stored, the remaining transactions are put back into the mempool.
+    fn seal_block(&self, storage: Storage) -> anyhow::Result<BlockHeader> {
+        let mut received = self.mempool.take();
+
+        // Execution may fall back to the fork, which caches the state it fetches in the database.
+        // The block's database transaction holds the write lock meanwhile, so the fork's writes
+        // are made part of it instead.
+        if let Some(fork) = &self.fork {
+            fork.defer_writes();
+        }
+
+        let result = self.try_seal_block(storage, &mut received);
+        match &result {
+            Ok(_) => self.mempool.sealed(),
+            Err(_) => {
+                if let Some(fork) = &self.fork {
+                    // Nothing can fail without a database transaction.
+                    let _ = fork.write_deferred(None);
+                }
+                self.mempool.restore(received);
+            }
+        }
+
+        result
+    }
+
+    /// Seals `received` into a block, removing the transactions which fail execution.
+    fn try_seal_block(
+        &self,
+        storage: Storage,
+        received: &mut Vec<ReceivedTransaction>,
+    ) -> anyhow::Result<BlockHeader> {
+        let mut connection = storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .context("Create database transaction")?;
+
+        // Class definitions must be present before the declaring transaction can be executed.
+        let mut inserted_classes = Vec::new();
+        for transaction in received.iter() {
+            if let Some(class_hash) = insert_class(&db, transaction)? {
+                inserted_classes.push(class_hash);
+            }
+        }
+
+        let parent = db
+            .block_header(BlockId::Latest)
+            .context("Fetching latest block header")?
+            .context("Genesis block is missing")?;
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .context("System time is before the unix epoch")?
+            .as_secs();
+        let header = BlockHeader {
+            parent_hash: parent.hash,
+            number: parent.number + 1,
+            timestamp: BlockTimestamp::new_or_panic(now.max(parent.timestamp.get())),
+            eth_l1_gas_price: parent.eth_l1_gas_price,
+            strk_l1_gas_price: parent.strk_l1_gas_price,
+            sequencer_address: parent.sequencer_address,
+            starknet_version: parent.starknet_version.clone(),
+            ..Default::default()
+        };
+
+        let fork = self.fork.clone().map(|fork| fork as Arc<dyn ForkState>);
+        let simulations = loop {
+            let transactions = received
+                .iter()
+                .map(|r| pathfinder_rpc::compose_executor_transaction(&r.transaction, &db))
+                .collect::<Result<Vec<_>, _>>()
+                .context("Converting transactions for execution")?;
+
+            let state = ExecutionState::trace(&db, self.chain_id, header.clone(), None)
+                .with_fork(fork.clone())
+                .with_config(self.config.clone());
+            match pathfinder_executor::simulate(state, transactions, false, false) {
+                Ok(simulations) => break simulations,
+                Err(TransactionExecutionError::ExecutionError {
+                    transaction_index,
+                    error,
+                }) => {
+                    let rejected = received.remove(transaction_index);
+                    let transaction_hash = rejected.transaction.hash();
+                    tracing::debug!(%transaction_hash, %error, "Transaction rejected");
+                    self.mempool.reject(transaction_hash, error);
+                }
+                Err(TransactionExecutionError::Internal(e))
+                | Err(TransactionExecutionError::Custom(e)) => {
+                    return Err(e.context("Executing transactions"))
+                }
+            }
+        };
+
+        let mut state_update = StateUpdate::default();
+        let mut transaction_data = Vec::with_capacity(received.len());
+        for (index, (received, simulation)) in received.iter().zip(simulations).enumerate() {
+            let receipt = receipt(received.transaction.hash(), index, &simulation);
+            state_update = merge_state_diff(state_update, state_diff(&simulation.trace));
+            transaction_data.push((received.transaction.clone(), receipt));
+        }
+
+        // Mirror the block hash system contract update performed by the executor.
+        if header.number.get() >= 10 {
+            let number = BlockNumber::new_or_panic(header.number.get() - 10);
+            let block_hash = match db
+                .block_hash(number.into())
+                .context("Fetching historical block hash")?
+            {
+                Some(block_hash) => Some(block_hash),
+                None => match &fork {
+                    Some(fork) => fork
+                        .block_hash(number)
+                        .context("Fetching historical block hash from fork")?,
+                    None => None,
+                },
+            }
+            .context("Historical block hash is missing")?;
+            state_update = state_update.with_system_storage_update(
+                ContractAddress::ONE,
+                StorageAddress::new_or_panic(Felt::from(number.get())),
+                StorageValue(block_hash.0),
+            );
+        }
+
+        let header = insert_block(&db, storage, header, transaction_data, state_update)?;
+        // The classes of rejected declare transactions were not declared by the block.
+        for class_hash in inserted_classes {
+            db.delete_undeclared_class(class_hash)
+                .context("Deleting class of rejected declare transaction")?;
+        }
+        if let Some(fork) = &self.fork {
+            fork.write_deferred(Some(&db))
+                .context("Recording forked state")?;
+        }
+        db.commit().context("Commit database transaction")?;
+
+        Ok(header)
+    }
+}
+
+/// Computes the commitments and hash of the block described by `header` and stores it.
+///
+/// `header` only needs the fields which are not derived from the block's contents to be set.
+pub(super) fn insert_block(
+    db: &Transaction<'_>,
+    storage: Storage,
+    header: BlockHeader,
+    transaction_data: Vec<(
+        starknet_gateway_types::reply::transaction::Transaction,
+        Receipt,
+    )>,
+    state_update: StateUpdate,
+) -> anyhow::Result<BlockHeader> {
+    let (storage_commitment, class_commitment) =
+        update_starknet_state(db, &state_update, false, header.number, storage)
+            .context("Updating Starknet state")?;
+    let state_commitment = StateCommitment::calculate(storage_commitment, class_commitment);
+
+    let (transactions, transaction_receipts) = transaction_data.into_iter().unzip();
+    let block = Block {
+        block_hash: BlockHash::ZERO,
+        block_number: header.number,
+        eth_l1_gas_price: Some(header.eth_l1_gas_price),
+        strk_l1_gas_price: Some(header.strk_l1_gas_price),
+        parent_block_hash: header.parent_hash,
+        sequencer_address: Some(header.sequencer_address),
+        state_commitment,
+        status: Status::AcceptedOnL2,
+        timestamp: header.timestamp,
+        transaction_receipts,
+        transactions,
+        starknet_version: header.starknet_version.clone(),
+    };
+    let (hash, transaction_commitment, event_commitment) =
+        compute_block_hash(&block).context("Computing block hash")?;
+
+    let parent_state_commitment = match header.number.parent() {
+        Some(parent) => {
+            db.block_header(parent.into())
+                .context("Fetching parent block header")?
+                .context("Parent block is missing")?
+                .state_commitment
+        }
+        None => StateCommitment::ZERO,
+    };
+
+    let header = BlockHeader {
+        hash,
+        class_commitment,
+        event_commitment,
+        state_commitment,
+        storage_commitment,
+        transaction_commitment,
+        transaction_count: block.transactions.len(),
+        event_count: block
+            .transaction_receipts
+            .iter()
+            .map(|r| r.events.len())
+            .sum(),
+        ..header
+    };
+
+    db.insert_block_header(&header)
+        .context("Inserting block header into database")?;
+
+    let transaction_data = block
+        .transactions
+        .into_iter()
+        .zip(block.transaction_receipts)
+        .collect::<Vec<_>>();
+    db.insert_transaction_data(header.hash, header.number, &transaction_data)
+        .context("Insert transaction data into database")?;
+    db.prune_block_bodies(header.number)
+        .context("Prune block bodies")?;
+
+    let state_update = state_update
+        .with_block_hash(hash)
+        .with_state_commitment(state_commitment)
+        .with_parent_state_commitment(parent_state_commitment);
+    db.insert_state_update(header.number, &state_update)
+        .context("Insert state update into database")?;
+
+    Ok(header)
+}
+
+async fn tick(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Inserts the class declared by `transaction`, returning its hash if it was not already present.
+fn insert_class(
+    db: &Transaction<'_>,
+    transaction: &ReceivedTransaction,
+) -> anyhow::Result<Option<ClassHash>> {
+    let hash = match &transaction.class {
+        Some(ClassDefinition::Cairo { hash, .. } | ClassDefinition::Sierra { hash, .. }) => *hash,
+        None => return Ok(None),
+    };
+    let exists = db
+        .class_definitions_exist(&[hash])
+        .context("Querying class existence")?;
+    if exists[0] {
+        return Ok(None);
+    }
+
+    match &transaction.class {
+        Some(ClassDefinition::Cairo { hash, definition }) => {
+            db.insert_cairo_class(*hash, definition)
+        }
+        Some(ClassDefinition::Sierra {
+            hash,
+            definition,
+            casm_hash,
+            casm_definition,
+        }) => db.insert_sierra_class(&SierraHash(hash.0), definition, casm_hash, casm_definition),
+        None => Ok(()),
+    }
+    .context("Inserting declared class")?;
+
+    Ok(Some(hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::{CallParam, EntryPoint};
+    use starknet_gateway_client::GatewayApi;
+    use starknet_gateway_test_fixtures::class_definitions::{
+        DUMMY_ACCOUNT, DUMMY_ACCOUNT_CLASS_HASH, ERC20_CONTRACT_DEFINITION,
+        ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+    };
+    use starknet_gateway_types::request::add_transaction::{InvokeFunction, InvokeFunctionV0V1};
+
+    use super::*;
+    use crate::devnet::{Devnet, Genesis};
+
+    #[tokio::test]
+    async fn fee_is_charged_in_configured_token() {
+        let config = Arc::new(ExecutionConfig {
+            eth_fee_token_address: contract_address!("0xfee1"),
+            strk_fee_token_address: contract_address!("0xfee2"),
+            ..Default::default()
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("erc20.json"), ERC20_CONTRACT_DEFINITION).unwrap();
+        std::fs::write(dir.path().join("account.json"), DUMMY_ACCOUNT).unwrap();
+        let path = dir.path().join("genesis.json");
+        let genesis = serde_json::json!({
+            "classes": ["erc20.json", "account.json"],
+            "fee_token_class": ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+            "accounts": [{
+                "address": "0x123",
+                "class_hash": DUMMY_ACCOUNT_CLASS_HASH,
+                "public_key": "0xabc",
+                "balance": "0x1000000000000000000"
+            }],
+            "sequencer_address": "0x1000",
+        });
+        std::fs::write(&path, serde_json::to_vec(&genesis).unwrap()).unwrap();
+
+        let storage = Storage::in_memory().unwrap();
+        Genesis::load(&path)
+            .unwrap()
+            .initialize(storage.clone(), &config)
+            .unwrap();
+
+        let (_devnet, producer) = Devnet::new(ChainId::SEPOLIA_TESTNET, config, None);
+
+        // Queries the account's balance in the configured ETH fee token.
+        let account = contract_address!("0x123");
+        producer
+            .mempool
+            .add_invoke_transaction(InvokeFunction::V1(InvokeFunctionV0V1 {
+                max_fee: fee!("0x10000000000000"),
+                signature: vec![],
+                nonce: Some(transaction_nonce!("0x0")),
+                sender_address: account,
+                entry_point_selector: None,
+                calldata: vec![
+                    call_param!("0xfee1"),
+                    CallParam(EntryPoint::hashed(b"balanceOf").0),
+                    call_param!("0x1"),
+                    CallParam(account.0),
+                ],
+            }))
+            .await
+            .unwrap();
+
+        let header = producer.seal_block(storage.clone()).unwrap();
+        assert_eq!(header.transaction_count, 1);
+
+        let mut connection = storage.connection().unwrap();
+        let db = connection.transaction().unwrap();
+        let state_update = db.state_update(header.number.into()).unwrap().unwrap();
+
+        let balance_key = StorageAddress::from_map_name_and_key(b"ERC20_balances", account.0);
+        let fee_token_updates = &state_update.contract_updates[&contract_address!("0xfee1")];
+        assert!(fee_token_updates.storage.contains_key(&balance_key));
+        assert!(!state_update
+            .contract_updates
+            .contains_key(&pathfinder_executor::ETH_FEE_TOKEN_ADDRESS));
+    }
+}
@@ -1,5 +1,7 @@
 #![deny(rust_2018_idioms)]
 
+pub mod chain_config;
+pub mod devnet;
 pub mod monitoring;
 pub mod state;
 mod sync;
@@ -45,12 +45,12 @@ pub enum HybridClient {
     /// Proxies blockchain data to non propagating nodes via p2p
     GatewayProxy {
         p2p_client: peer_agnostic::Client,
-        sequencer: starknet_gateway_client::Client,
+        sequencer: starknet_gateway_client::MultiSourceClient,
     },
     /// Syncs from p2p network, does not propagate
     NonPropagatingP2P {
         p2p_client: peer_agnostic::Client,
-        sequencer: starknet_gateway_client::Client,
+        sequencer: starknet_gateway_client::MultiSourceClient,
         head_rx: HeadRx,
         /// We need to cache the last two fetched blocks via p2p otherwise sync logic will
         /// produce a false reorg from genesis when we loose connection to other p2p nodes.
@@ -179,7 +179,7 @@ impl HybridClient {
     pub fn new(
         i_am_proxy: bool,
         p2p_client: peer_agnostic::Client,
-        sequencer: starknet_gateway_client::Client,
+        sequencer: starknet_gateway_client::MultiSourceClient,
         head_rx: HeadRx,
     ) -> Self {
         if i_am_proxy {
@@ -197,7 +197,7 @@ impl HybridClient {
         }
     }
 
-    fn as_sequencer(&self) -> &starknet_gateway_client::Client {
+    fn as_sequencer(&self) -> &starknet_gateway_client::MultiSourceClient {
         match self {
             HybridClient::GatewayProxy { sequencer, .. } => sequencer,
             HybridClient::NonPropagatingP2P { sequencer, .. } => sequencer,
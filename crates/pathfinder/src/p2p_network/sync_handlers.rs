@@ -255,6 +255,10 @@ fn get_transactions_for_block(
         return Ok(false);
     };
 
+    if tx.block_body_pruned(block_number)? {
+        return Ok(false);
+    }
+
     let Some(txn_data) = tx.transaction_data_for_block(block_number.into())? else {
         return Ok(false);
     };
@@ -290,6 +294,10 @@ fn get_receipts_for_block(
         return Ok(false);
     };
 
+    if tx.block_body_pruned(block_number)? {
+        return Ok(false);
+    }
+
     let Some(txn_data) = tx.transaction_data_for_block(block_number.into())? else {
         return Ok(false);
     };
@@ -322,6 +330,10 @@ fn get_events_for_block(
         return Ok(false);
     };
 
+    if tx.block_body_pruned(block_number)? {
+        return Ok(false);
+    }
+
     let Some(txn_data) = tx.transaction_data_for_block(block_number.into())? else {
         return Ok(false);
     };
@@ -1,4 +1,5 @@
 pub mod block_hash;
+pub(crate) mod execution;
 mod sync;
 
-pub use sync::{l1, l2, sync, SyncContext};
+pub use sync::{l1, l2, replica, sync, update_starknet_state, SyncContext};
@@ -1,8 +1,9 @@
 use anyhow::{Context, Result};
 use pathfinder_common::event::Event;
 use pathfinder_common::{
-    BlockHash, BlockNumber, BlockTimestamp, Chain, ChainId, EventCommitment, SequencerAddress,
-    StarknetVersion, StateCommitment, TransactionCommitment, TransactionSignatureElem,
+    BlockHash, BlockHeader, BlockNumber, BlockTimestamp, ChainId, EventCommitment,
+    SequencerAddress, StarknetVersion, StateCommitment, TransactionCommitment,
+    TransactionSignatureElem,
 };
 use pathfinder_crypto::{
     hash::{pedersen_hash, HashChain},
@@ -14,6 +15,8 @@ use starknet_gateway_types::reply::{
     Block,
 };
 
+pub use meta::BlockHashMetaInfo;
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum VerifyResult {
     Match((TransactionCommitment, EventCommitment)),
@@ -35,11 +38,10 @@ pub enum VerifyResult {
 /// Python implementation to compute the block hash for details.
 pub fn verify_block_hash(
     block: &Block,
-    chain: Chain,
+    meta_info: &BlockHashMetaInfo,
     chain_id: ChainId,
     expected_block_hash: BlockHash,
 ) -> Result<VerifyResult> {
-    let meta_info = meta::for_chain(chain);
     if !meta_info.can_verify(block.block_number) {
         return Ok(VerifyResult::NotVerifiable);
     }
@@ -57,11 +59,6 @@ pub fn verify_block_hash(
     let event_commitment = calculate_event_commitment(&block.transaction_receipts)?;
 
     let verified = if meta_info.uses_pre_0_7_hash_algorithm(block.block_number) {
-        anyhow::ensure!(
-            chain != Chain::Custom,
-            "Chain::Custom should not have any pre 0.7 block hashes"
-        );
-
         let block_hash = compute_final_hash_pre_0_7(
             block.block_number,
             block.state_commitment,
@@ -103,6 +100,103 @@ pub fn verify_block_hash(
     })
 }
 
+/// Verify the block hash value using the commitments stored in the block's header.
+///
+/// Unlike [verify_block_hash] this does not need the block's transactions and receipts, but
+/// trusts the transaction and event commitments of the header instead.
+pub fn verify_header_hash(
+    header: &BlockHeader,
+    meta_info: &BlockHashMetaInfo,
+    chain_id: ChainId,
+) -> VerifyResult {
+    if !meta_info.can_verify(header.number) {
+        return VerifyResult::NotVerifiable;
+    }
+
+    let num_transactions: u64 = header
+        .transaction_count
+        .try_into()
+        .expect("too many transactions in block");
+
+    let verified = if meta_info.uses_pre_0_7_hash_algorithm(header.number) {
+        let block_hash = compute_final_hash_pre_0_7(
+            header.number,
+            header.state_commitment,
+            num_transactions,
+            header.transaction_commitment.0,
+            header.parent_hash,
+            chain_id,
+        );
+        block_hash == header.hash
+    } else {
+        let num_events: u64 = header
+            .event_count
+            .try_into()
+            .expect("too many events in block");
+
+        std::iter::once(&header.sequencer_address)
+            .chain(meta_info.fallback_sequencer_address.iter())
+            .any(|address| {
+                let block_hash = compute_final_hash(
+                    header.number,
+                    header.state_commitment,
+                    address,
+                    header.timestamp,
+                    num_transactions,
+                    header.transaction_commitment.0,
+                    num_events,
+                    header.event_commitment.0,
+                    header.parent_hash,
+                );
+                block_hash == header.hash
+            })
+    };
+
+    match verified {
+        false => VerifyResult::Mismatch,
+        true => VerifyResult::Match((header.transaction_commitment, header.event_commitment)),
+    }
+}
+
+/// Computes the hash of a new block, along with its transaction and event commitments.
+///
+/// Only the current (post Starknet 0.8.2) algorithm is supported, which makes this
+/// suitable for blocks pathfinder produces itself, but not for verifying historical blocks.
+pub fn compute_block_hash(
+    block: &Block,
+) -> Result<(BlockHash, TransactionCommitment, EventCommitment)> {
+    let num_transactions: u64 = block
+        .transactions
+        .len()
+        .try_into()
+        .expect("too many transactions in block");
+    let num_events: u64 = number_of_events_in_block(block)
+        .try_into()
+        .expect("too many events in block");
+
+    let transaction_final_hash_type =
+        TransactionCommitmentFinalHashType::for_version(&block.starknet_version)?;
+    let transaction_commitment =
+        calculate_transaction_commitment(&block.transactions, transaction_final_hash_type)?;
+    let event_commitment = calculate_event_commitment(&block.transaction_receipts)?;
+
+    let block_hash = compute_final_hash(
+        block.block_number,
+        block.state_commitment,
+        &block
+            .sequencer_address
+            .unwrap_or(SequencerAddress(Felt::ZERO)),
+        block.timestamp,
+        num_transactions,
+        transaction_commitment.0,
+        num_events,
+        event_commitment.0,
+        block.parent_block_hash,
+    );
+
+    Ok((block_hash, transaction_commitment, event_commitment))
+}
+
 mod meta {
     use pathfinder_common::{sequencer_address, BlockNumber, Chain, SequencerAddress};
     use std::ops::Range;
@@ -127,7 +221,7 @@ mod meta {
     ///   value is irrecoverable.
     /// * After Starknet 0.8.2 all blocks include the correct sequencer address
     ///   value.
-    #[derive(Clone)]
+    #[derive(Clone, Debug, PartialEq)]
     pub struct BlockHashMetaInfo {
         /// The number of the first block that was hashed with the Starknet 0.7 hash algorithm.
         pub first_0_7_block: BlockNumber,
@@ -145,6 +239,17 @@ mod meta {
             }
         }
 
+        pub fn for_chain(chain: Chain) -> &'static Self {
+            match chain {
+                Chain::Mainnet => &MAINNET_METAINFO,
+                Chain::GoerliTestnet => &GOERLI_TESTNET_METAINFO,
+                Chain::GoerliIntegration => &GOERLI_INTEGRATION_METAINFO,
+                Chain::SepoliaTestnet => &SEPOLIA_TESTNET_METAINFO,
+                Chain::SepoliaIntegration => &SEPOLIA_INTEGRATION_METAINFO,
+                Chain::Custom => &CUSTOM_METAINFO,
+            }
+        }
+
         pub fn uses_pre_0_7_hash_algorithm(&self, block_number: BlockNumber) -> bool {
             block_number < self.first_0_7_block
         }
@@ -193,17 +298,6 @@ mod meta {
         not_verifiable_range: None,
         fallback_sequencer_address: None,
     };
-
-    pub fn for_chain(chain: Chain) -> &'static BlockHashMetaInfo {
-        match chain {
-            Chain::Mainnet => &MAINNET_METAINFO,
-            Chain::GoerliTestnet => &GOERLI_TESTNET_METAINFO,
-            Chain::GoerliIntegration => &GOERLI_INTEGRATION_METAINFO,
-            Chain::SepoliaTestnet => &SEPOLIA_TESTNET_METAINFO,
-            Chain::SepoliaIntegration => &SEPOLIA_INTEGRATION_METAINFO,
-            Chain::Custom => &CUSTOM_METAINFO,
-        }
-    }
 }
 
 /// Computes the final block hash for pre-0.7 blocks.
@@ -486,7 +580,7 @@ mod tests {
     use super::*;
     use assert_matches::assert_matches;
     use pathfinder_common::macro_prelude::*;
-    use pathfinder_common::{felt, Fee};
+    use pathfinder_common::{felt, Chain, Fee};
     use starknet_gateway_types::reply::{
         transaction::{EntryPointType, InvokeTransaction, InvokeTransactionV0},
         Block,
@@ -562,7 +656,7 @@ mod tests {
         assert_matches!(
             verify_block_hash(
                 &block,
-                Chain::GoerliTestnet,
+                BlockHashMetaInfo::for_chain(Chain::GoerliTestnet),
                 ChainId::GOERLI_TESTNET,
                 block.block_hash
             )
@@ -581,7 +675,7 @@ mod tests {
         assert_matches!(
             verify_block_hash(
                 &block,
-                Chain::GoerliTestnet,
+                BlockHashMetaInfo::for_chain(Chain::GoerliTestnet),
                 ChainId::GOERLI_TESTNET,
                 block.block_hash
             )
@@ -601,7 +695,7 @@ mod tests {
         assert_matches!(
             verify_block_hash(
                 &block,
-                Chain::GoerliTestnet,
+                BlockHashMetaInfo::for_chain(Chain::GoerliTestnet),
                 ChainId::GOERLI_TESTNET,
                 block.block_hash,
             )
@@ -610,6 +704,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn header_hash() {
+        let json = starknet_gateway_test_fixtures::v0_9_0::block::NUMBER_231579;
+        let block: Block = serde_json::from_str(json).unwrap();
+        let meta_info = BlockHashMetaInfo::for_chain(Chain::GoerliTestnet);
+
+        let VerifyResult::Match((transaction_commitment, event_commitment)) =
+            verify_block_hash(&block, meta_info, ChainId::GOERLI_TESTNET, block.block_hash)
+                .unwrap()
+        else {
+            panic!("Block hash should match");
+        };
+        let header = BlockHeader::builder()
+            .with_number(block.block_number)
+            .with_parent_hash(block.parent_block_hash)
+            .with_state_commitment(block.state_commitment)
+            .with_sequencer_address(block.sequencer_address.unwrap())
+            .with_timestamp(block.timestamp)
+            .with_transaction_count(block.transactions.len())
+            .with_transaction_commitment(transaction_commitment)
+            .with_event_count(number_of_events_in_block(&block))
+            .with_event_commitment(event_commitment)
+            .finalize_with_hash(block.block_hash);
+
+        assert_matches!(
+            verify_header_hash(&header, meta_info, ChainId::GOERLI_TESTNET),
+            VerifyResult::Match(_)
+        );
+
+        let header = BlockHeader {
+            event_count: header.event_count + 1,
+            ..header
+        };
+        assert_eq!(
+            verify_header_hash(&header, meta_info, ChainId::GOERLI_TESTNET),
+            VerifyResult::Mismatch
+        );
+    }
+
     #[test]
     fn test_block_hash_0_11_1() {
         let json = starknet_gateway_test_fixtures::integration::block::NUMBER_285915;
@@ -618,7 +751,7 @@ mod tests {
         assert_matches!(
             verify_block_hash(
                 &block,
-                Chain::GoerliIntegration,
+                BlockHashMetaInfo::for_chain(Chain::GoerliIntegration),
                 ChainId::GOERLI_INTEGRATION,
                 block.block_hash,
             )
@@ -627,6 +760,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn compute_block_hash_matches_gateway() {
+        let json = starknet_gateway_test_fixtures::integration::block::NUMBER_285915;
+        let block: Block = serde_json::from_str(json).unwrap();
+
+        let (block_hash, _, _) = compute_block_hash(&block).unwrap();
+        assert_eq!(block_hash, block.block_hash);
+    }
+
     #[test]
     fn test_block_hash_0() {
         // This tests with a pre-0.7 block where the chain ID was hashed into
@@ -637,7 +779,7 @@ mod tests {
         assert_matches!(
             verify_block_hash(
                 &block,
-                Chain::GoerliTestnet,
+                BlockHashMetaInfo::for_chain(Chain::GoerliTestnet),
                 ChainId::GOERLI_TESTNET,
                 block.block_hash
             )
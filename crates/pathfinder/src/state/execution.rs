@@ -0,0 +1,204 @@
+//! Conversion of execution results into the receipts and state update of a block.
+use pathfinder_common::event::Event;
+use pathfinder_common::state_update::ContractClassUpdate;
+use pathfinder_common::{
+    ContractAddress, EthereumAddress, EventData, EventKey, Fee, L2ToL1MessagePayloadElem,
+    StateUpdate, TransactionHash, TransactionIndex,
+};
+use pathfinder_crypto::Felt;
+use pathfinder_executor::types::{
+    ExecuteInvocation, FunctionInvocation, StateDiff, TransactionSimulation, TransactionTrace,
+};
+use primitive_types::H160;
+use starknet_gateway_types::reply::transaction::{
+    BuiltinCounters, ExecutionResources, ExecutionStatus, L2ToL1Message, Receipt,
+};
+
+/// Builds the receipt of the transaction with `transaction_hash` at `index` in its block from its
+/// execution.
+///
+/// The L1 to L2 message consumed by an L1 handler is not part of the execution, and is not set.
+pub(crate) fn receipt(
+    transaction_hash: TransactionHash,
+    index: usize,
+    simulation: &TransactionSimulation,
+) -> Receipt {
+    let invocations = match &simulation.trace {
+        TransactionTrace::Declare(trace) => vec![
+            trace.validate_invocation.as_ref(),
+            trace.fee_transfer_invocation.as_ref(),
+        ],
+        TransactionTrace::DeployAccount(trace) => vec![
+            trace.constructor_invocation.as_ref(),
+            trace.validate_invocation.as_ref(),
+            trace.fee_transfer_invocation.as_ref(),
+        ],
+        TransactionTrace::Invoke(trace) => {
+            let execute = match &trace.execute_invocation {
+                ExecuteInvocation::FunctionInvocation(invocation) => invocation.as_ref(),
+                ExecuteInvocation::RevertedReason(_) => None,
+            };
+            vec![
+                trace.validate_invocation.as_ref(),
+                execute,
+                trace.fee_transfer_invocation.as_ref(),
+            ]
+        }
+        TransactionTrace::L1Handler(trace) => vec![trace.function_invocation.as_ref()],
+    };
+
+    let mut events = Vec::new();
+    let mut messages = Vec::new();
+    for invocation in invocations.into_iter().flatten() {
+        let mut invocation_events = Vec::new();
+        let mut invocation_messages = Vec::new();
+        collect_events_and_messages(invocation, &mut invocation_events, &mut invocation_messages);
+
+        invocation_events.sort_by_key(|(order, _)| *order);
+        invocation_messages.sort_by_key(|(order, _)| *order);
+
+        events.extend(invocation_events.into_iter().map(|(_, event)| event));
+        messages.extend(invocation_messages.into_iter().map(|(_, message)| message));
+    }
+
+    let mut fee = [0u8; 32];
+    simulation
+        .fee_estimation
+        .overall_fee
+        .to_big_endian(&mut fee);
+    let actual_fee = Fee(Felt::from_be_bytes(fee).expect("Fee fits into a felt"));
+
+    let resources = &simulation.execution_resources;
+    let execution_resources = ExecutionResources {
+        builtin_instance_counter: BuiltinCounters {
+            output_builtin: 0,
+            pedersen_builtin: resources.pedersen_builtin_applications as u64,
+            range_check_builtin: resources.range_check_builtin_applications as u64,
+            ecdsa_builtin: resources.ecdsa_builtin_applications as u64,
+            bitwise_builtin: resources.bitwise_builtin_applications as u64,
+            ec_op_builtin: resources.ec_op_builtin_applications as u64,
+            keccak_builtin: resources.keccak_builtin_applications as u64,
+            poseidon_builtin: resources.poseidon_builtin_applications as u64,
+            segment_arena_builtin: resources.segment_arena_builtin as u64,
+        },
+        n_steps: resources.steps as u64,
+        n_memory_holes: resources.memory_holes as u64,
+    };
+
+    let (execution_status, revert_error) = match simulation.revert_reason() {
+        Some(reason) => (ExecutionStatus::Reverted, Some(reason.to_owned())),
+        None => (ExecutionStatus::Succeeded, None),
+    };
+
+    Receipt {
+        actual_fee: Some(actual_fee),
+        events,
+        execution_resources: Some(execution_resources),
+        l1_to_l2_consumed_message: None,
+        l2_to_l1_messages: messages,
+        transaction_hash,
+        transaction_index: TransactionIndex::new_or_panic(index as u64),
+        execution_status,
+        revert_error,
+    }
+}
+
+fn collect_events_and_messages(
+    invocation: &FunctionInvocation,
+    events: &mut Vec<(i64, Event)>,
+    messages: &mut Vec<(usize, L2ToL1Message)>,
+) {
+    events.extend(invocation.events.iter().map(|event| {
+        (
+            event.order,
+            Event {
+                data: event.data.iter().copied().map(EventData).collect(),
+                from_address: invocation.contract_address,
+                keys: event.keys.iter().copied().map(EventKey).collect(),
+            },
+        )
+    }));
+
+    messages.extend(invocation.messages.iter().map(|message| {
+        (
+            message.order,
+            L2ToL1Message {
+                from_address: invocation.contract_address,
+                payload: message
+                    .payload
+                    .iter()
+                    .copied()
+                    .map(L2ToL1MessagePayloadElem)
+                    .collect(),
+                to_address: EthereumAddress(H160::from_slice(
+                    &message.to_address.to_be_bytes()[12..],
+                )),
+            },
+        )
+    }));
+
+    for call in &invocation.internal_calls {
+        collect_events_and_messages(call, events, messages);
+    }
+}
+
+pub(crate) fn state_diff(trace: &TransactionTrace) -> &StateDiff {
+    match trace {
+        TransactionTrace::Declare(trace) => &trace.state_diff,
+        TransactionTrace::DeployAccount(trace) => &trace.state_diff,
+        TransactionTrace::Invoke(trace) => &trace.state_diff,
+        TransactionTrace::L1Handler(trace) => &trace.state_diff,
+    }
+}
+
+/// Applies a single transaction's state diff on top of the block's state update.
+pub(crate) fn merge_state_diff(mut state_update: StateUpdate, diff: &StateDiff) -> StateUpdate {
+    for (address, storage_diffs) in &diff.storage_diffs {
+        for storage_diff in storage_diffs {
+            state_update = if *address == ContractAddress::ONE {
+                state_update.with_system_storage_update(
+                    *address,
+                    storage_diff.key,
+                    storage_diff.value,
+                )
+            } else {
+                state_update.with_storage_update(*address, storage_diff.key, storage_diff.value)
+            };
+        }
+    }
+
+    for contract in &diff.deployed_contracts {
+        state_update = state_update.with_deployed_contract(contract.address, contract.class_hash);
+    }
+
+    for class in &diff.replaced_classes {
+        // A contract deployed earlier in this block remains a deployment, just of the new class.
+        let deployed_in_block = matches!(
+            state_update
+                .contract_updates
+                .get(&class.contract_address)
+                .and_then(|update| update.class.as_ref()),
+            Some(ContractClassUpdate::Deploy(_))
+        );
+        state_update = if deployed_in_block {
+            state_update.with_deployed_contract(class.contract_address, class.class_hash)
+        } else {
+            state_update.with_replaced_class(class.contract_address, class.class_hash)
+        };
+    }
+
+    for class in &diff.deprecated_declared_classes {
+        state_update = state_update.with_declared_cairo_class(*class);
+    }
+
+    for class in &diff.declared_classes {
+        state_update =
+            state_update.with_declared_sierra_class(class.class_hash, class.compiled_class_hash);
+    }
+
+    for (address, nonce) in &diff.nonces {
+        state_update = state_update.with_contract_nonce(*address, *nonce);
+    }
+
+    state_update
+}
@@ -2,15 +2,17 @@ mod class;
 pub mod l1;
 pub mod l2;
 mod pending;
+pub mod replica;
 
 use anyhow::Context;
 use pathfinder_common::{
     BlockCommitmentSignature, BlockHash, BlockHeader, BlockNumber, CasmHash, Chain, ChainId,
-    ClassCommitment, ClassHash, EventCommitment, GasPrice, SequencerAddress, SierraHash,
+    ClassCommitment, ClassHash, EventCommitment, GasPrice, PublicKey, SequencerAddress, SierraHash,
     StateCommitment, StateUpdate, StorageCommitment, TransactionCommitment,
 };
 use pathfinder_crypto::Felt;
-use pathfinder_ethereum::{EthereumApi, EthereumStateUpdate};
+use pathfinder_ethereum::{ConsumedMessageToL1Log, EthereumApi, MessageToL2Log, StateUpdateLog};
+use pathfinder_executor::ExecutionConfig;
 use pathfinder_merkle_tree::contract_state::update_contract_state;
 use pathfinder_merkle_tree::{ClassCommitmentTree, StorageCommitmentTree};
 use pathfinder_rpc::PendingData;
@@ -30,6 +32,7 @@ use std::time::Duration;
 use std::time::Instant;
 use tokio::sync::mpsc::{self, Receiver};
 
+use crate::state::block_hash::BlockHashMetaInfo;
 use crate::state::l1::L1SyncContext;
 use crate::state::l2::{BlockChain, L2SyncContext};
 
@@ -37,7 +40,14 @@ use tokio::sync::watch::Sender as WatchSender;
 
 #[derive(Debug)]
 pub enum SyncEvent {
-    L1Update(EthereumStateUpdate),
+    /// State updates, messages to L2 and consumed messages to L1 logged by the core contract, up
+    /// to and including L1 block `l1_block_number`.
+    L1Update {
+        logs: Vec<StateUpdateLog>,
+        messages: Vec<MessageToL2Log>,
+        consumed_messages: Vec<ConsumedMessageToL1Log>,
+        l1_block_number: u64,
+    },
     /// New L2 [block update](StateUpdate) found.
     Block(
         (Box<Block>, (TransactionCommitment, EventCommitment)),
@@ -70,6 +80,7 @@ pub struct SyncContext<G, E> {
     pub ethereum: E,
     pub chain: Chain,
     pub chain_id: ChainId,
+    pub block_hash_meta_info: BlockHashMetaInfo,
     pub core_address: H160,
     pub sequencer: G,
     pub state: Arc<SyncState>,
@@ -80,6 +91,10 @@ pub struct SyncContext<G, E> {
     pub block_cache_size: usize,
     pub restart_delay: Duration,
     pub verify_tree_hashes: bool,
+    pub download_window: std::num::NonZeroUsize,
+    pub stop_at_block: Option<BlockNumber>,
+    pub sequencer_public_key: Option<PublicKey>,
+    pub execute_pending: Option<Arc<ExecutionConfig>>,
 }
 
 impl<G, E> From<&SyncContext<G, E>> for L1SyncContext<E>
@@ -92,6 +107,7 @@ where
             chain: value.chain,
             core_address: value.core_address,
             poll_interval: value.head_poll_interval,
+            storage: value.storage.clone(),
         }
     }
 }
@@ -103,10 +119,14 @@ where
     fn from(value: &SyncContext<G, E>) -> Self {
         Self {
             sequencer: value.sequencer.clone(),
-            chain: value.chain,
+            block_hash_meta_info: value.block_hash_meta_info.clone(),
             chain_id: value.chain_id,
             block_validation_mode: value.block_validation_mode,
             storage: value.storage.clone(),
+            download_window: value.download_window,
+            stop_at_block: value.stop_at_block,
+            sequencer_public_key: value.sequencer_public_key,
+            execute_pending: value.execute_pending.clone(),
         }
     }
 }
@@ -139,6 +159,7 @@ where
         ethereum: _,
         chain: _,
         chain_id: _,
+        block_hash_meta_info: _,
         core_address: _,
         sequencer,
         state,
@@ -149,6 +170,10 @@ where
         block_cache_size,
         restart_delay,
         verify_tree_hashes: _,
+        download_window: _,
+        stop_at_block,
+        sequencer_public_key: _,
+        execute_pending: _,
     } = context;
 
     let mut db_conn = storage
@@ -181,6 +206,7 @@ where
         starting_block_hash,
         starting_block_num,
         head_poll_interval,
+        stop_at_block,
     ));
 
     // Start L1 producer task. Clone the event sender so that the channel remains open
@@ -362,9 +388,23 @@ async fn consumer(mut events: Receiver<SyncEvent>, context: ConsumerContext) ->
     while let Some(event) = events.recv().await {
         use SyncEvent::*;
         match event {
-            L1Update(update) => {
-                l1_update(&mut db_conn, &update).await?;
-                tracing::info!("L1 sync updated to block {}", update.block_number);
+            L1Update {
+                logs,
+                messages,
+                consumed_messages,
+                l1_block_number,
+            } => {
+                l1_update(
+                    &mut db_conn,
+                    &logs,
+                    &messages,
+                    &consumed_messages,
+                    l1_block_number,
+                )
+                .await?;
+                if let Some(log) = logs.last() {
+                    tracing::info!("L1 sync updated to block {}", log.update.block_number);
+                }
             }
             Block((block, (tx_comm, ev_comm)), state_update, signature, timings) => {
                 if block.block_number < next_number {
@@ -588,12 +628,21 @@ async fn update_sync_status_latest(
     starting_block_hash: BlockHash,
     starting_block_num: BlockNumber,
     poll_interval: Duration,
+    stop_at_block: Option<BlockNumber>,
 ) -> anyhow::Result<()> {
     let starting = NumberedBlock::from((starting_block_hash, starting_block_num));
     let mut last_propagated = Instant::now();
 
     loop {
-        match sequencer.head().await {
+        // Sync does not go past the stop block, so it is reported as the highest block.
+        let head = match (sequencer.head().await, stop_at_block) {
+            (Ok((number, _)), Some(stop)) if number > stop => {
+                sequencer.block_header(stop.into()).await
+            }
+            (head, _) => head,
+        };
+
+        match head {
             Ok((block_number, block_hash)) => {
                 let latest = NumberedBlock::from((block_hash, block_number));
 
@@ -656,16 +705,44 @@ async fn propagate_head(
 
 async fn l1_update(
     connection: &mut Connection,
-    update: &EthereumStateUpdate,
+    logs: &[StateUpdateLog],
+    messages: &[MessageToL2Log],
+    consumed_messages: &[ConsumedMessageToL1Log],
+    l1_block_number: u64,
 ) -> anyhow::Result<()> {
     tokio::task::block_in_place(move || {
         let transaction = connection
             .transaction_with_behavior(TransactionBehavior::Immediate)
             .context("Create database transaction")?;
 
+        for log in logs {
+            transaction
+                .insert_state_update_log(log)
+                .context("Insert update")?;
+        }
+        for message in messages {
+            transaction
+                .insert_message_to_l2_log(message)
+                .context("Insert message to L2")?;
+        }
+        for consumed in consumed_messages {
+            transaction
+                .insert_consumed_message_to_l1_log(consumed)
+                .context("Insert consumed message to L1")?;
+        }
         transaction
-            .upsert_l1_state(update)
-            .context("Insert update")?;
+            .update_l1_logs_head(l1_block_number)
+            .context("Updating L1 logs head")?;
+
+        let Some(update) = logs.last().map(|log| &log.update) else {
+            return transaction.commit().context("Commit database transaction");
+        };
+
+        // Older updates are seen while backfilling the logs, and must not move the pointer back.
+        let l1_l2_head = transaction.l1_l2_pointer()?;
+        if l1_l2_head.is_some_and(|head| head >= update.block_number) {
+            return transaction.commit().context("Commit database transaction");
+        }
 
         let l2_hash = transaction
             .block_hash(update.block_number.into())
@@ -787,6 +864,10 @@ async fn l2_update(
             .insert_transaction_data(header.hash, header.number, &transaction_data)
             .context("Insert transaction data into database")?;
 
+        transaction
+            .prune_block_bodies(header.number)
+            .context("Prune block bodies")?;
+
         // Insert state updates
         transaction
             .insert_state_update(block.block_number, &state_update)
@@ -847,6 +928,17 @@ async fn l2_reorg(connection: &mut Connection, reorg_tail: BlockNumber) -> anyho
             .context("Latest block number is none during reorg")?
             .0;
 
+        // The new blocks are applied on top of the state tries of the reorg's parent block.
+        if let Some(parent) = reorg_tail.parent() {
+            let pruned = transaction
+                .trie_pruned(parent)
+                .context("Querying trie pruning")?;
+            anyhow::ensure!(
+                !pruned,
+                "Reorg to block {reorg_tail} is deeper than the number of blocks kept by state trie pruning"
+            );
+        }
+
         transaction
             .increment_reorg_counter()
             .context("Incrementing reorg counter")?;
@@ -892,7 +984,10 @@ async fn l2_reorg(connection: &mut Connection, reorg_tail: BlockNumber) -> anyho
     })
 }
 
-fn update_starknet_state(
+/// Applies `state_update` to the state tries of the parent block, storing the tries of `block`.
+///
+/// Returns the resulting storage and class commitments.
+pub fn update_starknet_state(
     transaction: &Transaction<'_>,
     state_update: &StateUpdate,
     verify_hashes: bool,
@@ -980,13 +1075,13 @@ fn update_starknet_state(
     }
 
     // Apply storage commitment tree changes.
-    let (storage_commitment, nodes) = storage_commitment_tree
+    let (storage_commitment, trie_update) = storage_commitment_tree
         .commit()
         .context("Apply storage commitment tree updates")?;
 
     let root_idx = if !storage_commitment.0.is_zero() {
         let root_idx = transaction
-            .insert_storage_trie(storage_commitment, &nodes)
+            .insert_storage_trie(block, storage_commitment, &trie_update.nodes)
             .context("Persisting storage trie")?;
 
         Some(root_idx)
@@ -997,6 +1092,9 @@ fn update_starknet_state(
     transaction
         .insert_storage_root(block, root_idx)
         .context("Inserting storage root index")?;
+    transaction
+        .insert_storage_trie_removals(block, &trie_update.nodes_removed)
+        .context("Inserting storage trie removals")?;
 
     // Add new Sierra classes to class commitment tree.
     let mut class_commitment_tree = match block.parent() {
@@ -1019,13 +1117,13 @@ fn update_starknet_state(
     }
 
     // Apply all class commitment tree changes.
-    let (class_commitment, nodes) = class_commitment_tree
+    let (class_commitment, trie_update) = class_commitment_tree
         .commit()
         .context("Apply class commitment tree updates")?;
 
     let class_root_idx = if !class_commitment.0.is_zero() {
         let class_root_idx = transaction
-            .insert_class_trie(class_commitment, &nodes)
+            .insert_class_trie(block, class_commitment, &trie_update.nodes)
             .context("Persisting class trie")?;
 
         Some(class_root_idx)
@@ -1036,6 +1134,13 @@ fn update_starknet_state(
     transaction
         .insert_class_root(block, class_root_idx)
         .context("Inserting class root index")?;
+    transaction
+        .insert_class_trie_removals(block, &trie_update.nodes_removed)
+        .context("Inserting class trie removals")?;
+
+    transaction
+        .prune_tries(block)
+        .context("Pruning state tries")?;
 
     Ok((storage_commitment, class_commitment))
 }
@@ -1,13 +1,25 @@
 use std::{num::NonZeroU64, time::Duration};
 
+use anyhow::Context;
 use pathfinder_common::Chain;
-use pathfinder_ethereum::{EthereumApi, EthereumStateUpdate};
+use pathfinder_ethereum::{core_deployment_block, EthereumApi};
 use pathfinder_retry::Retry;
+use pathfinder_storage::Storage;
 use primitive_types::H160;
 use tokio::sync::mpsc;
 
 use crate::state::sync::SyncEvent;
 
+/// The number of L1 blocks whose logs are requested at once, which grows while requests
+/// succeed.
+const INITIAL_RANGE: u64 = 10_000;
+/// Providers limit the range or number of results of `eth_getLogs`, so the range is halved
+/// whenever a request fails, and never grows beyond this.
+const MAX_RANGE: u64 = 1_000_000;
+/// A new L1 block is expected every 12 seconds, so a subscription which has not delivered one
+/// for this long is considered stalled and replaced by polling.
+const NEW_HEAD_TIMEOUT: Duration = Duration::from_secs(60);
+
 #[derive(Clone)]
 pub struct L1SyncContext<EthereumClient> {
     pub ethereum: EthereumClient,
@@ -15,10 +27,20 @@ pub struct L1SyncContext<EthereumClient> {
     /// The Starknet core contract address on Ethereum
     pub core_address: H160,
     pub poll_interval: Duration,
+    pub storage: Storage,
 }
 
-/// Syncs L1 state update logs. Emits [Ethereum state update](EthereumStateUpdate)
-/// which should be handled to update storage and respond to queries.
+/// Syncs the core contract's logs. Emits [L1 updates](SyncEvent::L1Update) containing every state
+/// update accepted, every message sent to L2 and every message from L2 consumed by the core
+/// contract, which should be handled to update storage and respond to queries.
+///
+/// Only finalized L1 blocks are processed. The logs of all L1 blocks since the deployment of the
+/// core contract are backfilled when this is first run, and processing resumes from the last L1
+/// block stored in the database.
+///
+/// The finalized block is checked whenever a new L1 block is announced by a subscription, or
+/// every `poll_interval` if the Ethereum endpoints do not support subscriptions or the
+/// subscription is dropped.
 pub async fn sync<T>(
     tx_event: mpsc::Sender<SyncEvent>,
     context: L1SyncContext<T>,
@@ -28,16 +50,37 @@ where
 {
     let L1SyncContext {
         ethereum,
-        chain: _,
+        chain,
         core_address,
         poll_interval,
+        storage,
     } = context;
 
-    let mut previous = EthereumStateUpdate::default();
+    let head = tokio::task::spawn_blocking(move || {
+        let mut db = storage
+            .connection()
+            .context("Creating database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+        tx.l1_logs_head()
+    })
+    .await
+    .context("Joining database task")?
+    .context("Fetching L1 logs head")?;
+
+    let mut next = match head {
+        Some(head) => head + 1,
+        None => {
+            let start = backfill_start(chain);
+            tracing::info!(from=%start, "Backfilling L1 state updates from the core contract's logs");
+            start
+        }
+    };
+    let mut range = INITIAL_RANGE;
+    let mut new_heads = None;
 
     loop {
-        let state_update = Retry::exponential(
-            || async { ethereum.get_starknet_state(&core_address).await },
+        let finalized = Retry::exponential(
+            || async { ethereum.get_finalized_block_number().await },
             NonZeroU64::new(1).unwrap(),
         )
         .factor(NonZeroU64::new(2).unwrap())
@@ -45,11 +88,366 @@ where
         .when(|_| true)
         .await?;
 
-        if previous != state_update {
-            previous = state_update.clone();
-            tx_event.send(SyncEvent::L1Update(state_update)).await?;
+        while next <= finalized {
+            let to = finalized.min(next.saturating_add(range - 1));
+            let result = async {
+                let logs = ethereum
+                    .get_state_update_logs(&core_address, next, to)
+                    .await
+                    .context("Fetching state update logs")?;
+                let messages = ethereum
+                    .get_message_to_l2_logs(&core_address, next, to)
+                    .await
+                    .context("Fetching message to L2 logs")?;
+                let consumed_messages = ethereum
+                    .get_consumed_message_to_l1_logs(&core_address, next, to)
+                    .await
+                    .context("Fetching consumed message to L1 logs")?;
+                anyhow::Ok((logs, messages, consumed_messages))
+            }
+            .await;
+
+            match result {
+                Ok((logs, messages, consumed_messages)) => {
+                    tx_event
+                        .send(SyncEvent::L1Update {
+                            logs,
+                            messages,
+                            consumed_messages,
+                            l1_block_number: to,
+                        })
+                        .await?;
+                    next = to + 1;
+                    range = range.saturating_mul(2).min(MAX_RANGE);
+                }
+                Err(e) if range > 1 => {
+                    tracing::debug!(from=%next, %to, reason=?e, "Fetching logs failed, reducing the range");
+                    range /= 2;
+                }
+                Err(e) => {
+                    tracing::warn!(block=%next, reason=?e, "Fetching logs failed, retrying");
+                    tokio::time::sleep(poll_interval).await;
+                }
+            }
+        }
+
+        if new_heads.is_none() {
+            new_heads = match ethereum.subscribe_new_heads().await {
+                Ok(heads) => Some(heads),
+                Err(e) => {
+                    tracing::trace!(reason=?e, "Subscribing to new L1 blocks failed, polling instead");
+                    None
+                }
+            };
+        }
+
+        match new_heads.as_mut() {
+            Some(heads) => match tokio::time::timeout(NEW_HEAD_TIMEOUT, heads.recv()).await {
+                Ok(Some(number)) => tracing::trace!(%number, "New L1 block"),
+                Ok(None) | Err(_) => {
+                    tracing::debug!("New L1 block subscription dropped, polling instead");
+                    new_heads = None;
+                    tokio::time::sleep(poll_interval).await;
+                }
+            },
+            None => tokio::time::sleep(poll_interval).await,
+        }
+    }
+}
+
+/// The L1 block from which the logs of the chain's core contract are backfilled. The deployment
+/// block of the core contract of a custom chain is unknown, so all blocks are searched.
+fn backfill_start(chain: Chain) -> u64 {
+    match chain {
+        Chain::Mainnet => core_deployment_block::MAINNET,
+        Chain::GoerliTestnet => core_deployment_block::GOERLI_TESTNET,
+        Chain::GoerliIntegration => core_deployment_block::GOERLI_INTEGRATION,
+        Chain::SepoliaTestnet => core_deployment_block::SEPOLIA_TESTNET,
+        Chain::SepoliaIntegration => core_deployment_block::SEPOLIA_INTEGRATION,
+        Chain::Custom => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use pathfinder_common::{BlockHash, BlockNumber, EthereumChain, StateCommitment};
+    use pathfinder_crypto::Felt;
+    use pathfinder_ethereum::{
+        ConsumedMessageToL1Log, EthereumStateUpdate, MessageToL2Log, StateUpdateLog,
+    };
+    use primitive_types::H256;
+
+    use super::*;
+
+    /// Logs a state update every 100 L1 blocks, and fails requests for ranges above a limit and
+    /// the given number of requests regardless of their range.
+    #[derive(Clone)]
+    struct FakeEthereum {
+        finalized: Arc<AtomicU64>,
+        range_limit: u64,
+        failures: Arc<AtomicU64>,
+        requests: Arc<Mutex<Vec<(u64, u64)>>>,
+        new_heads: Arc<Mutex<Option<mpsc::Receiver<u64>>>>,
+    }
+
+    fn log(l1_block_number: u64) -> StateUpdateLog {
+        StateUpdateLog {
+            update: EthereumStateUpdate {
+                state_root: StateCommitment(Felt::from_u64(l1_block_number)),
+                block_number: BlockNumber::new_or_panic(l1_block_number / 100),
+                block_hash: BlockHash(Felt::from_u64(l1_block_number)),
+            },
+            l1_block_number,
+            l1_transaction_hash: H256::from_low_u64_be(l1_block_number),
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl EthereumApi for FakeEthereum {
+        async fn get_starknet_state(&self, _: &H160) -> anyhow::Result<EthereumStateUpdate> {
+            unimplemented!()
+        }
+
+        async fn get_chain(&self) -> anyhow::Result<EthereumChain> {
+            unimplemented!()
+        }
+
+        async fn get_finalized_block_number(&self) -> anyhow::Result<u64> {
+            Ok(self.finalized.load(Ordering::Relaxed))
         }
 
-        tokio::time::sleep(poll_interval).await;
+        async fn get_state_update_logs(
+            &self,
+            _: &H160,
+            from_block: u64,
+            to_block: u64,
+        ) -> anyhow::Result<Vec<StateUpdateLog>> {
+            self.requests.lock().unwrap().push((from_block, to_block));
+            anyhow::ensure!(
+                self.failures
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                    .is_err(),
+                "Request failed"
+            );
+            anyhow::ensure!(to_block - from_block < self.range_limit, "Range too large");
+            Ok((from_block..=to_block)
+                .filter(|block| block % 100 == 0)
+                .map(log)
+                .collect())
+        }
+
+        async fn get_message_to_l2_logs(
+            &self,
+            _: &H160,
+            from_block: u64,
+            to_block: u64,
+        ) -> anyhow::Result<Vec<MessageToL2Log>> {
+            Ok((from_block..=to_block)
+                .filter(|block| block % 1000 == 0)
+                .map(|block| MessageToL2Log {
+                    message_hash: H256::from_low_u64_be(block),
+                    l1_block_number: block,
+                    l1_transaction_hash: H256::from_low_u64_be(block),
+                })
+                .collect())
+        }
+
+        async fn get_consumed_message_to_l1_logs(
+            &self,
+            _: &H160,
+            _: u64,
+            _: u64,
+        ) -> anyhow::Result<Vec<ConsumedMessageToL1Log>> {
+            Ok(Vec::new())
+        }
+
+        async fn subscribe_new_heads(&self) -> anyhow::Result<mpsc::Receiver<u64>> {
+            self.new_heads
+                .lock()
+                .unwrap()
+                .take()
+                .context("Subscriptions not supported")
+        }
+    }
+
+    #[tokio::test]
+    async fn backfills_with_reduced_range() {
+        let (tx_event, mut rx_event) = mpsc::channel(100);
+        let ethereum = FakeEthereum {
+            finalized: Arc::new(AtomicU64::new(30_000)),
+            range_limit: 4_000,
+            failures: Default::default(),
+            requests: Default::default(),
+            new_heads: Default::default(),
+        };
+        let context = L1SyncContext {
+            ethereum: ethereum.clone(),
+            chain: Chain::Custom,
+            core_address: H160::zero(),
+            poll_interval: Duration::from_secs(60),
+            storage: Storage::in_memory().unwrap(),
+        };
+        let _handle = tokio::spawn(sync(tx_event, context));
+
+        let mut logs = Vec::new();
+        let mut messages = Vec::new();
+        let mut next = 0;
+        while next <= 30_000 {
+            let Some(SyncEvent::L1Update {
+                logs: batch,
+                messages: message_batch,
+                consumed_messages: _,
+                l1_block_number,
+            }) = rx_event.recv().await
+            else {
+                panic!("Expected an L1 update");
+            };
+            logs.extend(batch);
+            messages.extend(message_batch);
+            next = l1_block_number + 1;
+        }
+
+        let expected = (0..=300).map(|i| log(i * 100)).collect::<Vec<_>>();
+        assert_eq!(logs, expected);
+        let message_blocks = messages
+            .iter()
+            .map(|message| message.l1_block_number)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            message_blocks,
+            (0..=30).map(|i| i * 1000).collect::<Vec<_>>()
+        );
+
+        // Failed requests are retried from the same block with a smaller range.
+        let requests = ethereum.requests.lock().unwrap();
+        for pair in requests.windows(2) {
+            let [(from, to), (next_from, next_to)] = pair else {
+                unreachable!()
+            };
+            if to - from >= 4_000 {
+                assert_eq!(next_from, from);
+                assert!(next_to < to);
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_single_block_range() {
+        let (tx_event, mut rx_event) = mpsc::channel(100);
+        // Enough failures to reduce the range down to a single block and then some.
+        let ethereum = FakeEthereum {
+            finalized: Arc::new(AtomicU64::new(1_000)),
+            range_limit: u64::MAX,
+            failures: Arc::new(AtomicU64::new(20)),
+            requests: Default::default(),
+            new_heads: Default::default(),
+        };
+        let context = L1SyncContext {
+            ethereum: ethereum.clone(),
+            chain: Chain::Custom,
+            core_address: H160::zero(),
+            poll_interval: Duration::from_secs(60),
+            storage: Storage::in_memory().unwrap(),
+        };
+        let _handle = tokio::spawn(sync(tx_event, context));
+
+        let mut logs = Vec::new();
+        let mut next = 0;
+        while next <= 1_000 {
+            let Some(SyncEvent::L1Update {
+                logs: batch,
+                l1_block_number,
+                ..
+            }) = rx_event.recv().await
+            else {
+                panic!("Expected an L1 update");
+            };
+            logs.extend(batch);
+            next = l1_block_number + 1;
+        }
+
+        assert_eq!(logs, (0..=10).map(|i| log(i * 100)).collect::<Vec<_>>());
+        let requests = ethereum.requests.lock().unwrap();
+        assert_eq!(requests[20], (0, 0));
+    }
+
+    #[tokio::test]
+    async fn new_head_triggers_sync() {
+        let (tx_event, mut rx_event) = mpsc::channel(100);
+        let (tx_heads, rx_heads) = mpsc::channel(1);
+        let ethereum = FakeEthereum {
+            finalized: Arc::new(AtomicU64::new(100)),
+            range_limit: u64::MAX,
+            failures: Default::default(),
+            requests: Default::default(),
+            new_heads: Arc::new(Mutex::new(Some(rx_heads))),
+        };
+        // Polling alone would not pick up the new finalized block in time.
+        let context = L1SyncContext {
+            ethereum: ethereum.clone(),
+            chain: Chain::Custom,
+            core_address: H160::zero(),
+            poll_interval: Duration::from_secs(3600),
+            storage: Storage::in_memory().unwrap(),
+        };
+        let _handle = tokio::spawn(sync(tx_event, context));
+
+        let Some(SyncEvent::L1Update {
+            l1_block_number, ..
+        }) = rx_event.recv().await
+        else {
+            panic!("Expected an L1 update");
+        };
+        assert_eq!(l1_block_number, 100);
+
+        ethereum.finalized.store(200, Ordering::Relaxed);
+        tx_heads.send(264).await.unwrap();
+
+        let update = tokio::time::timeout(Duration::from_secs(5), rx_event.recv())
+            .await
+            .unwrap();
+        let Some(SyncEvent::L1Update {
+            logs,
+            l1_block_number,
+            ..
+        }) = update
+        else {
+            panic!("Expected an L1 update");
+        };
+        assert_eq!(l1_block_number, 200);
+        assert_eq!(logs, vec![log(200)]);
+    }
+
+    #[tokio::test]
+    async fn backfill_starts_at_core_contract_deployment() {
+        let (tx_event, mut rx_event) = mpsc::channel(100);
+        let start = core_deployment_block::MAINNET;
+        let ethereum = FakeEthereum {
+            finalized: Arc::new(AtomicU64::new(start + 1_000)),
+            range_limit: u64::MAX,
+            failures: Default::default(),
+            requests: Default::default(),
+            new_heads: Default::default(),
+        };
+        let context = L1SyncContext {
+            ethereum: ethereum.clone(),
+            chain: Chain::Mainnet,
+            core_address: H160::zero(),
+            poll_interval: Duration::from_secs(60),
+            storage: Storage::in_memory().unwrap(),
+        };
+        let _handle = tokio::spawn(sync(tx_event, context));
+
+        let Some(SyncEvent::L1Update {
+            l1_block_number, ..
+        }) = rx_event.recv().await
+        else {
+            panic!("Expected an L1 update");
+        };
+        assert_eq!(l1_block_number, start + 1_000);
+        assert_eq!(ethereum.requests.lock().unwrap()[0], (start, start + 1_000));
     }
 }
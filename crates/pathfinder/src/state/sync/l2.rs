@@ -1,21 +1,25 @@
-use crate::state::block_hash::{verify_block_hash, VerifyResult};
+use crate::state::block_hash::{verify_block_hash, BlockHashMetaInfo, VerifyResult};
 use crate::state::sync::class::{download_class, DownloadedClass};
 use crate::state::sync::{pending, SyncEvent};
 use anyhow::{anyhow, Context};
 use pathfinder_common::state_update::ContractClassUpdate;
 use pathfinder_common::{
-    BlockHash, BlockNumber, Chain, ChainId, ClassHash, EventCommitment, StarknetVersion,
-    StateCommitment, StateUpdate, TransactionCommitment,
+    BlockCommitmentSignature, BlockHash, BlockNumber, ChainId, ClassHash, EventCommitment,
+    PublicKey, StarknetVersion, StateCommitment, StateUpdate, TransactionCommitment,
 };
+use pathfinder_executor::ExecutionConfig;
 use pathfinder_storage::Storage;
 use starknet_gateway_client::GatewayApi;
 use starknet_gateway_types::{
     error::SequencerError,
-    reply::{Block, Status},
+    reply::{Block, BlockSignature, Status},
     transaction_hash::verify,
 };
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
 
@@ -82,10 +86,19 @@ impl BlockChain {
 #[derive(Clone)]
 pub struct L2SyncContext<GatewayClient> {
     pub sequencer: GatewayClient,
-    pub chain: Chain,
+    pub block_hash_meta_info: BlockHashMetaInfo,
     pub chain_id: ChainId,
     pub block_validation_mode: BlockValidationMode,
     pub storage: Storage,
+    /// The number of blocks which are downloaded concurrently while catching up.
+    pub download_window: NonZeroUsize,
+    /// Sync stops once this block has been synced, and pending data is not polled.
+    pub stop_at_block: Option<BlockNumber>,
+    /// Block signatures are verified against this key in [BlockValidationMode::Strict].
+    pub sequencer_public_key: Option<PublicKey>,
+    /// Pending transactions are executed locally with this configuration if set, instead of
+    /// using the sequencer's receipts and state diff as is.
+    pub execute_pending: Option<Arc<ExecutionConfig>>,
 }
 
 pub async fn sync<GatewayClient>(
@@ -99,13 +112,27 @@ where
 {
     let L2SyncContext {
         sequencer,
-        chain,
+        block_hash_meta_info,
         chain_id,
         block_validation_mode,
         storage,
+        download_window,
+        stop_at_block,
+        sequencer_public_key,
+        execute_pending,
     } = context;
 
     let mut pending_handle = None;
+    let mut prefetcher = Prefetcher {
+        window: download_window.get(),
+        sequencer: sequencer.clone(),
+        meta_info: block_hash_meta_info.clone(),
+        chain_id,
+        mode: block_validation_mode,
+        queue: VecDeque::new(),
+    };
+    // The latest block known to exist, beyond which blocks are not prefetched.
+    let mut latest = None;
 
     'outer: loop {
         // Get the next block from L2.
@@ -114,12 +141,47 @@ where
             None => (BlockNumber::GENESIS, None),
         };
 
+        if let Some(stop_at_block) = stop_at_block.filter(|stop| next > *stop) {
+            let (number, hash, _) = head.expect("Next block is after genesis");
+            anyhow::ensure!(
+                number == stop_at_block,
+                "Already synced up to block {number}, which is past the stop block {stop_at_block}"
+            );
+
+            return stay_at_stop_block(number, hash, &sequencer).await;
+        }
+
+        if prefetcher.is_enabled() {
+            // Refresh the latest block once it is reached, so that prefetching continues if the
+            // chain has grown in the meantime.
+            if latest.map_or(true, |latest| next >= latest) {
+                match sequencer.head().await {
+                    Ok((number, _)) => latest = Some(number),
+                    Err(e) => tracing::debug!(reason=?e, "Fetching the latest block failed"),
+                }
+            }
+            if let Some(latest) = latest {
+                let last = stop_at_block.map_or(latest, |stop| stop.min(latest));
+                prefetcher.fill(next, last);
+            }
+        }
+
         let t_block = std::time::Instant::now();
 
+        let mut signature = None;
         let (block, commitments, state_update) = loop {
+            if let Some(prefetched) = prefetcher.take(next).await {
+                signature = Some(prefetched.signature);
+                break (
+                    prefetched.block,
+                    prefetched.commitments,
+                    prefetched.state_update,
+                );
+            }
+
             match download_block(
                 next,
-                chain,
+                &block_hash_meta_info,
                 chain_id,
                 head_meta.map(|h| h.1),
                 &sequencer,
@@ -138,13 +200,16 @@ where
                         // Not implemented yet for P2P
                         tracing::info!("Skipping the pending blocks polling");
                         tokio::time::sleep(PENDING_POLL_INTERVAL).await;
-                    } else if pending_handle.is_none() {
+                    } else if pending_handle.is_none() && stop_at_block.is_none() {
                         tracing::info!("At head of chain, enabling polling of pending data");
                         pending_handle = Some(tokio::spawn(pending::poll_pending(
                             tx_event.clone(),
                             sequencer.clone(),
                             PENDING_POLL_INTERVAL,
                             storage.clone(),
+                            execute_pending.clone().map(|config| {
+                                pending::PendingExecutor::new(storage.clone(), chain_id, config)
+                            }),
                         )));
                     }
 
@@ -164,7 +229,7 @@ where
                     head = match head {
                         Some(some_head) => reorg(
                             &some_head,
-                            chain,
+                            &block_hash_meta_info,
                             chain_id,
                             &tx_event,
                             &sequencer,
@@ -182,6 +247,7 @@ where
                         }
                         None => blocks.reset_to_genesis(),
                     }
+                    prefetcher.clear();
 
                     continue 'outer;
                 }
@@ -193,7 +259,7 @@ where
             if some_head.1 != block.parent_block_hash {
                 head = reorg(
                     some_head,
-                    chain,
+                    &block_hash_meta_info,
                     chain_id,
                     &tx_event,
                     &sequencer,
@@ -207,29 +273,20 @@ where
                     Some((number, hash, commitment)) => blocks.push(*number, *hash, *commitment),
                     None => blocks.reset_to_genesis(),
                 }
+                prefetcher.clear();
 
                 continue 'outer;
             }
         }
 
-        // Download and emit newly declared classes.
-        let t_declare = std::time::Instant::now();
-        download_new_classes(
-            &state_update,
-            &sequencer,
-            &tx_event,
-            &block.starknet_version,
-            storage.clone(),
-        )
-        .await
-        .with_context(|| format!("Handling newly declared classes for block {next:?}"))?;
-        let t_declare = t_declare.elapsed();
-
         let t_signature = std::time::Instant::now();
-        let signature = sequencer
-            .signature(block.block_hash.into())
-            .await
-            .with_context(|| format!("Fetch signature for block {next:?} from sequencer"))?;
+        let signature = match signature {
+            Some(signature) => signature,
+            None => sequencer
+                .signature(block.block_hash.into())
+                .await
+                .with_context(|| format!("Fetch signature for block {next:?} from sequencer"))?,
+        };
         let t_signature = t_signature.elapsed();
 
         // An extra sanity check for the signature API.
@@ -239,8 +296,35 @@ where
             signature.signature_input.block_hash.0,
             block.block_hash.0,
         );
+        let state_update = match (block_validation_mode, sequencer_public_key) {
+            (BlockValidationMode::Strict, Some(public_key)) => {
+                let signature = signature.clone();
+                tokio::task::spawn_blocking(move || {
+                    verify_signature(&state_update, &signature, public_key)
+                        .with_context(|| format!("Verify signature of block {next}"))?;
+                    anyhow::Ok(state_update)
+                })
+                .await
+                .context("Joining signature verification task")??
+            }
+            _ => state_update,
+        };
         let signature = signature.into();
 
+        // Download and emit newly declared classes. This happens only once the block has been
+        // verified so that classes of a block with an invalid signature are never stored.
+        let t_declare = std::time::Instant::now();
+        download_new_classes(
+            &state_update,
+            &sequencer,
+            &tx_event,
+            &block.starknet_version,
+            storage.clone(),
+        )
+        .await
+        .with_context(|| format!("Handling newly declared classes for block {next:?}"))?;
+        let t_declare = t_declare.elapsed();
+
         head = Some((next, block.block_hash, state_update.state_commitment));
         blocks.push(next, block.block_hash, state_update.state_commitment);
 
@@ -404,7 +488,7 @@ pub enum BlockValidationMode {
 
 async fn download_block(
     block_number: BlockNumber,
-    chain: Chain,
+    meta_info: &BlockHashMetaInfo,
     chain_id: ChainId,
     prev_block_hash: Option<BlockHash>,
     sequencer: &impl GatewayApi,
@@ -422,6 +506,7 @@ async fn download_block(
             let state_update = Box::new(state_update);
 
             // Check if block hash is correct.
+            let meta_info = meta_info.clone();
             let verify_hash = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
                 let block_number = block.block_number;
                 // In p2p the state commitment which is required to calculate the block hash can be missing, and in such case it is marked as 0s.
@@ -430,8 +515,9 @@ async fn download_block(
                     return Ok((block, VerifyResult::NotVerifiable));
                 }
 
-                let verify_result = verify_block_hash(&block, chain, chain_id, block.block_hash)
-                    .with_context(move || format!("Verify block {block_number}"))?;
+                let verify_result =
+                    verify_block_hash(&block, &meta_info, chain_id, block.block_hash)
+                        .with_context(move || format!("Verify block {block_number}"))?;
                 Ok((block, verify_result))
             });
             let (block, verify_result) = verify_hash.await.context("Verify block hash")??;
@@ -528,9 +614,207 @@ async fn download_block(
     }
 }
 
+/// Checks that the state diff matches the commitment signed by the sequencer, and that the
+/// signature was made with the sequencer's key.
+fn verify_signature(
+    state_update: &StateUpdate,
+    signature: &BlockSignature,
+    public_key: PublicKey,
+) -> anyhow::Result<()> {
+    let state_diff_commitment = state_update.compute_state_diff_commitment();
+    if state_diff_commitment != signature.signature_input.state_diff_commitment {
+        metrics::increment_counter!(
+            "block_signature_verification_failed_total",
+            "reason" => "state_diff_commitment"
+        );
+        anyhow::bail!(
+            "State diff commitment mismatch, actual {:x}, expected {:x}",
+            state_diff_commitment.0,
+            signature.signature_input.state_diff_commitment.0,
+        );
+    }
+
+    let commitment_signature = BlockCommitmentSignature {
+        r: signature.signature[0],
+        s: signature.signature[1],
+    };
+    if let Err(e) = commitment_signature.verify(
+        public_key,
+        signature.signature_input.block_hash,
+        state_diff_commitment,
+    ) {
+        metrics::increment_counter!(
+            "block_signature_verification_failed_total",
+            "reason" => "signature"
+        );
+        anyhow::bail!("Block signature verification failed: {e}");
+    }
+
+    metrics::increment_counter!("block_signatures_verified_total");
+    Ok(())
+}
+
+/// Keeps the stop block as the head of the chain, checking periodically that the chain has not
+/// been reorged at or below it. Such a reorg is not applied, but returned as an error.
+async fn stay_at_stop_block(
+    number: BlockNumber,
+    hash: BlockHash,
+    sequencer: &impl GatewayApi,
+) -> anyhow::Result<()> {
+    use starknet_gateway_types::error::KnownStarknetErrorCode::BlockNotFound;
+
+    const REORG_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+    tracing::info!(%number, "Reached the stop block, sync is stopped");
+
+    loop {
+        match sequencer.block_header(number.into()).await {
+            Ok((_, current)) if current == hash => {}
+            Ok((_, current)) => anyhow::bail!(
+                "Stop block {number} was reorged, its hash changed from {hash} to {current}"
+            ),
+            Err(SequencerError::StarknetError(e)) if e.code == BlockNotFound.into() => {
+                anyhow::bail!("Stop block {number} was reorged away")
+            }
+            Err(e) => tracing::debug!(reason=?e, "Checking the stop block for reorgs failed"),
+        }
+
+        tokio::time::sleep(REORG_POLL_INTERVAL).await;
+    }
+}
+
+/// A block which was downloaded and validated ahead of being synced, together with its signature.
+struct PrefetchedBlock {
+    block: Box<Block>,
+    commitments: (TransactionCommitment, EventCommitment),
+    state_update: Box<StateUpdate>,
+    signature: BlockSignature,
+}
+
+/// Downloads and validates the blocks following the one being synced in the background, so that
+/// catching up is not bound by the latency of the gateway.
+///
+/// Blocks are still synced in order and checked against their parent, so prefetched blocks of a
+/// chain which has since been reorged are discarded like any other block.
+struct Prefetcher<GatewayClient> {
+    window: usize,
+    sequencer: GatewayClient,
+    meta_info: BlockHashMetaInfo,
+    chain_id: ChainId,
+    mode: BlockValidationMode,
+    queue: VecDeque<(
+        BlockNumber,
+        tokio::task::JoinHandle<Option<PrefetchedBlock>>,
+    )>,
+}
+
+impl<GatewayClient> Prefetcher<GatewayClient>
+where
+    GatewayClient: GatewayApi + Clone + Send + 'static,
+{
+    /// A window of one block downloads each block when it is synced, as without prefetching.
+    fn is_enabled(&self) -> bool {
+        self.window > 1
+    }
+
+    /// Starts downloading the blocks from `next` onwards which fit in the window, up to and
+    /// including `latest`.
+    fn fill(&mut self, next: BlockNumber, latest: BlockNumber) {
+        let mut number = match self.queue.back() {
+            Some((last, _)) => (*last + 1).max(next),
+            None => next,
+        };
+
+        while self.queue.len() < self.window && number <= latest {
+            let sequencer = self.sequencer.clone();
+            let meta_info = self.meta_info.clone();
+            let chain_id = self.chain_id;
+            let mode = self.mode;
+            let handle = tokio::spawn(async move {
+                prefetch_block(number, &meta_info, chain_id, &sequencer, mode).await
+            });
+
+            self.queue.push_back((number, handle));
+            number += 1;
+        }
+    }
+
+    /// Returns the block if it was prefetched successfully, waiting for its download to finish.
+    /// Prefetched blocks before it are discarded.
+    async fn take(&mut self, number: BlockNumber) -> Option<PrefetchedBlock> {
+        while let Some((prefetched, handle)) = self.queue.pop_front() {
+            if prefetched > number {
+                self.queue.push_front((prefetched, handle));
+                return None;
+            }
+            if prefetched < number {
+                handle.abort();
+                continue;
+            }
+
+            return handle.await.ok().flatten();
+        }
+
+        None
+    }
+}
+
+impl<GatewayClient> Prefetcher<GatewayClient> {
+    /// Discards all prefetched blocks, which is required after a reorg.
+    fn clear(&mut self) {
+        for (_, handle) in self.queue.drain(..) {
+            handle.abort();
+        }
+    }
+}
+
+impl<GatewayClient> Drop for Prefetcher<GatewayClient> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Returns `None` if the block is not available or fails validation, in which case it is
+/// downloaded again when it is synced so that the failure is handled as usual.
+async fn prefetch_block(
+    block_number: BlockNumber,
+    meta_info: &BlockHashMetaInfo,
+    chain_id: ChainId,
+    sequencer: &impl GatewayApi,
+    mode: BlockValidationMode,
+) -> Option<PrefetchedBlock> {
+    let result = async {
+        let DownloadBlock::Block(block, commitments, state_update) =
+            download_block(block_number, meta_info, chain_id, None, sequencer, mode).await?
+        else {
+            return Ok(None);
+        };
+        let signature = sequencer
+            .signature(block.block_hash.into())
+            .await
+            .context("Fetching signature")?;
+
+        anyhow::Ok(Some(PrefetchedBlock {
+            block,
+            commitments,
+            state_update,
+            signature,
+        }))
+    }
+    .await;
+
+    match result {
+        Ok(prefetched) => prefetched,
+        Err(e) => {
+            tracing::debug!(%block_number, reason=?e, "Prefetching block failed");
+            None
+        }
+    }
+}
+
 async fn reorg(
     head: &(BlockNumber, BlockHash, StateCommitment),
-    chain: Chain,
+    meta_info: &BlockHashMetaInfo,
     chain_id: ChainId,
     tx_event: &mpsc::Sender<SyncEvent>,
     sequencer: &impl GatewayApi,
@@ -553,7 +837,7 @@ async fn reorg(
 
         match download_block(
             previous_block_number,
-            chain,
+            meta_info,
             chain_id,
             Some(previous.0),
             sequencer,
@@ -588,12 +872,13 @@ async fn reorg(
 mod tests {
 
     mod sync {
+        use crate::state::block_hash::BlockHashMetaInfo;
         use crate::state::l2::{BlockChain, L2SyncContext};
         use pathfinder_common::macro_prelude::*;
         use pathfinder_common::BlockCommitmentSignature;
         use pathfinder_common::StateUpdate;
 
-        use super::super::{sync, BlockValidationMode, SyncEvent};
+        use super::super::{sync, BlockValidationMode, Prefetcher, SyncEvent};
         use assert_matches::assert_matches;
         use pathfinder_common::{
             BlockHash, BlockId, BlockNumber, BlockTimestamp, Chain, ChainId, ClassHash,
@@ -607,6 +892,8 @@ mod tests {
             error::{KnownStarknetErrorCode, SequencerError, StarknetError},
             reply,
         };
+        use std::collections::VecDeque;
+        use std::num::NonZeroUsize;
         use tokio::{sync::mpsc, task::JoinHandle};
 
         const MODE: BlockValidationMode = BlockValidationMode::AllowMismatch;
@@ -776,10 +1063,14 @@ mod tests {
             let sequencer = std::sync::Arc::new(sequencer);
             let context = L2SyncContext {
                 sequencer,
-                chain: Chain::GoerliTestnet,
+                block_hash_meta_info: BlockHashMetaInfo::for_chain(Chain::GoerliTestnet).clone(),
                 chain_id: ChainId::GOERLI_TESTNET,
                 block_validation_mode: MODE,
                 storage,
+                download_window: NonZeroUsize::new(1).unwrap(),
+                stop_at_block: None,
+                sequencer_public_key: None,
+                execute_pending: None,
             };
 
             tokio::spawn(sync(
@@ -989,18 +1280,18 @@ mod tests {
                     BLOCK0_NUMBER.into(),
                     Ok((BLOCK0.clone().into(), STATE_UPDATE0.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT0_HASH,
-                    Ok(CONTRACT0_DEF.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK0_HASH.into(),
                     Ok(BLOCK0_SIGNATURE.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT0_HASH,
+                    Ok(CONTRACT0_DEF.clone()),
+                );
                 // Download block #1 with respective state update and contracts
                 expect_state_update_with_block(
                     &mut mock,
@@ -1008,18 +1299,18 @@ mod tests {
                     BLOCK1_NUMBER.into(),
                     Ok((BLOCK1.clone().into(), STATE_UPDATE1.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT1_HASH,
-                    Ok(CONTRACT1_DEF.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK1_HASH.into(),
                     Ok(BLOCK1_SIGNATURE.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT1_HASH,
+                    Ok(CONTRACT1_DEF.clone()),
+                );
                 // Stay at head, no more blocks available
                 expect_state_update_with_block(
                     &mut mock,
@@ -1070,18 +1361,18 @@ mod tests {
                     BLOCK1_NUMBER.into(),
                     Ok((BLOCK1.clone().into(), STATE_UPDATE1.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT1_HASH,
-                    Ok(CONTRACT1_DEF.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK1_HASH.into(),
                     Ok(BLOCK1_SIGNATURE.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT1_HASH,
+                    Ok(CONTRACT1_DEF.clone()),
+                );
 
                 // Stay at head, no more blocks available
                 expect_state_update_with_block(
@@ -1101,10 +1392,15 @@ mod tests {
                 let mock = std::sync::Arc::new(mock);
                 let context = L2SyncContext {
                     sequencer: mock,
-                    chain: Chain::GoerliTestnet,
+                    block_hash_meta_info: BlockHashMetaInfo::for_chain(Chain::GoerliTestnet)
+                        .clone(),
                     chain_id: ChainId::GOERLI_TESTNET,
                     block_validation_mode: MODE,
                     storage: Storage::in_memory().unwrap(),
+                    download_window: NonZeroUsize::new(1).unwrap(),
+                    stop_at_block: None,
+                    sequencer_public_key: None,
+                    execute_pending: None,
                 };
 
                 let _jh = tokio::spawn(sync(
@@ -1182,18 +1478,18 @@ mod tests {
                     BLOCK0_NUMBER.into(),
                     Ok((BLOCK0.clone().into(), STATE_UPDATE0.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT0_HASH,
-                    Ok(CONTRACT0_DEF.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK0_HASH.into(),
                     Ok(BLOCK0_SIGNATURE.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT0_HASH,
+                    Ok(CONTRACT0_DEF.clone()),
+                );
 
                 // Block #1 is not there
                 expect_state_update_with_block(
@@ -1220,18 +1516,18 @@ mod tests {
                     BLOCK0_NUMBER.into(),
                     Ok((BLOCK0_V2.clone().into(), STATE_UPDATE0_V2.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT0_HASH_V2,
-                    Ok(CONTRACT0_DEF_V2.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK0_HASH_V2.into(),
                     Ok(BLOCK0_SIGNATURE_V2.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT0_HASH_V2,
+                    Ok(CONTRACT0_DEF_V2.clone()),
+                );
 
                 // Indicate that we are still staying at the head - no new blocks
                 expect_state_update_with_block(
@@ -1312,18 +1608,18 @@ mod tests {
                     BLOCK0_NUMBER.into(),
                     Ok((BLOCK0.clone().into(), STATE_UPDATE0.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT0_HASH,
-                    Ok(CONTRACT0_DEF.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK0_HASH.into(),
                     Ok(BLOCK0_SIGNATURE.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT0_HASH,
+                    Ok(CONTRACT0_DEF.clone()),
+                );
                 // Fetch block #1 with respective state update and contracts
                 expect_state_update_with_block(
                     &mut mock,
@@ -1331,18 +1627,18 @@ mod tests {
                     BLOCK1_NUMBER.into(),
                     Ok((BLOCK1.clone().into(), STATE_UPDATE1.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT1_HASH,
-                    Ok(CONTRACT1_DEF.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK1_HASH.into(),
                     Ok(BLOCK1_SIGNATURE.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT1_HASH,
+                    Ok(CONTRACT1_DEF.clone()),
+                );
                 // Fetch block #2 with respective state update and contracts
                 expect_state_update_with_block(
                     &mut mock,
@@ -1396,18 +1692,18 @@ mod tests {
                     BLOCK0_NUMBER.into(),
                     Ok((BLOCK0_V2.clone().into(), STATE_UPDATE0_V2.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT0_HASH_V2,
-                    Ok(CONTRACT0_DEF_V2.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK0_HASH_V2.into(),
                     Ok(BLOCK0_SIGNATURE_V2.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT0_HASH_V2,
+                    Ok(CONTRACT0_DEF_V2.clone()),
+                );
                 // Fetch the new block #1 from the fork with respective state update and contracts
                 expect_state_update_with_block(
                     &mut mock,
@@ -1549,18 +1845,18 @@ mod tests {
                     BLOCK0_NUMBER.into(),
                     Ok((BLOCK0.clone().into(), STATE_UPDATE0.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT0_HASH,
-                    Ok(CONTRACT0_DEF.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK0_HASH.into(),
                     Ok(BLOCK0_SIGNATURE.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT0_HASH,
+                    Ok(CONTRACT0_DEF.clone()),
+                );
                 // Fetch block #1 with respective state update and contracts
                 expect_state_update_with_block(
                     &mut mock,
@@ -1568,18 +1864,18 @@ mod tests {
                     BLOCK1_NUMBER.into(),
                     Ok((BLOCK1.clone().into(), STATE_UPDATE1.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT1_HASH,
-                    Ok(CONTRACT1_DEF.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK1_HASH.into(),
                     Ok(BLOCK1_SIGNATURE.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT1_HASH,
+                    Ok(CONTRACT1_DEF.clone()),
+                );
                 // Fetch block #2 with respective state update and contracts
                 expect_state_update_with_block(
                     &mut mock,
@@ -1764,18 +2060,18 @@ mod tests {
                     BLOCK0_NUMBER.into(),
                     Ok((BLOCK0.clone().into(), STATE_UPDATE0.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT0_HASH,
-                    Ok(CONTRACT0_DEF.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK0_HASH.into(),
                     Ok(BLOCK0_SIGNATURE.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT0_HASH,
+                    Ok(CONTRACT0_DEF.clone()),
+                );
                 // Fetch block #1 with respective state update and contracts
                 expect_state_update_with_block(
                     &mut mock,
@@ -1783,18 +2079,18 @@ mod tests {
                     BLOCK1_NUMBER.into(),
                     Ok((BLOCK1.clone().into(), STATE_UPDATE1.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT1_HASH,
-                    Ok(CONTRACT1_DEF.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK1_HASH.into(),
                     Ok(BLOCK1_SIGNATURE.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT1_HASH,
+                    Ok(CONTRACT1_DEF.clone()),
+                );
                 // Fetch block #2 with respective state update and contracts
                 expect_state_update_with_block(
                     &mut mock,
@@ -1948,18 +2244,18 @@ mod tests {
                     BLOCK0_NUMBER.into(),
                     Ok((BLOCK0.clone().into(), STATE_UPDATE0.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT0_HASH,
-                    Ok(CONTRACT0_DEF.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK0_HASH.into(),
                     Ok(BLOCK0_SIGNATURE.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT0_HASH,
+                    Ok(CONTRACT0_DEF.clone()),
+                );
 
                 // Fetch block #1 with respective state update and contracts
                 expect_state_update_with_block(
@@ -1968,18 +2264,18 @@ mod tests {
                     BLOCK1_NUMBER.into(),
                     Ok((BLOCK1.clone().into(), STATE_UPDATE1.clone())),
                 );
-                expect_class_by_hash(
-                    &mut mock,
-                    &mut seq,
-                    CONTRACT1_HASH,
-                    Ok(CONTRACT1_DEF.clone()),
-                );
                 expect_signature(
                     &mut mock,
                     &mut seq,
                     BLOCK1_HASH.into(),
                     Ok(BLOCK1_SIGNATURE.clone()),
                 );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT1_HASH,
+                    Ok(CONTRACT1_DEF.clone()),
+                );
                 // Fetch block #2 whose parent hash does not match block #1 hash
                 expect_state_update_with_block(
                     &mut mock,
@@ -2087,6 +2383,12 @@ mod tests {
                     BLOCK0_NUMBER.into(),
                     Ok((BLOCK0.clone().into(), STATE_UPDATE0.clone())),
                 );
+                expect_signature(
+                    &mut mock,
+                    &mut seq,
+                    BLOCK0_HASH.into(),
+                    Ok(BLOCK0_SIGNATURE.clone()),
+                );
                 expect_class_by_hash(
                     &mut mock,
                     &mut seq,
@@ -2106,6 +2408,274 @@ mod tests {
                     .unwrap_err();
             }
         }
+
+        mod stop_at_block {
+            use super::*;
+
+            fn context(
+                sequencer: MockGatewayApi,
+                stop_at_block: BlockNumber,
+            ) -> L2SyncContext<std::sync::Arc<MockGatewayApi>> {
+                L2SyncContext {
+                    sequencer: std::sync::Arc::new(sequencer),
+                    block_hash_meta_info: BlockHashMetaInfo::for_chain(Chain::GoerliTestnet)
+                        .clone(),
+                    chain_id: ChainId::GOERLI_TESTNET,
+                    block_validation_mode: MODE,
+                    storage: Storage::in_memory().unwrap(),
+                    download_window: NonZeroUsize::new(1).unwrap(),
+                    stop_at_block: Some(stop_at_block),
+                    sequencer_public_key: None,
+                    execute_pending: None,
+                }
+            }
+
+            #[tokio::test]
+            async fn stops_after_block() {
+                let (tx_event, mut rx_event) = tokio::sync::mpsc::channel(1);
+                let mut mock = MockGatewayApi::new();
+                let mut seq = mockall::Sequence::new();
+
+                expect_state_update_with_block(
+                    &mut mock,
+                    &mut seq,
+                    BLOCK0_NUMBER.into(),
+                    Ok((BLOCK0.clone().into(), STATE_UPDATE0.clone())),
+                );
+                expect_signature(
+                    &mut mock,
+                    &mut seq,
+                    BLOCK0_HASH.into(),
+                    Ok(BLOCK0_SIGNATURE.clone()),
+                );
+                expect_class_by_hash(
+                    &mut mock,
+                    &mut seq,
+                    CONTRACT0_HASH,
+                    Ok(CONTRACT0_DEF.clone()),
+                );
+                // Block 1 is never downloaded, only the stop block is checked for reorgs.
+                expect_block_header(
+                    &mut mock,
+                    &mut seq,
+                    BLOCK0_NUMBER.into(),
+                    Ok((BLOCK0_NUMBER, BLOCK0_HASH)),
+                );
+
+                let jh = tokio::spawn(sync(
+                    tx_event,
+                    context(mock, BLOCK0_NUMBER),
+                    None,
+                    BlockChain::with_capacity(100, vec![]),
+                ));
+
+                assert_matches!(rx_event.recv().await.unwrap(), SyncEvent::CairoClass { .. });
+                assert_matches!(rx_event.recv().await.unwrap(), SyncEvent::Block((block, _), ..) => {
+                    assert_eq!(*block, *BLOCK0);
+                });
+                tokio::time::timeout(std::time::Duration::from_millis(100), rx_event.recv())
+                    .await
+                    .unwrap_err();
+                assert!(!jh.is_finished());
+            }
+
+            #[tokio::test]
+            async fn reorg_of_stop_block_is_an_error() {
+                let (tx_event, _rx_event) = tokio::sync::mpsc::channel(1);
+                let mut mock = MockGatewayApi::new();
+                let mut seq = mockall::Sequence::new();
+
+                expect_block_header(
+                    &mut mock,
+                    &mut seq,
+                    BLOCK1_NUMBER.into(),
+                    Ok((BLOCK1_NUMBER, BLOCK1_HASH_V2)),
+                );
+
+                let result = sync(
+                    tx_event,
+                    context(mock, BLOCK1_NUMBER),
+                    Some((BLOCK1_NUMBER, BLOCK1_HASH, GLOBAL_ROOT1)),
+                    BlockChain::with_capacity(100, vec![]),
+                )
+                .await;
+                assert!(result.is_err());
+            }
+
+            #[tokio::test]
+            async fn synced_past_stop_block_is_an_error() {
+                let (tx_event, _rx_event) = tokio::sync::mpsc::channel(1);
+
+                let result = sync(
+                    tx_event,
+                    context(MockGatewayApi::new(), BLOCK0_NUMBER),
+                    Some((BLOCK1_NUMBER, BLOCK1_HASH, GLOBAL_ROOT1)),
+                    BlockChain::with_capacity(100, vec![]),
+                )
+                .await;
+                assert!(result.is_err());
+            }
+        }
+
+        mod prefetch {
+            use super::*;
+            use pretty_assertions_sorted::assert_eq;
+
+            /// Expects each block to be downloaded at most once, in any order.
+            fn expect_blocks(mock: &mut MockGatewayApi) {
+                use mockall::predicate::eq;
+
+                let blocks = [
+                    (BLOCK0.clone(), STATE_UPDATE0.clone(), BLOCK0_SIGNATURE),
+                    (BLOCK1.clone(), STATE_UPDATE1.clone(), BLOCK1_SIGNATURE),
+                    (BLOCK2.clone(), STATE_UPDATE2.clone(), BLOCK2_SIGNATURE),
+                ];
+                for (block, state_update, signature) in blocks {
+                    mock.expect_signature()
+                        .with(eq(BlockId::from(block.block_hash)))
+                        .times(0..=1)
+                        .return_once(|_| Ok(signature));
+                    mock.expect_state_update_with_block()
+                        .with(eq(BlockId::from(block.block_number)))
+                        .times(0..=1)
+                        .return_once(|_| Ok((block.into(), state_update)));
+                }
+            }
+
+            #[tokio::test]
+            async fn from_genesis() {
+                let (tx_event, mut rx_event) = tokio::sync::mpsc::channel(1);
+                let mut mock = MockGatewayApi::new();
+
+                expect_blocks(&mut mock);
+                mock.expect_block_header()
+                    .with(mockall::predicate::eq(BlockId::Latest))
+                    .returning(|_| Ok((BLOCK2.block_number, BLOCK2.block_hash)));
+                mock.expect_state_update_with_block()
+                    .with(mockall::predicate::eq(BlockId::from(BLOCK3_NUMBER)))
+                    .returning(|_| Err(block_not_found()));
+                mock.expect_pending_class_by_hash()
+                    .withf(|x| x == &CONTRACT0_HASH)
+                    .times(1)
+                    .return_once(|_| Ok(CONTRACT0_DEF.clone()));
+                mock.expect_pending_class_by_hash()
+                    .withf(|x| x == &CONTRACT1_HASH)
+                    .times(1)
+                    .return_once(|_| Ok(CONTRACT1_DEF.clone()));
+
+                let context = L2SyncContext {
+                    sequencer: std::sync::Arc::new(mock),
+                    block_hash_meta_info: BlockHashMetaInfo::for_chain(Chain::GoerliTestnet)
+                        .clone(),
+                    chain_id: ChainId::GOERLI_TESTNET,
+                    block_validation_mode: MODE,
+                    storage: Storage::in_memory().unwrap(),
+                    download_window: NonZeroUsize::new(3).unwrap(),
+                    stop_at_block: None,
+                    sequencer_public_key: None,
+                    execute_pending: None,
+                };
+                let _jh = tokio::spawn(sync(
+                    tx_event,
+                    context,
+                    None,
+                    BlockChain::with_capacity(100, vec![]),
+                ));
+
+                // Blocks are still emitted in order, each after its classes.
+                let mut blocks = Vec::new();
+                let mut classes = Vec::new();
+                while blocks.len() < 3 {
+                    match rx_event.recv().await.unwrap() {
+                        SyncEvent::CairoClass { hash, .. } => classes.push((hash, blocks.len())),
+                        SyncEvent::Block((block, _), _, signature, _) => {
+                            blocks.push((block.block_number, *signature))
+                        }
+                        _ => panic!("Unexpected event"),
+                    }
+                }
+                assert_eq!(classes, vec![(CONTRACT0_HASH, 0), (CONTRACT1_HASH, 1)]);
+                assert_eq!(
+                    blocks,
+                    vec![
+                        (BLOCK0_NUMBER, BLOCK0_COMMITMENT_SIGNATURE),
+                        (BLOCK1_NUMBER, BLOCK1_COMMITMENT_SIGNATURE),
+                        (BLOCK2_NUMBER, BLOCK2_SIGNATURE.into()),
+                    ]
+                );
+            }
+
+            #[tokio::test]
+            async fn discards_skipped_and_cleared_blocks() {
+                let mut mock = MockGatewayApi::new();
+                expect_blocks(&mut mock);
+
+                let mut prefetcher = Prefetcher {
+                    window: 3,
+                    sequencer: std::sync::Arc::new(mock),
+                    meta_info: BlockHashMetaInfo::for_chain(Chain::GoerliTestnet).clone(),
+                    chain_id: ChainId::GOERLI_TESTNET,
+                    mode: MODE,
+                    queue: VecDeque::new(),
+                };
+                prefetcher.fill(BLOCK0_NUMBER, BLOCK2_NUMBER);
+                assert_eq!(prefetcher.queue.len(), 3);
+
+                let prefetched = prefetcher.take(BLOCK1_NUMBER).await.unwrap();
+                assert_eq!(*prefetched.block, *BLOCK1);
+                assert_eq!(prefetcher.queue.len(), 1);
+
+                // After a reorg, block 2 must be downloaded again.
+                prefetcher.clear();
+                assert!(prefetcher.take(BLOCK2_NUMBER).await.is_none());
+            }
+        }
+    }
+
+    mod verify_signature {
+        use pathfinder_common::macro_prelude::*;
+        use pathfinder_common::StateUpdate;
+        use starknet_gateway_test_fixtures::v0_12_2;
+        use starknet_gateway_types::reply::BlockSignature;
+
+        use crate::state::l2::verify_signature;
+
+        const MAINNET_PUBLIC_KEY: pathfinder_common::PublicKey =
+            public_key!("0x48253ff2c3bed7af18bde0b611b083b39445959102d4947c51c4db6aa4f4e58");
+
+        fn block_350000() -> (StateUpdate, BlockSignature) {
+            let state_update: starknet_gateway_types::reply::StateUpdate =
+                serde_json::from_str(v0_12_2::state_update::BLOCK_350000).unwrap();
+            let signature = serde_json::from_str(v0_12_2::signature::BLOCK_350000).unwrap();
+
+            (state_update.into(), signature)
+        }
+
+        #[test]
+        fn valid() {
+            let (state_update, signature) = block_350000();
+            verify_signature(&state_update, &signature, MAINNET_PUBLIC_KEY).unwrap();
+        }
+
+        #[test]
+        fn state_diff_mismatch() {
+            let (mut state_update, signature) = block_350000();
+            state_update
+                .declared_cairo_classes
+                .insert(class_hash!("0x123"));
+
+            verify_signature(&state_update, &signature, MAINNET_PUBLIC_KEY).unwrap_err();
+        }
+
+        #[test]
+        fn wrong_key() {
+            let (state_update, signature) = block_350000();
+            // The public key of an account on testnet.
+            let key =
+                public_key!("0x792c60ec4fdfea7ce6409db046b8dde11f595911cb74906be02a87ae6a4f70d");
+
+            verify_signature(&state_update, &signature, key).unwrap_err();
+        }
     }
 
     mod block_chain {
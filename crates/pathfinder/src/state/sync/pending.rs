@@ -1,11 +1,19 @@
+use std::sync::Arc;
+
 use anyhow::Context;
-use pathfinder_common::BlockHash;
-use pathfinder_common::BlockId;
+use pathfinder_common::{
+    BlockHash, BlockHeader, BlockId, BlockNumber, ChainId, ContractAddress, StateUpdate,
+    StorageAddress, StorageValue,
+};
+use pathfinder_crypto::Felt;
+use pathfinder_executor::{ExecutionConfig, ExecutionState, TransactionExecutionError};
 use pathfinder_storage::Storage;
 use starknet_gateway_client::GatewayApi;
-use starknet_gateway_types::reply::MaybePendingBlock;
+use starknet_gateway_types::reply::transaction::Receipt;
+use starknet_gateway_types::reply::{MaybePendingBlock, PendingBlock};
 use tokio::time::Instant;
 
+use crate::state::execution::{merge_state_diff, receipt, state_diff};
 use crate::state::sync::SyncEvent;
 
 /// Poll's the Sequencer's pending block and emits [pending events](SyncEvent::Pending)
@@ -17,11 +25,15 @@ use crate::state::sync::SyncEvent;
 /// - the state update parent root does not match head.
 ///
 /// A full block or full state update can be returned from this function if it is encountered during polling.
+///
+/// If an `executor` is given, the pending transactions are executed locally and the resulting
+/// receipts and state diff are emitted instead of the sequencer's.
 pub async fn poll_pending<S: GatewayApi + Clone + Send + 'static>(
     tx_event: tokio::sync::mpsc::Sender<SyncEvent>,
     sequencer: S,
     poll_interval: std::time::Duration,
     storage: Storage,
+    mut executor: Option<PendingExecutor>,
 ) -> anyhow::Result<()> {
     let mut prev_tx_count = 0;
     let mut prev_hash = BlockHash::default();
@@ -68,6 +80,21 @@ pub async fn poll_pending<S: GatewayApi + Clone + Send + 'static>(
         } else {
             prev_tx_count = block.transactions.len();
             prev_hash = block.parent_hash;
+
+            let (block, state_update) = match executor.take() {
+                Some(mut pending_executor) => {
+                    let (pending_executor, result) = tokio::task::spawn_blocking(move || {
+                        let result = pending_executor.apply(block, state_update);
+                        (pending_executor, result)
+                    })
+                    .await
+                    .context("Joining pending execution task")?;
+                    executor = Some(pending_executor);
+                    result
+                }
+                None => (block, state_update),
+            };
+
             tracing::trace!("Emitting a pending update");
             tx_event
                 .send(SyncEvent::Pending(Box::new((block, state_update))))
@@ -79,6 +106,237 @@ pub async fn poll_pending<S: GatewayApi + Clone + Send + 'static>(
     }
 }
 
+/// Re-executes the transactions of the pending block on top of its parent block, so that the
+/// pending receipts and state diff are computed locally instead of being trusted as is.
+///
+/// Only transactions which were not part of the previously executed pending block are executed,
+/// on top of the state diff of those that were.
+pub struct PendingExecutor {
+    storage: Storage,
+    chain_id: ChainId,
+    config: Arc<ExecutionConfig>,
+    /// The parent of the transactions executed so far.
+    parent_hash: BlockHash,
+    receipts: Vec<Receipt>,
+    state_update: StateUpdate,
+}
+
+impl PendingExecutor {
+    pub fn new(storage: Storage, chain_id: ChainId, config: Arc<ExecutionConfig>) -> Self {
+        Self {
+            storage,
+            chain_id,
+            config,
+            parent_hash: BlockHash::ZERO,
+            receipts: Vec::new(),
+            state_update: StateUpdate::default(),
+        }
+    }
+
+    /// Replaces the receipts and state diff of the sequencer's pending block with the locally
+    /// computed ones, and logs where they differ.
+    ///
+    /// The sequencer's data is returned as is if the transactions cannot be executed, for example
+    /// because the parent block has not been stored yet.
+    fn apply(
+        &mut self,
+        block: PendingBlock,
+        state_update: StateUpdate,
+    ) -> (PendingBlock, StateUpdate) {
+        let first_new = match self.execute(&block) {
+            Ok(first_new) => first_new,
+            Err(e) => {
+                tracing::debug!(reason=?e, "Executing pending transactions failed, using the sequencer's data");
+                return (block, state_update);
+            }
+        };
+
+        for (expected, actual) in block
+            .transaction_receipts
+            .iter()
+            .zip(&self.receipts)
+            .skip(first_new)
+        {
+            let differences = receipt_differences(expected, actual);
+            if !differences.is_empty() {
+                tracing::warn!(transaction_hash=%actual.transaction_hash, ?differences, "Locally executed pending transaction differs from the sequencer's receipt");
+            }
+        }
+
+        let differences = state_diff_differences(&state_update, &self.state_update);
+        if !differences.is_empty() {
+            tracing::warn!(parent_hash=%block.parent_hash, ?differences, "Locally computed pending state diff differs from the sequencer's");
+        }
+
+        let block = PendingBlock {
+            transaction_receipts: self.receipts.clone(),
+            ..block
+        };
+        let state_update = StateUpdate {
+            block_hash: state_update.block_hash,
+            parent_state_commitment: state_update.parent_state_commitment,
+            state_commitment: state_update.state_commitment,
+            ..self.state_update.clone()
+        };
+
+        (block, state_update)
+    }
+
+    /// Executes the transactions of `block` which have not been executed yet, returning the
+    /// index of the first of them.
+    fn execute(&mut self, block: &PendingBlock) -> anyhow::Result<usize> {
+        let is_continuation = block.parent_hash == self.parent_hash
+            && block.transactions.len() >= self.receipts.len()
+            && self
+                .receipts
+                .iter()
+                .zip(&block.transactions)
+                .all(|(receipt, transaction)| receipt.transaction_hash == transaction.hash());
+        if !is_continuation {
+            self.parent_hash = block.parent_hash;
+            self.receipts.clear();
+            self.state_update = StateUpdate::default();
+        }
+
+        let first_new = self.receipts.len();
+        let new_transactions = &block.transactions[first_new..];
+        if new_transactions.is_empty() {
+            return Ok(first_new);
+        }
+
+        let mut connection = self
+            .storage
+            .connection()
+            .context("Creating database connection")?;
+        let db = connection
+            .transaction()
+            .context("Creating database transaction")?;
+
+        let parent = db
+            .block_header(block.parent_hash.into())
+            .context("Fetching parent block header")?
+            .context("Parent block is missing")?;
+        let header = BlockHeader {
+            parent_hash: block.parent_hash,
+            number: parent.number + 1,
+            timestamp: block.timestamp,
+            eth_l1_gas_price: block.eth_l1_gas_price,
+            strk_l1_gas_price: block.strk_l1_gas_price.unwrap_or_default(),
+            sequencer_address: block.sequencer_address,
+            starknet_version: block.starknet_version.clone(),
+            ..Default::default()
+        };
+
+        let mut state_update = self.state_update.clone();
+        // Mirror the block hash system contract update performed by the executor.
+        if first_new == 0 && header.number.get() >= 10 {
+            let number = BlockNumber::new_or_panic(header.number.get() - 10);
+            let block_hash = db
+                .block_hash(number.into())
+                .context("Fetching historical block hash")?
+                .context("Historical block hash is missing")?;
+            state_update = state_update.with_system_storage_update(
+                ContractAddress::ONE,
+                StorageAddress::new_or_panic(Felt::from(number.get())),
+                StorageValue(block_hash.0),
+            );
+        }
+
+        let transactions = new_transactions
+            .iter()
+            .map(|transaction| pathfinder_rpc::compose_executor_transaction(transaction, &db))
+            .collect::<Result<Vec<_>, _>>()
+            .context("Converting transactions for execution")?;
+
+        // Classes declared by pending transactions are only found with a pending state.
+        let execution_state = ExecutionState::trace(
+            &db,
+            self.chain_id,
+            header,
+            Some(Arc::new(state_update.clone())),
+        )
+        .with_config(self.config.clone());
+        let simulations =
+            match pathfinder_executor::simulate(execution_state, transactions, false, false) {
+                Ok(simulations) => simulations,
+                Err(TransactionExecutionError::ExecutionError {
+                    transaction_index,
+                    error,
+                }) => anyhow::bail!(
+                    "Transaction {} failed: {error}",
+                    new_transactions[transaction_index].hash()
+                ),
+                Err(TransactionExecutionError::Internal(e))
+                | Err(TransactionExecutionError::Custom(e)) => {
+                    return Err(e.context("Executing transactions"))
+                }
+            };
+
+        for (index, (transaction, simulation)) in
+            new_transactions.iter().zip(&simulations).enumerate()
+        {
+            let index = first_new + index;
+            let mut receipt = receipt(transaction.hash(), index, simulation);
+            receipt.l1_to_l2_consumed_message = block
+                .transaction_receipts
+                .get(index)
+                .and_then(|receipt| receipt.l1_to_l2_consumed_message.clone());
+
+            self.receipts.push(receipt);
+            state_update = merge_state_diff(state_update, state_diff(&simulation.trace));
+        }
+        self.state_update = state_update;
+
+        Ok(first_new)
+    }
+}
+
+/// The parts of a transaction's receipt which differ between the sequencer and local execution.
+///
+/// Execution resources are not compared, as they are not part of any commitment.
+fn receipt_differences(expected: &Receipt, actual: &Receipt) -> Vec<&'static str> {
+    [
+        (
+            "execution_status",
+            expected.execution_status != actual.execution_status,
+        ),
+        ("actual_fee", expected.actual_fee != actual.actual_fee),
+        ("events", expected.events != actual.events),
+        (
+            "l2_to_l1_messages",
+            expected.l2_to_l1_messages != actual.l2_to_l1_messages,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(field, differs)| differs.then_some(field))
+    .collect()
+}
+
+/// The parts of the pending state diff which differ between the sequencer and local execution.
+fn state_diff_differences(expected: &StateUpdate, actual: &StateUpdate) -> Vec<&'static str> {
+    [
+        (
+            "contract_updates",
+            expected.contract_updates != actual.contract_updates,
+        ),
+        (
+            "system_contract_updates",
+            expected.system_contract_updates != actual.system_contract_updates,
+        ),
+        (
+            "declared_cairo_classes",
+            expected.declared_cairo_classes != actual.declared_cairo_classes,
+        ),
+        (
+            "declared_sierra_classes",
+            expected.declared_sierra_classes != actual.declared_sierra_classes,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(field, differs)| differs.then_some(field))
+    .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -169,6 +427,7 @@ mod tests {
                 sequencer,
                 std::time::Duration::ZERO,
                 Storage::in_memory().unwrap(),
+                None,
             )
             .await
         });
@@ -247,6 +506,7 @@ mod tests {
                 sequencer,
                 std::time::Duration::ZERO,
                 Storage::in_memory().unwrap(),
+                None,
             )
             .await
         });
@@ -265,4 +525,239 @@ mod tests {
 
         assert_matches!(result2, SyncEvent::Pending(x) if x.0 == b1 && x.1 == *PENDING_UPDATE);
     }
+
+    mod local_execution {
+        use std::sync::Arc;
+
+        use pathfinder_common::macro_prelude::*;
+        use pathfinder_common::{
+            BlockHeader, BlockNumber, BlockTimestamp, CallParam, ChainId, ContractAddress,
+            EntryPoint, GasPrice, StarknetVersion, StateUpdate, StorageAddress, TransactionHash,
+            TransactionIndex, TransactionNonce,
+        };
+        use pathfinder_crypto::Felt;
+        use pathfinder_executor::ETH_FEE_TOKEN_ADDRESS;
+        use pathfinder_storage::Storage;
+        use starknet_gateway_client::MockGatewayApi;
+        use starknet_gateway_test_fixtures::class_definitions::{
+            DUMMY_ACCOUNT, DUMMY_ACCOUNT_CLASS_HASH, ERC20_CONTRACT_DEFINITION,
+            ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+        };
+        use starknet_gateway_types::reply::transaction::{
+            ExecutionStatus, InvokeTransaction, InvokeTransactionV1, Receipt, Transaction,
+        };
+        use starknet_gateway_types::reply::{MaybePendingBlock, PendingBlock};
+
+        use super::{PENDING_BLOCK, PENDING_UPDATE, TEST_TIMEOUT};
+        use crate::state::sync::pending::{
+            poll_pending, receipt_differences, state_diff_differences, PendingExecutor,
+        };
+        use crate::state::sync::SyncEvent;
+
+        const ACCOUNT: ContractAddress = contract_address!("0xc01");
+
+        fn receipt() -> Receipt {
+            Receipt {
+                actual_fee: Some(fee!("0x10")),
+                events: Vec::new(),
+                execution_resources: None,
+                l1_to_l2_consumed_message: None,
+                l2_to_l1_messages: Vec::new(),
+                transaction_hash: transaction_hash!("0x22"),
+                transaction_index: TransactionIndex::new_or_panic(0),
+                execution_status: Default::default(),
+                revert_error: None,
+            }
+        }
+
+        #[test]
+        fn receipts_are_compared_without_resources() {
+            let expected = receipt();
+
+            let mut actual = receipt();
+            actual.execution_resources = Some(Default::default());
+            assert!(receipt_differences(&expected, &actual).is_empty());
+
+            actual.actual_fee = Some(fee!("0x11"));
+            assert_eq!(receipt_differences(&expected, &actual), vec!["actual_fee"]);
+        }
+
+        #[test]
+        fn state_diffs_are_compared_without_commitments() {
+            let expected = PENDING_UPDATE.clone();
+
+            let actual = pathfinder_common::StateUpdate::default();
+            assert!(state_diff_differences(&expected, &actual).is_empty());
+
+            let actual =
+                actual.with_contract_nonce(contract_address!("0x1"), contract_nonce!("0x2"));
+            assert_eq!(
+                state_diff_differences(&expected, &actual),
+                vec!["contract_updates"]
+            );
+        }
+
+        #[test]
+        fn falls_back_to_sequencer_data_if_execution_fails() {
+            // The parent block is missing from the empty database.
+            let mut executor = PendingExecutor::new(
+                Storage::in_memory().unwrap(),
+                ChainId::SEPOLIA_TESTNET,
+                Arc::new(Default::default()),
+            );
+
+            let (block, state_update) =
+                executor.apply(PENDING_BLOCK.clone(), PENDING_UPDATE.clone());
+
+            assert_eq!(block, *PENDING_BLOCK);
+            assert_eq!(state_update, *PENDING_UPDATE);
+        }
+
+        /// Stores a parent block with a funded account and the fee token contract.
+        fn storage_with_parent() -> (Storage, BlockHeader) {
+            let storage = Storage::in_memory().unwrap();
+            let mut db = storage.connection().unwrap();
+            let tx = db.transaction().unwrap();
+
+            tx.insert_cairo_class(DUMMY_ACCOUNT_CLASS_HASH, DUMMY_ACCOUNT)
+                .unwrap();
+            tx.insert_cairo_class(
+                ERC20_CONTRACT_DEFINITION_CLASS_HASH,
+                ERC20_CONTRACT_DEFINITION,
+            )
+            .unwrap();
+
+            let parent = BlockHeader::builder()
+                .with_number(BlockNumber::GENESIS)
+                .with_timestamp(BlockTimestamp::new_or_panic(1))
+                .with_eth_l1_gas_price(GasPrice(1))
+                .with_starknet_version(StarknetVersion::new(0, 13, 0))
+                .finalize_with_hash(block_hash!("0xb00"));
+            tx.insert_block_header(&parent).unwrap();
+
+            let balance_key = StorageAddress::from_map_name_and_key(b"ERC20_balances", ACCOUNT.0);
+            let state_update = StateUpdate::default()
+                .with_block_hash(parent.hash)
+                .with_declared_cairo_class(DUMMY_ACCOUNT_CLASS_HASH)
+                .with_declared_cairo_class(ERC20_CONTRACT_DEFINITION_CLASS_HASH)
+                .with_deployed_contract(ACCOUNT, DUMMY_ACCOUNT_CLASS_HASH)
+                .with_deployed_contract(ETH_FEE_TOKEN_ADDRESS, ERC20_CONTRACT_DEFINITION_CLASS_HASH)
+                .with_storage_update(
+                    ETH_FEE_TOKEN_ADDRESS,
+                    balance_key,
+                    storage_value!("0x10000000000000000000000000000"),
+                );
+            tx.insert_state_update(parent.number, &state_update)
+                .unwrap();
+            tx.commit().unwrap();
+
+            (storage, parent)
+        }
+
+        /// An invoke by [ACCOUNT] of the fee token function `selector` with [ACCOUNT] as argument.
+        fn invoke(nonce: u64, hash: u64, selector: &[u8]) -> Transaction {
+            Transaction::Invoke(InvokeTransaction::V1(InvokeTransactionV1 {
+                calldata: vec![
+                    CallParam(ETH_FEE_TOKEN_ADDRESS.0),
+                    CallParam(EntryPoint::hashed(selector).0),
+                    call_param!("0x1"),
+                    CallParam(ACCOUNT.0),
+                ],
+                sender_address: ACCOUNT,
+                max_fee: fee!("0x100000000000"),
+                signature: vec![],
+                nonce: TransactionNonce(Felt::from_u64(nonce)),
+                transaction_hash: TransactionHash(Felt::from_u64(hash)),
+            }))
+        }
+
+        async fn next_pending(
+            rx: &mut tokio::sync::mpsc::Receiver<SyncEvent>,
+        ) -> (PendingBlock, StateUpdate) {
+            let event = tokio::time::timeout(TEST_TIMEOUT, rx.recv())
+                .await
+                .expect("Event should be emitted")
+                .unwrap();
+            match event {
+                SyncEvent::Pending(pending) => *pending,
+                _ => panic!("Expected a pending event"),
+            }
+        }
+
+        #[tokio::test]
+        async fn executes_new_pending_transactions() {
+            let (storage, parent) = storage_with_parent();
+
+            let pending = PendingBlock {
+                eth_l1_gas_price: GasPrice(1),
+                strk_l1_gas_price: None,
+                parent_hash: parent.hash,
+                sequencer_address: sequencer_address!("0x5e9"),
+                timestamp: BlockTimestamp::new_or_panic(2),
+                transaction_receipts: Vec::new(),
+                transactions: vec![invoke(0, 1, b"balanceOf")],
+                starknet_version: StarknetVersion::new(0, 13, 0),
+                ..PENDING_BLOCK.clone()
+            };
+            // Transactions are only executed once, so the first transaction still succeeds
+            // even though it now calls a function which does not exist.
+            let mut extended = pending.clone();
+            extended.transactions = vec![invoke(0, 1, b"missing"), invoke(1, 2, b"balanceOf")];
+
+            let polls = std::sync::atomic::AtomicUsize::new(0);
+            let mut sequencer = MockGatewayApi::new();
+            sequencer
+                .expect_state_update_with_block()
+                .returning(move |_| {
+                    let block = match polls.fetch_add(1, std::sync::atomic::Ordering::Relaxed) {
+                        0 => pending.clone(),
+                        _ => extended.clone(),
+                    };
+                    Ok((MaybePendingBlock::Pending(block), StateUpdate::default()))
+                });
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+            let executor = PendingExecutor::new(
+                storage.clone(),
+                ChainId::SEPOLIA_TESTNET,
+                Arc::new(Default::default()),
+            );
+            let _jh = tokio::spawn(poll_pending(
+                tx,
+                Arc::new(sequencer),
+                std::time::Duration::ZERO,
+                storage,
+                Some(executor),
+            ));
+
+            let (block, state_update) = next_pending(&mut rx).await;
+            assert_eq!(block.transaction_receipts.len(), 1);
+            let receipt = &block.transaction_receipts[0];
+            assert_eq!(receipt.transaction_hash, transaction_hash!("0x1"));
+            assert_eq!(receipt.execution_status, ExecutionStatus::Succeeded);
+            assert_ne!(receipt.actual_fee, Some(fee!("0x0")));
+            assert_eq!(
+                state_update.contract_updates[&ACCOUNT].nonce,
+                Some(contract_nonce!("0x1"))
+            );
+            assert!(state_update.contract_updates[&ETH_FEE_TOKEN_ADDRESS]
+                .storage
+                .contains_key(&StorageAddress::from_map_name_and_key(
+                    b"ERC20_balances",
+                    ACCOUNT.0
+                )));
+
+            let (extended_block, state_update) = next_pending(&mut rx).await;
+            assert_eq!(extended_block.transaction_receipts.len(), 2);
+            assert_eq!(extended_block.transaction_receipts[0], *receipt);
+            let receipt = &extended_block.transaction_receipts[1];
+            assert_eq!(receipt.transaction_hash, transaction_hash!("0x2"));
+            assert_eq!(receipt.transaction_index, TransactionIndex::new_or_panic(1));
+            assert_eq!(receipt.execution_status, ExecutionStatus::Succeeded);
+            assert_eq!(
+                state_update.contract_updates[&ACCOUNT].nonce,
+                Some(contract_nonce!("0x2"))
+            );
+        }
+    }
 }
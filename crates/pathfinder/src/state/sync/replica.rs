@@ -0,0 +1,325 @@
+//! Follows a database which is synced by another pathfinder node, for nodes running in
+//! read-only mode.
+//!
+//! Such a node cannot sync itself, so it instead polls the database for new blocks written by
+//! the owning node, reports them as its sync status and broadcasts them to `newHeads`
+//! subscribers. The pending block is not stored in the database, so it is still polled from the
+//! gateway.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use pathfinder_common::{BlockHash, BlockHeader, BlockNumber, StateUpdate};
+use pathfinder_rpc::v02::types::syncing::{self, NumberedBlock, Syncing};
+use pathfinder_rpc::{PendingData, SyncState, TopicBroadcasters};
+use pathfinder_storage::{Connection, Storage};
+use starknet_gateway_client::GatewayApi;
+use starknet_gateway_types::reply::PendingBlock;
+use tokio::sync::mpsc;
+use tokio::sync::watch::Sender as WatchSender;
+
+use super::{pending, SyncEvent};
+
+const PENDING_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// The maximum number of new headers read per poll, so that a replica far behind the owning node
+/// catches up in steps instead of reading all missing headers at once.
+const MAX_HEADERS_PER_POLL: u64 = 1_000;
+
+pub struct ReplicaContext<G> {
+    pub storage: Storage,
+    pub sequencer: G,
+    pub state: Arc<SyncState>,
+    pub pending_data: WatchSender<PendingData>,
+    pub websocket_txs: Option<TopicBroadcasters>,
+    /// How often the database is polled for new blocks.
+    pub poll_interval: Duration,
+    pub restart_delay: Duration,
+}
+
+/// Polls the database for new blocks and the gateway for the pending block, until an error
+/// occurs.
+pub async fn follow<G>(context: ReplicaContext<G>) -> anyhow::Result<()>
+where
+    G: GatewayApi + Clone + Send + 'static,
+{
+    let ReplicaContext {
+        storage,
+        sequencer,
+        state,
+        pending_data,
+        mut websocket_txs,
+        poll_interval,
+        restart_delay,
+    } = context;
+
+    let mut db_conn = storage
+        .connection()
+        .context("Creating database connection")?;
+
+    let mut head = tokio::task::block_in_place(|| latest_block(&mut db_conn))?;
+    if let Some((number, hash)) = head {
+        tracing::info!(%number, %hash, "Following database in read-only mode");
+    }
+    let starting = NumberedBlock::from(head.map(|(n, h)| (h, n)).unwrap_or_default());
+
+    let (tx_event, mut rx_event) = mpsc::channel(8);
+    let spawn_pending = || {
+        tokio::spawn(pending::poll_pending(
+            tx_event.clone(),
+            sequencer.clone(),
+            PENDING_POLL_INTERVAL,
+            storage.clone(),
+        ))
+    };
+    let mut pending_handle = spawn_pending();
+
+    // The latest pending block, which may be ahead of the database if the owning node has not
+    // stored its parent yet.
+    let mut latest_pending: Option<Box<(PendingBlock, StateUpdate)>> = None;
+
+    let mut interval = tokio::time::interval(poll_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let headers = tokio::task::block_in_place(|| new_headers(&mut db_conn, head))?;
+                let Some(last) = headers.last() else {
+                    continue;
+                };
+                head = Some((last.number, last.hash));
+                let current = NumberedBlock::from((last.hash, last.number));
+                update_sync_status(&state, starting, current).await;
+
+                for header in headers {
+                    tracing::debug!(number=%header.number, "New block in database");
+                    if let Some(sender) = &websocket_txs {
+                        if let Err(e) = sender.new_head.send_if_receiving(header.into()) {
+                            tracing::error!(error=?e, "Failed to send header over websocket broadcaster.");
+                            websocket_txs = None;
+                        }
+                    }
+                }
+
+                if let Some(pending) = &latest_pending {
+                    update_pending(&pending_data, pending, head);
+                }
+            }
+            Some(event) = rx_event.recv() => {
+                // Classes are stored by the node owning the database.
+                if let SyncEvent::Pending(pending) = event {
+                    update_pending(&pending_data, &pending, head);
+                    latest_pending = Some(pending);
+                }
+            }
+            result = &mut pending_handle => {
+                match result {
+                    Ok(Ok(())) => tracing::error!("Pending polling ended unexpectedly"),
+                    Ok(Err(e)) => tracing::warn!(reason=?e, "Pending polling failed, restarting"),
+                    Err(e) => tracing::error!(reason=?e, "Pending polling panicked, restarting"),
+                }
+                tokio::time::sleep(restart_delay).await;
+                pending_handle = spawn_pending();
+            }
+        }
+    }
+}
+
+/// Reports the replicated head as the current and highest block, as a replica only knows about
+/// the blocks stored by the owning node.
+async fn update_sync_status(state: &SyncState, starting: NumberedBlock, current: NumberedBlock) {
+    metrics::gauge!("current_block", current.number.get() as f64);
+    metrics::gauge!("highest_block", current.number.get() as f64);
+
+    match &mut *state.status.write().await {
+        status @ Syncing::False(_) => {
+            *status = Syncing::Status(syncing::Status {
+                starting,
+                current,
+                highest: current,
+            });
+        }
+        Syncing::Status(status) => {
+            status.current = current;
+            status.highest = current;
+        }
+    }
+}
+
+fn latest_block(db_conn: &mut Connection) -> anyhow::Result<Option<(BlockNumber, BlockHash)>> {
+    let tx = db_conn
+        .transaction()
+        .context("Creating database transaction")?;
+    tx.block_id(pathfinder_storage::BlockId::Latest)
+        .context("Fetching latest block hash")
+}
+
+/// Returns the headers of the blocks added to the database since `head`, in order and at most
+/// [MAX_HEADERS_PER_POLL] of them. The remaining headers are returned by the next calls.
+///
+/// If the owning node reverted `head` in a reorg, only the new latest block is returned.
+fn new_headers(
+    db_conn: &mut Connection,
+    head: Option<(BlockNumber, BlockHash)>,
+) -> anyhow::Result<Vec<BlockHeader>> {
+    let tx = db_conn
+        .transaction()
+        .context("Creating database transaction")?;
+
+    let Some(latest) = tx
+        .block_header(pathfinder_storage::BlockId::Latest)
+        .context("Fetching latest block header")?
+    else {
+        return Ok(Vec::new());
+    };
+
+    let first = match head {
+        Some((_, hash)) if hash == latest.hash => return Ok(Vec::new()),
+        None => BlockNumber::GENESIS,
+        Some((number, hash)) => {
+            let still_canonical = tx
+                .block_id(number.into())
+                .context("Fetching head block hash")?
+                .is_some_and(|(_, canonical)| canonical == hash);
+            if still_canonical {
+                number + 1
+            } else {
+                tracing::info!(%number, %hash, "Head was reorged by the node owning the database");
+                latest.number
+            }
+        }
+    };
+
+    let last = latest.number.min(first + (MAX_HEADERS_PER_POLL - 1));
+    let mut headers = Vec::new();
+    let mut number = first;
+    while number <= last {
+        let header = tx
+            .block_header(number.into())
+            .context("Fetching block header")?
+            .context("Block header missing from database")?;
+        headers.push(header);
+        number += 1;
+    }
+
+    Ok(headers)
+}
+
+/// Publishes the pending block if it is built on top of `head`.
+fn update_pending(
+    pending_data: &WatchSender<PendingData>,
+    pending: &(PendingBlock, StateUpdate),
+    head: Option<(BlockNumber, BlockHash)>,
+) {
+    let (number, hash) = head.unwrap_or_default();
+    if pending.0.parent_hash != hash {
+        return;
+    }
+
+    let data = PendingData {
+        block: pending.0.clone().into(),
+        state_update: pending.1.clone().into(),
+        number: number + 1,
+    };
+    pending_data.send_replace(data);
+    tracing::debug!("Updated pending data");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_crypto::Felt;
+
+    fn insert_blocks(storage: &Storage, headers: &[BlockHeader]) {
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        for header in headers {
+            tx.insert_block_header(header).unwrap();
+        }
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn new_headers_since_head() {
+        let storage = Storage::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+
+        assert!(new_headers(&mut db, None).unwrap().is_empty());
+
+        let genesis = BlockHeader::builder().finalize_with_hash(block_hash_bytes!(b"genesis"));
+        let block1 = genesis
+            .child_builder()
+            .finalize_with_hash(block_hash_bytes!(b"block 1"));
+        let block2 = block1
+            .child_builder()
+            .finalize_with_hash(block_hash_bytes!(b"block 2"));
+        insert_blocks(&storage, &[genesis.clone(), block1.clone(), block2.clone()]);
+
+        assert_eq!(
+            new_headers(&mut db, None).unwrap(),
+            vec![genesis.clone(), block1.clone(), block2.clone()]
+        );
+        assert_eq!(
+            new_headers(&mut db, Some((genesis.number, genesis.hash))).unwrap(),
+            vec![block1.clone(), block2.clone()]
+        );
+        assert!(new_headers(&mut db, Some((block2.number, block2.hash)))
+            .unwrap()
+            .is_empty());
+
+        // A reorged head only yields the new latest block.
+        let reorged = Some((block1.number, block_hash_bytes!(b"reorged")));
+        assert_eq!(new_headers(&mut db, reorged).unwrap(), vec![block2]);
+    }
+
+    #[test]
+    fn new_headers_are_paged() {
+        let storage = Storage::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+
+        let mut headers =
+            vec![BlockHeader::builder().finalize_with_hash(block_hash_bytes!(b"genesis"))];
+        for i in 1..MAX_HEADERS_PER_POLL + 10 {
+            let header = headers
+                .last()
+                .unwrap()
+                .child_builder()
+                .finalize_with_hash(BlockHash(Felt::from_u64(i)));
+            headers.push(header);
+        }
+        insert_blocks(&storage, &headers);
+
+        let page = new_headers(&mut db, None).unwrap();
+        assert_eq!(page, headers[..MAX_HEADERS_PER_POLL as usize]);
+
+        let last = page.last().unwrap();
+        let page = new_headers(&mut db, Some((last.number, last.hash))).unwrap();
+        assert_eq!(page, headers[MAX_HEADERS_PER_POLL as usize..]);
+    }
+
+    #[test]
+    fn pending_must_extend_head() {
+        let (tx, rx) = tokio::sync::watch::channel(PendingData::default());
+        let parent = block_hash_bytes!(b"parent");
+        let pending = (
+            PendingBlock {
+                parent_hash: parent,
+                ..Default::default()
+            },
+            StateUpdate::default(),
+        );
+
+        update_pending(
+            &tx,
+            &pending,
+            Some((BlockNumber::GENESIS, block_hash_bytes!(b"other"))),
+        );
+        assert!(!rx.has_changed().unwrap());
+
+        update_pending(&tx, &pending, Some((BlockNumber::GENESIS, parent)));
+        assert!(rx.has_changed().unwrap());
+        assert_eq!(rx.borrow().number, BlockNumber::GENESIS + 1);
+    }
+}
@@ -3,10 +3,13 @@ pub use crate::jsonrpc::websocket::WebsocketContext;
 use crate::pending::PendingData;
 use crate::pending::PendingWatcher;
 use crate::SyncState;
-use pathfinder_common::ChainId;
-use pathfinder_executor::TraceCache;
+use pathfinder_common::{BlockNumber, ChainId};
+use pathfinder_executor::{ExecutionConfig, ForkState, TraceCache};
 use pathfinder_storage::Storage;
+use starknet_gateway_client::GatewayApi;
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 type SequencerClient = starknet_gateway_client::Client;
@@ -17,6 +20,21 @@ pub struct RpcConfig {
     pub batch_concurrency_limit: NonZeroUsize,
     pub get_events_max_blocks_to_scan: NonZeroUsize,
     pub get_events_max_uncached_bloom_filters_to_load: NonZeroUsize,
+    /// Directory into which `pathfinder_backupDatabase` writes backups. The method is disabled if
+    /// this is `None`.
+    ///
+    /// The method is not authenticated, so anyone who can reach the RPC server can start backups.
+    pub backup_directory: Option<PathBuf>,
+}
+
+/// A sequencer running as part of this node, i.e. when pathfinder produces its own blocks.
+#[axum::async_trait]
+pub trait LocalSequencer: Send + Sync {
+    /// The gateway API through which transactions are submitted to this sequencer.
+    fn gateway(&self) -> &(dyn GatewayApi + Send + Sync);
+
+    /// Seals all transactions received so far into a new block and returns its number.
+    async fn create_block(&self) -> anyhow::Result<BlockNumber>;
 }
 
 #[derive(Clone)]
@@ -29,8 +47,15 @@ pub struct RpcContext {
     pub chain_id: ChainId,
     pub eth_gas_price: gas_price::Cached,
     pub sequencer: SequencerClient,
+    pub local_sequencer: Option<Arc<dyn LocalSequencer>>,
+    /// Remote state which execution falls back to, when running on top of a forked network.
+    pub fork: Option<Arc<dyn ForkState>>,
+    /// Chain specific execution parameters.
+    pub execution_config: Arc<ExecutionConfig>,
     pub websocket: Option<WebsocketContext>,
     pub config: RpcConfig,
+    /// Set while `pathfinder_backupDatabase` is writing a backup.
+    pub backup_in_progress: Arc<AtomicBool>,
 }
 
 impl RpcContext {
@@ -53,8 +78,21 @@ impl RpcContext {
             pending_data,
             eth_gas_price: gas_price::Cached::new(sequencer.clone()),
             sequencer,
+            local_sequencer: None,
+            fork: None,
+            execution_config: Default::default(),
             websocket: None,
             config,
+            backup_in_progress: Default::default(),
+        }
+    }
+
+    /// The gateway that transactions are submitted to. This is the [LocalSequencer] if one
+    /// is configured and the Starknet [sequencer](Self::sequencer) otherwise.
+    pub fn gateway(&self) -> &(dyn GatewayApi + Send + Sync) {
+        match &self.local_sequencer {
+            Some(local) => local.gateway(),
+            None => &self.sequencer,
         }
     }
 
@@ -87,6 +125,7 @@ impl RpcContext {
             batch_concurrency_limit: NonZeroUsize::new(8).unwrap(),
             get_events_max_blocks_to_scan: NonZeroUsize::new(1000).unwrap(),
             get_events_max_uncached_bloom_filters_to_load: NonZeroUsize::new(1000).unwrap(),
+            backup_directory: None,
         };
 
         Self::new(
@@ -128,6 +167,27 @@ impl RpcContext {
         context.with_pending_data(rx)
     }
 
+    pub fn with_local_sequencer(self, local_sequencer: Arc<dyn LocalSequencer>) -> Self {
+        Self {
+            local_sequencer: Some(local_sequencer),
+            ..self
+        }
+    }
+
+    pub fn with_fork(self, fork: Arc<dyn ForkState>) -> Self {
+        Self {
+            fork: Some(fork),
+            ..self
+        }
+    }
+
+    pub fn with_execution_config(self, execution_config: Arc<ExecutionConfig>) -> Self {
+        Self {
+            execution_config,
+            ..self
+        }
+    }
+
     pub fn with_websockets(self, websockets: WebsocketContext) -> Self {
         Self {
             websocket: Some(websockets),
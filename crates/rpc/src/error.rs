@@ -76,6 +76,10 @@ pub enum ApplicationError {
     UnexpectedError { data: String },
     #[error("Too many storage keys requested")]
     ProofLimitExceeded { limit: u32, requested: u32 },
+    #[error("The state tries of this block have been pruned")]
+    TriePruned,
+    #[error("Block data has been pruned")]
+    BlockPruned,
     #[error("Internal error")]
     GatewayError(starknet_gateway_types::error::StarknetError),
     #[error("Transaction execution error")]
@@ -131,6 +135,8 @@ impl ApplicationError {
             ApplicationError::UnexpectedError { .. } => 63,
             // doc/rpc/pathfinder_rpc_api.json
             ApplicationError::ProofLimitExceeded { .. } => 10000,
+            ApplicationError::TriePruned => 10001,
+            ApplicationError::BlockPruned => 10002,
             // https://www.jsonrpc.org/specification#error_object
             ApplicationError::GatewayError(_)
             | ApplicationError::Internal(_)
@@ -205,6 +211,8 @@ impl ApplicationError {
                 "requested": requested,
             })),
             ApplicationError::ValidationFailureV06(error) => Some(json!(error)),
+            ApplicationError::TriePruned => None,
+            ApplicationError::BlockPruned => None,
         }
     }
 }
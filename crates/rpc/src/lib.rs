@@ -319,9 +319,13 @@ pub mod test_utils {
             .set(contract0_addr, contract_state_hash)
             .unwrap();
 
-        let (storage_commitment0, nodes) = storage_commitment_tree.commit().unwrap();
+        let (storage_commitment0, trie_update) = storage_commitment_tree.commit().unwrap();
         let storage_root_idx = db_txn
-            .insert_storage_trie(storage_commitment0, &nodes)
+            .insert_storage_trie(
+                BlockNumber::GENESIS,
+                storage_commitment0,
+                &trie_update.nodes,
+            )
             .unwrap();
         db_txn
             .insert_storage_root(BlockNumber::GENESIS, Some(storage_root_idx))
@@ -360,9 +364,13 @@ pub mod test_utils {
         storage_commitment_tree
             .set(contract1_addr, contract_state_hash)
             .unwrap();
-        let (storage_commitment1, nodes) = storage_commitment_tree.commit().unwrap();
+        let (storage_commitment1, trie_update) = storage_commitment_tree.commit().unwrap();
         let storage_root_idx = db_txn
-            .insert_storage_trie(storage_commitment1, &nodes)
+            .insert_storage_trie(
+                BlockNumber::GENESIS + 1,
+                storage_commitment1,
+                &trie_update.nodes,
+            )
             .unwrap();
         db_txn
             .insert_storage_root(BlockNumber::GENESIS + 1, Some(storage_root_idx))
@@ -419,9 +427,13 @@ pub mod test_utils {
         storage_commitment_tree
             .set(contract2_addr, contract_state_hash)
             .unwrap();
-        let (storage_commitment2, nodes) = storage_commitment_tree.commit().unwrap();
+        let (storage_commitment2, trie_update) = storage_commitment_tree.commit().unwrap();
         let storage_root_idx = db_txn
-            .insert_storage_trie(storage_commitment2, &nodes)
+            .insert_storage_trie(
+                BlockNumber::GENESIS + 2,
+                storage_commitment2,
+                &trie_update.nodes,
+            )
             .unwrap();
         db_txn
             .insert_storage_root(BlockNumber::GENESIS + 2, Some(storage_root_idx))
@@ -5,7 +5,13 @@ pub(crate) mod methods;
 #[rustfmt::skip]
 pub fn register_routes() -> RpcRouterBuilder {
     RpcRouter::builder("v0.1")
-        .register("pathfinder_version",              || { pathfinder_common::consts::VERGEN_GIT_DESCRIBE })
-        .register("pathfinder_getProof",             methods::get_proof)
-        .register("pathfinder_getTransactionStatus", methods::get_transaction_status)
+        .register("pathfinder_version",                || { pathfinder_common::consts::VERGEN_GIT_DESCRIBE })
+        .register("pathfinder_getProof",               methods::get_proof)
+        .register("pathfinder_getTransactionStatus",   methods::get_transaction_status)
+        .register("pathfinder_createBlock",            methods::create_block)
+        .register("pathfinder_simulateTransactions",   methods::simulate_transactions)
+        .register("pathfinder_backupDatabase",         methods::backup_database)
+        .register("pathfinder_getL1Acceptance",        methods::get_l1_acceptance)
+        .register("pathfinder_getL1ToL2MessageStatus", methods::get_l1_to_l2_message_status)
+        .register("pathfinder_getL2ToL1MessageStatus", methods::get_l2_to_l1_message_status)
 }
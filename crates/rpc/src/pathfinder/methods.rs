@@ -1,5 +1,17 @@
+mod backup_database;
+mod create_block;
+mod get_l1_acceptance;
+mod get_l1_to_l2_message_status;
+mod get_l2_to_l1_message_status;
 mod get_proof;
 mod get_transaction_status;
+mod simulate_transactions;
 
+pub(crate) use backup_database::backup_database;
+pub(crate) use create_block::create_block;
+pub(crate) use get_l1_acceptance::{get_l1_acceptance, L1Acceptance};
+pub(crate) use get_l1_to_l2_message_status::get_l1_to_l2_message_status;
+pub(crate) use get_l2_to_l1_message_status::get_l2_to_l1_message_status;
 pub(crate) use get_proof::get_proof;
 pub(crate) use get_transaction_status::get_transaction_status;
+pub(crate) use simulate_transactions::simulate_transactions;
@@ -0,0 +1,165 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use pathfinder_storage::BackupOptions;
+
+use crate::context::RpcContext;
+
+#[derive(serde::Deserialize, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BackupDatabaseInput {
+    file_name: String,
+}
+
+#[derive(serde::Serialize, Debug, PartialEq, Eq)]
+pub struct BackupDatabaseOutput {
+    path: PathBuf,
+}
+
+crate::error::generate_rpc_error_subset!(BackupDatabaseError:);
+
+/// How often the progress of a backup is logged.
+const PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Writes a consistent backup of the database to `file_name` in the configured backup directory,
+/// while the node keeps running.
+///
+/// Only available if a backup directory has been configured, and only one backup is written at a
+/// time.
+pub async fn backup_database(
+    context: RpcContext,
+    input: BackupDatabaseInput,
+) -> Result<BackupDatabaseOutput, BackupDatabaseError> {
+    let Some(directory) = context.config.backup_directory else {
+        return Err(BackupDatabaseError::Custom(anyhow::anyhow!(
+            "Database backups are disabled, enable them using --rpc.backup-directory"
+        )));
+    };
+
+    // Only plain file names are accepted, so that backups can't be written outside the directory.
+    if Path::new(&input.file_name).file_name() != Some(OsStr::new(&input.file_name)) {
+        return Err(BackupDatabaseError::Custom(anyhow::anyhow!(
+            "Invalid backup file name {:?}",
+            input.file_name
+        )));
+    }
+    let path = directory.join(&input.file_name);
+    if path.exists() {
+        return Err(BackupDatabaseError::Custom(anyhow::anyhow!(
+            "Backup file {} already exists",
+            path.display()
+        )));
+    }
+
+    let Some(guard) = BackupGuard::acquire(context.backup_in_progress.clone()) else {
+        return Err(BackupDatabaseError::Custom(anyhow::anyhow!(
+            "Another database backup is in progress"
+        )));
+    };
+
+    let storage = context.storage.clone();
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        // Released once the backup is done, even if the request itself is dropped.
+        let _guard = guard;
+
+        tracing::info!(path=%path.display(), "Starting database backup");
+        let mut last_logged = Instant::now();
+        storage
+            .backup(&path, BackupOptions::default(), |progress| {
+                if last_logged.elapsed() >= PROGRESS_LOG_INTERVAL {
+                    tracing::info!(
+                        copied=%progress.pages_copied,
+                        total=%progress.pages_total,
+                        "Database backup in progress"
+                    );
+                    last_logged = Instant::now();
+                }
+            })
+            .context("Backing up database")?;
+        tracing::info!(path=%path.display(), "Database backup complete");
+
+        Ok(BackupDatabaseOutput { path })
+    })
+    .await
+    .context("Database backup panic or shutting down")?
+}
+
+/// Marks a backup as in progress until dropped.
+struct BackupGuard(Arc<AtomicBool>);
+
+impl BackupGuard {
+    fn acquire(in_progress: Arc<AtomicBool>) -> Option<Self> {
+        in_progress
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()
+            .map(|_| Self(in_progress))
+    }
+}
+
+impl Drop for BackupGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(file_name: &str) -> BackupDatabaseInput {
+        BackupDatabaseInput {
+            file_name: file_name.to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn disabled_without_backup_directory() {
+        let error = backup_database(RpcContext::for_tests(), input("backup.sqlite"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, BackupDatabaseError::Custom(_)));
+    }
+
+    #[tokio::test]
+    async fn writes_backup_into_directory() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut context = RpcContext::for_tests();
+        context.config.backup_directory = Some(directory.path().to_owned());
+
+        let output = backup_database(context.clone(), input("backup.sqlite"))
+            .await
+            .unwrap();
+        assert_eq!(output.path, directory.path().join("backup.sqlite"));
+        assert!(output.path.exists());
+
+        // Existing backups are not overwritten.
+        let error = backup_database(context.clone(), input("backup.sqlite"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, BackupDatabaseError::Custom(_)));
+
+        // Only one backup runs at a time.
+        let guard = BackupGuard::acquire(context.backup_in_progress.clone()).unwrap();
+        let error = backup_database(context.clone(), input("other.sqlite"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, BackupDatabaseError::Custom(_)));
+        drop(guard);
+        backup_database(context.clone(), input("other.sqlite"))
+            .await
+            .unwrap();
+
+        for file_name in ["../backup.sqlite", "nested/backup.sqlite", "", ".."] {
+            let error = backup_database(context.clone(), input(file_name))
+                .await
+                .unwrap_err();
+            assert!(matches!(error, BackupDatabaseError::Custom(_)));
+        }
+    }
+}
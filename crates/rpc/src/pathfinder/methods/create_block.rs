@@ -0,0 +1,62 @@
+use pathfinder_common::BlockNumber;
+
+use crate::context::RpcContext;
+
+crate::error::generate_rpc_error_subset!(CreateBlockError:);
+
+#[derive(serde::Serialize, Debug, PartialEq, Eq)]
+pub struct CreateBlockOutput {
+    block_number: BlockNumber,
+}
+
+/// Seals the transactions received by the local sequencer into a new block.
+///
+/// Only available when pathfinder is producing its own blocks, i.e. in devnet mode.
+pub async fn create_block(context: RpcContext) -> Result<CreateBlockOutput, CreateBlockError> {
+    let Some(local_sequencer) = context.local_sequencer else {
+        return Err(CreateBlockError::Custom(anyhow::anyhow!(
+            "Blocks can only be created when running with --network devnet"
+        )));
+    };
+
+    let block_number = local_sequencer.create_block().await?;
+
+    Ok(CreateBlockOutput { block_number })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::LocalSequencer;
+    use starknet_gateway_client::GatewayApi;
+    use std::sync::Arc;
+
+    struct FixedSequencer;
+
+    impl GatewayApi for FixedSequencer {}
+
+    #[axum::async_trait]
+    impl LocalSequencer for FixedSequencer {
+        fn gateway(&self) -> &(dyn GatewayApi + Send + Sync) {
+            self
+        }
+
+        async fn create_block(&self) -> anyhow::Result<BlockNumber> {
+            Ok(BlockNumber::new_or_panic(7))
+        }
+    }
+
+    #[tokio::test]
+    async fn seals_via_local_sequencer() {
+        let context = RpcContext::for_tests().with_local_sequencer(Arc::new(FixedSequencer));
+
+        let output = create_block(context).await.unwrap();
+        assert_eq!(output.block_number, BlockNumber::new_or_panic(7));
+    }
+
+    #[tokio::test]
+    async fn unavailable_without_local_sequencer() {
+        let error = create_block(RpcContext::for_tests()).await.unwrap_err();
+        assert!(matches!(error, CreateBlockError::Custom(_)));
+    }
+}
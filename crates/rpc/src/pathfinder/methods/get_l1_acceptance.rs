@@ -0,0 +1,135 @@
+use anyhow::Context;
+use pathfinder_common::{BlockId, BlockNumber};
+use pathfinder_ethereum::StateUpdateLog;
+use primitive_types::H256;
+
+use crate::context::RpcContext;
+
+#[derive(serde::Deserialize, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct GetL1AcceptanceInput {
+    block_id: BlockId,
+}
+
+/// The L1 transaction which accepted a block, by including it in a state update.
+#[derive(serde::Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct L1Acceptance {
+    /// The last block of the state update, which is the requested block or a later one.
+    state_update_block_number: BlockNumber,
+    l1_block_number: u64,
+    l1_transaction_hash: H256,
+}
+
+impl From<StateUpdateLog> for L1Acceptance {
+    fn from(log: StateUpdateLog) -> Self {
+        Self {
+            state_update_block_number: log.update.block_number,
+            l1_block_number: log.l1_block_number,
+            l1_transaction_hash: log.l1_transaction_hash,
+        }
+    }
+}
+
+crate::error::generate_rpc_error_subset!(GetL1AcceptanceError: BlockNotFound);
+
+/// Returns the L1 transaction which accepted the block, or `null` if the block has not been
+/// accepted on L1 yet or was accepted before the first state update indexed from the core
+/// contract's logs.
+pub async fn get_l1_acceptance(
+    context: RpcContext,
+    input: GetL1AcceptanceInput,
+) -> Result<Option<L1Acceptance>, GetL1AcceptanceError> {
+    let block_id = match input.block_id {
+        BlockId::Pending => return Ok(None),
+        other => other.try_into().expect("Only pending cast should fail"),
+    };
+
+    let storage = context.storage.clone();
+    let span = tracing::Span::current();
+
+    tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = storage
+            .connection()
+            .context("Opening database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let (number, _) = tx
+            .block_id(block_id)
+            .context("Fetching block number")?
+            .ok_or(GetL1AcceptanceError::BlockNotFound)?;
+
+        let acceptance = tx
+            .l1_acceptance(number)
+            .context("Fetching L1 acceptance")?
+            .map(Into::into);
+
+        Ok(acceptance)
+    })
+    .await
+    .context("Database read panic or shutting down")?
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_ethereum::EthereumStateUpdate;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn accepted_by_logged_state_update() {
+        let context = RpcContext::for_tests();
+
+        let log = StateUpdateLog {
+            update: EthereumStateUpdate {
+                state_root: state_commitment_bytes!(b"state root 1"),
+                block_number: BlockNumber::new_or_panic(1),
+                block_hash: block_hash_bytes!(b"block 1"),
+            },
+            l1_block_number: 100,
+            l1_transaction_hash: H256::from_low_u64_be(0xabcd),
+        };
+        let mut db = context.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        tx.insert_state_update_log(&log).unwrap();
+        tx.commit().unwrap();
+
+        let input = GetL1AcceptanceInput {
+            block_id: BlockId::Number(BlockNumber::new_or_panic(1)),
+        };
+        let result = get_l1_acceptance(context.clone(), input).await.unwrap();
+        assert_eq!(
+            result,
+            Some(L1Acceptance {
+                state_update_block_number: BlockNumber::new_or_panic(1),
+                l1_block_number: 100,
+                l1_transaction_hash: H256::from_low_u64_be(0xabcd),
+            })
+        );
+
+        // Accepted by an update which is not logged.
+        let input = GetL1AcceptanceInput {
+            block_id: BlockId::Number(BlockNumber::GENESIS),
+        };
+        let result = get_l1_acceptance(context.clone(), input).await.unwrap();
+        assert_eq!(result, None);
+
+        // Not accepted yet.
+        let input = GetL1AcceptanceInput {
+            block_id: BlockId::Latest,
+        };
+        let result = get_l1_acceptance(context, input).await.unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[tokio::test]
+    async fn block_not_found() {
+        let context = RpcContext::for_tests();
+        let input = GetL1AcceptanceInput {
+            block_id: BlockId::Number(BlockNumber::new_or_panic(9999)),
+        };
+        let error = get_l1_acceptance(context, input).await.unwrap_err();
+        assert_matches::assert_matches!(error, GetL1AcceptanceError::BlockNotFound);
+    }
+}
@@ -0,0 +1,311 @@
+use anyhow::Context;
+use pathfinder_common::TransactionHash;
+use primitive_types::H256;
+use starknet_gateway_types::reply::transaction::Transaction;
+use starknet_gateway_types::reply::PendingBlock;
+
+use crate::context::RpcContext;
+use crate::v06::method::get_transaction_receipt::types::{ExecutionStatus, FinalityStatus};
+
+#[derive(serde::Deserialize, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct GetL1ToL2MessageStatusInput {
+    l1_transaction_hash: H256,
+}
+
+/// The status of a message sent to L2, and of the L1 handler transaction which consumed it.
+#[derive(serde::Serialize, Debug, PartialEq, Eq)]
+pub struct L1ToL2MessageStatus {
+    message_hash: H256,
+    /// `None` until the L1 handler transaction has been included in an L2 block.
+    l2_transaction_hash: Option<TransactionHash>,
+    /// `None` if the L1 handler transaction is not included or is no longer stored.
+    finality_status: Option<FinalityStatus>,
+    execution_status: Option<ExecutionStatus>,
+    /// Whether the message may have been consumed by an L1 handler transaction of a block synced
+    /// before messages were tracked, in which case it is not known which.
+    consumption_unknown: bool,
+}
+
+crate::error::generate_rpc_error_subset!(GetL1ToL2MessageStatusError:);
+
+/// Returns the status of every message sent to L2 by the L1 transaction. The result is empty if
+/// the transaction sent no messages, or if its L1 block has not been finalized and synced yet.
+pub async fn get_l1_to_l2_message_status(
+    context: RpcContext,
+    input: GetL1ToL2MessageStatusInput,
+) -> Result<Vec<L1ToL2MessageStatus>, GetL1ToL2MessageStatusError> {
+    let span = tracing::Span::current();
+
+    tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let messages = tx
+            .l1_to_l2_messages(input.l1_transaction_hash)
+            .context("Fetching messages")?;
+        if messages.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let pending = context
+            .pending_data
+            .get(&tx)
+            .context("Querying pending data")?;
+
+        let mut statuses = Vec::with_capacity(messages.len());
+        for message in messages {
+            let status = match message.l2_transaction_hash {
+                Some(transaction_hash) => {
+                    match tx
+                        .transaction_with_receipt(transaction_hash)
+                        .context("Fetching receipt from database")?
+                    {
+                        Some((_, receipt, block_hash)) => {
+                            let l1_accepted = tx
+                                .block_is_l1_accepted(block_hash.into())
+                                .context("Querying block's status")?;
+                            let finality_status = if l1_accepted {
+                                FinalityStatus::AcceptedOnL1
+                            } else {
+                                FinalityStatus::AcceptedOnL2
+                            };
+
+                            L1ToL2MessageStatus {
+                                message_hash: message.message_hash,
+                                l2_transaction_hash: Some(transaction_hash),
+                                finality_status: Some(finality_status),
+                                execution_status: Some(receipt.execution_status.into()),
+                                consumption_unknown: false,
+                            }
+                        }
+                        // The block body has been pruned.
+                        None => L1ToL2MessageStatus {
+                            message_hash: message.message_hash,
+                            l2_transaction_hash: Some(transaction_hash),
+                            finality_status: None,
+                            execution_status: None,
+                            consumption_unknown: false,
+                        },
+                    }
+                }
+                None => {
+                    let mut status = pending_status(&pending.block, message.message_hash);
+                    status.consumption_unknown =
+                        status.l2_transaction_hash.is_none() && message.consumption_unknown;
+                    status
+                }
+            };
+            statuses.push(status);
+        }
+
+        Ok(statuses)
+    })
+    .await
+    .context("Database read panic or shutting down")?
+}
+
+/// The pending block is not stored, so its L1 handler transactions are not linked to their
+/// messages in the database.
+fn pending_status(pending: &PendingBlock, message_hash: H256) -> L1ToL2MessageStatus {
+    let receipt = pending
+        .transactions
+        .iter()
+        .zip(&pending.transaction_receipts)
+        .find_map(|(transaction, receipt)| match transaction {
+            Transaction::L1Handler(l1_handler)
+                if l1_handler.calculate_message_hash() == message_hash =>
+            {
+                Some(receipt)
+            }
+            _ => None,
+        });
+
+    L1ToL2MessageStatus {
+        message_hash,
+        l2_transaction_hash: receipt.map(|receipt| receipt.transaction_hash),
+        finality_status: receipt.map(|_| FinalityStatus::AcceptedOnL2),
+        execution_status: receipt.map(|receipt| receipt.execution_status.clone().into()),
+        consumption_unknown: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::{
+        BlockHash, BlockHeader, BlockNumber, TransactionIndex, TransactionNonce, TransactionVersion,
+    };
+    use pathfinder_crypto::Felt;
+    use pathfinder_ethereum::MessageToL2Log;
+    use starknet_gateway_types::reply::transaction::{
+        ExecutionStatus as GatewayExecutionStatus, L1HandlerTransaction, Receipt,
+    };
+
+    use super::*;
+
+    fn l1_handler(nonce: u64) -> L1HandlerTransaction {
+        L1HandlerTransaction {
+            contract_address: contract_address_bytes!(b"l2 contract"),
+            entry_point_selector: entry_point_bytes!(b"handler"),
+            nonce: TransactionNonce(Felt::from_u64(nonce)),
+            calldata: vec![
+                call_param_bytes!(b"l1 sender"),
+                call_param_bytes!(b"amount"),
+            ],
+            transaction_hash: TransactionHash(Felt::from_u64(nonce)),
+            version: TransactionVersion::ZERO,
+        }
+    }
+
+    fn receipt(
+        transaction_hash: TransactionHash,
+        execution_status: GatewayExecutionStatus,
+    ) -> Receipt {
+        Receipt {
+            actual_fee: None,
+            events: vec![],
+            execution_resources: None,
+            l1_to_l2_consumed_message: None,
+            l2_to_l1_messages: vec![],
+            transaction_hash,
+            transaction_index: TransactionIndex::new_or_panic(0),
+            execution_status,
+            revert_error: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn included_and_not_included_messages() {
+        let context = RpcContext::for_tests();
+        let l1_transaction_hash = H256::from_low_u64_be(0xabcd);
+
+        let included = l1_handler(1);
+        let not_included = l1_handler(2);
+
+        let mut db = context.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        let header = BlockHeader::builder()
+            .with_number(BlockNumber::new_or_panic(1000))
+            .finalize_with_hash(block_hash_bytes!(b"block 1000"));
+        tx.insert_block_header(&header).unwrap();
+        tx.insert_transaction_data(
+            header.hash,
+            header.number,
+            &[(
+                Transaction::L1Handler(included.clone()),
+                receipt(included.transaction_hash, GatewayExecutionStatus::Reverted),
+            )],
+        )
+        .unwrap();
+        for message in [&included, &not_included] {
+            tx.insert_message_to_l2_log(&MessageToL2Log {
+                message_hash: message.calculate_message_hash(),
+                l1_block_number: 100,
+                l1_transaction_hash,
+            })
+            .unwrap();
+        }
+        tx.commit().unwrap();
+
+        let input = GetL1ToL2MessageStatusInput {
+            l1_transaction_hash,
+        };
+        let mut result = get_l1_to_l2_message_status(context.clone(), input)
+            .await
+            .unwrap();
+        result.sort_by_key(|status| status.l2_transaction_hash.is_none());
+        assert_eq!(
+            result,
+            vec![
+                L1ToL2MessageStatus {
+                    message_hash: included.calculate_message_hash(),
+                    l2_transaction_hash: Some(included.transaction_hash),
+                    finality_status: Some(FinalityStatus::AcceptedOnL2),
+                    execution_status: Some(ExecutionStatus::Reverted),
+                    consumption_unknown: false,
+                },
+                L1ToL2MessageStatus {
+                    message_hash: not_included.calculate_message_hash(),
+                    l2_transaction_hash: None,
+                    finality_status: None,
+                    execution_status: None,
+                    consumption_unknown: false,
+                },
+            ]
+        );
+
+        let input = GetL1ToL2MessageStatusInput {
+            l1_transaction_hash: H256::from_low_u64_be(0xffff),
+        };
+        let result = get_l1_to_l2_message_status(context, input).await.unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[tokio::test]
+    async fn consumed_before_messages_were_tracked() {
+        let context = RpcContext::for_tests();
+        let l1_transaction_hash = H256::from_low_u64_be(0xabcd);
+        let message_hash = l1_handler(1).calculate_message_hash();
+
+        let mut db = context.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        tx.insert_message_to_l2_log(&MessageToL2Log {
+            message_hash,
+            l1_block_number: 100,
+            l1_transaction_hash,
+        })
+        .unwrap();
+        tx.set_first_block_with_l1_handler_links(BlockNumber::new_or_panic(1))
+            .unwrap();
+        tx.commit().unwrap();
+
+        let input = GetL1ToL2MessageStatusInput {
+            l1_transaction_hash,
+        };
+        let result = get_l1_to_l2_message_status(context, input).await.unwrap();
+        assert_eq!(
+            result,
+            vec![L1ToL2MessageStatus {
+                message_hash,
+                l2_transaction_hash: None,
+                finality_status: None,
+                execution_status: None,
+                consumption_unknown: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn pending_l1_handler() {
+        let l1_handler = l1_handler(3);
+        let pending = PendingBlock {
+            parent_hash: BlockHash::ZERO,
+            transactions: vec![Transaction::L1Handler(l1_handler.clone())],
+            transaction_receipts: vec![receipt(
+                l1_handler.transaction_hash,
+                GatewayExecutionStatus::Succeeded,
+            )],
+            ..Default::default()
+        };
+
+        let status = pending_status(&pending, l1_handler.calculate_message_hash());
+        assert_eq!(
+            status,
+            L1ToL2MessageStatus {
+                message_hash: l1_handler.calculate_message_hash(),
+                l2_transaction_hash: Some(l1_handler.transaction_hash),
+                finality_status: Some(FinalityStatus::AcceptedOnL2),
+                execution_status: Some(ExecutionStatus::Succeeded),
+                consumption_unknown: false,
+            }
+        );
+
+        let status = pending_status(&pending, H256::zero());
+        assert_eq!(status.l2_transaction_hash, None);
+    }
+}
@@ -0,0 +1,317 @@
+use anyhow::Context;
+use pathfinder_common::TransactionHash;
+use primitive_types::H256;
+use starknet_gateway_types::reply::PendingBlock;
+
+use crate::context::RpcContext;
+
+#[derive(serde::Deserialize, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct GetL2ToL1MessageStatusInput {
+    message_hash: H256,
+}
+
+/// The status of a message sent to L1 by an L2 transaction.
+#[derive(serde::Serialize, Debug, PartialEq, Eq)]
+pub struct L2ToL1MessageStatus {
+    l2_transaction_hash: TransactionHash,
+    status: MessageStatus,
+    /// The L1 transaction which consumed the message.
+    l1_transaction_hash: Option<H256>,
+}
+
+#[derive(serde::Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MessageStatus {
+    /// The block of the transaction has not been accepted on L1, so the message cannot be
+    /// consumed yet.
+    PendingL1Acceptance,
+    ReadyToConsume,
+    Consumed,
+}
+
+crate::error::generate_rpc_error_subset!(GetL2ToL1MessageStatusError:);
+
+/// Returns the status of every message with the given hash, in the order they were sent.
+///
+/// The core contract only counts how many times a message was sent and consumed, so identical
+/// messages are considered consumed in the order they were sent. Consumptions logged before the
+/// first indexed message was accepted on L1 consumed messages which are not indexed, because they
+/// were sent by transactions stored before messages were tracked, and are skipped.
+pub async fn get_l2_to_l1_message_status(
+    context: RpcContext,
+    input: GetL2ToL1MessageStatusInput,
+) -> Result<Vec<L2ToL1MessageStatus>, GetL2ToL1MessageStatusError> {
+    let span = tracing::Span::current();
+
+    tokio::task::spawn_blocking(move || {
+        let _g = span.enter();
+        let mut db = context
+            .storage
+            .connection()
+            .context("Opening database connection")?;
+        let tx = db.transaction().context("Creating database transaction")?;
+
+        let messages = tx
+            .l2_to_l1_messages(input.message_hash)
+            .context("Fetching messages")?;
+        let first_accepted_at = match messages.first() {
+            Some(first) => tx
+                .l1_acceptance(first.block_number)
+                .context("Fetching L1 acceptance")?
+                .map(|log| log.l1_block_number),
+            None => None,
+        };
+        let mut consumptions = tx
+            .l2_to_l1_message_consumptions(input.message_hash)
+            .context("Fetching message consumptions")?
+            .into_iter()
+            .filter(|consumption| {
+                matches!(first_accepted_at, Some(accepted_at) if consumption.l1_block_number >= accepted_at)
+            });
+
+        let mut statuses = Vec::with_capacity(messages.len());
+        for message in messages {
+            let status = match consumptions.next() {
+                Some(consumption) => L2ToL1MessageStatus {
+                    l2_transaction_hash: message.transaction_hash,
+                    status: MessageStatus::Consumed,
+                    l1_transaction_hash: Some(consumption.l1_transaction_hash),
+                },
+                None => {
+                    let l1_accepted = tx
+                        .block_is_l1_accepted(message.block_number.into())
+                        .context("Querying block's status")?;
+                    let status = if l1_accepted {
+                        MessageStatus::ReadyToConsume
+                    } else {
+                        MessageStatus::PendingL1Acceptance
+                    };
+
+                    L2ToL1MessageStatus {
+                        l2_transaction_hash: message.transaction_hash,
+                        status,
+                        l1_transaction_hash: None,
+                    }
+                }
+            };
+            statuses.push(status);
+        }
+
+        let pending = context
+            .pending_data
+            .get(&tx)
+            .context("Querying pending data")?;
+        statuses.extend(pending_statuses(&pending.block, input.message_hash));
+
+        Ok(statuses)
+    })
+    .await
+    .context("Database read panic or shutting down")?
+}
+
+/// The pending block is not stored, so its messages are not indexed in the database.
+fn pending_statuses(
+    pending: &PendingBlock,
+    message_hash: H256,
+) -> impl Iterator<Item = L2ToL1MessageStatus> + '_ {
+    pending
+        .transaction_receipts
+        .iter()
+        .flat_map(|receipt| {
+            receipt
+                .l2_to_l1_messages
+                .iter()
+                .map(|message| (receipt.transaction_hash, message))
+        })
+        .filter(move |(_, message)| message.calculate_message_hash() == message_hash)
+        .map(|(transaction_hash, _)| L2ToL1MessageStatus {
+            l2_transaction_hash: transaction_hash,
+            status: MessageStatus::PendingL1Acceptance,
+            l1_transaction_hash: None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::{
+        BlockHash, BlockHeader, BlockNumber, EthereumAddress, TransactionIndex,
+    };
+    use pathfinder_crypto::Felt;
+    use pathfinder_ethereum::{ConsumedMessageToL1Log, EthereumStateUpdate, StateUpdateLog};
+    use primitive_types::H160;
+    use starknet_gateway_types::reply::transaction::{
+        ExecutionStatus, InvokeTransaction, InvokeTransactionV1, L2ToL1Message, Receipt,
+        Transaction,
+    };
+
+    use super::*;
+
+    fn message() -> L2ToL1Message {
+        L2ToL1Message {
+            from_address: contract_address_bytes!(b"l2 contract"),
+            payload: vec![l2_to_l1_message_payload_elem_bytes!(b"amount")],
+            to_address: EthereumAddress(H160::from_low_u64_be(0xabcd)),
+        }
+    }
+
+    fn transaction_with_message(hash: u64) -> (Transaction, Receipt) {
+        let transaction_hash = TransactionHash(Felt::from_u64(hash));
+        let transaction = Transaction::Invoke(InvokeTransaction::V1(InvokeTransactionV1 {
+            calldata: vec![],
+            sender_address: contract_address_bytes!(b"l2 contract"),
+            max_fee: fee_bytes!(b"max fee"),
+            signature: vec![],
+            nonce: transaction_nonce_bytes!(b"nonce"),
+            transaction_hash,
+        }));
+        let receipt = Receipt {
+            actual_fee: None,
+            events: vec![],
+            execution_resources: None,
+            l1_to_l2_consumed_message: None,
+            l2_to_l1_messages: vec![message()],
+            transaction_hash,
+            transaction_index: TransactionIndex::new_or_panic(0),
+            execution_status: ExecutionStatus::Succeeded,
+            revert_error: None,
+        };
+
+        (transaction, receipt)
+    }
+
+    /// Stores three blocks sending the message, the first two of which are accepted on L1 in
+    /// L1 block 150.
+    fn insert_blocks(tx: &pathfinder_storage::Transaction<'_>) {
+        let mut parent = BlockHeader::builder()
+            .with_number(BlockNumber::new_or_panic(999))
+            .finalize_with_hash(block_hash_bytes!(b"block 999"));
+        for number in 1000..1003 {
+            let header = parent
+                .child_builder()
+                .finalize_with_hash(BlockHash(Felt::from_u64(number)));
+            tx.insert_block_header(&header).unwrap();
+            tx.insert_transaction_data(
+                header.hash,
+                header.number,
+                &[transaction_with_message(number)],
+            )
+            .unwrap();
+            parent = header;
+        }
+        for (block_number, l1_block_number) in [(999, 50), (1001, 150)] {
+            tx.insert_state_update_log(&StateUpdateLog {
+                update: EthereumStateUpdate {
+                    block_number: BlockNumber::new_or_panic(block_number),
+                    ..Default::default()
+                },
+                l1_block_number,
+                l1_transaction_hash: H256::from_low_u64_be(l1_block_number),
+            })
+            .unwrap();
+        }
+        tx.update_l1_l2_pointer(Some(BlockNumber::new_or_panic(1001)))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn consumed_in_the_order_sent() {
+        let context = RpcContext::for_tests();
+        let message_hash = message().calculate_message_hash();
+
+        let mut db = context.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        insert_blocks(&tx);
+        // The message was consumed once.
+        tx.insert_consumed_message_to_l1_log(&ConsumedMessageToL1Log {
+            message_hash,
+            l1_block_number: 200,
+            l1_log_index: 0,
+            l1_transaction_hash: H256::from_low_u64_be(200),
+        })
+        .unwrap();
+        tx.commit().unwrap();
+
+        let input = GetL2ToL1MessageStatusInput { message_hash };
+        let result = get_l2_to_l1_message_status(context, input).await.unwrap();
+        assert_eq!(
+            result,
+            vec![
+                L2ToL1MessageStatus {
+                    l2_transaction_hash: TransactionHash(Felt::from_u64(1000)),
+                    status: MessageStatus::Consumed,
+                    l1_transaction_hash: Some(H256::from_low_u64_be(200)),
+                },
+                L2ToL1MessageStatus {
+                    l2_transaction_hash: TransactionHash(Felt::from_u64(1001)),
+                    status: MessageStatus::ReadyToConsume,
+                    l1_transaction_hash: None,
+                },
+                L2ToL1MessageStatus {
+                    l2_transaction_hash: TransactionHash(Felt::from_u64(1002)),
+                    status: MessageStatus::PendingL1Acceptance,
+                    l1_transaction_hash: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn consumption_of_unindexed_message_is_skipped() {
+        let context = RpcContext::for_tests();
+        let message_hash = message().calculate_message_hash();
+
+        let mut db = context.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        insert_blocks(&tx);
+        // Consumes a message sent before messages were indexed, since none of the indexed ones
+        // were accepted on L1 yet.
+        tx.insert_consumed_message_to_l1_log(&ConsumedMessageToL1Log {
+            message_hash,
+            l1_block_number: 100,
+            l1_log_index: 0,
+            l1_transaction_hash: H256::from_low_u64_be(100),
+        })
+        .unwrap();
+        tx.commit().unwrap();
+
+        let input = GetL2ToL1MessageStatusInput { message_hash };
+        let result = get_l2_to_l1_message_status(context, input).await.unwrap();
+        let statuses = result
+            .iter()
+            .map(|status| &status.status)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                &MessageStatus::ReadyToConsume,
+                &MessageStatus::ReadyToConsume,
+                &MessageStatus::PendingL1Acceptance,
+            ]
+        );
+    }
+
+    #[test]
+    fn pending_messages() {
+        let (transaction, receipt) = transaction_with_message(1);
+        let pending = PendingBlock {
+            transactions: vec![transaction],
+            transaction_receipts: vec![receipt],
+            ..Default::default()
+        };
+
+        let statuses =
+            pending_statuses(&pending, message().calculate_message_hash()).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![L2ToL1MessageStatus {
+                l2_transaction_hash: TransactionHash(Felt::from_u64(1)),
+                status: MessageStatus::PendingL1Acceptance,
+                l1_transaction_hash: None,
+            }]
+        );
+
+        assert_eq!(pending_statuses(&pending, H256::zero()).count(), 0);
+    }
+}
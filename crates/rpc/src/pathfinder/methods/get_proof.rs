@@ -21,6 +21,7 @@ pub enum GetProofError {
     Internal(anyhow::Error),
     BlockNotFound,
     ProofLimitExceeded { limit: u32, requested: u32 },
+    TriePruned,
 }
 
 impl From<anyhow::Error> for GetProofError {
@@ -36,6 +37,7 @@ impl From<GetProofError> for crate::error::ApplicationError {
                 Self::ProofLimitExceeded { limit, requested }
             }
             GetProofError::BlockNotFound => Self::BlockNotFound,
+            GetProofError::TriePruned => Self::TriePruned,
             GetProofError::Internal(internal) => Self::Internal(internal),
         }
     }
@@ -180,6 +182,14 @@ pub async fn get_proof(
             .context("Fetching block header")?
             .ok_or(GetProofError::BlockNotFound)?;
 
+        // The tries of older blocks may have been pruned, in which case no proof can be created.
+        if tx
+            .trie_pruned(header.number)
+            .context("Querying trie pruning")?
+        {
+            return Err(GetProofError::TriePruned);
+        }
+
         let state_commitment = match header.state_commitment {
             StateCommitment::ZERO => None,
             other => Some(other),
@@ -278,4 +288,33 @@ mod tests {
         let err = get_proof(context, input).await.unwrap_err();
         assert_matches::assert_matches!(err, GetProofError::ProofLimitExceeded { .. });
     }
+
+    #[tokio::test]
+    async fn pruned_tries() {
+        let storage = pathfinder_storage::Storage::in_memory_with_trie_pruning(
+            pathfinder_storage::TriePruneMode::Prune { num_blocks_kept: 0 },
+        )
+        .unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        for number in 0..2 {
+            let header = BlockHeader::builder()
+                .with_number(BlockNumber::new_or_panic(number))
+                .finalize_with_hash(BlockHash(Felt::from_u64(number)));
+            tx.insert_block_header(&header).unwrap();
+            tx.insert_storage_root(header.number, None).unwrap();
+        }
+        tx.prune_tries(BlockNumber::GENESIS + 1).unwrap();
+        tx.commit().unwrap();
+
+        let context = RpcContext::for_tests().with_storage(storage);
+        let input = GetProofInput {
+            block_id: BlockId::Number(BlockNumber::GENESIS),
+            contract_address: contract_address!("0xdeadbeef"),
+            keys: vec![],
+        };
+
+        let err = get_proof(context, input).await.unwrap_err();
+        assert_matches::assert_matches!(err, GetProofError::TriePruned);
+    }
 }
@@ -65,9 +65,8 @@ pub async fn get_transaction_status(
     }
 
     // Check gateway for rejected transactions.
-    use starknet_gateway_client::GatewayApi;
     context
-        .sequencer
+        .gateway()
         .transaction(input.transaction_hash)
         .await
         .context("Fetching transaction from gateway")
@@ -0,0 +1,191 @@
+use pathfinder_common::{BlockId, ContractAddress};
+use serde_with::serde_as;
+
+use crate::context::RpcContext;
+use crate::v02::types::request::BroadcastedTransaction;
+use crate::v06::method::simulate_transactions::{
+    dto, simulate_transactions_impl, SimulateTransactionError, SimulateTransactionInput,
+    SimulateTransactionOutput, SimulationCheats,
+};
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SimulateTransactionsInput {
+    block_id: BlockId,
+    transactions: Vec<BroadcastedTransaction>,
+    simulation_flags: dto::SimulationFlags,
+    /// Accounts whose `__validate__` is skipped, so that their transactions can be simulated
+    /// without a valid signature.
+    #[serde(default)]
+    impersonated_accounts: Vec<ContractAddress>,
+    #[serde(default)]
+    balance_overrides: Vec<BalanceOverride>,
+}
+
+/// Sets the balance of an address in both fee tokens.
+#[serde_as]
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BalanceOverride {
+    address: ContractAddress,
+    #[serde_as(as = "pathfinder_serde::U256AsHexStr")]
+    balance: primitive_types::U256,
+}
+
+/// `starknet_simulateTransactions` with additional options for acting as arbitrary accounts.
+pub async fn simulate_transactions(
+    context: RpcContext,
+    input: SimulateTransactionsInput,
+) -> Result<SimulateTransactionOutput, SimulateTransactionError> {
+    let cheats = SimulationCheats {
+        impersonated_accounts: input.impersonated_accounts,
+        balances: input
+            .balance_overrides
+            .into_iter()
+            .map(|o| (o.address, o.balance))
+            .collect(),
+    };
+    let input = SimulateTransactionInput {
+        block_id: input.block_id,
+        transactions: input.transactions,
+        simulation_flags: input.simulation_flags,
+    };
+
+    simulate_transactions_impl(context, input, cheats).await
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::TransactionVersion;
+    use serde::Deserialize;
+    use starknet_gateway_test_fixtures::class_definitions::DUMMY_ACCOUNT_CLASS_HASH;
+
+    use super::*;
+
+    fn input(impersonated_accounts: serde_json::Value) -> SimulateTransactionsInput {
+        let json = serde_json::json!({
+            "block_id": {"block_number": 1},
+            "transactions": [
+                {
+                    "contract_address_salt": "0x46c0d4abf0192a788aca261e58d7031576f7d8ea5229f452b0f23e691dd5971",
+                    "max_fee": "0x0",
+                    "signature": [],
+                    "class_hash": DUMMY_ACCOUNT_CLASS_HASH,
+                    "nonce": "0x0",
+                    "version": TransactionVersion::ONE_WITH_QUERY_VERSION,
+                    "constructor_calldata": [],
+                    "type": "DEPLOY_ACCOUNT"
+                }
+            ],
+            "simulation_flags": ["SKIP_FEE_CHARGE"],
+            "impersonated_accounts": impersonated_accounts,
+            "balance_overrides": [
+                {"address": "0x798c1bfdaf2077f4900e37c8815affa8d217d46db8a84c3fba1838c8bd4a65", "balance": "0x1000"}
+            ]
+        });
+        SimulateTransactionsInput::deserialize(&json).unwrap()
+    }
+
+    /// Deploys the account at 0x798c..65, which holds no fee tokens, and charges it the fee.
+    fn fee_charging_input(balance_overrides: serde_json::Value) -> SimulateTransactionsInput {
+        let json = serde_json::json!({
+            "block_id": {"block_number": 1},
+            "transactions": [
+                {
+                    "contract_address_salt": "0x46c0d4abf0192a788aca261e58d7031576f7d8ea5229f452b0f23e691dd5971",
+                    "max_fee": "0x100000",
+                    "signature": [],
+                    "class_hash": DUMMY_ACCOUNT_CLASS_HASH,
+                    "nonce": "0x0",
+                    "version": TransactionVersion::ONE_WITH_QUERY_VERSION,
+                    "constructor_calldata": [],
+                    "type": "DEPLOY_ACCOUNT"
+                }
+            ],
+            "simulation_flags": [],
+            "balance_overrides": balance_overrides
+        });
+        SimulateTransactionsInput::deserialize(&json).unwrap()
+    }
+
+    fn validate_invocation(output: SimulateTransactionOutput) -> Option<dto::FunctionInvocation> {
+        match output.0.into_iter().next().unwrap().transaction_trace {
+            dto::TransactionTrace::DeployAccount(trace) => trace.validate_invocation,
+            other => panic!("Unexpected trace {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn impersonated_account_is_not_validated() {
+        let (context, _, _, _) = crate::test_setup::test_context().await;
+
+        let output = simulate_transactions(
+            context,
+            input(serde_json::json!([
+                "0x798c1bfdaf2077f4900e37c8815affa8d217d46db8a84c3fba1838c8bd4a65"
+            ])),
+        )
+        .await
+        .unwrap();
+        assert_eq!(validate_invocation(output), None);
+    }
+
+    #[tokio::test]
+    async fn other_accounts_are_validated() {
+        let (context, _, _, _) = crate::test_setup::test_context().await;
+
+        let output = simulate_transactions(context, input(serde_json::json!(["0x123"])))
+            .await
+            .unwrap();
+        assert!(validate_invocation(output).is_some());
+    }
+
+    #[tokio::test]
+    async fn fee_cannot_be_paid_without_balance_override() {
+        let (context, _, _, _) = crate::test_setup::test_context().await;
+
+        let error = simulate_transactions(context, fee_charging_input(serde_json::json!([])))
+            .await
+            .unwrap_err();
+        assert_matches::assert_matches!(
+            error,
+            SimulateTransactionError::TransactionExecutionError {
+                transaction_index: 0,
+                ..
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn balance_override_pays_the_fee() {
+        let (context, _, _, _) = crate::test_setup::test_context().await;
+
+        let output = simulate_transactions(
+            context,
+            fee_charging_input(serde_json::json!([
+                {"address": "0x798c1bfdaf2077f4900e37c8815affa8d217d46db8a84c3fba1838c8bd4a65", "balance": "0x1000000"}
+            ])),
+        )
+        .await
+        .unwrap();
+
+        let simulation = output.0.into_iter().next().unwrap();
+        match simulation.transaction_trace {
+            dto::TransactionTrace::DeployAccount(trace) => {
+                assert!(trace.fee_transfer_invocation.is_some())
+            }
+            other => panic!("Unexpected trace {other:?}"),
+        }
+    }
+
+    #[test]
+    fn balance_override_is_parsed() {
+        let input = input(serde_json::json!([]));
+        assert_eq!(
+            input.balance_overrides[0].address,
+            contract_address!("0x798c1bfdaf2077f4900e37c8815affa8d217d46db8a84c3fba1838c8bd4a65")
+        );
+        assert_eq!(input.balance_overrides[0].balance, 0x1000.into());
+    }
+}
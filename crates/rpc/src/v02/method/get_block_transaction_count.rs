@@ -10,7 +10,7 @@ pub struct GetBlockTransactionCountInput {
 
 type BlockTransactionCount = u64;
 
-crate::error::generate_rpc_error_subset!(GetBlockTransactionCountError: BlockNotFound);
+crate::error::generate_rpc_error_subset!(GetBlockTransactionCountError: BlockNotFound, BlockPruned);
 
 pub async fn get_block_transaction_count(
     context: RpcContext,
@@ -44,16 +44,21 @@ pub async fn get_block_transaction_count(
             .transaction_count(block_id)
             .context("Reading transaction count from database")?;
 
-        // Check if the value was 0 because there were no transactions, or because the block hash is invalid.
+        // Check if the value was 0 because there were no transactions, because the block hash is invalid
+        // or because the block's transactions have been pruned.
         if block_transaction_count == 0 {
             let header = tx
                 .block_header(block_id)
-                .context("Querying block existence")?;
-
-            return if header.is_some() {
-                Ok(0)
+                .context("Querying block existence")?
+                .ok_or(GetBlockTransactionCountError::BlockNotFound)?;
+
+            return if tx
+                .block_body_pruned(header.number)
+                .context("Querying block body pruning")?
+            {
+                Err(GetBlockTransactionCountError::BlockPruned)
             } else {
-                Err(GetBlockTransactionCountError::BlockNotFound)
+                Ok(0)
             };
         }
         Ok(block_transaction_count as BlockTransactionCount)
@@ -12,7 +12,8 @@ pub struct GetTransactionByBlockIdAndIndexInput {
 
 crate::error::generate_rpc_error_subset!(
     GetTransactionByBlockIdAndIndexError: BlockNotFound,
-    InvalidTxnIndex
+    InvalidTxnIndex,
+    BlockPruned
 );
 
 pub async fn get_transaction_by_block_id_and_index_impl(
@@ -60,14 +61,19 @@ pub async fn get_transaction_by_block_id_and_index_impl(
             Some(transaction) => Ok(transaction),
             None => {
                 // We now need to check whether it was the block hash or transaction index which were invalid. We do this by checking if the block exists
-                // at all. If no, then the block hash is invalid. If yes, then the index is invalid.
-                let block_exists = db_tx
-                    .block_exists(block_id)
-                    .context("Querying block existence")?;
-                if block_exists {
-                    Err(GetTransactionByBlockIdAndIndexError::InvalidTxnIndex)
+                // at all. If no, then the block hash is invalid. If yes, then the index is invalid unless the block's
+                // transactions have been pruned.
+                let header = db_tx
+                    .block_header(block_id)
+                    .context("Querying block existence")?
+                    .ok_or(GetTransactionByBlockIdAndIndexError::BlockNotFound)?;
+                if db_tx
+                    .block_body_pruned(header.number)
+                    .context("Querying block body pruning")?
+                {
+                    Err(GetTransactionByBlockIdAndIndexError::BlockPruned)
                 } else {
-                    Err(GetTransactionByBlockIdAndIndexError::BlockNotFound)
+                    Err(GetTransactionByBlockIdAndIndexError::InvalidTxnIndex)
                 }
             }
         }
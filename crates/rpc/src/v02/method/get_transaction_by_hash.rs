@@ -10,10 +10,12 @@ pub struct GetTransactionByHashInput {
     transaction_hash: TransactionHash,
 }
 
+crate::error::generate_rpc_error_subset!(GetTransactionByHashError: TxnHashNotFound, BlockPruned);
+
 pub async fn get_transaction_by_hash_impl(
     context: RpcContext,
     input: GetTransactionByHashInput,
-) -> anyhow::Result<Option<GatewayTransaction>> {
+) -> Result<GatewayTransaction, GetTransactionByHashError> {
     let storage = context.storage.clone();
     let span = tracing::Span::current();
 
@@ -36,13 +38,25 @@ pub async fn get_transaction_by_hash_impl(
             .find(|tx| tx.hash() == input.transaction_hash)
             .cloned()
         {
-            return Ok(Some(tx));
+            return Ok(tx);
         }
 
         // Get the transaction from storage.
-        db_tx
+        if let Some(tx) = db_tx
             .transaction(input.transaction_hash)
-            .context("Reading transaction from database")
+            .context("Reading transaction from database")?
+        {
+            return Ok(tx);
+        }
+
+        if db_tx
+            .transaction_body_pruned(input.transaction_hash)
+            .context("Querying whether transaction was pruned")?
+        {
+            return Err(GetTransactionByHashError::BlockPruned);
+        }
+
+        Err(GetTransactionByHashError::TxnHashNotFound)
     });
 
     jh.await.context("Database read panic or shutting down")?
@@ -14,6 +14,7 @@ pub enum GetEventsError {
     Internal(anyhow::Error),
     Custom(anyhow::Error),
     BlockNotFound,
+    BlockPruned,
     PageSizeTooBig,
     InvalidContinuationToken,
     TooManyKeysInFilter { limit: usize, requested: usize },
@@ -31,6 +32,7 @@ impl From<GetEventsError> for crate::error::ApplicationError {
             GetEventsError::Internal(internal) => Self::Internal(internal),
             GetEventsError::Custom(internal) => Self::Custom(internal),
             GetEventsError::BlockNotFound => Self::BlockNotFound,
+            GetEventsError::BlockPruned => Self::BlockPruned,
             GetEventsError::PageSizeTooBig => Self::PageSizeTooBig,
             GetEventsError::InvalidContinuationToken => Self::InvalidContinuationToken,
             GetEventsError::TooManyKeysInFilter { limit, requested } => {
@@ -151,6 +153,7 @@ pub async fn get_events(
         }
 
         let from_block = map_from_block_to_number(&transaction, request.from_block)?;
+        let from_block = skip_pruned_blocks(&transaction, from_block)?;
         let to_block = map_to_block_to_number(&transaction, request.to_block)?;
 
         let (from_block, requested_offset) = match continuation_token {
@@ -357,6 +360,23 @@ fn map_from_block_to_number(
     }
 }
 
+// Events of pruned blocks are no longer available, so an open range starts at the oldest
+// unpruned block instead.
+fn skip_pruned_blocks(
+    tx: &pathfinder_storage::Transaction<'_>,
+    from_block: Option<BlockNumber>,
+) -> Result<Option<BlockNumber>, GetEventsError> {
+    let first_unpruned = tx
+        .first_unpruned_block_body()
+        .context("Querying pruned block bodies")?;
+
+    match from_block {
+        Some(from_block) if from_block < first_unpruned => Err(GetEventsError::BlockPruned),
+        None if first_unpruned != BlockNumber::GENESIS => Ok(Some(first_unpruned)),
+        other => Ok(other),
+    }
+}
+
 /// Append's pending events to `dst` based on the filter requirements and returns
 /// true if this was the last pending data i.e. `is_last_page`.
 fn append_pending_events(
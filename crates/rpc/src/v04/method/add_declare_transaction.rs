@@ -2,7 +2,6 @@ use crate::context::RpcContext;
 use crate::felt::RpcFelt;
 use crate::v02::types::request::BroadcastedDeclareTransaction;
 use pathfinder_common::{ClassHash, TransactionHash};
-use starknet_gateway_client::GatewayApi;
 use starknet_gateway_types::error::SequencerError;
 use starknet_gateway_types::request::add_transaction::{
     CairoContractDefinition, ContractDefinition, SierraContractDefinition,
@@ -156,7 +155,7 @@ pub async fn add_declare_transaction(
                 .map_err(|e| anyhow::anyhow!("Failed to convert contract definition: {}", e))?;
 
             let response = context
-                .sequencer
+                .gateway()
                 .add_declare_transaction(
                     add_transaction::Declare::V1(add_transaction::DeclareV0V1V2 {
                         version: tx.version,
@@ -183,7 +182,7 @@ pub async fn add_declare_transaction(
                 .map_err(|e| anyhow::anyhow!("Failed to convert contract definition: {}", e))?;
 
             let response = context
-                .sequencer
+                .gateway()
                 .add_declare_transaction(
                     add_transaction::Declare::V2(add_transaction::DeclareV0V1V2 {
                         version: tx.version,
@@ -210,7 +209,7 @@ pub async fn add_declare_transaction(
                 .map_err(|e| anyhow::anyhow!("Failed to convert contract definition: {}", e))?;
 
             let response = context
-                .sequencer
+                .gateway()
                 .add_declare_transaction(
                     add_transaction::Declare::V3(add_transaction::DeclareV3 {
                         signature: tx.signature,
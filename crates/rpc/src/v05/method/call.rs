@@ -109,7 +109,9 @@ pub async fn call(context: RpcContext, input: CallInput) -> Result<CallOutput, C
             }
         };
 
-        let state = ExecutionState::simulation(&db, context.chain_id, header, pending);
+        let state = ExecutionState::simulation(&db, context.chain_id, header, pending)
+            .with_fork(context.fork.clone())
+            .with_config(context.execution_config.clone());
 
         let result = pathfinder_executor::call(
             state,
@@ -127,7 +127,9 @@ pub async fn estimate_fee(
             }
         };
 
-        let state = ExecutionState::simulation(&db, context.chain_id, header, pending);
+        let state = ExecutionState::simulation(&db, context.chain_id, header, pending)
+            .with_fork(context.fork.clone())
+            .with_config(context.execution_config.clone());
 
         let transactions = input
             .request
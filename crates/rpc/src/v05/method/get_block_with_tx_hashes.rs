@@ -12,7 +12,7 @@ pub struct GetBlockInput {
     block_id: BlockId,
 }
 
-crate::error::generate_rpc_error_subset!(GetBlockError: BlockNotFound);
+crate::error::generate_rpc_error_subset!(GetBlockError: BlockNotFound, BlockPruned);
 
 /// Get block information with transaction hashes given the block id
 pub async fn get_block_with_tx_hashes(
@@ -51,6 +51,13 @@ pub async fn get_block_with_tx_hashes(
             .context("Reading block from database")?
             .ok_or(GetBlockError::BlockNotFound)?;
 
+        if transaction
+            .block_body_pruned(header.number)
+            .context("Querying block body pruning")?
+        {
+            return Err(GetBlockError::BlockPruned);
+        }
+
         let l1_accepted = transaction.block_is_l1_accepted(header.number.into())?;
         let block_status = if l1_accepted {
             BlockStatus::AcceptedOnL1
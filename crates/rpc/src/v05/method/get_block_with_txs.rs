@@ -13,7 +13,7 @@ pub struct GetBlockInput {
     block_id: BlockId,
 }
 
-crate::error::generate_rpc_error_subset!(GetBlockError: BlockNotFound);
+crate::error::generate_rpc_error_subset!(GetBlockError: BlockNotFound, BlockPruned);
 
 /// Get block information with full transactions given the block id
 pub async fn get_block_with_txs(
@@ -52,6 +52,13 @@ pub async fn get_block_with_txs(
             .context("Reading block from database")?
             .ok_or(GetBlockError::BlockNotFound)?;
 
+        if transaction
+            .block_body_pruned(header.number)
+            .context("Querying block body pruning")?
+        {
+            return Err(GetBlockError::BlockPruned);
+        }
+
         let l1_accepted = transaction.block_is_l1_accepted(header.number.into())?;
         let block_status = if l1_accepted {
             BlockStatus::AcceptedOnL1
@@ -124,7 +124,9 @@ pub async fn simulate_transactions(
         };
 
         let state =
-            pathfinder_executor::ExecutionState::simulation(&db, context.chain_id, header, pending);
+            pathfinder_executor::ExecutionState::simulation(&db, context.chain_id, header, pending)
+                .with_fork(context.fork.clone())
+                .with_config(context.execution_config.clone());
 
         let transactions = input
             .transactions
@@ -35,6 +35,7 @@ pub enum TraceBlockTransactionsError {
     Internal(anyhow::Error),
     Custom(anyhow::Error),
     BlockNotFound,
+    BlockPruned,
     ContractErrorV05 { revert_error: String },
 }
 
@@ -49,6 +50,7 @@ impl From<TraceBlockTransactionsError> for crate::error::ApplicationError {
         match value {
             TraceBlockTransactionsError::Internal(e) => Self::Internal(e),
             TraceBlockTransactionsError::BlockNotFound => Self::BlockNotFound,
+            TraceBlockTransactionsError::BlockPruned => Self::BlockPruned,
             TraceBlockTransactionsError::ContractErrorV05 { revert_error } => {
                 Self::ContractErrorV05 { revert_error }
             }
@@ -169,6 +171,10 @@ pub async fn trace_block_transactions(
                     .block_header(block_id)?
                     .ok_or(TraceBlockTransactionsError::BlockNotFound)?;
 
+                if db.block_body_pruned(header.number)? {
+                    return Err(TraceBlockTransactionsError::BlockPruned);
+                }
+
                 let transactions = db
                     .transactions_for_block(block_id)?
                     .context("Transaction data missing")?;
@@ -205,7 +211,9 @@ pub async fn trace_block_transactions(
             .collect::<Result<Vec<_>, _>>()?;
 
         let hash = header.hash;
-        let state = ExecutionState::trace(&db, context.chain_id, header, None);
+        let state = ExecutionState::trace(&db, context.chain_id, header, None)
+            .with_fork(context.fork.clone())
+            .with_config(context.execution_config.clone());
         let traces = pathfinder_executor::trace(state, cache, hash, transactions, true, true)?;
 
         let result = traces
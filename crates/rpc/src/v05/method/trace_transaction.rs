@@ -72,6 +72,7 @@ impl From<super::trace_block_transactions::TraceBlockTransactionsError> for Trac
         match e {
             Internal(e) => Self::Internal(e),
             BlockNotFound => Self::Custom(anyhow::anyhow!("Block not found")),
+            BlockPruned => Self::Custom(anyhow::anyhow!("Block data has been pruned")),
             ContractErrorV05 { revert_error } => Self::ContractErrorV05 { revert_error },
             Custom(e) => Self::Custom(e),
         }
@@ -156,6 +157,15 @@ pub async fn trace_transaction(
                 .context("Fetching block header")?
                 .context("Block header is missing")?;
 
+            if db
+                .block_body_pruned(header.number)
+                .context("Querying whether block was pruned")?
+            {
+                return Err(TraceTransactionError::Custom(anyhow::anyhow!(
+                    "Block data has been pruned"
+                )));
+            }
+
             let starknet_version = header
                 .starknet_version
                 .parse_as_semver()
@@ -181,7 +191,9 @@ pub async fn trace_transaction(
         };
 
         let hash = header.hash;
-        let state = ExecutionState::trace(&db, context.chain_id, header, None);
+        let state = ExecutionState::trace(&db, context.chain_id, header, None)
+            .with_fork(context.fork.clone())
+            .with_config(context.execution_config.clone());
 
         let transactions = transactions
             .iter()
@@ -4,7 +4,6 @@ use crate::v02::types::request::{
     BroadcastedDeployAccountTransaction, BroadcastedDeployAccountTransactionV0V1,
 };
 use pathfinder_common::{ContractAddress, TransactionHash};
-use starknet_gateway_client::GatewayApi;
 use starknet_gateway_types::error::{KnownStarknetErrorCode, SequencerError};
 
 #[derive(serde::Deserialize, Debug, PartialEq, Eq)]
@@ -123,7 +122,7 @@ pub(crate) async fn add_deploy_account_transaction_impl(
             tx @ BroadcastedDeployAccountTransactionV0V1 { version, .. },
         ) if version.without_query_version() == 0 => {
             context
-                .sequencer
+                .gateway()
                 .add_deploy_account(add_transaction::DeployAccount::V0(
                     add_transaction::DeployAccountV0V1 {
                         max_fee: tx.max_fee,
@@ -140,7 +139,7 @@ pub(crate) async fn add_deploy_account_transaction_impl(
             tx @ BroadcastedDeployAccountTransactionV0V1 { version, .. },
         ) if version.without_query_version() == 1 => {
             context
-                .sequencer
+                .gateway()
                 .add_deploy_account(add_transaction::DeployAccount::V1(
                     add_transaction::DeployAccountV0V1 {
                         max_fee: tx.max_fee,
@@ -161,7 +160,7 @@ pub(crate) async fn add_deploy_account_transaction_impl(
         )),
         BroadcastedDeployAccountTransaction::V3(tx) => {
             context
-                .sequencer
+                .gateway()
                 .add_deploy_account(add_transaction::DeployAccount::V3(
                     add_transaction::DeployAccountV3 {
                         signature: tx.signature,
@@ -2,7 +2,6 @@ use crate::context::RpcContext;
 use crate::felt::RpcFelt;
 use crate::v02::types::request::BroadcastedInvokeTransaction;
 use pathfinder_common::TransactionHash;
-use starknet_gateway_client::GatewayApi;
 use starknet_gateway_types::error::SequencerError;
 
 #[derive(serde::Deserialize, Debug, PartialEq, Eq)]
@@ -111,7 +110,7 @@ pub(crate) async fn add_invoke_transaction_impl(
     match tx {
         BroadcastedInvokeTransaction::V0(tx) => {
             context
-                .sequencer
+                .gateway()
                 .add_invoke_transaction(add_transaction::InvokeFunction::V0(
                     add_transaction::InvokeFunctionV0V1 {
                         max_fee: tx.max_fee,
@@ -126,7 +125,7 @@ pub(crate) async fn add_invoke_transaction_impl(
         }
         BroadcastedInvokeTransaction::V1(tx) => {
             context
-                .sequencer
+                .gateway()
                 .add_invoke_transaction(add_transaction::InvokeFunction::V1(
                     add_transaction::InvokeFunctionV0V1 {
                         max_fee: tx.max_fee,
@@ -141,7 +140,7 @@ pub(crate) async fn add_invoke_transaction_impl(
         }
         BroadcastedInvokeTransaction::V3(tx) => {
             context
-                .sequencer
+                .gateway()
                 .add_invoke_transaction(add_transaction::InvokeFunction::V3(
                     add_transaction::InvokeFunctionV3 {
                         signature: tx.signature,
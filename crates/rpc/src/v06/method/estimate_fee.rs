@@ -143,7 +143,9 @@ pub async fn estimate_fee(
             }
         };
 
-        let state = ExecutionState::simulation(&db, context.chain_id, header, pending);
+        let state = ExecutionState::simulation(&db, context.chain_id, header, pending)
+            .with_fork(context.fork.clone())
+            .with_config(context.execution_config.clone());
 
         let skip_validate = input
             .simulation_flags
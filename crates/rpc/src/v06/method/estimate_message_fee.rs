@@ -130,7 +130,9 @@ pub(crate) async fn estimate_message_fee_impl(
             return Err(EstimateMessageFeeError::ContractNotFound);
         }
 
-        let state = ExecutionState::simulation(&db, context.chain_id, header, pending);
+        let state = ExecutionState::simulation(&db, context.chain_id, header, pending)
+            .with_fork(context.fork.clone())
+            .with_config(context.execution_config.clone());
 
         let transaction = create_executor_transaction(input, context.chain_id)?;
 
@@ -12,7 +12,7 @@ pub struct GetBlockInput {
     block_id: BlockId,
 }
 
-crate::error::generate_rpc_error_subset!(GetBlockError: BlockNotFound);
+crate::error::generate_rpc_error_subset!(GetBlockError: BlockNotFound, BlockPruned);
 
 /// Get block information with transaction hashes given the block id
 pub async fn get_block_with_tx_hashes(
@@ -51,25 +51,41 @@ pub async fn get_block_with_tx_hashes(
             .context("Reading block from database")?
             .ok_or(GetBlockError::BlockNotFound)?;
 
+        if transaction
+            .block_body_pruned(header.number)
+            .context("Querying block body pruning")?
+        {
+            return Err(GetBlockError::BlockPruned);
+        }
+
         let l1_accepted = transaction.block_is_l1_accepted(header.number.into())?;
         let block_status = if l1_accepted {
             BlockStatus::AcceptedOnL1
         } else {
             BlockStatus::AcceptedOnL2
         };
+        let l1_acceptance = transaction
+            .l1_acceptance(header.number)
+            .context("Querying L1 acceptance")?;
 
         let transactions = transaction
             .transaction_hashes_for_block(header.number.into())
             .context("Reading transaction hashes")?
             .context("Missing block")?;
 
-        Ok(types::Block::from_parts(header, block_status, transactions))
+        Ok(types::Block::from_parts(
+            header,
+            block_status,
+            l1_acceptance.map(Into::into),
+            transactions,
+        ))
     })
     .await
     .context("Database read panic or shutting down")?
 }
 
 mod types {
+    use crate::pathfinder::methods::L1Acceptance;
     use crate::v02::types::reply::BlockStatus;
     use pathfinder_common::{BlockHeader, TransactionHash};
     use serde::Serialize;
@@ -80,6 +96,10 @@ mod types {
         #[serde(flatten)]
         pub header: crate::v06::types::BlockHeader,
         pub status: BlockStatus,
+        /// The L1 transaction which accepted the block, if known. This is not part of the
+        /// specification.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub l1_acceptance: Option<L1Acceptance>,
         pub transactions: Vec<TransactionHash>,
     }
 
@@ -87,11 +107,13 @@ mod types {
         pub fn from_parts(
             header: BlockHeader,
             status: BlockStatus,
+            l1_acceptance: Option<L1Acceptance>,
             transactions: Vec<TransactionHash>,
         ) -> Self {
             Self {
                 header: header.into(),
                 status,
+                l1_acceptance,
                 transactions,
             }
         }
@@ -100,6 +122,7 @@ mod types {
         pub fn from_sequencer(block: starknet_gateway_types::reply::MaybePendingBlock) -> Self {
             Self {
                 status: block.status().into(),
+                l1_acceptance: None,
                 transactions: block.transactions().iter().map(|t| t.hash()).collect(),
                 header: crate::v06::types::BlockHeader::from_sequencer(block),
             }
@@ -201,6 +224,55 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn l1_acceptance() {
+        let context = RpcContext::for_tests_with_pending().await;
+
+        let log = pathfinder_ethereum::StateUpdateLog {
+            update: pathfinder_ethereum::EthereumStateUpdate {
+                state_root: state_commitment_bytes!(b"state root 1"),
+                block_number: BlockNumber::new_or_panic(1),
+                block_hash: block_hash_bytes!(b"block 1"),
+            },
+            l1_block_number: 100,
+            l1_transaction_hash: primitive_types::H256::from_low_u64_be(0xabcd),
+        };
+        let mut db = context.storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        tx.insert_state_update_log(&log).unwrap();
+        tx.commit().unwrap();
+
+        let result = get_block_with_tx_hashes(
+            context.clone(),
+            GetBlockInput {
+                block_id: BlockNumber::new_or_panic(1).into(),
+            },
+        )
+        .await
+        .unwrap();
+        let result = serde_json::to_value(result).unwrap();
+        assert_eq!(
+            result["l1_acceptance"],
+            json!({
+                "state_update_block_number": 1,
+                "l1_block_number": 100,
+                "l1_transaction_hash": "0x000000000000000000000000000000000000000000000000000000000000abcd",
+            })
+        );
+
+        // Omitted when the acceptance is not known.
+        let result = get_block_with_tx_hashes(
+            context,
+            GetBlockInput {
+                block_id: BlockId::Latest,
+            },
+        )
+        .await
+        .unwrap();
+        let result = serde_json::to_value(result).unwrap();
+        assert!(result.get("l1_acceptance").is_none());
+    }
+
     #[tokio::test]
     async fn not_found_by_number() {
         let context = RpcContext::for_tests_with_pending().await;
@@ -13,7 +13,7 @@ pub struct GetBlockInput {
     block_id: BlockId,
 }
 
-crate::error::generate_rpc_error_subset!(GetBlockError: BlockNotFound);
+crate::error::generate_rpc_error_subset!(GetBlockError: BlockNotFound, BlockPruned);
 
 /// Get block information with full transactions given the block id
 pub async fn get_block_with_txs(
@@ -52,16 +52,31 @@ pub async fn get_block_with_txs(
             .context("Reading block from database")?
             .ok_or(GetBlockError::BlockNotFound)?;
 
+        if transaction
+            .block_body_pruned(header.number)
+            .context("Querying block body pruning")?
+        {
+            return Err(GetBlockError::BlockPruned);
+        }
+
         let l1_accepted = transaction.block_is_l1_accepted(header.number.into())?;
         let block_status = if l1_accepted {
             BlockStatus::AcceptedOnL1
         } else {
             BlockStatus::AcceptedOnL2
         };
+        let l1_acceptance = transaction
+            .l1_acceptance(header.number)
+            .context("Querying L1 acceptance")?;
 
         let transactions = get_block_transactions(&transaction, header.number)?;
 
-        Ok(types::Block::from_parts(header, block_status, transactions))
+        Ok(types::Block::from_parts(
+            header,
+            block_status,
+            l1_acceptance.map(Into::into),
+            transactions,
+        ))
     })
     .await
     .context("Database read panic or shutting down")?
@@ -84,6 +99,7 @@ fn get_block_transactions(
 }
 
 mod types {
+    use crate::pathfinder::methods::L1Acceptance;
     use crate::v02::types::reply::BlockStatus;
     use crate::v06::types::TransactionWithHash;
     use pathfinder_common::BlockHeader;
@@ -99,6 +115,9 @@ mod types {
         pub header: crate::v06::types::BlockHeader,
         #[serde(skip_serializing_if = "BlockStatus::is_pending")]
         pub status: BlockStatus,
+        /// The L1 transaction which accepted the block, if known.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub l1_acceptance: Option<L1Acceptance>,
         pub transactions: Vec<TransactionWithHash>,
     }
 
@@ -106,11 +125,13 @@ mod types {
         pub fn from_parts(
             header: BlockHeader,
             status: BlockStatus,
+            l1_acceptance: Option<L1Acceptance>,
             transactions: Vec<TransactionWithHash>,
         ) -> Self {
             Self {
                 header: header.into(),
                 status,
+                l1_acceptance,
                 transactions,
             }
         }
@@ -119,6 +140,7 @@ mod types {
         pub fn from_sequencer(block: starknet_gateway_types::reply::MaybePendingBlock) -> Self {
             Self {
                 status: block.status().into(),
+                l1_acceptance: None,
                 transactions: block
                     .transactions()
                     .iter()
@@ -286,4 +308,43 @@ mod tests {
         assert!(pending.get("status").is_none());
         assert!(latest.get("status").is_some());
     }
+
+    #[tokio::test]
+    async fn pruned_block() {
+        use pathfinder_common::{BlockHash, BlockHeader};
+
+        let storage = pathfinder_storage::Storage::in_memory_with_block_body_history(1).unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        for i in 0..3 {
+            let header = BlockHeader::builder()
+                .with_number(BlockNumber::new_or_panic(i))
+                .finalize_with_hash(BlockHash(pathfinder_crypto::Felt::from_u64(i + 1)));
+            tx.insert_block_header(&header).unwrap();
+            tx.insert_transaction_data(header.hash, header.number, &[])
+                .unwrap();
+        }
+        tx.prune_block_bodies(BlockNumber::new_or_panic(2)).unwrap();
+        tx.commit().unwrap();
+
+        let context = RpcContext::for_tests().with_storage(storage);
+
+        let result = get_block_with_txs(
+            context.clone(),
+            GetBlockInput {
+                block_id: BlockNumber::GENESIS.into(),
+            },
+        )
+        .await;
+        assert_matches::assert_matches!(result, Err(GetBlockError::BlockPruned));
+
+        get_block_with_txs(
+            context,
+            GetBlockInput {
+                block_id: BlockNumber::new_or_panic(1).into(),
+            },
+        )
+        .await
+        .unwrap();
+    }
 }
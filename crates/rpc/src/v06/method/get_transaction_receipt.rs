@@ -8,7 +8,7 @@ pub struct GetTransactionReceiptInput {
     pub transaction_hash: TransactionHash,
 }
 
-crate::error::generate_rpc_error_subset!(GetTransactionReceiptError: TxnHashNotFound);
+crate::error::generate_rpc_error_subset!(GetTransactionReceiptError: TxnHashNotFound, BlockPruned);
 
 pub async fn get_transaction_receipt(
     context: RpcContext,
@@ -51,10 +51,18 @@ pub async fn get_transaction_receipt_impl(
             return Ok(types::MaybePendingTransactionReceipt::Pending(pending));
         }
 
-        let (transaction, receipt, block_hash) = db_tx
+        let Some((transaction, receipt, block_hash)) = db_tx
             .transaction_with_receipt(input.transaction_hash)
             .context("Reading transaction receipt from database")?
-            .ok_or(GetTransactionReceiptError::TxnHashNotFound)?;
+        else {
+            if db_tx
+                .transaction_body_pruned(input.transaction_hash)
+                .context("Querying whether transaction was pruned")?
+            {
+                return Err(GetTransactionReceiptError::BlockPruned);
+            }
+            return Err(GetTransactionReceiptError::TxnHashNotFound);
+        };
 
         let block_number = db_tx
             .block_id(block_hash.into())
@@ -765,6 +773,45 @@ mod tests {
                 Err(GetTransactionReceiptError::TxnHashNotFound)
             );
         }
+
+        #[tokio::test]
+        async fn pruned() {
+            use pathfinder_common::{BlockHash, BlockHeader};
+
+            let genesis = {
+                let storage = RpcContext::for_tests().storage;
+                let mut connection = storage.connection().unwrap();
+                let tx = connection.transaction().unwrap();
+                tx.transaction_data_for_block(BlockNumber::GENESIS.into())
+                    .unwrap()
+                    .unwrap()
+            };
+
+            let storage =
+                pathfinder_storage::Storage::in_memory_with_block_body_history(1).unwrap();
+            let mut connection = storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+            for i in 0..3 {
+                let header = BlockHeader::builder()
+                    .with_number(BlockNumber::new_or_panic(i))
+                    .finalize_with_hash(BlockHash(pathfinder_crypto::Felt::from_u64(i + 1)));
+                tx.insert_block_header(&header).unwrap();
+                let data = if i == 0 { genesis.as_slice() } else { &[] };
+                tx.insert_transaction_data(header.hash, header.number, data)
+                    .unwrap();
+            }
+            tx.prune_block_bodies(BlockNumber::new_or_panic(2)).unwrap();
+            tx.commit().unwrap();
+
+            let context = RpcContext::for_tests().with_storage(storage);
+            let input = GetTransactionReceiptInput {
+                transaction_hash: transaction_hash_bytes!(b"txn 0"),
+            };
+
+            let result = get_transaction_receipt(context, input).await;
+
+            assert_matches::assert_matches!(result, Err(GetTransactionReceiptError::BlockPruned));
+        }
     }
 
     #[tokio::test]
@@ -3,7 +3,7 @@ use crate::{
 };
 
 use anyhow::Context;
-use pathfinder_common::{BlockId, CallParam, EntryPoint};
+use pathfinder_common::{BlockId, CallParam, ContractAddress, EntryPoint};
 use pathfinder_crypto::Felt;
 use pathfinder_executor::{types::TransactionSimulation, TransactionExecutionError};
 use serde::{Deserialize, Serialize};
@@ -11,9 +11,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct SimulateTransactionInput {
-    block_id: BlockId,
-    transactions: Vec<BroadcastedTransaction>,
-    simulation_flags: dto::SimulationFlags,
+    pub(crate) block_id: BlockId,
+    pub(crate) transactions: Vec<BroadcastedTransaction>,
+    pub(crate) simulation_flags: dto::SimulationFlags,
 }
 
 #[derive(Debug, Serialize, Eq, PartialEq)]
@@ -79,9 +79,26 @@ impl From<ExecutionStateError> for SimulateTransactionError {
     }
 }
 
+/// Pathfinder specific simulation options, which are not part of the Starknet specification.
+#[derive(Debug, Default)]
+pub(crate) struct SimulationCheats {
+    /// Accounts whose `__validate__` is skipped.
+    pub impersonated_accounts: Vec<ContractAddress>,
+    /// Fee token balances to set before simulating.
+    pub balances: Vec<(ContractAddress, primitive_types::U256)>,
+}
+
 pub async fn simulate_transactions(
     context: RpcContext,
     input: SimulateTransactionInput,
+) -> Result<SimulateTransactionOutput, SimulateTransactionError> {
+    simulate_transactions_impl(context, input, SimulationCheats::default()).await
+}
+
+pub(crate) async fn simulate_transactions_impl(
+    context: RpcContext,
+    input: SimulateTransactionInput,
+    cheats: SimulationCheats,
 ) -> Result<SimulateTransactionOutput, SimulateTransactionError> {
     let span = tracing::Span::current();
     tokio::task::spawn_blocking(move || {
@@ -126,8 +143,16 @@ pub async fn simulate_transactions(
             }
         };
 
-        let state =
-            pathfinder_executor::ExecutionState::simulation(&db, context.chain_id, header, pending);
+        let mut state =
+            pathfinder_executor::ExecutionState::simulation(&db, context.chain_id, header, pending)
+                .with_fork(context.fork.clone())
+                .with_config(context.execution_config.clone());
+        for account in cheats.impersonated_accounts {
+            state = state.with_impersonated_account(account);
+        }
+        for (address, balance) in cheats.balances {
+            state = state.with_fee_token_balance(address, balance);
+        }
 
         let transactions = input
             .transactions
@@ -0,0 +1,229 @@
+//! Online backups of the database using SQLite's [backup API](https://sqlite.org/backup.html).
+
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::Context;
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::OpenFlags;
+
+/// Throttles a backup so that it does not starve concurrent writes to the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupOptions {
+    /// The number of pages copied in one go, while holding the database lock.
+    pub pages_per_step: NonZeroU32,
+    /// The time to wait between steps.
+    pub pause: Duration,
+}
+
+impl Default for BackupOptions {
+    fn default() -> Self {
+        Self {
+            pages_per_step: NonZeroU32::new(1024).unwrap(),
+            pause: Duration::from_millis(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupProgress {
+    pub pages_copied: u64,
+    pub pages_total: u64,
+}
+
+/// Backs up the database at `source` to `destination`, without requiring exclusive access to it.
+///
+/// This is meant for databases which are in use by another process. See [crate::Storage::backup]
+/// for the details.
+pub fn backup_file(
+    source: &Path,
+    destination: &Path,
+    options: BackupOptions,
+    progress: impl FnMut(BackupProgress),
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        source.exists(),
+        "Database {} does not exist",
+        source.display()
+    );
+
+    let mut connection = rusqlite::Connection::open_with_flags(
+        source,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .context("Opening database")?;
+
+    backup(&mut connection, destination, options, progress)
+}
+
+pub(crate) fn backup(
+    source: &mut rusqlite::Connection,
+    destination: &Path,
+    options: BackupOptions,
+    progress: impl FnMut(BackupProgress),
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !destination.exists(),
+        "Backup destination {} already exists",
+        destination.display()
+    );
+
+    // The backup is written to a temporary file first, so that the destination only ever
+    // contains a complete backup. The file name is unique to this backup so that concurrent
+    // backups to the same destination do not clobber each other's progress.
+    let partial = partial_path(destination);
+    if partial.exists() {
+        // Left behind by an earlier process which happened to have the same id.
+        std::fs::remove_file(&partial).context("Removing incomplete backup")?;
+    }
+
+    let result = write_backup(source, &partial, options, progress).and_then(|_| {
+        std::fs::rename(&partial, destination).context("Moving backup to its destination")
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+
+    result
+}
+
+fn write_backup(
+    source: &mut rusqlite::Connection,
+    partial: &Path,
+    options: BackupOptions,
+    mut progress: impl FnMut(BackupProgress),
+) -> anyhow::Result<()> {
+    let mut target = rusqlite::Connection::open(partial).context("Creating backup file")?;
+
+    {
+        // SQLite restarts a backup from scratch whenever another connection writes to the
+        // source, which would never complete while syncing. Holding a read transaction for
+        // the whole backup pins the snapshot being copied instead.
+        let source = source
+            .transaction()
+            .context("Creating database transaction")?;
+        source
+            .query_row("SELECT COUNT(1) FROM sqlite_master", [], |_| Ok(()))
+            .context("Starting read transaction")?;
+
+        let backup = Backup::new(&source, &mut target).context("Starting backup")?;
+        let pages_per_step = options.pages_per_step.get().try_into().unwrap_or(i32::MAX);
+        loop {
+            let step = backup.step(pages_per_step).context("Copying pages")?;
+
+            let state = backup.progress();
+            progress(BackupProgress {
+                pages_copied: (state.pagecount - state.remaining) as u64,
+                pages_total: state.pagecount as u64,
+            });
+
+            if step == StepResult::Done {
+                break;
+            }
+
+            std::thread::sleep(options.pause);
+        }
+    }
+
+    target
+        .close()
+        .map_err(|(_connection, error)| error)
+        .context("Closing backup file")?;
+
+    Ok(())
+}
+
+fn partial_path(destination: &Path) -> PathBuf {
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+    let count = COUNT.fetch_add(1, Ordering::Relaxed);
+
+    let mut path = destination.as_os_str().to_owned();
+    path.push(format!(".{}-{count}.partial", std::process::id()));
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::BlockHeader;
+
+    use super::*;
+    use crate::{BlockId, JournalMode, Storage};
+
+    #[test]
+    fn backup_is_a_copy_of_the_database() {
+        let db_dir = tempfile::TempDir::new().unwrap();
+        let storage =
+            Storage::migrate(db_dir.path().join("pathfinder.sqlite"), JournalMode::WAL, 1)
+                .unwrap()
+                .create_pool(NonZeroU32::new(2).unwrap())
+                .unwrap();
+
+        let header = BlockHeader::builder().finalize_with_hash(block_hash_bytes!(b"genesis"));
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        tx.insert_block_header(&header).unwrap();
+        tx.commit().unwrap();
+
+        let destination = db_dir.path().join("backup.sqlite");
+        let mut last_progress = None;
+        storage
+            .backup(
+                &destination,
+                BackupOptions {
+                    pages_per_step: NonZeroU32::new(1).unwrap(),
+                    pause: Duration::ZERO,
+                },
+                |progress| last_progress = Some(progress),
+            )
+            .unwrap();
+
+        let last_progress = last_progress.unwrap();
+        assert_eq!(last_progress.pages_copied, last_progress.pages_total);
+        let partial_files = std::fs::read_dir(db_dir.path())
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(".partial")
+            })
+            .count();
+        assert_eq!(partial_files, 0);
+
+        let backup = Storage::migrate(destination, JournalMode::WAL, 1)
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let mut connection = backup.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        assert_eq!(
+            tx.block_id(BlockId::Latest).unwrap(),
+            Some((header.number, header.hash))
+        );
+    }
+
+    #[test]
+    fn existing_destination_is_not_overwritten() {
+        let db_dir = tempfile::TempDir::new().unwrap();
+        let destination = db_dir.path().join("backup.sqlite");
+        std::fs::write(&destination, b"precious").unwrap();
+
+        let storage = Storage::in_memory().unwrap();
+        storage
+            .backup(&destination, BackupOptions::default(), |_| {})
+            .unwrap_err();
+
+        assert_eq!(std::fs::read(&destination).unwrap(), b"precious");
+    }
+
+    #[test]
+    fn concurrent_backups_use_distinct_partial_files() {
+        let destination = Path::new("backup.sqlite");
+        assert_ne!(partial_path(destination), partial_path(destination));
+    }
+}
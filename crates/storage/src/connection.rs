@@ -4,14 +4,18 @@ use std::sync::Arc;
 
 mod block;
 mod class;
+mod compression;
 mod ethereum;
 mod event;
+mod message;
 mod reference;
 mod reorg_counter;
 mod signature;
 mod state_update;
+mod stats;
 mod transaction;
 mod trie;
+mod trie_backend;
 
 // Re-export this so users don't require rusqlite as a direct dep.
 pub use rusqlite::TransactionBehavior;
@@ -20,12 +24,22 @@ pub use event::KEY_FILTER_LIMIT as EVENT_KEY_FILTER_LIMIT;
 pub use event::PAGE_SIZE_LIMIT as EVENT_PAGE_SIZE_LIMIT;
 pub use event::{EmittedEvent, EventFilter, EventFilterError, PageOfEvents};
 
+pub(crate) use compression::DictionaryCache;
+pub use compression::{DictionaryKind, TrainedDictionary};
+
 pub(crate) use reorg_counter::ReorgCounter;
 
+pub use message::{L1ToL2Message, L2ToL1MessageOrigin};
+
+pub use stats::{DataCategory, DatabaseStats, TableStats};
+
 pub use transaction::TransactionStatus;
 
+pub(crate) use trie::prune_roots;
 pub use trie::{Child, Node, StoredNode};
 
+pub use trie_backend::{MemoryTrieBackend, SqliteTrieBackend, Trie, TrieBackend};
+
 use pathfinder_common::{
     BlockCommitmentSignature, BlockHash, BlockHeader, BlockNumber, CasmHash, ClassCommitment,
     ClassCommitmentLeafHash, ClassHash, ContractAddress, ContractNonce, ContractRoot,
@@ -33,26 +47,41 @@ use pathfinder_common::{
     TransactionHash,
 };
 use pathfinder_crypto::Felt;
-use pathfinder_ethereum::EthereumStateUpdate;
+use pathfinder_ethereum::{
+    ConsumedMessageToL1Log, EthereumStateUpdate, MessageToL2Log, StateUpdateLog,
+};
+use primitive_types::H256;
 use starknet_gateway_types::reply::transaction as gateway;
 
-use crate::BlockId;
+use crate::{BlockId, TriePruneMode};
 
 type PooledConnection = r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager>;
 
 pub struct Connection {
     connection: PooledConnection,
     bloom_filter_cache: Arc<crate::bloom::Cache>,
+    dictionary_cache: Arc<DictionaryCache>,
+    trie_backend: Arc<dyn TrieBackend>,
+    trie_prune_mode: TriePruneMode,
+    block_body_history: Option<u64>,
 }
 
 impl Connection {
     pub(crate) fn new(
         connection: PooledConnection,
         bloom_filter_cache: Arc<crate::bloom::Cache>,
+        dictionary_cache: Arc<DictionaryCache>,
+        trie_backend: Arc<dyn TrieBackend>,
+        trie_prune_mode: TriePruneMode,
+        block_body_history: Option<u64>,
     ) -> Self {
         Self {
             connection,
             bloom_filter_cache,
+            dictionary_cache,
+            trie_backend,
+            trie_prune_mode,
+            block_body_history,
         }
     }
 
@@ -61,6 +90,10 @@ impl Connection {
         Ok(Transaction {
             transaction: tx,
             bloom_filter_cache: self.bloom_filter_cache.clone(),
+            dictionary_cache: self.dictionary_cache.clone(),
+            trie_backend: self.trie_backend.clone(),
+            trie_prune_mode: self.trie_prune_mode,
+            block_body_history: self.block_body_history,
         })
     }
 
@@ -72,6 +105,10 @@ impl Connection {
         Ok(Transaction {
             transaction: tx,
             bloom_filter_cache: self.bloom_filter_cache.clone(),
+            dictionary_cache: self.dictionary_cache.clone(),
+            trie_backend: self.trie_backend.clone(),
+            trie_prune_mode: self.trie_prune_mode,
+            block_body_history: self.block_body_history,
         })
     }
 }
@@ -79,6 +116,10 @@ impl Connection {
 pub struct Transaction<'inner> {
     transaction: rusqlite::Transaction<'inner>,
     bloom_filter_cache: Arc<crate::bloom::Cache>,
+    dictionary_cache: Arc<DictionaryCache>,
+    trie_backend: Arc<dyn TrieBackend>,
+    trie_prune_mode: TriePruneMode,
+    block_body_history: Option<u64>,
 }
 
 impl<'inner> Transaction<'inner> {
@@ -90,6 +131,10 @@ impl<'inner> Transaction<'inner> {
         Self {
             transaction: tx,
             bloom_filter_cache: Arc::new(crate::bloom::Cache::with_size(1)),
+            dictionary_cache: Default::default(),
+            trie_backend: Arc::new(SqliteTrieBackend),
+            trie_prune_mode: TriePruneMode::Archive,
+            block_body_history: None,
         }
     }
 
@@ -184,6 +229,69 @@ impl<'inner> Transaction<'inner> {
         ethereum::latest_l1_state(self)
     }
 
+    /// Stores a state update together with the L1 transaction which accepted it.
+    pub fn insert_state_update_log(&self, log: &StateUpdateLog) -> anyhow::Result<()> {
+        ethereum::insert_state_update_log(self, log)
+    }
+
+    /// Returns the logged state update which accepted the block on L1, if any.
+    pub fn l1_acceptance(&self, block: BlockNumber) -> anyhow::Result<Option<StateUpdateLog>> {
+        ethereum::l1_acceptance(self, block)
+    }
+
+    /// Stores a message sent to L2 together with the L1 transaction which sent it.
+    pub fn insert_message_to_l2_log(&self, log: &MessageToL2Log) -> anyhow::Result<()> {
+        message::insert_message_to_l2_log(self, log)
+    }
+
+    /// Returns the messages sent to L2 by the L1 transaction, and the L1 handler transactions
+    /// which consumed them.
+    pub fn l1_to_l2_messages(
+        &self,
+        l1_transaction_hash: H256,
+    ) -> anyhow::Result<Vec<L1ToL2Message>> {
+        message::l1_to_l2_messages(self, l1_transaction_hash)
+    }
+
+    /// Marks the L1 handler transactions of all blocks before `block` as not linked to the
+    /// messages they consumed, whose consumption is then reported as unknown.
+    pub fn set_first_block_with_l1_handler_links(&self, block: BlockNumber) -> anyhow::Result<()> {
+        message::set_first_block_with_l1_handler_links(self, block)
+    }
+
+    /// Stores the consumption of an L2 to L1 message by an L1 transaction.
+    pub fn insert_consumed_message_to_l1_log(
+        &self,
+        log: &ConsumedMessageToL1Log,
+    ) -> anyhow::Result<()> {
+        message::insert_consumed_message_to_l1_log(self, log)
+    }
+
+    /// Returns the L2 transactions which sent the L2 to L1 message, in the order they were sent.
+    pub fn l2_to_l1_messages(
+        &self,
+        message_hash: H256,
+    ) -> anyhow::Result<Vec<L2ToL1MessageOrigin>> {
+        message::l2_to_l1_messages(self, message_hash)
+    }
+
+    /// Returns the consumptions of the L2 to L1 message on L1, in the order they were logged.
+    pub fn l2_to_l1_message_consumptions(
+        &self,
+        message_hash: H256,
+    ) -> anyhow::Result<Vec<ConsumedMessageToL1Log>> {
+        message::l2_to_l1_message_consumptions(self, message_hash)
+    }
+
+    pub fn update_l1_logs_head(&self, l1_block: u64) -> anyhow::Result<()> {
+        reference::update_l1_logs_head(self, l1_block)
+    }
+
+    /// Returns the last L1 block whose core contract logs have been processed.
+    pub fn l1_logs_head(&self) -> anyhow::Result<Option<u64>> {
+        reference::l1_logs_head(self)
+    }
+
     /// Inserts the transaction, receipt and event data.
     pub fn insert_transaction_data(
         &self,
@@ -323,6 +431,34 @@ impl<'inner> Transaction<'inner> {
         class::class_definition(self, class_hash)
     }
 
+    /// Returns all classes declared at or before `block`, together with the block in which they
+    /// were declared.
+    pub fn declared_classes(
+        &self,
+        block: BlockNumber,
+    ) -> anyhow::Result<Vec<(ClassHash, BlockNumber)>> {
+        class::declared_classes(self, block)
+    }
+
+    /// Returns the number of classes declared after `block`, which
+    /// [purge_classes_declared_after](Self::purge_classes_declared_after) would delete.
+    pub fn count_classes_declared_after(&self, block: BlockNumber) -> anyhow::Result<usize> {
+        class::count_classes_declared_after(self, block)
+    }
+
+    /// Deletes the definitions of all classes declared after `block`, returning their number.
+    ///
+    /// Must be called before the blocks are purged, which forgets in which block the classes
+    /// were declared.
+    pub fn purge_classes_declared_after(&self, block: BlockNumber) -> anyhow::Result<usize> {
+        class::purge_classes_declared_after(self, block)
+    }
+
+    /// Deletes the definition of a class which has not been declared in any block.
+    pub fn delete_undeclared_class(&self, class_hash: ClassHash) -> anyhow::Result<()> {
+        class::delete_undeclared_class(self, class_hash)
+    }
+
     /// Returns the uncompressed class definition as well as the block number at which it was declared.
     pub fn class_definition_with_block_number(
         &self,
@@ -331,15 +467,6 @@ impl<'inner> Transaction<'inner> {
         class::class_definition_with_block_number(self, class_hash)
     }
 
-    /// Returns the compressed class definition if it has been declared at `block_id`.
-    pub fn compressed_class_definition_at(
-        &self,
-        block_id: BlockId,
-        class_hash: ClassHash,
-    ) -> anyhow::Result<Option<Vec<u8>>> {
-        class::compressed_class_definition_at(self, block_id, class_hash)
-    }
-
     /// Returns the uncompressed class definition if it has been declared at `block_id`.
     pub fn class_definition_at(
         &self,
@@ -400,6 +527,24 @@ impl<'inner> Transaction<'inner> {
         state_update::contract_class_hash(self, block_id, contract_address)
     }
 
+    /// Returns the address and class hash of all contracts deployed at or before `block`, ordered
+    /// by address.
+    pub fn deployed_contracts(
+        &self,
+        block: BlockNumber,
+    ) -> anyhow::Result<Vec<(ContractAddress, ClassHash)>> {
+        state_update::deployed_contracts(self, block)
+    }
+
+    /// Returns all non-zero storage values of a contract at `block`.
+    pub fn contract_storage(
+        &self,
+        block: BlockNumber,
+        contract_address: ContractAddress,
+    ) -> anyhow::Result<Vec<(StorageAddress, StorageValue)>> {
+        state_update::contract_storage(self, block, contract_address)
+    }
+
     /// Returns the compiled class hash for a class.
     pub fn casm_hash(&self, class_hash: ClassHash) -> anyhow::Result<Option<CasmHash>> {
         class::casm_hash(self, class_hash)
@@ -414,55 +559,58 @@ impl<'inner> Transaction<'inner> {
         class::casm_hash_at(self, block_id, class_hash)
     }
 
-    /// Stores the class trie information.
+    /// Stores the class trie information of the given block.
     pub fn insert_class_trie(
         &self,
+        block_number: BlockNumber,
         root: ClassCommitment,
         nodes: &HashMap<Felt, Node>,
     ) -> anyhow::Result<u64> {
-        trie::trie_class::insert(self, root.0, nodes)
+        trie::insert_trie(self, Trie::Class, block_number, root.0, nodes)
     }
 
-    /// Stores a single contract's storage trie information.
+    /// Stores a single contract's storage trie information of the given block.
     pub fn insert_contract_trie(
         &self,
+        block_number: BlockNumber,
         root: ContractRoot,
         nodes: &HashMap<Felt, Node>,
     ) -> anyhow::Result<u64> {
-        trie::trie_contracts::insert(self, root.0, nodes)
+        trie::insert_trie(self, Trie::Contract, block_number, root.0, nodes)
     }
 
-    /// Stores the global starknet storage trie information.
+    /// Stores the global starknet storage trie information of the given block.
     pub fn insert_storage_trie(
         &self,
+        block_number: BlockNumber,
         root: StorageCommitment,
         nodes: &HashMap<Felt, Node>,
     ) -> anyhow::Result<u64> {
-        trie::trie_storage::insert(self, root.0, nodes)
+        trie::insert_trie(self, Trie::Storage, block_number, root.0, nodes)
     }
 
     pub fn class_trie_node(&self, index: u64) -> anyhow::Result<Option<StoredNode>> {
-        trie::trie_class::node(self, index)
+        self.trie_backend.node(self, Trie::Class, index)
     }
 
     pub fn storage_trie_node(&self, index: u64) -> anyhow::Result<Option<StoredNode>> {
-        trie::trie_storage::node(self, index)
+        self.trie_backend.node(self, Trie::Storage, index)
     }
 
     pub fn contract_trie_node(&self, index: u64) -> anyhow::Result<Option<StoredNode>> {
-        trie::trie_contracts::node(self, index)
+        self.trie_backend.node(self, Trie::Contract, index)
     }
 
     pub fn class_trie_node_hash(&self, index: u64) -> anyhow::Result<Option<Felt>> {
-        trie::trie_class::hash(self, index)
+        self.trie_backend.hash(self, Trie::Class, index)
     }
 
     pub fn storage_trie_node_hash(&self, index: u64) -> anyhow::Result<Option<Felt>> {
-        trie::trie_storage::hash(self, index)
+        self.trie_backend.hash(self, Trie::Storage, index)
     }
 
     pub fn contract_trie_node_hash(&self, index: u64) -> anyhow::Result<Option<Felt>> {
-        trie::trie_contracts::hash(self, index)
+        self.trie_backend.hash(self, Trie::Contract, index)
     }
 
     pub fn class_root_index(&self, block: BlockNumber) -> anyhow::Result<Option<u64>> {
@@ -514,6 +662,141 @@ impl<'inner> Transaction<'inner> {
         trie::insert_contract_root(self, block_number, contract, root)
     }
 
+    /// Records the class trie nodes which were removed by this block, so that they can be pruned
+    /// later on. Does nothing unless [trie pruning](TriePruneMode::Prune) is enabled.
+    pub fn insert_class_trie_removals(
+        &self,
+        block_number: BlockNumber,
+        indices: &[u64],
+    ) -> anyhow::Result<()> {
+        if self.trie_prune_mode == TriePruneMode::Archive {
+            return Ok(());
+        }
+        trie::trie_class::insert_removals(self, block_number, indices)
+    }
+
+    /// Records the contract trie nodes which were removed by this block, so that they can be
+    /// pruned later on. Does nothing unless [trie pruning](TriePruneMode::Prune) is enabled.
+    pub fn insert_contract_trie_removals(
+        &self,
+        block_number: BlockNumber,
+        indices: &[u64],
+    ) -> anyhow::Result<()> {
+        if self.trie_prune_mode == TriePruneMode::Archive {
+            return Ok(());
+        }
+        trie::trie_contracts::insert_removals(self, block_number, indices)
+    }
+
+    /// Records the storage trie nodes which were removed by this block, so that they can be pruned
+    /// later on. Does nothing unless [trie pruning](TriePruneMode::Prune) is enabled.
+    pub fn insert_storage_trie_removals(
+        &self,
+        block_number: BlockNumber,
+        indices: &[u64],
+    ) -> anyhow::Result<()> {
+        if self.trie_prune_mode == TriePruneMode::Archive {
+            return Ok(());
+        }
+        trie::trie_storage::insert_removals(self, block_number, indices)
+    }
+
+    /// Deletes the trie nodes and roots which are no longer required, given `head` as the latest
+    /// block. Does nothing unless [trie pruning](TriePruneMode::Prune) is enabled.
+    pub fn prune_tries(&self, head: BlockNumber) -> anyhow::Result<()> {
+        trie::prune_tries(self, head)
+    }
+
+    /// Returns true if the state tries of this block have been pruned.
+    pub fn trie_pruned(&self, block: BlockNumber) -> anyhow::Result<bool> {
+        trie::trie_pruned(self, block)
+    }
+
+    pub fn trie_prune_mode(&self) -> TriePruneMode {
+        self.trie_prune_mode
+    }
+
+    /// Returns the space used by each table. This reads the entire database.
+    pub fn database_stats(&self) -> anyhow::Result<DatabaseStats> {
+        stats::database_stats(self)
+    }
+
+    /// Returns the id of the latest compression dictionary of this kind, which new rows are
+    /// compressed with.
+    pub fn compression_dictionary_id(&self, kind: DictionaryKind) -> anyhow::Result<Option<i64>> {
+        compression::dictionary_id(self, kind)
+    }
+
+    /// Trains a new compression dictionary on a sample of the existing rows of this kind, or
+    /// returns `None` if there are too few rows yet or too few new rows since the latest
+    /// dictionary was trained. This takes a while and only reads the database.
+    pub fn train_compression_dictionary(
+        &self,
+        kind: DictionaryKind,
+    ) -> anyhow::Result<Option<TrainedDictionary>> {
+        compression::train_dictionary(self, kind)
+    }
+
+    /// Stores a compression dictionary, which new rows of its kind are compressed with from then
+    /// on. Returns its id.
+    pub fn insert_compression_dictionary(
+        &self,
+        kind: DictionaryKind,
+        dictionary: &TrainedDictionary,
+    ) -> anyhow::Result<i64> {
+        compression::insert_dictionary(self, kind, dictionary)
+    }
+
+    /// Recompresses up to `limit` rows after `after` with the latest compression dictionary of
+    /// this kind. Returns the `rowid` to continue from, or `None` once all rows are recompressed.
+    pub fn recompress_rows(
+        &self,
+        kind: DictionaryKind,
+        after: i64,
+        limit: usize,
+    ) -> anyhow::Result<Option<i64>> {
+        compression::recompress_rows(self, kind, after, limit)
+    }
+
+    /// Deletes the transactions, receipts and events of blocks which are older than the configured
+    /// [block body history](crate::StorageManager::with_block_body_history), given `head` as the
+    /// latest block. Block headers, state updates and signatures are kept.
+    pub fn prune_block_bodies(&self, head: BlockNumber) -> anyhow::Result<()> {
+        transaction::prune_block_bodies(self, head)
+    }
+
+    /// Returns true if the transactions, receipts and events of this block have been pruned.
+    pub fn block_body_pruned(&self, block: BlockNumber) -> anyhow::Result<bool> {
+        transaction::block_body_pruned(self, block)
+    }
+
+    /// Returns true if the transaction is known, but its block's transactions, receipts and events
+    /// have been pruned.
+    pub fn transaction_body_pruned(&self, hash: TransactionHash) -> anyhow::Result<bool> {
+        transaction::transaction_body_pruned(self, hash)
+    }
+
+    /// Returns the oldest block whose transactions, receipts and events have not been pruned.
+    pub fn first_unpruned_block_body(&self) -> anyhow::Result<BlockNumber> {
+        transaction::first_unpruned_block_body(self)
+    }
+
+    /// Marks the transactions, receipts and events of all blocks before `block` as pruned.
+    pub fn set_first_unpruned_block_body(&self, block: BlockNumber) -> anyhow::Result<()> {
+        transaction::set_first_unpruned_block_body(self, block)
+    }
+
+    /// Returns the oldest block whose state is available. Older blocks only have headers if the
+    /// database was initialized from a snapshot.
+    pub fn first_block_with_state(&self) -> anyhow::Result<BlockNumber> {
+        state_update::first_block_with_state(self)
+    }
+
+    /// Marks the state of all blocks before `block` as unavailable, so that querying it fails.
+    pub fn set_first_block_with_state(&self, block: BlockNumber) -> anyhow::Result<()> {
+        state_update::set_first_block_with_state(self, block)
+    }
+
     pub fn insert_state_update(
         &self,
         block_number: BlockNumber,
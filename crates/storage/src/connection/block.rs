@@ -127,6 +127,9 @@ pub(super) fn purge_block(tx: &Transaction<'_>, block: BlockNumber) -> anyhow::R
         )
         .context("Deleting bloom filter")?;
 
+    super::message::unlink_l1_handler_messages(tx, block)?;
+    super::message::delete_l2_to_l1_messages(tx, block)?;
+
     tx.inner()
         .execute(
             r"DELETE FROM starknet_transactions WHERE block_hash = (
@@ -150,6 +153,9 @@ pub(super) fn purge_block(tx: &Transaction<'_>, block: BlockNumber) -> anyhow::R
         )
         .context("Deleting block from block_headers table")?;
 
+    // Nodes added by the block are only part of its own tries, which are removed below.
+    super::trie::delete_added_nodes(tx, block).context("Deleting trie nodes of block")?;
+
     tx.inner()
         .execute(
             "DELETE FROM contract_roots WHERE block_number = ?",
@@ -185,6 +191,29 @@ pub(super) fn purge_block(tx: &Transaction<'_>, block: BlockNumber) -> anyhow::R
         )
         .context("Deleting block from storage_roots table")?;
 
+    // A block replacing this one has a body again, even if this one's body was pruned.
+    tx.inner()
+        .execute(
+            "UPDATE storage_options SET value = ?1 WHERE option = 'block_bodies_pruned_before' AND value > ?1",
+            params![&block],
+        )
+        .context("Updating pruned block bodies")?;
+
+    // The nodes removed by this block are part of the parent's tries, and must therefore
+    // not be pruned.
+    for table in [
+        "trie_class_removals",
+        "trie_contracts_removals",
+        "trie_storage_removals",
+    ] {
+        tx.inner()
+            .execute(
+                &format!("DELETE FROM {table} WHERE block_number = ?"),
+                params![&block],
+            )
+            .with_context(|| format!("Deleting block from {table} table"))?;
+    }
+
     Ok(())
 }
 
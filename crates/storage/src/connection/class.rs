@@ -1,6 +1,7 @@
 use anyhow::Context;
 use pathfinder_common::{BlockNumber, CasmHash, ClassCommitmentLeafHash, ClassHash, SierraHash};
 
+use super::compression::{self, Compressor, DictionaryKind};
 use crate::{prelude::*, BlockId};
 
 pub(super) fn insert_sierra_class(
@@ -10,10 +11,13 @@ pub(super) fn insert_sierra_class(
     casm_hash: &CasmHash,
     casm_definition: &[u8],
 ) -> anyhow::Result<()> {
-    let mut compressor = zstd::bulk::Compressor::new(10).context("Creating zstd compressor")?;
+    let mut compressor = Compressor::new(transaction, DictionaryKind::ClassDefinition)?;
     let sierra_definition = compressor
         .compress(sierra_definition)
         .context("Compressing sierra definition")?;
+    let sierra_dictionary_id = compressor.dictionary_id();
+
+    let mut compressor = Compressor::new(transaction, DictionaryKind::CasmDefinition)?;
     let casm_definition = compressor
         .compress(casm_definition)
         .context("Compressing casm definition")?;
@@ -21,8 +25,8 @@ pub(super) fn insert_sierra_class(
     transaction
         .inner()
         .execute(
-            r"INSERT OR IGNORE INTO class_definitions (hash,  definition) VALUES (?, ?)",
-            params![sierra_hash, &sierra_definition],
+            r"INSERT OR IGNORE INTO class_definitions (hash,  definition, dictionary_id) VALUES (?, ?, ?)",
+            params![sierra_hash, &sierra_definition, &sierra_dictionary_id],
         )
         .context("Inserting sierra definition")?;
 
@@ -30,13 +34,14 @@ pub(super) fn insert_sierra_class(
         .inner()
         .execute(
             r"INSERT OR REPLACE INTO casm_definitions
-                (hash, definition, compiled_class_hash)
+                (hash, definition, compiled_class_hash, dictionary_id)
             VALUES
-                (:hash, :definition, :compiled_class_hash)",
+                (:hash, :definition, :compiled_class_hash, :dictionary_id)",
             named_params! {
                 ":hash": sierra_hash,
                 ":definition": &casm_definition,
                 ":compiled_class_hash": casm_hash,
+                ":dictionary_id": &compressor.dictionary_id(),
             },
         )
         .context("Inserting casm definition")?;
@@ -49,7 +54,7 @@ pub(super) fn insert_cairo_class(
     cairo_hash: ClassHash,
     definition: &[u8],
 ) -> anyhow::Result<()> {
-    let mut compressor = zstd::bulk::Compressor::new(10).context("Creating zstd compressor")?;
+    let mut compressor = Compressor::new(transaction, DictionaryKind::ClassDefinition)?;
     let definition = compressor
         .compress(definition)
         .context("Compressing cairo definition")?;
@@ -57,8 +62,8 @@ pub(super) fn insert_cairo_class(
     transaction
         .inner()
         .execute(
-            r"INSERT OR IGNORE INTO class_definitions (hash,  definition) VALUES (?, ?)",
-            params![&cairo_hash, &definition],
+            r"INSERT OR IGNORE INTO class_definitions (hash,  definition, dictionary_id) VALUES (?, ?, ?)",
+            params![&cairo_hash, &definition, &compressor.dictionary_id()],
         )
         .context("Inserting cairo definition")?;
 
@@ -95,51 +100,108 @@ pub(super) fn class_definition_with_block_number(
     let from_row = |row: &rusqlite::Row<'_>| {
         let definition = row.get_blob(0).map(|x| x.to_vec())?;
         let block_number = row.get_optional_block_number(1)?;
-        Ok((block_number, definition))
+        let dictionary_id = row.get_optional_i64(2)?;
+        Ok((block_number, definition, dictionary_id))
     };
 
-    let mut stmt = transaction
-        .inner()
-        .prepare_cached("SELECT definition, block_number FROM class_definitions WHERE hash = ?")?;
+    let mut stmt = transaction.inner().prepare_cached(
+        "SELECT definition, block_number, dictionary_id FROM class_definitions WHERE hash = ?",
+    )?;
 
     let result = stmt
         .query_row(params![&class_hash], from_row)
         .optional()
         .context("Querying for class definition")?;
 
-    let Some((block_number, definition)) = result else {
+    let Some((block_number, definition, dictionary_id)) = result else {
         return Ok(None);
     };
-    let definition =
-        zstd::decode_all(definition.as_slice()).context("Decompressing class definition")?;
+    let definition = compression::decompress(transaction, &definition, dictionary_id)
+        .context("Decompressing class definition")?;
 
     Ok(Some((block_number, definition)))
 }
 
-pub(super) fn compressed_class_definition_at(
-    tx: &Transaction<'_>,
-    block_id: BlockId,
+pub(super) fn declared_classes(
+    transaction: &Transaction<'_>,
+    block: BlockNumber,
+) -> anyhow::Result<Vec<(ClassHash, BlockNumber)>> {
+    let mut stmt = transaction.inner().prepare_cached(
+        "SELECT hash, block_number FROM class_definitions WHERE block_number <= ? ORDER BY block_number",
+    )?;
+
+    let classes = stmt
+        .query_map(params![&block], |row| {
+            Ok((row.get_class_hash(0)?, row.get_block_number(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Querying declared classes")?;
+
+    Ok(classes)
+}
+
+pub(super) fn purge_classes_declared_after(
+    transaction: &Transaction<'_>,
+    block: BlockNumber,
+) -> anyhow::Result<usize> {
+    // Compiled classes are removed along with their class by the foreign key constraint.
+    transaction
+        .inner()
+        .execute(
+            "DELETE FROM class_definitions WHERE block_number > ?",
+            params![&block],
+        )
+        .context("Deleting class definitions")
+}
+
+pub(super) fn count_classes_declared_after(
+    transaction: &Transaction<'_>,
+    block: BlockNumber,
+) -> anyhow::Result<usize> {
+    transaction
+        .inner()
+        .query_row(
+            "SELECT COUNT(1) FROM class_definitions WHERE block_number > ?",
+            params![&block],
+            |row| row.get(0),
+        )
+        .context("Counting class definitions")
+}
+
+pub(super) fn delete_undeclared_class(
+    transaction: &Transaction<'_>,
     class_hash: ClassHash,
-) -> anyhow::Result<Option<Vec<u8>>> {
-    self::compressed_class_definition_at_with_block_number(tx, block_id, class_hash)
-        .map(|option| option.map(|(_block_number, definition)| definition))
+) -> anyhow::Result<()> {
+    // The compiled class is removed along with the class by the foreign key constraint.
+    transaction
+        .inner()
+        .execute(
+            "DELETE FROM class_definitions WHERE hash = ? AND block_number IS NULL",
+            params![&class_hash],
+        )
+        .context("Deleting class definition")?;
+
+    Ok(())
 }
 
-pub(super) fn compressed_class_definition_at_with_block_number(
+/// Returns the compressed class definition, the block number at which it was declared and the
+/// dictionary it was compressed with.
+fn compressed_class_definition_at_with_block_number(
     tx: &Transaction<'_>,
     block_id: BlockId,
     class_hash: ClassHash,
-) -> anyhow::Result<Option<(BlockNumber, Vec<u8>)>> {
+) -> anyhow::Result<Option<(BlockNumber, Vec<u8>, Option<i64>)>> {
     let from_row = |row: &rusqlite::Row<'_>| {
         let definition = row.get_blob(0).map(|x| x.to_vec())?;
         let block_number = row.get_block_number(1)?;
-        Ok((block_number, definition))
+        let dictionary_id = row.get_optional_i64(2)?;
+        Ok((block_number, definition, dictionary_id))
     };
 
     match block_id {
         BlockId::Latest => {
             let mut stmt = tx.inner().prepare_cached(
-                "SELECT definition, block_number FROM class_definitions WHERE hash=? AND block_number IS NOT NULL",
+                "SELECT definition, block_number, dictionary_id FROM class_definitions WHERE hash=? AND block_number IS NOT NULL",
             )?;
             stmt.query_row(
                 params![&class_hash],
@@ -148,7 +210,7 @@ pub(super) fn compressed_class_definition_at_with_block_number(
         }
         BlockId::Number(number) => {
             let mut stmt = tx.inner().prepare_cached(
-                "SELECT definition, block_number FROM class_definitions WHERE hash=? AND block_number <= ?",
+                "SELECT definition, block_number, dictionary_id FROM class_definitions WHERE hash=? AND block_number <= ?",
             )?;
             stmt.query_row(
                 params![&class_hash, &number],
@@ -157,7 +219,7 @@ pub(super) fn compressed_class_definition_at_with_block_number(
         }
         BlockId::Hash(hash) => {
             let mut stmt = tx.inner().prepare_cached(
-                r"SELECT definition, block_number FROM class_definitions
+                r"SELECT definition, block_number, dictionary_id FROM class_definitions
                 WHERE hash = ? AND block_number <= (SELECT number from canonical_blocks WHERE hash = ?)",
             )?;
             stmt.query_row(
@@ -185,11 +247,11 @@ pub(super) fn class_definition_at_with_block_number(
     class_hash: ClassHash,
 ) -> anyhow::Result<Option<(BlockNumber, Vec<u8>)>> {
     let definition = compressed_class_definition_at_with_block_number(tx, block_id, class_hash)?;
-    let Some((block_number, definition)) = definition else {
+    let Some((block_number, definition, dictionary_id)) = definition else {
         return Ok(None);
     };
-    let definition =
-        zstd::decode_all(definition.as_slice()).context("Decompressing class definition")?;
+    let definition = compression::decompress(tx, &definition, dictionary_id)
+        .context("Decompressing class definition")?;
 
     Ok(Some((block_number, definition)))
 }
@@ -202,17 +264,22 @@ pub(super) fn casm_definition(
     let definition = transaction
         .inner()
         .query_row(
-            "SELECT definition FROM casm_definitions WHERE hash = ?",
+            "SELECT definition, dictionary_id FROM casm_definitions WHERE hash = ?",
             params![&class_hash],
-            |row| row.get_blob(0).map(|x| x.to_vec()),
+            |row| {
+                Ok((
+                    row.get_blob(0).map(|x| x.to_vec())?,
+                    row.get_optional_i64(1)?,
+                ))
+            },
         )
         .optional()
         .context("Querying for compiled class definition")?;
 
-    let Some(definition) = definition else {
+    let Some((definition, dictionary_id)) = definition else {
         return Ok(None);
     };
-    let definition = zstd::decode_all(definition.as_slice())
+    let definition = compression::decompress(transaction, &definition, dictionary_id)
         .context("Decompressing compiled class definition")?;
 
     Ok(Some(definition))
@@ -225,7 +292,8 @@ pub(super) fn casm_definition_with_block_number(
     let from_row = |row: &rusqlite::Row<'_>| {
         let definition = row.get_blob(0).map(|x| x.to_vec())?;
         let block_number = row.get_optional_block_number(1)?;
-        Ok((block_number, definition))
+        let dictionary_id = row.get_optional_i64(2)?;
+        Ok((block_number, definition, dictionary_id))
     };
 
     let result = transaction
@@ -234,7 +302,8 @@ pub(super) fn casm_definition_with_block_number(
             r"
             SELECT
                 casm_definitions.definition,
-                class_definitions.block_number
+                class_definitions.block_number,
+                casm_definitions.dictionary_id
             FROM
                 casm_definitions
                 LEFT JOIN class_definitions ON (
@@ -248,10 +317,10 @@ pub(super) fn casm_definition_with_block_number(
         .optional()
         .context("Querying for compiled class definition")?;
 
-    let Some((block_number, definition)) = result else {
+    let Some((block_number, definition, dictionary_id)) = result else {
         return Ok(None);
     };
-    let definition = zstd::decode_all(definition.as_slice())
+    let definition = compression::decompress(transaction, &definition, dictionary_id)
         .context("Decompressing compiled class definition")?;
 
     Ok(Some((block_number, definition)))
@@ -274,14 +343,16 @@ pub(super) fn casm_definition_at_with_block_number(
     let from_row = |row: &rusqlite::Row<'_>| {
         let definition = row.get_blob(0).map(|x| x.to_vec())?;
         let block_number = row.get_optional_block_number(1)?;
-        Ok((block_number, definition))
+        let dictionary_id = row.get_optional_i64(2)?;
+        Ok((block_number, definition, dictionary_id))
     };
 
     let definition = match block_id {
         BlockId::Latest => tx.inner().query_row(
             r"SELECT
                 casm_definitions.definition,
-                class_definitions.block_number
+                class_definitions.block_number,
+                casm_definitions.dictionary_id
             FROM
                 casm_definitions
                 INNER JOIN class_definitions ON (
@@ -296,7 +367,8 @@ pub(super) fn casm_definition_at_with_block_number(
         BlockId::Number(number) => tx.inner().query_row(
             r"SELECT
                 casm_definitions.definition,
-                class_definitions.block_number
+                class_definitions.block_number,
+                casm_definitions.dictionary_id
             FROM
                 casm_definitions
                 INNER JOIN class_definitions ON (
@@ -311,7 +383,8 @@ pub(super) fn casm_definition_at_with_block_number(
         BlockId::Hash(hash) => tx.inner().query_row(
             r"SELECT
                 casm_definitions.definition,
-                class_definitions.block_number
+                class_definitions.block_number,
+                casm_definitions.dictionary_id
             FROM
                 casm_definitions
                 INNER JOIN class_definitions ON (
@@ -327,10 +400,10 @@ pub(super) fn casm_definition_at_with_block_number(
     .optional()
     .context("Querying for compiled class definition")?;
 
-    let Some((block_number, definition)) = definition else {
+    let Some((block_number, definition, dictionary_id)) = definition else {
         return Ok(None);
     };
-    let definition = zstd::decode_all(definition.as_slice())
+    let definition = compression::decompress(tx, &definition, dictionary_id)
         .context("Decompressing compiled class definition")?;
 
     Ok(Some((block_number, definition)))
@@ -496,6 +569,17 @@ mod tests {
         assert_eq!(definition, cairo_definition);
     }
 
+    #[test]
+    fn delete_undeclared() {
+        let mut connection = Storage::in_memory().unwrap().connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let (hash, _, _) = setup_class(&tx);
+        delete_undeclared_class(&tx, hash).unwrap();
+
+        assert_eq!(classes_exist(&tx, &[hash]).unwrap(), vec![false]);
+    }
+
     #[test]
     fn insert_sierra() {
         let mut connection = Storage::in_memory().unwrap().connection().unwrap();
@@ -542,6 +626,56 @@ mod tests {
         assert_eq!(definition, sierra_definition);
     }
 
+    #[test]
+    fn purge_classes_declared_after_block() {
+        let mut connection = Storage::in_memory().unwrap().connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let genesis = pathfinder_common::BlockHeader::builder()
+            .finalize_with_hash(block_hash_bytes!(b"genesis"));
+        let block1 = genesis
+            .child_builder()
+            .finalize_with_hash(block_hash_bytes!(b"block 1"));
+        tx.insert_block_header(&genesis).unwrap();
+        tx.insert_block_header(&block1).unwrap();
+
+        let cairo0 = class_hash_bytes!(b"cairo 0");
+        let cairo1 = class_hash_bytes!(b"cairo 1");
+        let sierra1 = sierra_hash_bytes!(b"sierra 1");
+        let casm1 = casm_hash_bytes!(b"casm 1");
+        tx.insert_cairo_class(cairo0, b"cairo 0 definition")
+            .unwrap();
+        tx.insert_cairo_class(cairo1, b"cairo 1 definition")
+            .unwrap();
+        tx.insert_sierra_class(&sierra1, b"sierra definition", &casm1, b"casm definition")
+            .unwrap();
+        tx.insert_state_update(
+            genesis.number,
+            &pathfinder_common::StateUpdate::default().with_declared_cairo_class(cairo0),
+        )
+        .unwrap();
+        tx.insert_state_update(
+            block1.number,
+            &pathfinder_common::StateUpdate::default()
+                .with_declared_cairo_class(cairo1)
+                .with_declared_sierra_class(sierra1, casm1),
+        )
+        .unwrap();
+
+        let count = count_classes_declared_after(&tx, genesis.number).unwrap();
+        assert_eq!(count, 2);
+
+        let purged = purge_classes_declared_after(&tx, genesis.number).unwrap();
+        assert_eq!(purged, 2);
+        let count = count_classes_declared_after(&tx, genesis.number).unwrap();
+        assert_eq!(count, 0);
+
+        let sierra1 = ClassHash(sierra1.0);
+        let result = classes_exist(&tx, &[cairo0, cairo1, sierra1]).unwrap();
+        assert_eq!(result, vec![true, false, false]);
+        assert_eq!(casm_definition(&tx, sierra1).unwrap(), None);
+    }
+
     #[test]
     fn compiled_class_leaves() {
         let mut connection = Storage::in_memory().unwrap().connection().unwrap();
@@ -0,0 +1,549 @@
+//! Compression of class definitions and transactions.
+//!
+//! Compressing each row on its own stores the JSON structure shared by all classes or
+//! transactions over and over again. Instead, rows are compressed using a zstd dictionary trained
+//! on existing rows, which captures this shared structure once. Dictionaries are stored in the
+//! `compression_dictionaries` table and each row records the dictionary it was compressed with in
+//! its `dictionary_id` column. Rows compressed before a dictionary existed have no dictionary id
+//! and remain readable, until they are recompressed using [recompress_rows].
+//!
+//! As the stored data changes over time, a new dictionary is trained each time a table has grown
+//! by [RETRAIN_GROWTH] since its latest dictionary was trained. New rows are compressed with the
+//! latest dictionary, and older dictionaries are kept for the rows which still use them.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+
+use crate::prelude::*;
+
+const COMPRESSION_LEVEL: i32 = 10;
+
+/// The number of rows sampled to train a dictionary. No dictionary is trained until a table
+/// holds at least [MIN_TRAINING_ROWS] rows, so that the first dictionary is not trained on the
+/// first few blocks only.
+const SAMPLE_ROWS: i64 = 2_048;
+const MIN_TRAINING_ROWS: i64 = 10_000;
+/// Samples are truncated to this size, which limits the memory used for training.
+const MAX_SAMPLE_SIZE: usize = 32 * 1024;
+/// A new dictionary is trained once a table holds this many times the rows it held when its
+/// latest dictionary was trained.
+const RETRAIN_GROWTH: i64 = 4;
+
+/// zstd recommends a dictionary of about a hundredth of the size of its samples, and 112 KiB
+/// at most.
+const MIN_DICTIONARY_SIZE: usize = 1024;
+const MAX_DICTIONARY_SIZE: usize = 112 * 1024;
+
+/// The data a compression dictionary is trained on and used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DictionaryKind {
+    ClassDefinition,
+    CasmDefinition,
+    /// Transactions and their receipts.
+    Transaction,
+}
+
+impl DictionaryKind {
+    pub const ALL: [Self; 3] = [
+        Self::ClassDefinition,
+        Self::CasmDefinition,
+        Self::Transaction,
+    ];
+
+    fn table(self) -> &'static str {
+        match self {
+            Self::ClassDefinition => "class_definitions",
+            Self::CasmDefinition => "casm_definitions",
+            Self::Transaction => "starknet_transactions",
+        }
+    }
+
+    /// The compressed columns of the table.
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            Self::ClassDefinition | Self::CasmDefinition => &["definition"],
+            Self::Transaction => &["tx", "receipt"],
+        }
+    }
+
+    fn to_sql_int(self) -> i64 {
+        match self {
+            Self::ClassDefinition => 0,
+            Self::CasmDefinition => 1,
+            Self::Transaction => 2,
+        }
+    }
+}
+
+/// A dictionary trained by [train_dictionary].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainedDictionary {
+    dictionary: Vec<u8>,
+    /// The largest `rowid` of the table when the dictionary was trained.
+    max_rowid: i64,
+}
+
+impl TrainedDictionary {
+    pub fn size(&self) -> usize {
+        self.dictionary.len()
+    }
+}
+
+/// Caches dictionaries by their id. Dictionaries are never modified once stored, and there are
+/// only ever a few of them.
+#[derive(Default)]
+pub(crate) struct DictionaryCache(Mutex<HashMap<i64, Arc<Vec<u8>>>>);
+
+fn dictionary(tx: &Transaction<'_>, id: i64) -> anyhow::Result<Arc<Vec<u8>>> {
+    if let Some(dictionary) = tx.dictionary_cache.0.lock().unwrap().get(&id) {
+        return Ok(dictionary.clone());
+    }
+
+    let dictionary: Vec<u8> = tx
+        .inner()
+        .query_row(
+            "SELECT dictionary FROM compression_dictionaries WHERE id = ?",
+            params![&id],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Querying compression dictionary")?
+        .with_context(|| format!("Compression dictionary {id} is missing"))?;
+    let dictionary = Arc::new(dictionary);
+
+    tx.dictionary_cache
+        .0
+        .lock()
+        .unwrap()
+        .insert(id, dictionary.clone());
+
+    Ok(dictionary)
+}
+
+/// Returns the id of the latest dictionary of the given kind, which is used to compress new rows.
+pub(super) fn dictionary_id(
+    tx: &Transaction<'_>,
+    kind: DictionaryKind,
+) -> anyhow::Result<Option<i64>> {
+    tx.inner()
+        .query_row(
+            "SELECT MAX(id) FROM compression_dictionaries WHERE kind = ?",
+            params![&kind.to_sql_int()],
+            |row| row.get_optional_i64(0),
+        )
+        .context("Querying compression dictionary id")
+}
+
+/// Compresses rows with the latest dictionary of a kind, if there is one.
+pub(super) struct Compressor {
+    compressor: zstd::bulk::Compressor<'static>,
+    dictionary_id: Option<i64>,
+}
+
+impl Compressor {
+    pub fn new(tx: &Transaction<'_>, kind: DictionaryKind) -> anyhow::Result<Self> {
+        let dictionary_id = dictionary_id(tx, kind)?;
+        let compressor = match dictionary_id {
+            Some(id) => {
+                let dictionary = dictionary(tx, id)?;
+                zstd::bulk::Compressor::with_dictionary(COMPRESSION_LEVEL, &dictionary)
+            }
+            None => zstd::bulk::Compressor::new(COMPRESSION_LEVEL),
+        }
+        .context("Creating zstd compressor")?;
+
+        Ok(Self {
+            compressor,
+            dictionary_id,
+        })
+    }
+
+    pub fn compress(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        self.compressor.compress(data)
+    }
+
+    /// The dictionary to store alongside the compressed data.
+    pub fn dictionary_id(&self) -> Option<i64> {
+        self.dictionary_id
+    }
+}
+
+/// Decompresses data compressed with the given dictionary, or without one if `dictionary_id`
+/// is `None`.
+pub(super) fn decompress(
+    tx: &Transaction<'_>,
+    data: &[u8],
+    dictionary_id: Option<i64>,
+) -> anyhow::Result<Vec<u8>> {
+    let Some(id) = dictionary_id else {
+        return Ok(zstd::decode_all(data)?);
+    };
+
+    let dictionary = dictionary(tx, id)?;
+    let mut decoder = zstd::stream::read::Decoder::with_dictionary(data, &dictionary)
+        .context("Creating zstd decoder")?;
+    let mut decompressed = Vec::new();
+    decoder.read_to_end(&mut decompressed)?;
+
+    Ok(decompressed)
+}
+
+/// Trains a new dictionary on a sample of the existing rows of the given kind.
+///
+/// Returns `None` if there are too few rows to train a useful dictionary, or if the table has
+/// not grown by [RETRAIN_GROWTH] since its latest dictionary was trained. The number of rows is
+/// estimated by the largest `rowid`, which avoids counting the rows of large tables.
+pub(super) fn train_dictionary(
+    tx: &Transaction<'_>,
+    kind: DictionaryKind,
+) -> anyhow::Result<Option<TrainedDictionary>> {
+    let table = kind.table();
+    let columns = kind.columns();
+
+    let max_rowid = tx
+        .inner()
+        .query_row(
+            &format!("SELECT IFNULL(MAX(rowid), 0) FROM {table}"),
+            [],
+            |row| row.get_i64(0),
+        )
+        .context("Querying largest rowid")?;
+    if max_rowid < MIN_TRAINING_ROWS {
+        return Ok(None);
+    }
+
+    let trained_at = tx
+        .inner()
+        .query_row(
+            "SELECT max_rowid FROM compression_dictionaries WHERE kind = ? ORDER BY id DESC LIMIT 1",
+            params![&kind.to_sql_int()],
+            |row| row.get_i64(0),
+        )
+        .optional()
+        .context("Querying latest dictionary")?;
+    if let Some(trained_at) = trained_at {
+        if max_rowid < trained_at.saturating_mul(RETRAIN_GROWTH) {
+            return Ok(None);
+        }
+    }
+
+    // Spread the samples evenly over the table, as the contents change over time.
+    let mut stmt = tx
+        .inner()
+        .prepare(&format!(
+            "SELECT rowid, {}, dictionary_id FROM {table} WHERE rowid >= ? ORDER BY rowid LIMIT 1",
+            columns.join(", ")
+        ))
+        .context("Preparing sample query")?;
+
+    let mut samples = Vec::new();
+    let mut last_rowid = 0;
+    for i in 0..SAMPLE_ROWS {
+        let rowid = (max_rowid * i / SAMPLE_ROWS).max(last_rowid + 1);
+        let mut rows = stmt.query(params![&rowid]).context("Querying sample")?;
+        let Some(row) = rows.next().context("Iterating over samples")? else {
+            break;
+        };
+
+        last_rowid = row.get_i64(0)?;
+        let dictionary_id = row.get_optional_i64(columns.len() + 1)?;
+        for column in 1..=columns.len() {
+            let Some(data) = row.get_optional_blob(column)? else {
+                continue;
+            };
+            let mut sample = decompress(tx, data, dictionary_id).context("Decompressing sample")?;
+            sample.truncate(MAX_SAMPLE_SIZE);
+            samples.push(sample);
+        }
+    }
+
+    let sample_size = samples.iter().map(Vec::len).sum::<usize>();
+    let dictionary_size = (sample_size / 100).clamp(MIN_DICTIONARY_SIZE, MAX_DICTIONARY_SIZE);
+    let dictionary =
+        zstd::dict::from_samples(&samples, dictionary_size).context("Training dictionary")?;
+
+    Ok(Some(TrainedDictionary {
+        dictionary,
+        max_rowid,
+    }))
+}
+
+/// Stores a dictionary, which is used for all rows of its kind compressed from then on.
+pub(super) fn insert_dictionary(
+    tx: &Transaction<'_>,
+    kind: DictionaryKind,
+    dictionary: &TrainedDictionary,
+) -> anyhow::Result<i64> {
+    tx.inner()
+        .execute(
+            "INSERT INTO compression_dictionaries (kind, dictionary, max_rowid) VALUES (?, ?, ?)",
+            params![
+                &kind.to_sql_int(),
+                &dictionary.dictionary,
+                &dictionary.max_rowid
+            ],
+        )
+        .context("Inserting dictionary")?;
+
+    Ok(tx.inner().last_insert_rowid())
+}
+
+/// Recompresses up to `limit` rows with a `rowid` greater than `after`, which were not
+/// compressed with the latest dictionary of the given kind.
+///
+/// Returns the `rowid` of the last row recompressed, which should be passed as `after` for the
+/// next batch, or `None` once all rows use the latest dictionary.
+pub(super) fn recompress_rows(
+    tx: &Transaction<'_>,
+    kind: DictionaryKind,
+    after: i64,
+    limit: usize,
+) -> anyhow::Result<Option<i64>> {
+    let mut compressor = Compressor::new(tx, kind)?;
+    let Some(dictionary_id) = compressor.dictionary_id() else {
+        return Ok(None);
+    };
+
+    let table = kind.table();
+    let columns = kind.columns();
+
+    let mut select = tx
+        .inner()
+        .prepare(&format!(
+            "SELECT rowid, {}, dictionary_id FROM {table}
+            WHERE rowid > ? AND dictionary_id IS NOT ?
+            ORDER BY rowid LIMIT ?",
+            columns.join(", ")
+        ))
+        .context("Preparing select statement")?;
+    let mut update = tx
+        .inner()
+        .prepare(&format!(
+            "UPDATE {table} SET {}, dictionary_id = ? WHERE rowid = ?",
+            columns
+                .iter()
+                .map(|column| format!("{column} = ?"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+        .context("Preparing update statement")?;
+
+    let mut rows = select
+        .query(params![&after, &dictionary_id, &limit.try_into_sql_int()?])
+        .context("Querying rows")?;
+
+    let mut last_rowid = None;
+    while let Some(row) = rows.next().context("Iterating over rows")? {
+        let rowid = row.get_i64(0)?;
+        let old_dictionary_id = row.get_optional_i64(columns.len() + 1)?;
+
+        let mut values = Vec::with_capacity(columns.len());
+        for column in 1..=columns.len() {
+            let value = match row.get_optional_blob(column)? {
+                Some(data) => {
+                    let data = decompress(tx, data, old_dictionary_id)
+                        .with_context(|| format!("Decompressing row {rowid} of {table}"))?;
+                    let data = compressor
+                        .compress(&data)
+                        .with_context(|| format!("Compressing row {rowid} of {table}"))?;
+                    Some(data)
+                }
+                None => None,
+            };
+            values.push(value);
+        }
+
+        let mut update_params = values
+            .iter()
+            .map(|value| value as &dyn rusqlite::ToSql)
+            .collect::<Vec<_>>();
+        update_params.push(&dictionary_id);
+        update_params.push(&rowid);
+        update
+            .execute(update_params.as_slice())
+            .with_context(|| format!("Updating row {rowid} of {table}"))?;
+
+        last_rowid = Some(rowid);
+    }
+
+    Ok(last_rowid)
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::ClassHash;
+    use pathfinder_crypto::Felt;
+
+    use super::*;
+    use crate::Storage;
+
+    fn class_definition(i: usize) -> Vec<u8> {
+        serde_json::json!({
+            "abi": [{"type": "function", "name": format!("function_{i}"), "inputs": []}],
+            "entry_points_by_type": {
+                "EXTERNAL": [{"selector": format!("0x{i:x}"), "offset": format!("0x{:x}", i * 7)}],
+                "L1_HANDLER": [],
+                "CONSTRUCTOR": []
+            },
+            "program": {"data": (0..20).map(|j| format!("0x{:x}", i * j)).collect::<Vec<_>>()}
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn class_hash(i: usize) -> ClassHash {
+        ClassHash(Felt::from_u64(i as u64))
+    }
+
+    fn dictionary_ids(tx: &Transaction<'_>) -> Vec<Option<i64>> {
+        tx.inner()
+            .prepare("SELECT dictionary_id FROM class_definitions ORDER BY rowid")
+            .unwrap()
+            .query_map([], |row| row.get_optional_i64(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn too_few_rows_to_train() {
+        let storage = Storage::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        tx.insert_cairo_class(class_hash_bytes!(b"class"), &class_definition(0))
+            .unwrap();
+
+        let dictionary = train_dictionary(&tx, DictionaryKind::ClassDefinition).unwrap();
+        assert_eq!(dictionary, None);
+        assert_eq!(
+            recompress_rows(&tx, DictionaryKind::ClassDefinition, 0, 10).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn recompressed_and_old_rows_are_readable() {
+        let storage = Storage::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let count = MIN_TRAINING_ROWS as usize;
+        for i in 0..count {
+            tx.insert_cairo_class(class_hash(i), &class_definition(i))
+                .unwrap();
+        }
+
+        let dictionary = train_dictionary(&tx, DictionaryKind::ClassDefinition)
+            .unwrap()
+            .unwrap();
+        let id = insert_dictionary(&tx, DictionaryKind::ClassDefinition, &dictionary).unwrap();
+        assert_eq!(
+            dictionary_id(&tx, DictionaryKind::ClassDefinition).unwrap(),
+            Some(id)
+        );
+        assert_eq!(
+            dictionary_id(&tx, DictionaryKind::Transaction).unwrap(),
+            None
+        );
+
+        // New rows use the dictionary.
+        tx.insert_cairo_class(class_hash(count), &class_definition(count))
+            .unwrap();
+        let ids = dictionary_ids(&tx);
+        assert_eq!(ids[..count], vec![None; count]);
+        assert_eq!(ids[count], Some(id));
+
+        // Recompress only some of the old rows, in two batches.
+        let last = recompress_rows(&tx, DictionaryKind::ClassDefinition, 0, 10)
+            .unwrap()
+            .unwrap();
+        let last = recompress_rows(&tx, DictionaryKind::ClassDefinition, last, 10)
+            .unwrap()
+            .unwrap();
+        assert_eq!(last, 20);
+        let ids = dictionary_ids(&tx);
+        assert_eq!(ids[..20], vec![Some(id); 20]);
+        assert_eq!(ids[20], None);
+
+        for i in 0..=count {
+            let definition = tx.class_definition(class_hash(i)).unwrap().unwrap();
+            assert_eq!(definition, class_definition(i));
+        }
+
+        let mut after = last;
+        while let Some(last) =
+            recompress_rows(&tx, DictionaryKind::ClassDefinition, after, 100).unwrap()
+        {
+            after = last;
+        }
+        assert!(dictionary_ids(&tx).iter().all(|x| x == &Some(id)));
+
+        for i in 0..=count {
+            let definition = tx.class_definition(class_hash(i)).unwrap().unwrap();
+            assert_eq!(definition, class_definition(i));
+        }
+    }
+
+    #[test]
+    fn retrain_once_the_table_has_grown() {
+        let storage = Storage::in_memory().unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let count = MIN_TRAINING_ROWS as usize;
+        for i in 0..count {
+            tx.insert_cairo_class(class_hash(i), &class_definition(i))
+                .unwrap();
+        }
+
+        let dictionary = train_dictionary(&tx, DictionaryKind::ClassDefinition)
+            .unwrap()
+            .unwrap();
+        assert_eq!(dictionary.max_rowid, MIN_TRAINING_ROWS);
+        let first = insert_dictionary(&tx, DictionaryKind::ClassDefinition, &dictionary).unwrap();
+        let last = recompress_rows(&tx, DictionaryKind::ClassDefinition, 0, 10)
+            .unwrap()
+            .unwrap();
+
+        // The table has not grown since.
+        assert_eq!(
+            train_dictionary(&tx, DictionaryKind::ClassDefinition).unwrap(),
+            None
+        );
+
+        // Pretend the latest dictionary was trained on a much smaller table.
+        let small = TrainedDictionary {
+            max_rowid: MIN_TRAINING_ROWS / RETRAIN_GROWTH,
+            ..dictionary
+        };
+        let second = insert_dictionary(&tx, DictionaryKind::ClassDefinition, &small).unwrap();
+        assert!(second > first);
+
+        let dictionary = train_dictionary(&tx, DictionaryKind::ClassDefinition)
+            .unwrap()
+            .unwrap();
+        let third = insert_dictionary(&tx, DictionaryKind::ClassDefinition, &dictionary).unwrap();
+        assert_eq!(
+            dictionary_id(&tx, DictionaryKind::ClassDefinition).unwrap(),
+            Some(third)
+        );
+
+        // Rows compressed with older dictionaries remain readable.
+        recompress_rows(&tx, DictionaryKind::ClassDefinition, last, 10).unwrap();
+        tx.insert_cairo_class(class_hash(count), &class_definition(count))
+            .unwrap();
+        let ids = dictionary_ids(&tx);
+        assert_eq!(ids[..10], vec![Some(first); 10]);
+        assert_eq!(ids[10..20], vec![Some(third); 10]);
+        assert_eq!(ids[count], Some(third));
+        for i in 0..=count {
+            let definition = tx.class_definition(class_hash(i)).unwrap().unwrap();
+            assert_eq!(definition, class_definition(i));
+        }
+    }
+}
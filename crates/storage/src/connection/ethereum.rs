@@ -1,5 +1,6 @@
 use pathfinder_common::BlockNumber;
-use pathfinder_ethereum::EthereumStateUpdate;
+use pathfinder_ethereum::{EthereumStateUpdate, StateUpdateLog};
+use primitive_types::H256;
 
 use crate::prelude::*;
 
@@ -7,8 +8,9 @@ pub(super) fn upsert_l1_state(
     tx: &Transaction<'_>,
     update: &EthereumStateUpdate,
 ) -> anyhow::Result<()> {
+    // The L1 origin of an update logged earlier is kept.
     tx.inner().execute(
-        r"INSERT OR REPLACE INTO l1_state (
+        r"INSERT INTO l1_state (
                     starknet_block_number,
                     starknet_block_hash,
                     starknet_state_root
@@ -16,7 +18,10 @@ pub(super) fn upsert_l1_state(
                     :starknet_block_number,
                     :starknet_block_hash,
                     :starknet_state_root
-                )",
+                )
+                ON CONFLICT(starknet_block_number) DO UPDATE SET
+                    starknet_block_hash = excluded.starknet_block_hash,
+                    starknet_state_root = excluded.starknet_state_root",
         named_params! {
             ":starknet_block_number": &update.block_number,
             ":starknet_block_hash": &update.block_hash,
@@ -27,6 +32,80 @@ pub(super) fn upsert_l1_state(
     Ok(())
 }
 
+pub(super) fn insert_state_update_log(
+    tx: &Transaction<'_>,
+    log: &StateUpdateLog,
+) -> anyhow::Result<()> {
+    tx.inner().execute(
+        r"INSERT OR REPLACE INTO l1_state (
+                    starknet_block_number,
+                    starknet_block_hash,
+                    starknet_state_root,
+                    l1_block_number,
+                    l1_transaction_hash
+                ) VALUES (
+                    :starknet_block_number,
+                    :starknet_block_hash,
+                    :starknet_state_root,
+                    :l1_block_number,
+                    :l1_transaction_hash
+                )",
+        named_params! {
+            ":starknet_block_number": &log.update.block_number,
+            ":starknet_block_hash": &log.update.block_hash,
+            ":starknet_state_root": &log.update.state_root,
+            ":l1_block_number": &log.l1_block_number,
+            ":l1_transaction_hash": &log.l1_transaction_hash.as_bytes(),
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Returns the logged state update which accepted `block` on L1, which is the first update of
+/// `block` or a later block.
+///
+/// Returns `None` for blocks before the first logged update, which were accepted by an update
+/// that is not logged with its block hash and so is not indexed.
+pub(super) fn l1_acceptance(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+) -> anyhow::Result<Option<StateUpdateLog>> {
+    tx.inner()
+        .query_row(
+            r"SELECT starknet_block_number, starknet_block_hash, starknet_state_root,
+                l1_block_number, l1_transaction_hash
+            FROM l1_state
+            WHERE starknet_block_number >= ?1 AND l1_transaction_hash IS NOT NULL
+                AND ?1 >= (
+                    SELECT MIN(starknet_block_number) FROM l1_state
+                    WHERE l1_transaction_hash IS NOT NULL
+                )
+            ORDER BY starknet_block_number ASC
+            LIMIT 1",
+            params![&block],
+            |row| {
+                let block_number = row.get_block_number(0)?;
+                let block_hash = row.get_block_hash(1)?;
+                let state_root = row.get_state_commitment(2)?;
+                let l1_block_number = row.get_i64(3)? as u64;
+                let l1_transaction_hash = H256::from_slice(row.get_blob(4)?);
+
+                Ok(StateUpdateLog {
+                    update: EthereumStateUpdate {
+                        state_root,
+                        block_number,
+                        block_hash,
+                    },
+                    l1_block_number,
+                    l1_transaction_hash,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| e.into())
+}
+
 pub(super) fn l1_state_at_number(
     tx: &Transaction<'_>,
     block: BlockNumber,
@@ -146,6 +225,61 @@ mod tests {
         }
     }
 
+    #[test]
+    fn l1_acceptance_is_the_next_logged_update() {
+        let storage = Storage::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let log = |update: EthereumStateUpdate, l1_block_number| StateUpdateLog {
+            update,
+            l1_block_number,
+            l1_transaction_hash: H256::from_low_u64_be(l1_block_number),
+        };
+        let [first, second, third] = create_updates();
+        insert_state_update_log(&tx, &log(first.clone(), 100)).unwrap();
+        insert_state_update_log(&tx, &log(third.clone(), 300)).unwrap();
+        // Not logged, so it has no known L1 origin.
+        upsert_l1_state(&tx, &second).unwrap();
+
+        let result = l1_acceptance(&tx, first.block_number).unwrap();
+        assert_eq!(result, Some(log(first.clone(), 100)));
+
+        let result = l1_acceptance(&tx, second.block_number).unwrap();
+        assert_eq!(result, Some(log(third.clone(), 300)));
+
+        let result = l1_acceptance(&tx, third.block_number + 1).unwrap();
+        assert_eq!(result, None);
+
+        // Reading the contract's state does not erase the origin of a logged update.
+        upsert_l1_state(&tx, &first).unwrap();
+        let result = l1_acceptance(&tx, first.block_number).unwrap();
+        assert_eq!(result, Some(log(first, 100)));
+    }
+
+    #[test]
+    fn l1_acceptance_unknown_before_first_logged_update() {
+        let storage = Storage::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let log = |update: EthereumStateUpdate, l1_block_number| StateUpdateLog {
+            update,
+            l1_block_number,
+            l1_transaction_hash: H256::from_low_u64_be(l1_block_number),
+        };
+        let [first, second, third] = create_updates();
+        upsert_l1_state(&tx, &first).unwrap();
+        insert_state_update_log(&tx, &log(second.clone(), 200)).unwrap();
+        insert_state_update_log(&tx, &log(third, 300)).unwrap();
+
+        let result = l1_acceptance(&tx, first.block_number).unwrap();
+        assert_eq!(result, None);
+
+        let result = l1_acceptance(&tx, second.block_number).unwrap();
+        assert_eq!(result, Some(log(second, 200)));
+    }
+
     #[test]
     fn upsert_overwrites() {
         let storage = Storage::in_memory().unwrap();
@@ -0,0 +1,467 @@
+//! Messages between L1 and L2, identified by their hash.
+//!
+//! The L1 side of a message is recorded from the core contract's logs, and the L2 side when its
+//! block is inserted. Either side may be recorded first, since L1 and L2 are synced
+//! independently.
+
+use anyhow::Context;
+use pathfinder_common::{BlockNumber, TransactionHash};
+use pathfinder_ethereum::{ConsumedMessageToL1Log, MessageToL2Log};
+use primitive_types::H256;
+use starknet_gateway_types::reply::transaction as gateway;
+
+use crate::prelude::*;
+
+/// An L1 to L2 message sent by an L1 transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L1ToL2Message {
+    pub message_hash: H256,
+    /// The L1 handler transaction which consumed the message, if it has been synced.
+    pub l2_transaction_hash: Option<TransactionHash>,
+    /// Whether the message may have been consumed by an L1 handler transaction which is not
+    /// linked to it, because its block was stored before messages were tracked.
+    pub consumption_unknown: bool,
+}
+
+/// The L2 transaction which sent an L2 to L1 message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct L2ToL1MessageOrigin {
+    pub block_number: BlockNumber,
+    pub transaction_hash: TransactionHash,
+}
+
+pub(super) fn insert_message_to_l2_log(
+    tx: &Transaction<'_>,
+    log: &MessageToL2Log,
+) -> anyhow::Result<()> {
+    tx.inner()
+        .execute(
+            r"INSERT INTO l1_to_l2_messages (message_hash, l1_block_number, l1_transaction_hash)
+            VALUES (:message_hash, :l1_block_number, :l1_transaction_hash)
+            ON CONFLICT(message_hash) DO UPDATE SET
+                l1_block_number = excluded.l1_block_number,
+                l1_transaction_hash = excluded.l1_transaction_hash",
+            named_params! {
+                ":message_hash": &log.message_hash.as_bytes(),
+                ":l1_block_number": &log.l1_block_number,
+                ":l1_transaction_hash": &log.l1_transaction_hash.as_bytes(),
+            },
+        )
+        .context("Inserting message to L2")?;
+
+    Ok(())
+}
+
+/// Records the L1 handler transaction which consumed the message.
+pub(super) fn insert_l1_handler_message(
+    tx: &Transaction<'_>,
+    message_hash: H256,
+    transaction_hash: TransactionHash,
+) -> anyhow::Result<()> {
+    tx.inner()
+        .execute(
+            r"INSERT INTO l1_to_l2_messages (message_hash, l2_transaction_hash)
+            VALUES (:message_hash, :l2_transaction_hash)
+            ON CONFLICT(message_hash) DO UPDATE SET
+                l2_transaction_hash = excluded.l2_transaction_hash",
+            named_params! {
+                ":message_hash": &message_hash.as_bytes(),
+                ":l2_transaction_hash": &transaction_hash,
+            },
+        )
+        .context("Inserting L1 handler message")?;
+
+    Ok(())
+}
+
+/// Unlinks the messages consumed by the L1 handler transactions of a block which is purged.
+pub(super) fn unlink_l1_handler_messages(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+) -> anyhow::Result<()> {
+    tx.inner()
+        .execute(
+            r"UPDATE l1_to_l2_messages SET l2_transaction_hash = NULL
+            WHERE l2_transaction_hash IN (
+                SELECT starknet_transactions.hash FROM starknet_transactions
+                JOIN canonical_blocks ON starknet_transactions.block_hash = canonical_blocks.hash
+                WHERE canonical_blocks.number = ?
+            )",
+            params![&block],
+        )
+        .context("Unlinking L1 handler messages")?;
+
+    Ok(())
+}
+
+/// Marks the L1 handler transactions of all blocks before `block` as not linked to the messages
+/// they consumed, because they were stored before messages were tracked or are not stored at all.
+pub(super) fn set_first_block_with_l1_handler_links(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+) -> anyhow::Result<()> {
+    tx.inner()
+        .execute(
+            "INSERT OR REPLACE INTO storage_options (option, value) VALUES ('l1_handlers_linked_from', ?)",
+            params![&block],
+        )
+        .context("Updating first block with L1 handler links")?;
+
+    Ok(())
+}
+
+fn first_block_with_l1_handler_links(tx: &Transaction<'_>) -> anyhow::Result<BlockNumber> {
+    let block = tx
+        .inner()
+        .query_row(
+            "SELECT value FROM storage_options WHERE option = 'l1_handlers_linked_from'",
+            [],
+            |row| row.get_block_number(0),
+        )
+        .optional()
+        .context("Querying first block with L1 handler links")?;
+
+    Ok(block.unwrap_or_default())
+}
+
+/// Returns the messages sent by the L1 transaction, in no particular order.
+pub(super) fn l1_to_l2_messages(
+    tx: &Transaction<'_>,
+    l1_transaction_hash: H256,
+) -> anyhow::Result<Vec<L1ToL2Message>> {
+    let mut stmt = tx
+        .inner()
+        .prepare_cached(
+            r"SELECT message_hash, l2_transaction_hash, l1_block_number FROM l1_to_l2_messages
+            WHERE l1_transaction_hash = ?",
+        )
+        .context("Preparing statement")?;
+
+    let messages = stmt
+        .query_map(params![&l1_transaction_hash.as_bytes()], |row| {
+            let message_hash = H256::from_slice(row.get_blob(0)?);
+            let l2_transaction_hash = row.get_optional_felt(1)?.map(TransactionHash);
+            let l1_block_number = row.get_i64(2)? as u64;
+
+            Ok((
+                L1ToL2Message {
+                    message_hash,
+                    l2_transaction_hash,
+                    consumption_unknown: false,
+                },
+                l1_block_number,
+            ))
+        })
+        .context("Querying messages")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Iterating over messages")?;
+
+    // A message sent after the last unlinked block was accepted on L1 cannot have been consumed
+    // in that block or an earlier one.
+    let unlinked_accepted_at = match first_block_with_l1_handler_links(tx)?.parent() {
+        Some(last_unlinked) => Some(
+            super::ethereum::l1_acceptance(tx, last_unlinked)
+                .context("Querying L1 acceptance of last unlinked block")?
+                .map(|log| log.l1_block_number),
+        ),
+        None => None,
+    };
+
+    let messages = messages
+        .into_iter()
+        .map(|(mut message, l1_block_number)| {
+            message.consumption_unknown = message.l2_transaction_hash.is_none()
+                && match unlinked_accepted_at {
+                    Some(Some(accepted_at)) => l1_block_number <= accepted_at,
+                    Some(None) => true,
+                    None => false,
+                };
+            message
+        })
+        .collect();
+
+    Ok(messages)
+}
+
+pub(super) fn insert_l2_to_l1_messages(
+    tx: &Transaction<'_>,
+    block_number: BlockNumber,
+    transaction_hash: TransactionHash,
+    messages: &[gateway::L2ToL1Message],
+) -> anyhow::Result<()> {
+    let mut stmt = tx
+        .inner()
+        .prepare_cached(
+            r"INSERT INTO l2_to_l1_messages (message_hash, block_number, transaction_hash)
+            VALUES (?, ?, ?)",
+        )
+        .context("Preparing statement")?;
+
+    for message in messages {
+        stmt.execute(params![
+            &message.calculate_message_hash().as_bytes(),
+            &block_number,
+            &transaction_hash,
+        ])
+        .context("Inserting L2 to L1 message")?;
+    }
+
+    Ok(())
+}
+
+pub(super) fn delete_l2_to_l1_messages(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+) -> anyhow::Result<()> {
+    tx.inner()
+        .execute(
+            "DELETE FROM l2_to_l1_messages WHERE block_number = ?",
+            params![&block],
+        )
+        .context("Deleting L2 to L1 messages")?;
+
+    Ok(())
+}
+
+pub(super) fn insert_consumed_message_to_l1_log(
+    tx: &Transaction<'_>,
+    log: &ConsumedMessageToL1Log,
+) -> anyhow::Result<()> {
+    tx.inner()
+        .execute(
+            r"INSERT OR REPLACE INTO consumed_l2_to_l1_messages
+                (l1_block_number, l1_log_index, l1_transaction_hash, message_hash)
+            VALUES (:l1_block_number, :l1_log_index, :l1_transaction_hash, :message_hash)",
+            named_params! {
+                ":l1_block_number": &log.l1_block_number,
+                ":l1_log_index": &log.l1_log_index,
+                ":l1_transaction_hash": &log.l1_transaction_hash.as_bytes(),
+                ":message_hash": &log.message_hash.as_bytes(),
+            },
+        )
+        .context("Inserting consumed message to L1")?;
+
+    Ok(())
+}
+
+/// Returns the L2 transactions which sent the message, in the order they were sent.
+pub(super) fn l2_to_l1_messages(
+    tx: &Transaction<'_>,
+    message_hash: H256,
+) -> anyhow::Result<Vec<L2ToL1MessageOrigin>> {
+    let mut stmt = tx
+        .inner()
+        .prepare_cached(
+            r"SELECT block_number, transaction_hash FROM l2_to_l1_messages
+            WHERE message_hash = ?
+            ORDER BY block_number, rowid",
+        )
+        .context("Preparing statement")?;
+
+    let messages = stmt
+        .query_map(params![&message_hash.as_bytes()], |row| {
+            Ok(L2ToL1MessageOrigin {
+                block_number: row.get_block_number(0)?,
+                transaction_hash: row.get_transaction_hash(1)?,
+            })
+        })
+        .context("Querying messages")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Iterating over messages")?;
+
+    Ok(messages)
+}
+
+/// Returns the consumptions of the message on L1, in the order they were logged.
+pub(super) fn l2_to_l1_message_consumptions(
+    tx: &Transaction<'_>,
+    message_hash: H256,
+) -> anyhow::Result<Vec<ConsumedMessageToL1Log>> {
+    let mut stmt = tx
+        .inner()
+        .prepare_cached(
+            r"SELECT l1_block_number, l1_log_index, l1_transaction_hash
+            FROM consumed_l2_to_l1_messages
+            WHERE message_hash = ?
+            ORDER BY l1_block_number, l1_log_index",
+        )
+        .context("Preparing statement")?;
+
+    let consumptions = stmt
+        .query_map(params![&message_hash.as_bytes()], |row| {
+            Ok(ConsumedMessageToL1Log {
+                message_hash,
+                l1_block_number: row.get_i64(0)? as u64,
+                l1_log_index: row.get_i64(1)? as u64,
+                l1_transaction_hash: H256::from_slice(row.get_blob(2)?),
+            })
+        })
+        .context("Querying consumptions")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Iterating over consumptions")?;
+
+    Ok(consumptions)
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_crypto::Felt;
+
+    use super::*;
+    use crate::Storage;
+
+    #[test]
+    fn either_side_can_be_recorded_first() {
+        let storage = Storage::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let l1_transaction_hash = H256::from_low_u64_be(0xabcd);
+        let log = |message_hash| MessageToL2Log {
+            message_hash,
+            l1_block_number: 100,
+            l1_transaction_hash,
+        };
+        let first = H256::from_low_u64_be(1);
+        let second = H256::from_low_u64_be(2);
+
+        insert_message_to_l2_log(&tx, &log(first)).unwrap();
+        insert_l1_handler_message(&tx, first, transaction_hash_bytes!(b"first")).unwrap();
+        insert_l1_handler_message(&tx, second, transaction_hash_bytes!(b"second")).unwrap();
+        insert_l1_handler_message(
+            &tx,
+            H256::from_low_u64_be(3),
+            transaction_hash_bytes!(b"other"),
+        )
+        .unwrap();
+        insert_message_to_l2_log(&tx, &log(second)).unwrap();
+
+        let mut messages = l1_to_l2_messages(&tx, l1_transaction_hash).unwrap();
+        messages.sort_by_key(|message| message.message_hash);
+        assert_eq!(
+            messages,
+            vec![
+                L1ToL2Message {
+                    message_hash: first,
+                    l2_transaction_hash: Some(transaction_hash_bytes!(b"first")),
+                    consumption_unknown: false,
+                },
+                L1ToL2Message {
+                    message_hash: second,
+                    l2_transaction_hash: Some(transaction_hash_bytes!(b"second")),
+                    consumption_unknown: false,
+                },
+            ]
+        );
+
+        let messages = l1_to_l2_messages(&tx, H256::from_low_u64_be(0xffff)).unwrap();
+        assert!(messages.is_empty());
+    }
+
+    #[test]
+    fn consumption_unknown_before_first_linked_block() {
+        let storage = Storage::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let log = |message_hash, l1_block_number| MessageToL2Log {
+            message_hash,
+            l1_block_number,
+            l1_transaction_hash: H256::from_low_u64_be(l1_block_number),
+        };
+        let consumption_unknown = |l1_block_number| {
+            let messages = l1_to_l2_messages(&tx, H256::from_low_u64_be(l1_block_number)).unwrap();
+            messages[0].consumption_unknown
+        };
+        insert_message_to_l2_log(&tx, &log(H256::from_low_u64_be(1), 100)).unwrap();
+        insert_message_to_l2_log(&tx, &log(H256::from_low_u64_be(2), 300)).unwrap();
+
+        // All L1 handler transactions are linked.
+        assert!(!consumption_unknown(100));
+
+        set_first_block_with_l1_handler_links(&tx, BlockNumber::new_or_panic(10)).unwrap();
+        assert!(consumption_unknown(100));
+        assert!(consumption_unknown(300));
+
+        // Messages sent after the last unlinked block was accepted on L1 are consumed later.
+        super::super::ethereum::insert_state_update_log(
+            &tx,
+            &pathfinder_ethereum::StateUpdateLog {
+                update: pathfinder_ethereum::EthereumStateUpdate {
+                    block_number: BlockNumber::new_or_panic(9),
+                    ..Default::default()
+                },
+                l1_block_number: 200,
+                l1_transaction_hash: H256::from_low_u64_be(200),
+            },
+        )
+        .unwrap();
+        assert!(consumption_unknown(100));
+        assert!(!consumption_unknown(300));
+
+        // A linked message is known to be consumed.
+        insert_l1_handler_message(
+            &tx,
+            H256::from_low_u64_be(1),
+            transaction_hash_bytes!(b"tx"),
+        )
+        .unwrap();
+        assert!(!consumption_unknown(100));
+    }
+
+    #[test]
+    fn l2_to_l1_messages_and_consumptions() {
+        let storage = Storage::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let message = gateway::L2ToL1Message {
+            from_address: contract_address_bytes!(b"l2 contract"),
+            payload: vec![l2_to_l1_message_payload_elem_bytes!(b"amount")],
+            to_address: pathfinder_common::EthereumAddress(primitive_types::H160::zero()),
+        };
+        let message_hash = message.calculate_message_hash();
+
+        for number in [2, 1] {
+            let block_number = BlockNumber::new_or_panic(number);
+            insert_l2_to_l1_messages(
+                &tx,
+                block_number,
+                TransactionHash(Felt::from_u64(number)),
+                &[message.clone()],
+            )
+            .unwrap();
+        }
+
+        let consumption = |l1_block_number, l1_log_index| ConsumedMessageToL1Log {
+            message_hash,
+            l1_block_number,
+            l1_log_index,
+            l1_transaction_hash: H256::from_low_u64_be(l1_block_number),
+        };
+        insert_consumed_message_to_l1_log(&tx, &consumption(100, 2)).unwrap();
+        insert_consumed_message_to_l1_log(&tx, &consumption(100, 1)).unwrap();
+        // Logs are identified by their position, so inserting one again has no effect.
+        insert_consumed_message_to_l1_log(&tx, &consumption(100, 1)).unwrap();
+
+        let origin = |number| L2ToL1MessageOrigin {
+            block_number: BlockNumber::new_or_panic(number),
+            transaction_hash: TransactionHash(Felt::from_u64(number)),
+        };
+        assert_eq!(
+            l2_to_l1_messages(&tx, message_hash).unwrap(),
+            vec![origin(1), origin(2)]
+        );
+        assert_eq!(
+            l2_to_l1_message_consumptions(&tx, message_hash).unwrap(),
+            vec![consumption(100, 1), consumption(100, 2)]
+        );
+
+        delete_l2_to_l1_messages(&tx, BlockNumber::new_or_panic(2)).unwrap();
+        assert_eq!(
+            l2_to_l1_messages(&tx, message_hash).unwrap(),
+            vec![origin(1)]
+        );
+    }
+}
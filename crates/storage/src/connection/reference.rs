@@ -23,6 +23,25 @@ pub(super) fn l1_l2_pointer(tx: &Transaction<'_>) -> anyhow::Result<Option<Block
         .map_err(|e| e.into())
 }
 
+/// Sets the last L1 block whose core contract logs have been processed.
+pub(super) fn update_l1_logs_head(tx: &Transaction<'_>, l1_block: u64) -> anyhow::Result<()> {
+    tx.inner().execute(
+        "UPDATE refs SET l1_logs_head = ? WHERE idx = 1",
+        params![&l1_block],
+    )?;
+
+    Ok(())
+}
+
+pub(super) fn l1_logs_head(tx: &Transaction<'_>) -> anyhow::Result<Option<u64>> {
+    tx.inner()
+        .query_row("SELECT l1_logs_head FROM refs WHERE idx = 1", [], |row| {
+            row.get_optional_i64(0)
+        })
+        .map(|head| head.map(|head| head as u64))
+        .map_err(|e| e.into())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::Storage;
@@ -57,4 +76,16 @@ mod tests {
         let result = l1_l2_pointer(&tx).unwrap();
         assert_eq!(result, None);
     }
+
+    #[test]
+    fn l1_logs_head() {
+        let storage = Storage::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        assert_eq!(super::l1_logs_head(&tx).unwrap(), None);
+
+        update_l1_logs_head(&tx, 1234).unwrap();
+        assert_eq!(super::l1_logs_head(&tx).unwrap(), Some(1234));
+    }
 }
@@ -273,12 +273,72 @@ pub(super) fn state_update(
     Ok(Some(state_update))
 }
 
+/// Marks the state of all blocks before `block` as unavailable. This is the case for databases
+/// initialized from a snapshot, which only contains the state at the snapshot's block.
+pub(super) fn set_first_block_with_state(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+) -> anyhow::Result<()> {
+    tx.inner()
+        .execute(
+            "INSERT OR REPLACE INTO storage_options (option, value) VALUES ('state_available_from', ?)",
+            params![&block],
+        )
+        .context("Updating first block with state")?;
+
+    Ok(())
+}
+
+/// Returns the oldest block whose state is available.
+pub(super) fn first_block_with_state(tx: &Transaction<'_>) -> anyhow::Result<BlockNumber> {
+    let block = tx
+        .inner()
+        .query_row(
+            "SELECT value FROM storage_options WHERE option = 'state_available_from'",
+            [],
+            |row| row.get_block_number(0),
+        )
+        .optional()
+        .context("Querying first block with state")?;
+
+    Ok(block.unwrap_or_default())
+}
+
+/// Fails instead of returning incorrect results when querying the state of a block which is not
+/// available.
+fn ensure_state_available(tx: &Transaction<'_>, block: BlockId) -> anyhow::Result<()> {
+    if block == BlockId::Latest {
+        return Ok(());
+    }
+    let first = first_block_with_state(tx)?;
+    if first == BlockNumber::GENESIS {
+        return Ok(());
+    }
+
+    let number = match block {
+        BlockId::Number(number) => number,
+        // Unknown blocks have no state either way.
+        _ => match tx.block_id(block)? {
+            Some((number, _)) => number,
+            None => return Ok(()),
+        },
+    };
+    anyhow::ensure!(
+        number >= first,
+        "State of block {number} is not available, the database only contains the state from block {first} onwards"
+    );
+
+    Ok(())
+}
+
 pub(super) fn storage_value(
     tx: &Transaction<'_>,
     block: BlockId,
     contract_address: ContractAddress,
     key: StorageAddress,
 ) -> anyhow::Result<Option<StorageValue>> {
+    ensure_state_available(tx, block)?;
+
     match block {
         BlockId::Latest => {
             let mut stmt = tx.inner().prepare_cached(
@@ -322,6 +382,8 @@ pub(super) fn contract_exists(
     contract_address: ContractAddress,
     block_id: BlockId,
 ) -> anyhow::Result<bool> {
+    ensure_state_available(tx, block_id)?;
+
     match block_id {
         BlockId::Number(number) => {
             let mut stmt = tx.inner().prepare_cached(
@@ -363,6 +425,8 @@ pub(super) fn contract_nonce(
     contract_address: ContractAddress,
     block_id: BlockId,
 ) -> anyhow::Result<Option<ContractNonce>> {
+    ensure_state_available(tx, block_id)?;
+
     match block_id {
         BlockId::Latest => {
             let mut stmt = tx.inner().prepare_cached(
@@ -404,6 +468,8 @@ pub(super) fn contract_class_hash(
     block_id: BlockId,
     contract_address: ContractAddress,
 ) -> anyhow::Result<Option<ClassHash>> {
+    ensure_state_available(tx, block_id)?;
+
     match block_id {
         BlockId::Latest => {
             let mut stmt = tx.inner().prepare_cached(
@@ -440,6 +506,51 @@ pub(super) fn contract_class_hash(
     .map_err(|e| e.into())
 }
 
+pub(super) fn deployed_contracts(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+) -> anyhow::Result<Vec<(ContractAddress, ClassHash)>> {
+    // SQLite takes the bare columns from the row with the maximum block number.
+    let mut stmt = tx.inner().prepare_cached(
+        r"SELECT contract_address, class_hash, MAX(block_number) FROM contract_updates
+        WHERE block_number <= ?
+        GROUP BY contract_address
+        ORDER BY contract_address",
+    )?;
+
+    let contracts = stmt
+        .query_map(params![&block], |row| {
+            Ok((row.get_contract_address(0)?, row.get_class_hash(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Querying deployed contracts")?;
+
+    Ok(contracts)
+}
+
+pub(super) fn contract_storage(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+    contract_address: ContractAddress,
+) -> anyhow::Result<Vec<(StorageAddress, StorageValue)>> {
+    // SQLite takes the bare columns from the row with the maximum block number.
+    let mut stmt = tx.inner().prepare_cached(
+        r"SELECT storage_address, storage_value, MAX(block_number) FROM storage_updates
+        WHERE contract_address = ? AND block_number <= ?
+        GROUP BY storage_address",
+    )?;
+
+    let storage = stmt
+        .query_map(params![&contract_address, &block], |row| {
+            Ok((row.get_storage_address(0)?, row.get_storage_value(1)?))
+        })?
+        .filter(|entry| !matches!(entry, Ok((_, value)) if value.0.is_zero()))
+        .collect::<Result<Vec<_>, _>>()
+        .context("Querying contract storage")?;
+
+    Ok(storage)
+}
+
 #[cfg(test)]
 mod tests {
     use pathfinder_common::macro_prelude::*;
@@ -770,4 +881,100 @@ mod tests {
             assert_eq!(by_number, None);
         }
     }
+
+    #[test]
+    fn contract_state_at_block() {
+        let mut db = crate::Storage::in_memory().unwrap().connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let contract_a = contract_address!("0xa");
+        let contract_b = contract_address!("0xb");
+        let class_a = class_hash!("0x1a");
+        let class_b = class_hash!("0x1b");
+        let key_1 = storage_address!("0x1");
+        let key_2 = storage_address!("0x2");
+
+        let header_0 = BlockHeader::builder().finalize_with_hash(block_hash!("0xabc"));
+        let header_1 = header_0
+            .child_builder()
+            .finalize_with_hash(block_hash!("0xabcdef"));
+        tx.insert_block_header(&header_0).unwrap();
+        tx.insert_block_header(&header_1).unwrap();
+
+        let diff_0 = StateUpdate::default()
+            .with_deployed_contract(contract_b, class_b)
+            .with_storage_update(contract_b, key_1, storage_value!("0x10"))
+            .with_storage_update(contract_b, key_2, storage_value!("0x20"));
+        let diff_1 = StateUpdate::default()
+            .with_deployed_contract(contract_a, class_a)
+            .with_replaced_class(contract_b, class_a)
+            .with_storage_update(contract_b, key_1, StorageValue::ZERO)
+            .with_storage_update(contract_b, key_2, storage_value!("0x21"));
+        tx.insert_state_update(header_0.number, &diff_0).unwrap();
+        tx.insert_state_update(header_1.number, &diff_1).unwrap();
+
+        let contracts = deployed_contracts(&tx, header_0.number).unwrap();
+        assert_eq!(contracts, vec![(contract_b, class_b)]);
+        let contracts = deployed_contracts(&tx, header_1.number).unwrap();
+        assert_eq!(
+            contracts,
+            vec![(contract_a, class_a), (contract_b, class_a)]
+        );
+
+        let mut storage = contract_storage(&tx, header_0.number, contract_b).unwrap();
+        storage.sort_by_key(|(key, _)| *key);
+        assert_eq!(
+            storage,
+            vec![
+                (key_1, storage_value!("0x10")),
+                (key_2, storage_value!("0x20"))
+            ]
+        );
+        // Zeroed values are omitted.
+        let storage = contract_storage(&tx, header_1.number, contract_b).unwrap();
+        assert_eq!(storage, vec![(key_2, storage_value!("0x21"))]);
+    }
+
+    #[test]
+    fn state_before_first_block_with_state_is_unavailable() {
+        let mut db = crate::Storage::in_memory().unwrap().connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let contract = contract_address!("0xa");
+        let key = storage_address!("0x1");
+
+        let header_0 = BlockHeader::builder().finalize_with_hash(block_hash!("0xabc"));
+        let header_1 = header_0
+            .child_builder()
+            .finalize_with_hash(block_hash!("0xabcdef"));
+        tx.insert_block_header(&header_0).unwrap();
+        tx.insert_block_header(&header_1).unwrap();
+
+        let diff = StateUpdate::default()
+            .with_deployed_contract(contract, class_hash!("0x1a"))
+            .with_contract_nonce(contract, contract_nonce!("0x1"))
+            .with_storage_update(contract, key, storage_value!("0x10"));
+        tx.insert_state_update(header_1.number, &diff).unwrap();
+        tx.set_first_block_with_state(header_1.number).unwrap();
+        assert_eq!(tx.first_block_with_state().unwrap(), header_1.number);
+
+        for block in [header_0.number.into(), header_0.hash.into()] {
+            storage_value(&tx, block, contract, key).unwrap_err();
+            contract_nonce(&tx, contract, block).unwrap_err();
+            contract_class_hash(&tx, block, contract).unwrap_err();
+            contract_exists(&tx, contract, block).unwrap_err();
+        }
+
+        for block in [
+            header_1.number.into(),
+            header_1.hash.into(),
+            BlockId::Latest,
+        ] {
+            assert_eq!(
+                storage_value(&tx, block, contract, key).unwrap(),
+                Some(storage_value!("0x10"))
+            );
+            assert!(contract_exists(&tx, contract, block).unwrap());
+        }
+    }
 }
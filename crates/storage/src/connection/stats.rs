@@ -0,0 +1,208 @@
+use anyhow::Context;
+
+use crate::prelude::*;
+
+/// The kind of data a table holds, used to group the tables of [DatabaseStats].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DataCategory {
+    /// Block headers, signatures and the canonical chain.
+    Blocks,
+    /// Transactions and receipts, which includes the events.
+    Transactions,
+    /// Event bloom filters.
+    Events,
+    /// Storage, nonce and class updates of each block.
+    StateDiffs,
+    /// Class definitions and compiled classes.
+    Classes,
+    /// State trie nodes and roots, and the leaves derived from them.
+    Tries,
+    /// Everything else, including SQLite's own tables.
+    Other,
+}
+
+impl DataCategory {
+    fn of_table(table: &str) -> Self {
+        match table {
+            "block_headers" | "block_signatures" | "canonical_blocks" | "starknet_versions"
+            | "l1_state" => Self::Blocks,
+            "starknet_transactions" => Self::Transactions,
+            "starknet_events_filters" => Self::Events,
+            "storage_updates" | "nonce_updates" | "contract_updates" => Self::StateDiffs,
+            "class_definitions" | "casm_definitions" => Self::Classes,
+            "class_commitment_leaves" | "contract_state_hashes" => Self::Tries,
+            table if table.starts_with("trie_") || table.ends_with("_roots") => Self::Tries,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// The space used by a table, including its indexes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableStats {
+    pub name: String,
+    pub category: DataCategory,
+    /// The number of rows in the table.
+    pub rows: u64,
+    /// The total size of the pages used by the table and its indexes.
+    pub size: u64,
+    /// The number of bytes of the table's rows, excluding indexes and page overhead.
+    pub payload: u64,
+    /// The number of unused bytes within the pages of the table and its indexes.
+    pub unused: u64,
+}
+
+impl TableStats {
+    pub fn average_row_size(&self) -> u64 {
+        self.payload.checked_div(self.rows).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseStats {
+    pub page_size: u64,
+    pub page_count: u64,
+    /// The number of unused pages, which are reclaimed by `VACUUM`.
+    pub freelist_count: u64,
+    /// Sorted by size, largest first.
+    pub tables: Vec<TableStats>,
+}
+
+impl DatabaseStats {
+    pub fn size(&self) -> u64 {
+        self.page_size * self.page_count
+    }
+
+    /// The space `VACUUM` would free, which is at least the free pages. Unused space within
+    /// pages may be reclaimed as well, but that depends on how the rows get repacked.
+    pub fn vacuum_savings(&self) -> u64 {
+        self.page_size * self.freelist_count
+    }
+}
+
+/// Reads the size of every table using SQLite's [dbstat](https://sqlite.org/dbstat.html) virtual
+/// table. This reads every page of the database, so it takes a while for large databases.
+pub(super) fn database_stats(tx: &Transaction<'_>) -> anyhow::Result<DatabaseStats> {
+    let pragma = |name: &str| -> anyhow::Result<u64> {
+        tx.inner()
+            .pragma_query_value(None, name, |row| row.get::<_, u64>(0))
+            .with_context(|| format!("Querying {name}"))
+    };
+    let page_size = pragma("page_size")?;
+    let page_count = pragma("page_count")?;
+    let freelist_count = pragma("freelist_count")?;
+
+    // Indexes are accounted to their table. Only a table's own leaf cells are its rows, and
+    // only its own payload is row data.
+    let mut stmt = tx
+        .inner()
+        .prepare(
+            r"SELECT
+    COALESCE(sqlite_master.tbl_name, dbstat.name) AS tbl,
+    SUM(CASE WHEN dbstat.name = sqlite_master.tbl_name AND pagetype = 'leaf' THEN ncell ELSE 0 END),
+    SUM(pgsize),
+    SUM(CASE WHEN dbstat.name = sqlite_master.tbl_name THEN payload ELSE 0 END),
+    SUM(unused)
+FROM dbstat
+LEFT JOIN sqlite_master ON sqlite_master.name = dbstat.name
+GROUP BY tbl
+ORDER BY SUM(pgsize) DESC",
+        )
+        .context("Preparing dbstat query")?;
+
+    let tables = stmt
+        .query_map([], |row| {
+            let name: String = row.get(0)?;
+            Ok(TableStats {
+                category: DataCategory::of_table(&name),
+                name,
+                rows: row.get(1)?,
+                size: row.get(2)?,
+                payload: row.get(3)?,
+                unused: row.get(4)?,
+            })
+        })
+        .context("Querying dbstat")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Iterating over dbstat")?;
+
+    Ok(DatabaseStats {
+        page_size,
+        page_count,
+        freelist_count,
+        tables,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::BlockHeader;
+
+    use super::*;
+    use crate::Storage;
+
+    #[test]
+    fn tables_are_reported_with_their_indexes() {
+        let storage = Storage::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let genesis = BlockHeader::builder().finalize_with_hash(block_hash_bytes!(b"genesis"));
+        let block1 = genesis
+            .child_builder()
+            .finalize_with_hash(block_hash_bytes!(b"block 1"));
+        tx.insert_block_header(&genesis).unwrap();
+        tx.insert_block_header(&block1).unwrap();
+
+        let stats = database_stats(&tx).unwrap();
+        assert_eq!(stats.size(), stats.page_size * stats.page_count);
+
+        let headers = stats
+            .tables
+            .iter()
+            .find(|table| table.name == "block_headers")
+            .unwrap();
+        assert_eq!(headers.category, DataCategory::Blocks);
+        assert_eq!(headers.rows, 2);
+        assert!(headers.payload > 0);
+        assert!(headers.size >= headers.payload);
+
+        // Indexes are not listed separately.
+        for table in &stats.tables {
+            let kind = tx
+                .inner()
+                .query_row(
+                    "SELECT type FROM sqlite_master WHERE name = ?",
+                    [&table.name],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()
+                .unwrap();
+            assert_ne!(kind.as_deref(), Some("index"), "{}", table.name);
+        }
+        assert!(stats.tables.windows(2).all(|w| w[0].size >= w[1].size));
+    }
+
+    #[test]
+    fn categories() {
+        assert_eq!(DataCategory::of_table("trie_storage"), DataCategory::Tries);
+        assert_eq!(
+            DataCategory::of_table("trie_storage_removals"),
+            DataCategory::Tries
+        );
+        assert_eq!(DataCategory::of_table("storage_roots"), DataCategory::Tries);
+        assert_eq!(
+            DataCategory::of_table("casm_definitions"),
+            DataCategory::Classes
+        );
+        assert_eq!(
+            DataCategory::of_table("storage_updates"),
+            DataCategory::StateDiffs
+        );
+        assert_eq!(
+            DataCategory::of_table("sqlite_sequence"),
+            DataCategory::Other
+        );
+    }
+}
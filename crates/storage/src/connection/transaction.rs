@@ -4,6 +4,7 @@ use anyhow::Context;
 use pathfinder_common::{BlockHash, BlockNumber, TransactionHash};
 use starknet_gateway_types::reply::transaction as gateway;
 
+use super::compression::{self, Compressor, DictionaryKind};
 use crate::{prelude::*, BlockId};
 
 pub enum TransactionStatus {
@@ -21,7 +22,10 @@ pub(super) fn insert_transactions(
         return Ok(());
     }
 
-    let mut compressor = zstd::bulk::Compressor::new(10).context("Create zstd compressor")?;
+    // Transactions are replaced if the block is inserted again, and so are its messages.
+    super::message::delete_l2_to_l1_messages(tx, block_number)?;
+
+    let mut compressor = Compressor::new(tx, DictionaryKind::Transaction)?;
     for (i, (transaction, receipt)) in transaction_data.iter().enumerate() {
         // Serialize and compress transaction data.
         let tx_data = serde_json::to_vec(&transaction).context("Serializing transaction")?;
@@ -39,8 +43,8 @@ pub(super) fn insert_transactions(
             gateway::ExecutionStatus::Reverted => 1,
         };
 
-        tx.inner().execute(r"INSERT OR REPLACE INTO starknet_transactions (hash,  idx,  block_hash,  tx,  receipt,  execution_status) 
-                                                                  VALUES (:hash, :idx, :block_hash, :tx, :receipt, :execution_status)",
+        tx.inner().execute(r"INSERT OR REPLACE INTO starknet_transactions (hash,  idx,  block_hash,  tx,  receipt,  execution_status,  dictionary_id) 
+                                                                  VALUES (:hash, :idx, :block_hash, :tx, :receipt, :execution_status, :dictionary_id)",
             named_params![
             ":hash": &transaction.hash(),
             ":idx": &i.try_into_sql_int()?,
@@ -48,7 +52,22 @@ pub(super) fn insert_transactions(
             ":tx": &tx_data,
             ":receipt": &serialized_receipt,
             ":execution_status": &execution_status,
+            ":dictionary_id": &compressor.dictionary_id(),
         ]).context("Inserting transaction data")?;
+
+        if let gateway::Transaction::L1Handler(l1_handler) = transaction {
+            super::message::insert_l1_handler_message(
+                tx,
+                l1_handler.calculate_message_hash(),
+                l1_handler.transaction_hash,
+            )?;
+        }
+        super::message::insert_l2_to_l1_messages(
+            tx,
+            block_number,
+            receipt.transaction_hash,
+            &receipt.l2_to_l1_messages,
+        )?;
     }
 
     let events = transaction_data
@@ -59,13 +78,112 @@ pub(super) fn insert_transactions(
     Ok(())
 }
 
+/// The maximum number of blocks whose bodies are deleted by a single [prune_block_bodies] call.
+/// This prevents pruning an existing database from stalling sync, and instead spreads the work
+/// over the following blocks.
+const MAX_BLOCKS_PRUNED_AT_ONCE: u64 = 1_000;
+
+pub(super) fn prune_block_bodies(tx: &Transaction<'_>, head: BlockNumber) -> anyhow::Result<()> {
+    let Some(num_blocks_kept) = tx.block_body_history else {
+        return Ok(());
+    };
+    let Some(prune_before) = head.get().checked_sub(num_blocks_kept) else {
+        return Ok(());
+    };
+
+    let pruned_before = block_bodies_pruned_before(tx)?;
+    let prune_before = prune_before.min(pruned_before + MAX_BLOCKS_PRUNED_AT_ONCE);
+    if prune_before <= pruned_before {
+        return Ok(());
+    }
+
+    // The hashes are kept, so that lookups by hash can tell pruned transactions from unknown ones.
+    tx.inner()
+        .execute(
+            r"UPDATE starknet_transactions SET tx = NULL, receipt = NULL WHERE block_hash IN (
+                SELECT hash FROM canonical_blocks WHERE number >= ? AND number < ?
+            )",
+            params![&pruned_before, &prune_before],
+        )
+        .context("Deleting transactions")?;
+    tx.inner()
+        .execute(
+            "DELETE FROM starknet_events_filters WHERE block_number >= ? AND block_number < ?",
+            params![&pruned_before, &prune_before],
+        )
+        .context("Deleting event filters")?;
+
+    // Never exceeds `head`, and is therefore a valid block number.
+    set_first_unpruned_block_body(tx, BlockNumber::new_or_panic(prune_before))
+}
+
+pub(super) fn set_first_unpruned_block_body(
+    tx: &Transaction<'_>,
+    block: BlockNumber,
+) -> anyhow::Result<()> {
+    tx.inner()
+        .execute(
+            "INSERT OR REPLACE INTO storage_options (option, value) VALUES ('block_bodies_pruned_before', ?)",
+            params![&block],
+        )
+        .context("Updating pruned block bodies")?;
+
+    Ok(())
+}
+
+pub(super) fn block_body_pruned(tx: &Transaction<'_>, block: BlockNumber) -> anyhow::Result<bool> {
+    Ok(block.get() < block_bodies_pruned_before(tx)?)
+}
+
+pub(super) fn transaction_body_pruned(
+    tx: &Transaction<'_>,
+    transaction: TransactionHash,
+) -> anyhow::Result<bool> {
+    let block = tx
+        .inner()
+        .query_row(
+            r"SELECT number FROM starknet_transactions
+            JOIN canonical_blocks ON starknet_transactions.block_hash = canonical_blocks.hash
+            WHERE starknet_transactions.hash = ?",
+            params![&transaction],
+            |row| row.get_block_number(0),
+        )
+        .optional()
+        .context("Querying transaction block number")?;
+
+    match block {
+        Some(block) => block_body_pruned(tx, block),
+        None => Ok(false),
+    }
+}
+
+pub(super) fn first_unpruned_block_body(tx: &Transaction<'_>) -> anyhow::Result<BlockNumber> {
+    let block = block_bodies_pruned_before(tx)?;
+    BlockNumber::new(block).context("Pruned block bodies exceed block number range")
+}
+
+/// Returns the number of the first block whose body has not been pruned.
+fn block_bodies_pruned_before(tx: &Transaction<'_>) -> anyhow::Result<u64> {
+    let pruned_before = tx
+        .inner()
+        .query_row(
+            "SELECT value FROM storage_options WHERE option = 'block_bodies_pruned_before'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Querying pruned block bodies")?;
+
+    Ok(pruned_before.unwrap_or_default())
+}
+
 pub(super) fn transaction(
     tx: &Transaction<'_>,
     transaction: TransactionHash,
 ) -> anyhow::Result<Option<gateway::Transaction>> {
     let mut stmt = tx
         .inner()
-        .prepare("SELECT tx FROM starknet_transactions WHERE hash = ?")
+        .prepare("SELECT tx, dictionary_id FROM starknet_transactions WHERE hash = ?")
         .context("Preparing statement")?;
 
     let mut rows = stmt
@@ -77,8 +195,13 @@ pub(super) fn transaction(
         None => return Ok(None),
     };
 
-    let transaction = row.get_ref_unwrap(0).as_blob()?;
-    let transaction = zstd::decode_all(transaction).context("Decompressing transaction")?;
+    let transaction = match row.get_ref_unwrap(0).as_blob_or_null()? {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    let dictionary_id = row.get_optional_i64(1)?;
+    let transaction = compression::decompress(tx, transaction, dictionary_id)
+        .context("Decompressing transaction")?;
     let transaction = serde_json::from_slice(&transaction).context("Deserializing transaction")?;
 
     Ok(Some(transaction))
@@ -90,7 +213,9 @@ pub(super) fn transaction_with_receipt(
 ) -> anyhow::Result<Option<(gateway::Transaction, gateway::Receipt, BlockHash)>> {
     let mut stmt = tx
         .inner()
-        .prepare("SELECT tx, receipt, block_hash FROM starknet_transactions WHERE hash = ?1")
+        .prepare(
+            "SELECT tx, receipt, block_hash, dictionary_id FROM starknet_transactions WHERE hash = ?1",
+        )
         .context("Preparing statement")?;
 
     let mut rows = stmt.query(params![&txn_hash]).context("Executing query")?;
@@ -100,15 +225,21 @@ pub(super) fn transaction_with_receipt(
         None => return Ok(None),
     };
 
-    let transaction = row.get_ref_unwrap("tx").as_blob()?;
-    let transaction = zstd::decode_all(transaction).context("Decompressing transaction")?;
+    let dictionary_id = row.get_optional_i64("dictionary_id")?;
+    let transaction = match row.get_ref_unwrap("tx").as_blob_or_null()? {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    let transaction = compression::decompress(tx, transaction, dictionary_id)
+        .context("Decompressing transaction")?;
     let transaction = serde_json::from_slice(&transaction).context("Deserializing transaction")?;
 
     let receipt = match row.get_ref_unwrap("receipt").as_blob_or_null()? {
         Some(data) => data,
         None => return Ok(None),
     };
-    let receipt = zstd::decode_all(receipt).context("Decompressing receipt")?;
+    let receipt =
+        compression::decompress(tx, receipt, dictionary_id).context("Decompressing receipt")?;
     let receipt = serde_json::from_slice(&receipt).context("Deserializing receipt")?;
 
     let block_hash = row.get_block_hash("block_hash")?;
@@ -128,7 +259,9 @@ pub(super) fn transaction_at_block(
 
     let mut stmt = tx
         .inner()
-        .prepare("SELECT tx FROM starknet_transactions WHERE block_hash = ? AND idx = ?")
+        .prepare(
+            "SELECT tx, dictionary_id FROM starknet_transactions WHERE block_hash = ? AND idx = ?",
+        )
         .context("Preparing statement")?;
 
     let mut rows = stmt
@@ -145,7 +278,9 @@ pub(super) fn transaction_at_block(
         None => return Ok(None),
     };
 
-    let transaction = zstd::decode_all(transaction).context("Decompressing transaction")?;
+    let dictionary_id = row.get_optional_i64(1)?;
+    let transaction = compression::decompress(tx, transaction, dictionary_id)
+        .context("Decompressing transaction")?;
     let transaction = serde_json::from_slice(&transaction).context("Deserializing transaction")?;
 
     Ok(Some(transaction))
@@ -194,7 +329,7 @@ pub(super) fn transaction_data_for_block(
     let mut stmt = tx
         .inner()
         .prepare(
-            "SELECT tx, receipt FROM starknet_transactions WHERE block_hash = ? ORDER BY idx ASC",
+            "SELECT tx, receipt, dictionary_id FROM starknet_transactions WHERE block_hash = ? AND tx IS NOT NULL ORDER BY idx ASC",
         )
         .context("Preparing statement")?;
 
@@ -204,11 +339,13 @@ pub(super) fn transaction_data_for_block(
 
     let mut data = Vec::new();
     while let Some(row) = rows.next()? {
+        let dictionary_id = row.get_optional_i64("dictionary_id")?;
         let receipt = row
             .get_ref_unwrap("receipt")
             .as_blob_or_null()?
             .context("Receipt data missing")?;
-        let receipt = zstd::decode_all(receipt).context("Decompressing transaction receipt")?;
+        let receipt = compression::decompress(tx, receipt, dictionary_id)
+            .context("Decompressing transaction receipt")?;
         let receipt =
             serde_json::from_slice(&receipt).context("Deserializing transaction receipt")?;
 
@@ -216,7 +353,8 @@ pub(super) fn transaction_data_for_block(
             .get_ref_unwrap("tx")
             .as_blob_or_null()?
             .context("Transaction data missing")?;
-        let transaction = zstd::decode_all(transaction).context("Decompressing transaction")?;
+        let transaction = compression::decompress(tx, transaction, dictionary_id)
+            .context("Decompressing transaction")?;
         let transaction =
             serde_json::from_slice(&transaction).context("Deserializing transaction")?;
 
@@ -236,7 +374,9 @@ pub(super) fn transactions_for_block(
 
     let mut stmt = tx
         .inner()
-        .prepare("SELECT tx FROM starknet_transactions WHERE block_hash = ? ORDER BY idx ASC")
+        .prepare(
+            "SELECT tx, dictionary_id FROM starknet_transactions WHERE block_hash = ? AND tx IS NOT NULL ORDER BY idx ASC",
+        )
         .context("Preparing statement")?;
 
     let mut rows = stmt
@@ -245,11 +385,13 @@ pub(super) fn transactions_for_block(
 
     let mut data = Vec::new();
     while let Some(row) = rows.next()? {
+        let dictionary_id = row.get_optional_i64("dictionary_id")?;
         let transaction = row
             .get_ref_unwrap("tx")
             .as_blob_or_null()?
             .context("Transaction data missing")?;
-        let transaction = zstd::decode_all(transaction).context("Decompressing transaction")?;
+        let transaction = compression::decompress(tx, transaction, dictionary_id)
+            .context("Decompressing transaction")?;
         let transaction =
             serde_json::from_slice(&transaction).context("Deserializing transaction")?;
 
@@ -269,7 +411,9 @@ pub(super) fn receipts_for_block(
 
     let mut stmt = tx
         .inner()
-        .prepare("SELECT receipt FROM starknet_transactions WHERE block_hash = ? ORDER BY idx ASC")
+        .prepare(
+            "SELECT receipt, dictionary_id FROM starknet_transactions WHERE block_hash = ? AND tx IS NOT NULL ORDER BY idx ASC",
+        )
         .context("Preparing statement")?;
 
     let mut rows = stmt
@@ -278,11 +422,13 @@ pub(super) fn receipts_for_block(
 
     let mut data = Vec::new();
     while let Some(row) = rows.next()? {
+        let dictionary_id = row.get_optional_i64("dictionary_id")?;
         let receipt = row
             .get_ref_unwrap("receipt")
             .as_blob_or_null()?
             .context("Transaction data missing")?;
-        let receipt = zstd::decode_all(receipt).context("Decompressing receipt")?;
+        let receipt =
+            compression::decompress(tx, receipt, dictionary_id).context("Decompressing receipt")?;
         let receipt = serde_json::from_slice(&receipt).context("Deserializing receipt")?;
 
         data.push(receipt);
@@ -637,4 +783,38 @@ mod tests {
             super::transaction_block_hash(&tx, transaction_hash_bytes!(b"invalid hash")).unwrap();
         assert_eq!(invalid, None);
     }
+
+    #[test]
+    fn prune_block_bodies() {
+        let (mut db, header, body) = setup();
+        let mut tx = db.transaction().unwrap();
+
+        // Without a configured history nothing is pruned.
+        super::prune_block_bodies(&tx, header.number + 10).unwrap();
+        assert!(!super::block_body_pruned(&tx, header.number).unwrap());
+
+        tx.block_body_history = Some(1);
+        super::prune_block_bodies(&tx, header.number + 1).unwrap();
+        assert!(!super::block_body_pruned(&tx, header.number).unwrap());
+
+        super::prune_block_bodies(&tx, header.number + 2).unwrap();
+        assert!(super::block_body_pruned(&tx, header.number).unwrap());
+        assert!(!super::block_body_pruned(&tx, header.number + 1).unwrap());
+
+        let result = super::transaction(&tx, body[0].0.hash()).unwrap();
+        assert_eq!(result, None);
+        let result = super::transaction_with_receipt(&tx, body[0].0.hash()).unwrap();
+        assert_eq!(result, None);
+        assert!(super::transaction_body_pruned(&tx, body[0].0.hash()).unwrap());
+        let unknown = transaction_hash_bytes!(b"unknown hash");
+        assert!(!super::transaction_body_pruned(&tx, unknown).unwrap());
+        let result = super::transaction_data_for_block(&tx, header.number.into())
+            .unwrap()
+            .unwrap();
+        assert!(result.is_empty());
+
+        // The header is kept.
+        let result = tx.block_header(header.number.into()).unwrap();
+        assert_eq!(result, Some(header));
+    }
 }
@@ -6,7 +6,9 @@ use bitvec::vec::BitVec;
 use pathfinder_common::prelude::*;
 use pathfinder_crypto::Felt;
 
+use super::trie_backend::Trie;
 use crate::prelude::*;
+use crate::TriePruneMode;
 
 macros::create_trie_fns!(trie_class);
 macros::create_trie_fns!(trie_contracts);
@@ -139,20 +141,165 @@ pub(super) fn insert_contract_root(
     Ok(())
 }
 
+pub(super) fn prune_tries(tx: &Transaction<'_>, head: BlockNumber) -> anyhow::Result<()> {
+    let TriePruneMode::Prune { num_blocks_kept } = tx.trie_prune_mode else {
+        return Ok(());
+    };
+    // The tries of this block, and all blocks after it, are kept.
+    let Some(oldest) = head.get().checked_sub(num_blocks_kept) else {
+        return Ok(());
+    };
+    let oldest = BlockNumber::new_or_panic(oldest);
+
+    let removed = trie_class::take_removals(tx, oldest).context("Pruning class trie")?;
+    tx.trie_backend
+        .delete(tx, Trie::Class, &removed)
+        .context("Pruning class trie")?;
+    let removed = trie_contracts::take_removals(tx, oldest).context("Pruning contract tries")?;
+    tx.trie_backend
+        .delete(tx, Trie::Contract, &removed)
+        .context("Pruning contract tries")?;
+    let removed = trie_storage::take_removals(tx, oldest).context("Pruning storage trie")?;
+    tx.trie_backend
+        .delete(tx, Trie::Storage, &removed)
+        .context("Pruning storage trie")?;
+
+    // Blocks whose parent tries are pruned can no longer be purged.
+    trie_class::forget_additions(tx, oldest).context("Pruning class trie additions")?;
+    trie_contracts::forget_additions(tx, oldest).context("Pruning contract trie additions")?;
+    trie_storage::forget_additions(tx, oldest).context("Pruning storage trie additions")?;
+
+    prune_roots(tx.inner(), oldest)
+}
+
+/// Stores the new nodes of a trie of the given block and returns the index of the root.
+///
+/// With [trie pruning](TriePruneMode::Prune) the new nodes are recorded, so that they can be
+/// deleted if the block is purged.
+pub(super) fn insert_trie(
+    tx: &Transaction<'_>,
+    trie: Trie,
+    block: BlockNumber,
+    root: Felt,
+    nodes: &HashMap<Felt, Node>,
+) -> anyhow::Result<u64> {
+    let added = tx.trie_backend.insert(tx, trie, root, nodes)?;
+    let root_index = *added.last().context("Root node was not stored")?;
+
+    if tx.trie_prune_mode != TriePruneMode::Archive {
+        match trie {
+            Trie::Class => trie_class::insert_additions(tx, block, &added),
+            Trie::Contract => trie_contracts::insert_additions(tx, block, &added),
+            Trie::Storage => trie_storage::insert_additions(tx, block, &added),
+        }?;
+    }
+
+    Ok(root_index)
+}
+
+/// Deletes the nodes which were added to the tries by the given block. They are only part of the
+/// block's own tries, as later blocks have been purged already.
+///
+/// Nothing is deleted unless [trie pruning](TriePruneMode::Prune) is enabled, as the added nodes
+/// are not recorded otherwise.
+pub(super) fn delete_added_nodes(tx: &Transaction<'_>, block: BlockNumber) -> anyhow::Result<()> {
+    let added = trie_class::take_additions(tx, block).context("Querying class trie additions")?;
+    tx.trie_backend
+        .delete(tx, Trie::Class, &added)
+        .context("Deleting class trie nodes")?;
+    let added =
+        trie_contracts::take_additions(tx, block).context("Querying contract trie additions")?;
+    tx.trie_backend
+        .delete(tx, Trie::Contract, &added)
+        .context("Deleting contract trie nodes")?;
+    let added =
+        trie_storage::take_additions(tx, block).context("Querying storage trie additions")?;
+    tx.trie_backend
+        .delete(tx, Trie::Storage, &added)
+        .context("Deleting storage trie nodes")?;
+
+    Ok(())
+}
+
+/// Deletes the trie roots of the blocks before `oldest`, apart from the contract roots which are
+/// still the latest root of their contract at `oldest`.
+pub(crate) fn prune_roots(
+    connection: &rusqlite::Connection,
+    oldest: BlockNumber,
+) -> anyhow::Result<()> {
+    // The roots of the blocks before this one have been pruned already. It is usually the block
+    // before `oldest`, unless the number of kept blocks has been lowered since.
+    let pruned_before = connection
+        .query_row("SELECT MIN(block_number) FROM storage_roots", [], |row| {
+            row.get::<_, Option<u64>>(0)
+        })
+        .context("Querying oldest storage root")?
+        .unwrap_or_default();
+
+    // Contract roots are only inserted when a contract's storage changes. The older roots are
+    // therefore only obsolete once a contract has a newer root which is not after `oldest`.
+    connection
+        .execute(
+            r"DELETE FROM contract_roots WHERE rowid IN (
+                SELECT older.rowid FROM contract_roots AS newer
+                JOIN contract_roots AS older ON older.contract_address = newer.contract_address
+                    AND older.block_number < newer.block_number
+                WHERE newer.block_number BETWEEN ?1 AND ?2
+            )",
+            params![&pruned_before, &oldest],
+        )
+        .context("Deleting contract roots")?;
+    connection
+        .execute(
+            "DELETE FROM class_roots WHERE block_number < ?",
+            params![&oldest],
+        )
+        .context("Deleting class roots")?;
+    connection
+        .execute(
+            "DELETE FROM storage_roots WHERE block_number < ?",
+            params![&oldest],
+        )
+        .context("Deleting storage roots")?;
+
+    Ok(())
+}
+
+pub(super) fn trie_pruned(tx: &Transaction<'_>, block: BlockNumber) -> anyhow::Result<bool> {
+    if tx.trie_prune_mode == TriePruneMode::Archive {
+        return Ok(false);
+    }
+
+    // Every block has a storage root, which gets deleted once the block's tries are pruned.
+    tx.inner()
+        .query_row(
+            "SELECT NOT EXISTS(SELECT 1 FROM storage_roots WHERE block_number <= ?)",
+            params![&block],
+            |row| row.get(0),
+        )
+        .map_err(Into::into)
+}
+
 mod macros {
-    /// Generates the `insert`, `node` and `hash` trie functions for the given table name, within
-    /// a module with the table name.
+    /// Generates the `insert`, `node`, `hash` and `delete` functions of the SQLite
+    /// [trie backend](crate::SqliteTrieBackend), and the functions tracking the nodes added and
+    /// removed by each block, for the given table name, within a module with the table name.
     macro_rules! create_trie_fns {
         ($table: ident) => {
             pub(super) mod $table {
                 use super::*;
 
-                /// Stores the node data for this trie and returns the index of the root.
+                /// Stores the node data for this trie and returns the indices of the new nodes,
+                /// the root's being the last.
+                ///
+                /// Every occurrence of a node is stored separately, even if an identical node
+                /// exists elsewhere in the trie. Each stored node therefore has exactly one parent,
+                /// which is what allows removed nodes to be pruned.
                 pub fn insert(
                     tx: &Transaction<'_>,
                     root: Felt,
                     nodes: &HashMap<Felt, Node>,
-                ) -> anyhow::Result<u64> {
+                ) -> anyhow::Result<Vec<u64>> {
                     let mut stmt = tx
                         .inner()
                         .prepare_cached(concat!(
@@ -162,63 +309,202 @@ mod macros {
                         ))
                         .context("Creating insert statement")?;
 
-                    let mut to_insert = Vec::new();
-                    let mut to_process = vec![Child::Hash(root)];
-
-                    while let Some(node) = to_process.pop() {
-                        // Only hash variants need to be stored.
-                        //
-                        // Leaf nodes never get stored and a node having an
-                        // ID indicates it has already been stored as part of a
-                        // previous tree - and its children as well.
-                        let Child::Hash(hash) = node else {
-                            continue;
-                        };
-
-                        let node = nodes.get(&hash).context("New node data is missing")?;
-                        to_insert.push(hash);
-
-                        match node {
-                            Node::Binary { left, right } => {
-                                to_process.push(left.clone());
-                                to_process.push(right.clone());
-                            }
-                            Node::Edge { child, .. } => {
-                                to_process.push(child.clone());
-                            }
-                            // Leaves are not stored as separate nodes but are instead serialized in-line in their parents.
-                            Node::LeafEdge { .. } | Node::LeafBinary { .. } => {}
-                        }
-                    }
-
-                    let mut indices = HashMap::new();
-
                     // Reusable (and oversized) buffer for encoding.
                     let mut buffer = vec![0u8; 256];
+                    let mut indices = Vec::new();
 
-                    // Insert nodes in reverse to ensure children always have an assigned index for the parent to use.
-                    for hash in to_insert.into_iter().rev() {
-                        let node = nodes
-                            .get(&hash)
-                            .expect("Node must exist as hash is dependent on this");
-
-                        let node = node.as_stored(&indices)?;
-
+                    insert_nodes(nodes, root, &mut |hash, node| {
                         let length = node.encode(&mut buffer).context("Encoding node")?;
 
-                        let idx: u64 = stmt
+                        let index = stmt
                             .query_row(
                                 params![&hash.as_be_bytes().as_slice(), &&buffer[..length]],
                                 |row| row.get(0),
                             )
                             .context("Inserting node")?;
+                        indices.push(index);
+                        Ok(index)
+                    })?;
+
+                    Ok(indices)
+                }
+
+                /// Records the indices of the nodes which stopped being part of this trie at
+                /// the given block.
+                pub fn insert_removals(
+                    tx: &Transaction<'_>,
+                    block: BlockNumber,
+                    indices: &[u64],
+                ) -> anyhow::Result<()> {
+                    if indices.is_empty() {
+                        return Ok(());
+                    }
+
+                    let indices = bincode::encode_to_vec(indices, bincode::config::standard())
+                        .context("Encoding removed node indices")?;
+
+                    tx.inner()
+                        .execute(
+                            concat!(
+                                "INSERT INTO ",
+                                stringify!($table),
+                                "_removals (block_number, indices) VALUES(?, ?)",
+                            ),
+                            params![&block, &indices],
+                        )
+                        .context("Inserting removed node indices")?;
+
+                    Ok(())
+                }
+
+                /// Records the indices of the nodes which were added to this trie by the given
+                /// block.
+                pub fn insert_additions(
+                    tx: &Transaction<'_>,
+                    block: BlockNumber,
+                    indices: &[u64],
+                ) -> anyhow::Result<()> {
+                    if indices.is_empty() {
+                        return Ok(());
+                    }
+
+                    let indices = bincode::encode_to_vec(indices, bincode::config::standard())
+                        .context("Encoding added node indices")?;
+
+                    tx.inner()
+                        .execute(
+                            concat!(
+                                "INSERT INTO ",
+                                stringify!($table),
+                                "_additions (block_number, indices) VALUES(?, ?)",
+                            ),
+                            params![&block, &indices],
+                        )
+                        .context("Inserting added node indices")?;
+
+                    Ok(())
+                }
+
+                /// Returns the indices of the nodes which were added to this trie by the given
+                /// block, and forgets about them. The nodes themselves still need to be deleted.
+                pub fn take_additions(
+                    tx: &Transaction<'_>,
+                    block: BlockNumber,
+                ) -> anyhow::Result<Vec<u64>> {
+                    let mut select = tx
+                        .inner()
+                        .prepare_cached(concat!(
+                            "SELECT indices FROM ",
+                            stringify!($table),
+                            "_additions WHERE block_number = ?",
+                        ))
+                        .context("Creating select statement")?;
+
+                    let mut added = Vec::new();
+                    let mut rows = select
+                        .query(params![&block])
+                        .context("Querying added node indices")?;
+                    while let Some(row) = rows.next().context("Iterating over rows")? {
+                        let indices: Vec<u8> = row.get(0)?;
+                        let (indices, _): (Vec<u64>, _) =
+                            bincode::decode_from_slice(&indices, bincode::config::standard())
+                                .context("Decoding added node indices")?;
+
+                        added.extend(indices);
+                    }
 
-                        indices.insert(hash, idx);
+                    tx.inner()
+                        .execute(
+                            concat!(
+                                "DELETE FROM ",
+                                stringify!($table),
+                                "_additions WHERE block_number = ?",
+                            ),
+                            params![&block],
+                        )
+                        .context("Deleting added node indices")?;
+
+                    Ok(added)
+                }
+
+                /// Forgets which nodes were added to this trie at or before the given block.
+                pub fn forget_additions(
+                    tx: &Transaction<'_>,
+                    block: BlockNumber,
+                ) -> anyhow::Result<()> {
+                    tx.inner()
+                        .execute(
+                            concat!(
+                                "DELETE FROM ",
+                                stringify!($table),
+                                "_additions WHERE block_number <= ?",
+                            ),
+                            params![&block],
+                        )
+                        .context("Deleting added node indices")?;
+
+                    Ok(())
+                }
+
+                /// Returns the indices of the nodes which were removed from this trie at or before
+                /// the given block, and forgets about them. The nodes themselves still need to be
+                /// deleted.
+                pub fn take_removals(
+                    tx: &Transaction<'_>,
+                    block: BlockNumber,
+                ) -> anyhow::Result<Vec<u64>> {
+                    let mut select = tx
+                        .inner()
+                        .prepare_cached(concat!(
+                            "SELECT indices FROM ",
+                            stringify!($table),
+                            "_removals WHERE block_number <= ?",
+                        ))
+                        .context("Creating select statement")?;
+
+                    let mut removed = Vec::new();
+                    let mut rows = select
+                        .query(params![&block])
+                        .context("Querying removed node indices")?;
+                    while let Some(row) = rows.next().context("Iterating over rows")? {
+                        let indices: Vec<u8> = row.get(0)?;
+                        let (indices, _): (Vec<u64>, _) =
+                            bincode::decode_from_slice(&indices, bincode::config::standard())
+                                .context("Decoding removed node indices")?;
+
+                        removed.extend(indices);
                     }
 
-                    Ok(*indices
-                        .get(&root)
-                        .expect("Root index must exist as we just inserted it"))
+                    tx.inner()
+                        .execute(
+                            concat!(
+                                "DELETE FROM ",
+                                stringify!($table),
+                                "_removals WHERE block_number <= ?",
+                            ),
+                            params![&block],
+                        )
+                        .context("Deleting removed node indices")?;
+
+                    Ok(removed)
+                }
+
+                /// Deletes the nodes with the given indices.
+                pub fn delete(tx: &Transaction<'_>, indices: &[u64]) -> anyhow::Result<()> {
+                    let mut delete = tx
+                        .inner()
+                        .prepare_cached(concat!(
+                            "DELETE FROM ",
+                            stringify!($table),
+                            " WHERE idx = ?",
+                        ))
+                        .context("Creating delete statement")?;
+
+                    for idx in indices {
+                        delete.execute(params![idx]).context("Deleting node")?;
+                    }
+
+                    Ok(())
                 }
 
                 /// Returns the node with the given index.
@@ -273,6 +559,51 @@ mod macros {
     pub(super) use create_trie_fns;
 }
 
+/// Inserts the node with the given hash, and any of its new descendants, using `store` which
+/// stores a single node and returns its index. Returns the index of the inserted node.
+///
+/// Children are stored before their parents, as a parent refers to its children by index.
+pub(super) fn insert_nodes(
+    nodes: &HashMap<Felt, Node>,
+    hash: Felt,
+    store: &mut dyn FnMut(Felt, &StoredNode) -> anyhow::Result<u64>,
+) -> anyhow::Result<u64> {
+    let node = match nodes.get(&hash).context("New node data is missing")? {
+        Node::Binary { left, right } => {
+            let left = insert_child(nodes, left, store).context("Inserting left child")?;
+            let right = insert_child(nodes, right, store).context("Inserting right child")?;
+
+            StoredNode::Binary { left, right }
+        }
+        Node::Edge { child, path } => {
+            let child = insert_child(nodes, child, store).context("Inserting child")?;
+
+            StoredNode::Edge {
+                child,
+                path: path.clone(),
+            }
+        }
+        // Leaves are not stored as separate nodes but are instead serialized in-line in their parents.
+        Node::LeafEdge { path } => StoredNode::LeafEdge { path: path.clone() },
+        Node::LeafBinary => StoredNode::LeafBinary,
+    };
+
+    store(hash, &node)
+}
+
+/// Returns the index of the child, inserting it first if it is a new node.
+fn insert_child(
+    nodes: &HashMap<Felt, Node>,
+    child: &Child,
+    store: &mut dyn FnMut(Felt, &StoredNode) -> anyhow::Result<u64>,
+) -> anyhow::Result<u64> {
+    match child {
+        // A node having an ID indicates it has already been stored as part of a previous tree.
+        Child::Id(idx) => Ok(*idx),
+        Child::Hash(hash) => insert_nodes(nodes, *hash, store),
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Node {
     Binary {
@@ -410,41 +741,6 @@ impl StoredNode {
     }
 }
 
-impl Node {
-    fn as_stored(&self, indices: &HashMap<Felt, u64>) -> anyhow::Result<StoredNode> {
-        let node = match self {
-            Node::Binary { left, right } => {
-                let left = match left {
-                    Child::Id(id) => *id,
-                    Child::Hash(hash) => *indices.get(hash).context("Left child index missing")?,
-                };
-
-                let right = match right {
-                    Child::Id(id) => *id,
-                    Child::Hash(hash) => *indices.get(hash).context("Right child index missing")?,
-                };
-
-                StoredNode::Binary { left, right }
-            }
-            Node::Edge { child, path } => {
-                let child = match child {
-                    Child::Id(id) => id,
-                    Child::Hash(hash) => indices.get(hash).context("Child index missing")?,
-                };
-
-                StoredNode::Edge {
-                    child: *child,
-                    path: path.clone(),
-                }
-            }
-            Node::LeafEdge { path } => StoredNode::LeafEdge { path: path.clone() },
-            Node::LeafBinary => StoredNode::LeafBinary,
-        };
-
-        Ok(node)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -534,7 +830,10 @@ mod tests {
         let mut nodes = HashMap::new();
         nodes.insert(root0.0, root_node.clone());
 
-        let idx0 = trie_contracts::insert(&tx, root0.0, &nodes).unwrap();
+        let idx0 = *trie_contracts::insert(&tx, root0.0, &nodes)
+            .unwrap()
+            .last()
+            .unwrap();
 
         let result1 = contract_root_index(&tx, BlockNumber::GENESIS, c1).unwrap();
         assert_eq!(result1, None);
@@ -552,7 +851,10 @@ mod tests {
         let root1 = contract_root_bytes!(b"root 1");
         nodes.clear();
         nodes.insert(root1.0, root_node.clone());
-        let idx1 = trie_contracts::insert(&tx, root1.0, &nodes).unwrap();
+        let idx1 = *trie_contracts::insert(&tx, root1.0, &nodes)
+            .unwrap()
+            .last()
+            .unwrap();
 
         insert_contract_root(&tx, BlockNumber::GENESIS + 1, c1, Some(idx1)).unwrap();
         insert_contract_root(&tx, BlockNumber::GENESIS + 1, c2, Some(888)).unwrap();
@@ -578,7 +880,10 @@ mod tests {
         let root2 = contract_root_bytes!(b"root 2");
         nodes.clear();
         nodes.insert(root2.0, root_node.clone());
-        let idx2 = trie_contracts::insert(&tx, root2.0, &nodes).unwrap();
+        let idx2 = *trie_contracts::insert(&tx, root2.0, &nodes)
+            .unwrap()
+            .last()
+            .unwrap();
 
         insert_contract_root(&tx, BlockNumber::GENESIS + 10, c1, Some(idx2)).unwrap();
         insert_contract_root(&tx, BlockNumber::GENESIS + 11, c2, Some(999)).unwrap();
@@ -649,6 +954,11 @@ mod tests {
                 [],
             )
             .unwrap();
+            db.execute(
+                "CREATE TABLE test_table_removals (block_number INTEGER NOT NULL, indices BLOB NOT NULL)",
+                [],
+            )
+            .unwrap();
 
             db
         }
@@ -745,7 +1055,10 @@ mod tests {
             nodes.insert(edge_hash, edge_node);
             nodes.insert(root_hash, root_node);
 
-            let root_idx = test_table::insert(&tx, root_hash, &nodes).unwrap();
+            let root_idx = *test_table::insert(&tx, root_hash, &nodes)
+                .unwrap()
+                .last()
+                .unwrap();
 
             // Root node
             let hash = test_table::hash(&tx, root_idx).unwrap();
@@ -774,6 +1087,87 @@ mod tests {
             node.into_binary_leaf().unwrap();
         }
 
+        #[test]
+        fn identical_nodes_are_stored_separately() {
+            let mut db = setup_db();
+            let tx = db.transaction().unwrap();
+            let tx = crate::Transaction::new(tx);
+
+            let leaf_hash = felt_bytes!(b"binary leaf");
+            let root_hash = felt_bytes!(b"root");
+
+            let mut nodes = HashMap::new();
+            nodes.insert(leaf_hash, Node::LeafBinary);
+            nodes.insert(
+                root_hash,
+                Node::Binary {
+                    left: Child::Hash(leaf_hash),
+                    right: Child::Hash(leaf_hash),
+                },
+            );
+
+            let root_idx = *test_table::insert(&tx, root_hash, &nodes)
+                .unwrap()
+                .last()
+                .unwrap();
+
+            let (left, right) = test_table::node(&tx, root_idx)
+                .unwrap()
+                .unwrap()
+                .into_binary()
+                .unwrap();
+            assert_ne!(left, right);
+            assert_eq!(test_table::hash(&tx, left).unwrap(), Some(leaf_hash));
+            assert_eq!(test_table::hash(&tx, right).unwrap(), Some(leaf_hash));
+        }
+
+        #[test]
+        fn prune() {
+            let mut db = setup_db();
+            let tx = db.transaction().unwrap();
+            let tx = crate::Transaction::new(tx);
+
+            let mut indices = Vec::new();
+            for i in 0..4u8 {
+                let hash = Felt::from_u64(i.into());
+                let nodes = HashMap::from([(hash, Node::LeafBinary)]);
+                indices.push(
+                    *test_table::insert(&tx, hash, &nodes)
+                        .unwrap()
+                        .last()
+                        .unwrap(),
+                );
+            }
+
+            test_table::insert_removals(&tx, BlockNumber::GENESIS, &indices[..1]).unwrap();
+            test_table::insert_removals(&tx, BlockNumber::GENESIS + 1, &indices[1..3]).unwrap();
+            test_table::insert_removals(&tx, BlockNumber::GENESIS + 2, &indices[3..]).unwrap();
+
+            let removed = test_table::removals(&tx, BlockNumber::GENESIS + 1).unwrap();
+            assert_eq!(removed, indices[1..3]);
+
+            let removed = test_table::take_removals(&tx, BlockNumber::GENESIS + 1).unwrap();
+            assert_eq!(removed, indices[..3]);
+            test_table::delete(&tx, &removed).unwrap();
+
+            for idx in &indices[..3] {
+                assert_eq!(test_table::node(&tx, *idx).unwrap(), None);
+            }
+            assert_eq!(
+                test_table::node(&tx, indices[3]).unwrap(),
+                Some(StoredNode::LeafBinary)
+            );
+
+            // Only the removals of the unpruned block remain.
+            let remaining: u64 = tx
+                .inner()
+                .query_row("SELECT COUNT(1) FROM test_table_removals", [], |row| {
+                    row.get(0)
+                })
+                .unwrap();
+            assert_eq!(remaining, 1);
+        }
+
         #[test]
         fn index_children() {
             // Insert nodes which use indices as children instead of hashes.
@@ -806,7 +1200,10 @@ mod tests {
             nodes.insert(binary_hash0, binary_node0);
             nodes.insert(root_hash, root_node);
 
-            let root_idx = test_table::insert(&tx, root_hash, &nodes).unwrap();
+            let root_idx = *test_table::insert(&tx, root_hash, &nodes)
+                .unwrap()
+                .last()
+                .unwrap();
 
             // Root node
             let hash = test_table::hash(&tx, root_idx).unwrap();
@@ -831,6 +1228,143 @@ mod tests {
         }
     }
 
+    #[test]
+    fn prune_tries() {
+        let storage = crate::Storage::in_memory_with_trie_pruning(TriePruneMode::Prune {
+            num_blocks_kept: 1,
+        })
+        .unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let contract = contract_address_bytes!(b"contract");
+        let other = contract_address_bytes!(b"other");
+
+        for block in 0..4 {
+            let block = BlockNumber::new_or_panic(block);
+            insert_class_root(&tx, block, Some(block.get())).unwrap();
+            insert_storage_root(&tx, block, Some(block.get())).unwrap();
+        }
+        insert_contract_root(&tx, BlockNumber::new_or_panic(0), contract, Some(1)).unwrap();
+        insert_contract_root(&tx, BlockNumber::new_or_panic(0), other, Some(2)).unwrap();
+        insert_contract_root(&tx, BlockNumber::new_or_panic(2), contract, Some(3)).unwrap();
+
+        prune_tries(&tx, BlockNumber::new_or_panic(3)).unwrap();
+
+        assert!(trie_pruned(&tx, BlockNumber::new_or_panic(1)).unwrap());
+        assert!(!trie_pruned(&tx, BlockNumber::new_or_panic(2)).unwrap());
+        assert!(!trie_pruned(&tx, BlockNumber::new_or_panic(3)).unwrap());
+
+        let result = class_root_index(&tx, BlockNumber::new_or_panic(2)).unwrap();
+        assert_eq!(result, Some(2));
+        let result = storage_root_index(&tx, BlockNumber::new_or_panic(1)).unwrap();
+        assert_eq!(result, None);
+
+        // The contract's older root is superseded within the kept range, while the other
+        // contract's root is still the latest one.
+        let result = contract_root_index(&tx, BlockNumber::new_or_panic(1), contract).unwrap();
+        assert_eq!(result, None);
+        let result = contract_root_index(&tx, BlockNumber::new_or_panic(3), contract).unwrap();
+        assert_eq!(result, Some(3));
+        let result = contract_root_index(&tx, BlockNumber::new_or_panic(3), other).unwrap();
+        assert_eq!(result, Some(2));
+    }
+
+    #[test]
+    fn purged_block_releases_its_nodes() {
+        let storage = crate::Storage::in_memory_with_trie_pruning(TriePruneMode::Prune {
+            num_blocks_kept: 10,
+        })
+        .unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let leaf = |name: &[u8]| (Felt::from_be_slice(name).unwrap(), Node::LeafBinary);
+        let (a, b, c) = (leaf(b"a"), leaf(b"b"), leaf(b"c"));
+        let block0 = BlockNumber::GENESIS;
+        let block1 = BlockNumber::GENESIS + 1;
+
+        // Block 0 has a root with two children.
+        let root0 = felt_bytes!(b"root 0");
+        let nodes = HashMap::from([
+            a.clone(),
+            b.clone(),
+            (
+                root0,
+                Node::Binary {
+                    left: Child::Hash(a.0),
+                    right: Child::Hash(b.0),
+                },
+            ),
+        ]);
+        let root0 = insert_trie(&tx, Trie::Storage, block0, root0, &nodes).unwrap();
+        let (a_idx, b_idx) = trie_storage::node(&tx, root0)
+            .unwrap()
+            .unwrap()
+            .into_binary()
+            .unwrap();
+        insert_storage_root(&tx, block0, Some(root0)).unwrap();
+
+        // Block 1 keeps the left child, replaces the right one and adds a contract trie.
+        let root1 = felt_bytes!(b"root 1");
+        let nodes = HashMap::from([
+            c.clone(),
+            (
+                root1,
+                Node::Binary {
+                    left: Child::Id(a_idx),
+                    right: Child::Hash(c.0),
+                },
+            ),
+        ]);
+        let root1 = insert_trie(&tx, Trie::Storage, block1, root1, &nodes).unwrap();
+        let (_, c_idx) = trie_storage::node(&tx, root1)
+            .unwrap()
+            .unwrap()
+            .into_binary()
+            .unwrap();
+        insert_storage_root(&tx, block1, Some(root1)).unwrap();
+        trie_storage::insert_removals(&tx, block1, &[root0, b_idx]).unwrap();
+
+        let contract = contract_address_bytes!(b"contract");
+        let contract_root = insert_trie(
+            &tx,
+            Trie::Contract,
+            block1,
+            felt_bytes!(b"contract root"),
+            &HashMap::from([(felt_bytes!(b"contract root"), Node::LeafBinary)]),
+        )
+        .unwrap();
+        insert_contract_root(&tx, block1, contract, Some(contract_root)).unwrap();
+
+        tx.purge_block(block1).unwrap();
+
+        for idx in [root1, c_idx] {
+            assert_eq!(trie_storage::node(&tx, idx).unwrap(), None);
+        }
+        assert_eq!(trie_contracts::node(&tx, contract_root).unwrap(), None);
+        // The parent's tries are untouched.
+        for idx in [root0, a_idx, b_idx] {
+            assert!(trie_storage::node(&tx, idx).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn archive_is_never_pruned() {
+        let mut db = crate::Storage::in_memory().unwrap().connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        for block in 0..4 {
+            insert_storage_root(&tx, BlockNumber::new_or_panic(block), Some(block)).unwrap();
+        }
+
+        prune_tries(&tx, BlockNumber::new_or_panic(3)).unwrap();
+
+        assert!(!trie_pruned(&tx, BlockNumber::GENESIS).unwrap());
+        let result = storage_root_index(&tx, BlockNumber::GENESIS).unwrap();
+        assert_eq!(result, Some(0));
+    }
+
     #[test]
     fn contract_state_hash() {
         let mut db = crate::Storage::in_memory().unwrap().connection().unwrap();
@@ -0,0 +1,287 @@
+//! Storage of the state trie nodes, which are the hot path of state updates and proofs.
+//!
+//! The nodes are stored by a [TrieBackend], which defaults to the [SqliteTrieBackend]. Everything
+//! else, including the trie roots of each block and the nodes removed by each block, is always
+//! stored in SQLite. In particular, state lookups such as storage values, nonces and class hashes
+//! are read from their own SQLite tables and not from the tries, so they are out of the scope of
+//! this trait.
+//!
+//! No persistent alternative to SQLite is provided, and the node cannot be configured to use
+//! another backend. Backends outside of SQLite do not take part in the database transaction (see
+//! [TrieBackend]), so a persistent one would first need a way to recover after a crash.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use anyhow::Context;
+use pathfinder_crypto::Felt;
+
+use super::trie::{self, trie_class, trie_contracts, trie_storage, Node, StoredNode};
+use crate::prelude::*;
+
+/// The kind of state trie a node belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trie {
+    Class,
+    /// The storage tries of all contracts.
+    Contract,
+    /// The global storage trie, which contains the contract states.
+    Storage,
+}
+
+/// Stores the nodes of the state tries.
+///
+/// Each stored node is identified by an index assigned by the backend when it is inserted,
+/// which must be unique within its [Trie]. Nodes are never modified once inserted.
+///
+/// The database transaction is passed to each call, so that a backend storing nodes in SQLite
+/// takes part in it. Other backends are not bound to the transaction, so nodes inserted or deleted
+/// by a transaction which is rolled back are not restored. Orphaned nodes are harmless, but the
+/// tries of a block can become unusable if a rolled back transaction pruned them.
+pub trait TrieBackend: Send + Sync {
+    /// Stores the new nodes of a trie, starting from the node with the hash `root`, and returns the
+    /// indices of the stored nodes, the root's being the last. Children referred to by index are
+    /// already stored.
+    fn insert(
+        &self,
+        tx: &Transaction<'_>,
+        trie: Trie,
+        root: Felt,
+        nodes: &HashMap<Felt, Node>,
+    ) -> anyhow::Result<Vec<u64>>;
+
+    /// Returns the node with the given index.
+    fn node(
+        &self,
+        tx: &Transaction<'_>,
+        trie: Trie,
+        index: u64,
+    ) -> anyhow::Result<Option<StoredNode>>;
+
+    /// Returns the hash of the node with the given index.
+    fn hash(&self, tx: &Transaction<'_>, trie: Trie, index: u64) -> anyhow::Result<Option<Felt>>;
+
+    /// Deletes the nodes with the given indices, which are no longer part of any kept trie.
+    fn delete(&self, tx: &Transaction<'_>, trie: Trie, indices: &[u64]) -> anyhow::Result<()>;
+}
+
+/// Stores the trie nodes in the `trie_class`, `trie_contracts` and `trie_storage` tables.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SqliteTrieBackend;
+
+impl TrieBackend for SqliteTrieBackend {
+    fn insert(
+        &self,
+        tx: &Transaction<'_>,
+        trie: Trie,
+        root: Felt,
+        nodes: &HashMap<Felt, Node>,
+    ) -> anyhow::Result<Vec<u64>> {
+        match trie {
+            Trie::Class => trie_class::insert(tx, root, nodes),
+            Trie::Contract => trie_contracts::insert(tx, root, nodes),
+            Trie::Storage => trie_storage::insert(tx, root, nodes),
+        }
+    }
+
+    fn node(
+        &self,
+        tx: &Transaction<'_>,
+        trie: Trie,
+        index: u64,
+    ) -> anyhow::Result<Option<StoredNode>> {
+        match trie {
+            Trie::Class => trie_class::node(tx, index),
+            Trie::Contract => trie_contracts::node(tx, index),
+            Trie::Storage => trie_storage::node(tx, index),
+        }
+    }
+
+    fn hash(&self, tx: &Transaction<'_>, trie: Trie, index: u64) -> anyhow::Result<Option<Felt>> {
+        match trie {
+            Trie::Class => trie_class::hash(tx, index),
+            Trie::Contract => trie_contracts::hash(tx, index),
+            Trie::Storage => trie_storage::hash(tx, index),
+        }
+    }
+
+    fn delete(&self, tx: &Transaction<'_>, trie: Trie, indices: &[u64]) -> anyhow::Result<()> {
+        match trie {
+            Trie::Class => trie_class::delete(tx, indices),
+            Trie::Contract => trie_contracts::delete(tx, indices),
+            Trie::Storage => trie_storage::delete(tx, indices),
+        }
+    }
+}
+
+/// Keeps the trie nodes in memory, and loses them when dropped.
+///
+/// Meant for tests and benchmarks of the trie code without the overhead of SQLite, and as an
+/// example of a key-value backend.
+#[derive(Debug, Default)]
+pub struct MemoryTrieBackend {
+    class: RwLock<MemoryTrie>,
+    contract: RwLock<MemoryTrie>,
+    storage: RwLock<MemoryTrie>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryTrie {
+    /// The index of the last inserted node. Indices start at 1, like SQLite's row ids.
+    last_index: u64,
+    nodes: HashMap<u64, (Felt, StoredNode)>,
+}
+
+impl MemoryTrieBackend {
+    fn trie(&self, trie: Trie) -> &RwLock<MemoryTrie> {
+        match trie {
+            Trie::Class => &self.class,
+            Trie::Contract => &self.contract,
+            Trie::Storage => &self.storage,
+        }
+    }
+}
+
+impl TrieBackend for MemoryTrieBackend {
+    fn insert(
+        &self,
+        _tx: &Transaction<'_>,
+        trie: Trie,
+        root: Felt,
+        nodes: &HashMap<Felt, Node>,
+    ) -> anyhow::Result<Vec<u64>> {
+        let mut memory = self.trie(trie).write().unwrap();
+        let mut indices = Vec::new();
+
+        trie::insert_nodes(nodes, root, &mut |hash, node| {
+            memory.last_index += 1;
+            let index = memory.last_index;
+            memory.nodes.insert(index, (hash, node.clone()));
+            indices.push(index);
+            Ok(index)
+        })
+        .context("Inserting nodes")?;
+
+        Ok(indices)
+    }
+
+    fn node(
+        &self,
+        _tx: &Transaction<'_>,
+        trie: Trie,
+        index: u64,
+    ) -> anyhow::Result<Option<StoredNode>> {
+        let trie = self.trie(trie).read().unwrap();
+        Ok(trie.nodes.get(&index).map(|(_, node)| node.clone()))
+    }
+
+    fn hash(&self, _tx: &Transaction<'_>, trie: Trie, index: u64) -> anyhow::Result<Option<Felt>> {
+        let trie = self.trie(trie).read().unwrap();
+        Ok(trie.nodes.get(&index).map(|(hash, _)| *hash))
+    }
+
+    fn delete(&self, _tx: &Transaction<'_>, trie: Trie, indices: &[u64]) -> anyhow::Result<()> {
+        let mut trie = self.trie(trie).write().unwrap();
+        for index in indices {
+            trie.nodes.remove(index);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use pathfinder_common::macro_prelude::*;
+    use pathfinder_common::{BlockNumber, ClassCommitment, StorageCommitment};
+
+    use super::*;
+    use crate::{Child, Storage, TriePruneMode};
+
+    fn nodes() -> (Felt, HashMap<Felt, Node>) {
+        let leaf = felt_bytes!(b"leaf");
+        let root = felt_bytes!(b"root");
+        let nodes = HashMap::from([
+            (leaf, Node::LeafBinary),
+            (
+                root,
+                Node::Binary {
+                    left: Child::Hash(leaf),
+                    right: Child::Id(123),
+                },
+            ),
+        ]);
+
+        (root, nodes)
+    }
+
+    #[test]
+    fn memory_backend() {
+        let backend = Arc::new(MemoryTrieBackend::default());
+        let storage = Storage::in_memory_with_trie_backend(backend.clone()).unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let (root, nodes) = nodes();
+        let root_idx = tx
+            .insert_storage_trie(BlockNumber::GENESIS, StorageCommitment(root), &nodes)
+            .unwrap();
+
+        let (left, right) = match tx.storage_trie_node(root_idx).unwrap().unwrap() {
+            StoredNode::Binary { left, right } => (left, right),
+            other => panic!("Expected binary node, got {other:?}"),
+        };
+        assert_eq!(right, 123);
+        assert_eq!(
+            tx.storage_trie_node(left).unwrap(),
+            Some(StoredNode::LeafBinary)
+        );
+        assert_eq!(
+            tx.storage_trie_node_hash(left).unwrap(),
+            Some(felt_bytes!(b"leaf"))
+        );
+
+        // The tries are separate, and nothing is stored in SQLite.
+        assert_eq!(tx.class_trie_node(root_idx).unwrap(), None);
+        let rows: u64 = tx
+            .inner()
+            .query_row("SELECT COUNT(1) FROM trie_storage", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 0);
+
+        // Nodes are visible through other connections sharing the backend.
+        drop(tx);
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+        assert_eq!(
+            tx.storage_trie_node_hash(root_idx).unwrap(),
+            Some(felt_bytes!(b"root"))
+        );
+    }
+
+    #[test]
+    fn pruning_deletes_from_backend() {
+        let backend = Arc::new(MemoryTrieBackend::default());
+        let storage = Storage::in_memory_with(
+            TriePruneMode::Prune { num_blocks_kept: 0 },
+            backend.clone(),
+            None,
+        )
+        .unwrap();
+        let mut db = storage.connection().unwrap();
+        let tx = db.transaction().unwrap();
+
+        let (root, nodes) = nodes();
+        let root_idx = tx
+            .insert_class_trie(BlockNumber::GENESIS, ClassCommitment(root), &nodes)
+            .unwrap();
+        tx.insert_class_trie_removals(BlockNumber::GENESIS, &[root_idx])
+            .unwrap();
+
+        tx.prune_tries(BlockNumber::GENESIS + 1).unwrap();
+
+        assert_eq!(tx.class_trie_node(root_idx).unwrap(), None);
+        assert_eq!(backend.class.read().unwrap().nodes.len(), 1);
+    }
+}
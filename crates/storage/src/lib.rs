@@ -5,6 +5,7 @@
 // This is intended for internal use only -- do not make public.
 mod prelude;
 
+mod backup;
 mod bloom;
 mod connection;
 pub mod fake;
@@ -16,6 +17,7 @@ use std::num::NonZeroU32;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+pub use backup::{backup_file, BackupOptions, BackupProgress};
 pub use connection::*;
 
 use pathfinder_common::{BlockHash, BlockNumber};
@@ -23,6 +25,7 @@ use pathfinder_common::{BlockHash, BlockNumber};
 use anyhow::Context;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::OptionalExtension;
 
 /// Sqlite key used for the PRAGMA user version.
 const VERSION_KEY: &str = "user_version";
@@ -35,6 +38,17 @@ pub enum JournalMode {
     WAL,
 }
 
+/// Specifies how much of the state tries' history is kept by the [Storage].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TriePruneMode {
+    /// Keeps the state tries of all blocks.
+    #[default]
+    Archive,
+    /// Keeps only the state tries of the latest block and the `num_blocks_kept` blocks before it.
+    /// Reorgs deeper than this cannot be handled.
+    Prune { num_blocks_kept: u64 },
+}
+
 /// Identifies a specific starknet block stored in the database.
 ///
 /// Note that this excludes the `Pending` variant since we never store pending data
@@ -89,19 +103,32 @@ struct Inner {
     database_path: Arc<PathBuf>,
     pool: Pool<SqliteConnectionManager>,
     bloom_filter_cache: Arc<bloom::Cache>,
+    dictionary_cache: Arc<connection::DictionaryCache>,
+    trie_backend: Arc<dyn TrieBackend>,
+    trie_prune_mode: TriePruneMode,
+    block_body_history: Option<u64>,
 }
 
 pub struct StorageManager {
     database_path: PathBuf,
     journal_mode: JournalMode,
     bloom_filter_cache: Arc<bloom::Cache>,
+    dictionary_cache: Arc<connection::DictionaryCache>,
+    trie_backend: Arc<dyn TrieBackend>,
+    trie_prune_mode: TriePruneMode,
+    block_body_history: Option<u64>,
+    /// Set by [Storage::open_read_only], in which case all connections are opened read-only.
+    read_only: bool,
 }
 
 impl StorageManager {
     pub fn create_pool(&self, capacity: NonZeroU32) -> anyhow::Result<Storage> {
         let journal_mode = self.journal_mode;
-        let pool_manager = SqliteConnectionManager::file(&self.database_path)
+        let mut pool_manager = SqliteConnectionManager::file(&self.database_path)
             .with_init(move |connection| setup_connection(connection, journal_mode));
+        if self.read_only {
+            pool_manager = pool_manager.with_flags(read_only_flags());
+        }
         let pool = Pool::builder()
             .max_size(capacity.get())
             .build(pool_manager)?;
@@ -110,8 +137,101 @@ impl StorageManager {
             database_path: Arc::new(self.database_path.clone()),
             pool,
             bloom_filter_cache: self.bloom_filter_cache.clone(),
+            dictionary_cache: self.dictionary_cache.clone(),
+            trie_backend: self.trie_backend.clone(),
+            trie_prune_mode: self.trie_prune_mode,
+            block_body_history: self.block_body_history,
         }))
     }
+
+    pub fn trie_prune_mode(&self) -> TriePruneMode {
+        self.trie_prune_mode
+    }
+
+    /// Keeps the transactions, receipts and events of only the latest block and the `num_blocks`
+    /// blocks before it. Older block bodies are deleted as new blocks are added, while their
+    /// headers, state updates and signatures are kept.
+    ///
+    /// Block bodies are kept for all blocks if this is `None`.
+    pub fn with_block_body_history(mut self, num_blocks: Option<u64>) -> Self {
+        self.block_body_history = num_blocks;
+        self
+    }
+
+    /// Stores the state trie nodes using the given [TrieBackend] instead of the default
+    /// [SqliteTrieBackend]. All other data, including the trie roots, remains in SQLite.
+    ///
+    /// The backend is not recorded in the database, so the same backend must be used every time
+    /// the database is opened. The node itself always uses [SqliteTrieBackend], so this is only
+    /// meant for tests, benchmarks and experiments embedding this crate.
+    pub fn with_trie_backend(mut self, backend: Arc<dyn TrieBackend>) -> Self {
+        self.trie_backend = backend;
+        self
+    }
+
+    /// Sets the database's [TriePruneMode], which is persisted in the database.
+    ///
+    /// Pruning can only be enabled for a new database, and a pruned database cannot be
+    /// turned back into an archive. The number of kept blocks may be changed freely.
+    pub fn with_trie_prune_mode(mut self, mode: TriePruneMode) -> anyhow::Result<Self> {
+        if mode == self.trie_prune_mode {
+            return Ok(self);
+        }
+        anyhow::ensure!(
+            !self.read_only,
+            "The trie prune mode cannot be changed for a read-only database"
+        );
+
+        let connection = rusqlite::Connection::open(&self.database_path).context("Opening DB")?;
+
+        match (self.trie_prune_mode, mode) {
+            (TriePruneMode::Prune { .. }, TriePruneMode::Archive) => {
+                anyhow::bail!("The state tries of this database have been pruned and cannot be used in archive mode")
+            }
+            (TriePruneMode::Archive, TriePruneMode::Prune { .. }) => {
+                let has_blocks: bool = connection
+                    .query_row("SELECT EXISTS(SELECT 1 FROM canonical_blocks)", [], |row| {
+                        row.get(0)
+                    })
+                    .context("Querying for blocks")?;
+                anyhow::ensure!(
+                    !has_blocks,
+                    "State trie pruning can only be enabled for a new database"
+                );
+            }
+            (
+                TriePruneMode::Prune {
+                    num_blocks_kept: previous,
+                },
+                TriePruneMode::Prune { num_blocks_kept },
+            ) if num_blocks_kept < previous => {
+                // The roots of the blocks which are no longer kept are pruned right away, their
+                // nodes are pruned together with the next block.
+                let head = connection
+                    .query_row("SELECT MAX(number) FROM canonical_blocks", [], |row| {
+                        row.get::<_, Option<u64>>(0)
+                    })
+                    .context("Querying latest block")?;
+                if let Some(oldest) = head.and_then(|head| head.checked_sub(num_blocks_kept)) {
+                    connection::prune_roots(&connection, BlockNumber::new_or_panic(oldest))
+                        .context("Pruning trie roots")?;
+                }
+            }
+            _ => {}
+        }
+
+        if let TriePruneMode::Prune { num_blocks_kept } = mode {
+            connection
+                .execute(
+                    "INSERT OR REPLACE INTO storage_options (option, value) VALUES ('prune_tries', ?)",
+                    [num_blocks_kept],
+                )
+                .context("Persisting trie prune mode")?;
+        }
+
+        self.trie_prune_mode = mode;
+        Ok(self)
+    }
 }
 
 impl Storage {
@@ -139,6 +259,8 @@ impl Storage {
 
         migrate_database(&mut connection).context("Migrate database")?;
 
+        let trie_prune_mode = trie_prune_mode(&connection).context("Reading trie prune mode")?;
+
         // Set the journal mode to the desired value.
         setup_journal_mode(&mut connection, journal_mode).context("Setting journal mode")?;
 
@@ -151,19 +273,102 @@ impl Storage {
             database_path,
             journal_mode,
             bloom_filter_cache: Arc::new(bloom::Cache::with_size(bloom_filter_cache_size)),
+            dictionary_cache: Default::default(),
+            trie_backend: Arc::new(SqliteTrieBackend),
+            trie_prune_mode,
+            block_body_history: None,
+            read_only: false,
+        })
+    }
+
+    /// Opens an existing database without modifying it, for use by a replica of a node which
+    /// owns and writes to the database.
+    ///
+    /// Unlike [Storage::migrate], the database is not migrated. Instead its schema must already
+    /// be at the version expected by this application. The journal mode is left as the owning
+    /// node configured it.
+    pub fn open_read_only(
+        database_path: PathBuf,
+        bloom_filter_cache_size: usize,
+    ) -> anyhow::Result<StorageManager> {
+        let connection = rusqlite::Connection::open_with_flags(&database_path, read_only_flags())
+            .context("Opening DB")?;
+
+        let current_revision = schema_version(&connection)?;
+        let latest_revision = schema::BASE_SCHEMA_REVISION + schema::migrations().len();
+        anyhow::ensure!(
+            current_revision == latest_revision,
+            "Database version {current_revision} does not match the expected version {latest_revision}, \
+            the node owning the database must migrate it first"
+        );
+
+        let trie_prune_mode = trie_prune_mode(&connection).context("Reading trie prune mode")?;
+
+        let journal_mode = connection
+            .pragma_query_value(None, "journal_mode", |row| row.get::<_, String>(0))
+            .context("Reading journal mode")?;
+        let journal_mode = if journal_mode.eq_ignore_ascii_case("wal") {
+            JournalMode::WAL
+        } else {
+            JournalMode::Rollback
+        };
+
+        Ok(StorageManager {
+            database_path,
+            journal_mode,
+            bloom_filter_cache: Arc::new(bloom::Cache::with_size(bloom_filter_cache_size)),
+            dictionary_cache: Default::default(),
+            trie_backend: Arc::new(SqliteTrieBackend),
+            trie_prune_mode,
+            block_body_history: None,
+            read_only: true,
         })
     }
 
     /// Returns a new Sqlite [Connection] to the database.
     pub fn connection(&self) -> anyhow::Result<Connection> {
         let conn = self.0.pool.get()?;
-        Ok(Connection::new(conn, self.0.bloom_filter_cache.clone()))
+        Ok(Connection::new(
+            conn,
+            self.0.bloom_filter_cache.clone(),
+            self.0.dictionary_cache.clone(),
+            self.0.trie_backend.clone(),
+            self.0.trie_prune_mode,
+            self.0.block_body_history,
+        ))
     }
 
     /// Convenience function for tests to create an in-memory database.
     /// Equivalent to [Storage::migrate] with an in-memory backed database.
     // No longer cfg(test) because needed in benchmarks
     pub fn in_memory() -> anyhow::Result<Self> {
+        Self::in_memory_with_trie_pruning(TriePruneMode::Archive)
+    }
+
+    /// Same as [Storage::in_memory], but with the given [TriePruneMode].
+    pub fn in_memory_with_trie_pruning(trie_prune_mode: TriePruneMode) -> anyhow::Result<Self> {
+        Self::in_memory_with(trie_prune_mode, Arc::new(SqliteTrieBackend), None)
+    }
+
+    /// Same as [Storage::in_memory], but storing the trie nodes using the given [TrieBackend].
+    pub fn in_memory_with_trie_backend(backend: Arc<dyn TrieBackend>) -> anyhow::Result<Self> {
+        Self::in_memory_with(TriePruneMode::Archive, backend, None)
+    }
+
+    /// Same as [Storage::in_memory], but only keeping the bodies of the latest `num_blocks` blocks.
+    pub fn in_memory_with_block_body_history(num_blocks: u64) -> anyhow::Result<Self> {
+        Self::in_memory_with(
+            TriePruneMode::Archive,
+            Arc::new(SqliteTrieBackend),
+            Some(num_blocks),
+        )
+    }
+
+    fn in_memory_with(
+        trie_prune_mode: TriePruneMode,
+        trie_backend: Arc<dyn TrieBackend>,
+        block_body_history: Option<u64>,
+    ) -> anyhow::Result<Self> {
         // Create a unique database name so that they are not shared between
         // concurrent tests. i.e. Make every in-mem Storage unique.
         lazy_static::lazy_static!(
@@ -183,7 +388,10 @@ impl Storage {
         // therefore holds the database in-place until the pool is established.
         let _conn = rusqlite::Connection::open(&database_path)?;
 
-        let storage = Self::migrate(database_path, JournalMode::Rollback, 16)?;
+        let storage = Self::migrate(database_path, JournalMode::Rollback, 16)?
+            .with_trie_prune_mode(trie_prune_mode)?
+            .with_trie_backend(trie_backend)
+            .with_block_body_history(block_body_history);
 
         storage.create_pool(NonZeroU32::new(5).unwrap())
     }
@@ -191,6 +399,26 @@ impl Storage {
     pub fn path(&self) -> &Path {
         &self.0.database_path
     }
+
+    /// Writes a consistent snapshot of the database to `destination`, which must not exist yet.
+    ///
+    /// Other connections may continue writing while the backup is in progress, but the
+    /// write-ahead log cannot be checkpointed until it is complete.
+    pub fn backup(
+        &self,
+        destination: &Path,
+        options: BackupOptions,
+        progress: impl FnMut(BackupProgress),
+    ) -> anyhow::Result<()> {
+        let mut connection = self.0.pool.get().context("Getting database connection")?;
+        backup::backup(&mut connection, destination, options, progress)
+    }
+}
+
+fn read_only_flags() -> rusqlite::OpenFlags {
+    use rusqlite::OpenFlags;
+
+    OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX
 }
 
 fn setup_journal_mode(
@@ -321,6 +549,22 @@ fn migrate_database(connection: &mut rusqlite::Connection) -> anyhow::Result<()>
     Ok(())
 }
 
+/// Returns the [TriePruneMode] persisted in the database.
+fn trie_prune_mode(connection: &rusqlite::Connection) -> anyhow::Result<TriePruneMode> {
+    let num_blocks_kept = connection
+        .query_row(
+            "SELECT value FROM storage_options WHERE option = 'prune_tries'",
+            [],
+            |row| row.get::<_, u64>(0),
+        )
+        .optional()?;
+
+    Ok(match num_blocks_kept {
+        Some(num_blocks_kept) => TriePruneMode::Prune { num_blocks_kept },
+        None => TriePruneMode::Archive,
+    })
+}
+
 /// Returns the current schema version of the existing database,
 /// or `0` if database does not yet exist.
 fn schema_version(connection: &rusqlite::Connection) -> anyhow::Result<usize> {
@@ -410,6 +654,135 @@ mod tests {
             .unwrap_err();
     }
 
+    #[test]
+    fn read_only_sees_writes_but_cannot_write() {
+        let db_dir = tempfile::TempDir::new().unwrap();
+        let db_path = db_dir.path().join("pathfinder.sqlite");
+
+        // The database must be migrated by its owner first.
+        rusqlite::Connection::open(&db_path).unwrap();
+        Storage::open_read_only(db_path.clone(), 1).unwrap_err();
+
+        let writer = Storage::migrate(db_path.clone(), JournalMode::WAL, 1)
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let reader = Storage::open_read_only(db_path, 1)
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+
+        let mut connection = writer.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        tx.insert_block_header(&pathfinder_common::BlockHeader::default())
+            .unwrap();
+        tx.commit().unwrap();
+
+        let mut connection = reader.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        assert!(tx.block_id(BlockId::Latest).unwrap().is_some());
+        tx.insert_block_header(&pathfinder_common::BlockHeader::default())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn trie_prune_mode_is_persisted_and_validated() {
+        let db_dir = tempfile::TempDir::new().unwrap();
+        let db_path = db_dir.path().join("pathfinder.sqlite");
+        let migrate = || Storage::migrate(db_path.clone(), JournalMode::Rollback, 1).unwrap();
+
+        let pruned = TriePruneMode::Prune {
+            num_blocks_kept: 10,
+        };
+        let manager = migrate().with_trie_prune_mode(pruned).unwrap();
+        assert_eq!(manager.trie_prune_mode(), pruned);
+        assert_eq!(migrate().trie_prune_mode(), pruned);
+
+        // A pruned database cannot become an archive again.
+        migrate()
+            .with_trie_prune_mode(TriePruneMode::Archive)
+            .unwrap_err();
+
+        // But the number of kept blocks may be changed.
+        let pruned = TriePruneMode::Prune { num_blocks_kept: 5 };
+        migrate().with_trie_prune_mode(pruned).unwrap();
+        assert_eq!(migrate().trie_prune_mode(), pruned);
+    }
+
+    #[test]
+    fn lowering_kept_blocks_prunes_roots() {
+        let db_dir = tempfile::TempDir::new().unwrap();
+        let db_path = db_dir.path().join("pathfinder.sqlite");
+        let migrate = || Storage::migrate(db_path.clone(), JournalMode::Rollback, 1).unwrap();
+
+        let contract = pathfinder_common::contract_address!("0x1");
+        let storage = migrate()
+            .with_trie_prune_mode(TriePruneMode::Prune {
+                num_blocks_kept: 10,
+            })
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        for block in 0..=10 {
+            let block = BlockNumber::new_or_panic(block);
+            let header = pathfinder_common::BlockHeader::builder()
+                .with_number(block)
+                .finalize_with_hash(BlockHash(block.get().into()));
+            tx.insert_block_header(&header).unwrap();
+            tx.insert_storage_root(block, Some(block.get())).unwrap();
+        }
+        tx.insert_contract_root(BlockNumber::new_or_panic(1), contract, Some(1))
+            .unwrap();
+        tx.insert_contract_root(BlockNumber::new_or_panic(3), contract, Some(3))
+            .unwrap();
+        tx.commit().unwrap();
+        drop(connection);
+
+        let storage = migrate()
+            .with_trie_prune_mode(TriePruneMode::Prune { num_blocks_kept: 5 })
+            .unwrap()
+            .create_pool(NonZeroU32::new(1).unwrap())
+            .unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        assert!(tx.trie_pruned(BlockNumber::new_or_panic(4)).unwrap());
+        assert!(!tx.trie_pruned(BlockNumber::new_or_panic(5)).unwrap());
+        // The contract's older root is superseded before the kept range, the newer one is still
+        // the contract's root within it.
+        let root = tx
+            .contract_root_index(BlockNumber::new_or_panic(2), contract)
+            .unwrap();
+        assert_eq!(root, None);
+        let root = tx
+            .contract_root_index(BlockNumber::new_or_panic(5), contract)
+            .unwrap();
+        assert_eq!(root, Some(3));
+    }
+
+    #[test]
+    fn trie_pruning_requires_an_empty_database() {
+        let db_dir = tempfile::TempDir::new().unwrap();
+        let db_path = db_dir.path().join("pathfinder.sqlite");
+        let manager = Storage::migrate(db_path, JournalMode::Rollback, 1).unwrap();
+
+        let storage = manager.create_pool(NonZeroU32::new(1).unwrap()).unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+        tx.insert_block_header(&pathfinder_common::BlockHeader::default())
+            .unwrap();
+        tx.commit().unwrap();
+        drop(connection);
+
+        manager
+            .with_trie_prune_mode(TriePruneMode::Prune {
+                num_blocks_kept: 10,
+            })
+            .unwrap_err();
+    }
+
     #[test]
     fn rpc_test_db_is_migrated() {
         let mut source_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
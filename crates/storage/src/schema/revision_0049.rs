@@ -0,0 +1,39 @@
+use anyhow::Context;
+
+/// Adds the tables required to prune the state tries.
+///
+/// The `*_removals` tables track which trie nodes stopped being part of the trie at a given block,
+/// and the `*_additions` tables which nodes were added by a given block, so that they can be
+/// deleted when the block is purged. `storage_options` persists the database's pruning
+/// configuration.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    for table in [
+        "trie_class_removals",
+        "trie_contracts_removals",
+        "trie_storage_removals",
+        "trie_class_additions",
+        "trie_contracts_additions",
+        "trie_storage_additions",
+    ] {
+        tx.execute_batch(&format!(
+            r"
+CREATE TABLE {table} (
+    block_number INTEGER NOT NULL,
+    indices      BLOB NOT NULL
+);
+CREATE INDEX {table}_block_number ON {table}(block_number);"
+        ))
+        .with_context(|| format!("Creating {table} table"))?;
+    }
+
+    tx.execute(
+        r"CREATE TABLE storage_options (
+    option TEXT PRIMARY KEY NOT NULL,
+    value  INTEGER
+)",
+        [],
+    )
+    .context("Creating storage_options table")?;
+
+    Ok(())
+}
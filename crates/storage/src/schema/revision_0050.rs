@@ -0,0 +1,23 @@
+use anyhow::Context;
+
+/// Adds the trained zstd dictionaries used to compress class definitions and transactions.
+///
+/// Existing rows keep a `NULL` dictionary id, meaning they were compressed without a dictionary.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch(
+        r"
+CREATE TABLE compression_dictionaries (
+    id         INTEGER PRIMARY KEY,
+    kind       INTEGER NOT NULL,
+    dictionary BLOB NOT NULL,
+    -- The largest rowid of the table when the dictionary was trained.
+    max_rowid  INTEGER NOT NULL
+);
+ALTER TABLE class_definitions ADD COLUMN dictionary_id INTEGER REFERENCES compression_dictionaries(id);
+ALTER TABLE casm_definitions ADD COLUMN dictionary_id INTEGER REFERENCES compression_dictionaries(id);
+ALTER TABLE starknet_transactions ADD COLUMN dictionary_id INTEGER REFERENCES compression_dictionaries(id);",
+    )
+    .context("Adding compression dictionaries")?;
+
+    Ok(())
+}
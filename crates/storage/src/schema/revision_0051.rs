@@ -0,0 +1,18 @@
+use anyhow::Context;
+
+/// Records the L1 block and transaction which accepted each state update, and how far the core
+/// contract's logs have been processed.
+///
+/// Existing rows were read from the contract's state instead of its logs, so their origin is
+/// `NULL` until the logs are backfilled.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch(
+        r"
+ALTER TABLE l1_state ADD COLUMN l1_block_number INTEGER;
+ALTER TABLE l1_state ADD COLUMN l1_transaction_hash BLOB;
+ALTER TABLE refs ADD COLUMN l1_logs_head INTEGER;",
+    )
+    .context("Adding L1 state update origin")?;
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use anyhow::Context;
+
+/// Tracks L1 to L2 messages by their hash, linking the L1 transaction which sent a message to the
+/// L1 handler transaction which consumed it on L2. Either side can be recorded first.
+///
+/// The L1 logs head is reset so that the messages of all L1 blocks processed so far are
+/// backfilled. L1 handler transactions stored before this migration are not linked, so the first
+/// block whose L1 handler transactions are linked is recorded.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch(
+        r"
+CREATE TABLE l1_to_l2_messages (
+    message_hash        BLOB PRIMARY KEY,
+    l1_block_number     INTEGER,
+    l1_transaction_hash BLOB,
+    l2_transaction_hash BLOB
+);
+CREATE INDEX l1_to_l2_messages_l1_transaction_hash ON l1_to_l2_messages(l1_transaction_hash);
+CREATE INDEX l1_to_l2_messages_l2_transaction_hash ON l1_to_l2_messages(l2_transaction_hash);
+UPDATE refs SET l1_logs_head = NULL;",
+    )
+    .context("Creating L1 to L2 messages table")?;
+
+    tx.execute(
+        r"INSERT INTO storage_options (option, value)
+        SELECT 'l1_handlers_linked_from', MAX(number) + 1 FROM canonical_blocks
+        HAVING COUNT(number) > 0",
+        [],
+    )
+    .context("Recording first block with L1 handler links")?;
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use anyhow::Context;
+
+/// Indexes the L2 to L1 messages sent by each transaction, and their consumption on L1, by the
+/// message hash.
+///
+/// The L1 logs head is reset so that the consumptions of all L1 blocks processed so far are
+/// backfilled. Messages sent by transactions stored before this migration are not indexed, so
+/// consumptions logged before the first indexed message was accepted on L1 are not paired with
+/// any message.
+pub(crate) fn migrate(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    tx.execute_batch(
+        r"
+CREATE TABLE l2_to_l1_messages (
+    message_hash     BLOB NOT NULL,
+    block_number     INTEGER NOT NULL,
+    transaction_hash BLOB NOT NULL
+);
+CREATE INDEX l2_to_l1_messages_message_hash ON l2_to_l1_messages(message_hash);
+CREATE INDEX l2_to_l1_messages_block_number ON l2_to_l1_messages(block_number);
+CREATE TABLE consumed_l2_to_l1_messages (
+    l1_block_number     INTEGER NOT NULL,
+    l1_log_index        INTEGER NOT NULL,
+    l1_transaction_hash BLOB NOT NULL,
+    message_hash        BLOB NOT NULL,
+    PRIMARY KEY (l1_block_number, l1_log_index)
+);
+CREATE INDEX consumed_l2_to_l1_messages_message_hash ON consumed_l2_to_l1_messages(message_hash);
+UPDATE refs SET l1_logs_head = NULL;",
+    )
+    .context("Creating L2 to L1 message tables")?;
+
+    Ok(())
+}